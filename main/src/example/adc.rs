@@ -3,6 +3,7 @@ use core::ptr::NonNull;
 
 use sces::mcu::adc::{AdcCtrl, AdcCtrlEvent, AdcDevice};
 use sces::mcu::uart::UartDevice;
+use sces::os::tick::Duration;
 use sces::mcu::EventLaunch;
 use sces_mcu_stm32::adc::{ADC_HandleTypeDef, Adc, AdcQueue};
 use sces_mcu_stm32::uart::{UART_HandleTypeDef, UartQueue};
@@ -74,7 +75,7 @@ impl Write for AdcExample
 {
     fn write_str(&mut self, s: &str) -> fmt::Result
     {
-        self.log.transmit(s.as_bytes(), 1000).map_err(|_| fmt::Error)
+        self.log.transmit(s.as_bytes(), Duration::from_secs(1)).map_err(|_| fmt::Error)
     }
 }
 
@@ -1,4 +1,5 @@
 use sces::mcu::uart::UartDevice;
+use sces::os::tick::Duration;
 use sces_mcu_stm32::uart::{UART_HandleTypeDef, UartQueue};
 
 #[allow(improper_ctypes)]
@@ -32,12 +33,12 @@ impl UartEchoExample
 
     pub fn tick(&mut self)
     {
-        let value = self.uart.receive(&mut self.cache, 1000);
+        let value = self.uart.receive(&mut self.cache, Duration::from_secs(1));
 
         if let Ok(x) = value
         {
             #[allow(unused_must_use)]
-            self.uart.transmit(&self.cache[0..x as usize], 1000);
+            self.uart.transmit(&self.cache[0..x as usize], Duration::from_secs(1));
         }
     }
 }
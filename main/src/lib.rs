@@ -3,8 +3,6 @@
 // mod example;
 mod model;
 
-use core::panic::PanicInfo;
-
 #[inline]
 #[no_mangle]
 #[allow(static_mut_refs)]
@@ -19,10 +17,3 @@ pub unsafe extern "C" fn app_main()
     #[cfg(feature = "nucleo-h563zi")]
     model::nucleo_h563zi::app_main();
 }
-
-#[panic_handler]
-fn app_panic(_info: &PanicInfo) -> !
-{
-    loop
-    {}
-}
@@ -1,16 +1,27 @@
-use log::info;
+use core::mem::MaybeUninit;
+
+use log::{info, warn};
 use log::LevelFilter;
 use sces::cell::StaticCell;
 use sces::os::mem::MemZone;
+use sces::mcu::power::{PowerIdle, PowerPolicy};
+use sces::mcu::sys::SystemCtrl;
+use sces::os::idle;
 use sces::os::task::{TaskPriority, TaskSample};
+use sces::os::tick::Duration;
+use sces::retain::{self, ResetReason, RetainedLog, RetainedRing};
 use sces::value::RetValue;
+use sces_mcu_stm32::sys::System;
+use sces_mcu_stm32::STM32;
 use sces_mcu_stm32::uart::{UART_HandleTypeDef, UartQueue};
 use sces_mcu_stm32::wd::{IWDG_HandleTypeDef, WatchDogQueue};
 use sces_os_cmsis::mem::initialize_mem_space;
 use sces_os_cmsis::task::Task;
 use sces_os_cmsis::CMSISOS;
 use sces_svc_alive::{AliveWatchService, NativeAliveWatch};
-use sces_svc_console::{ConsoleService, NativeConsole};
+use sces_svc_console::{Console, ConsoleService, NativeConsole, NativeConsoleDrain};
+use sces_svc_console::{RetainedDump, UartTransport};
+use sces_svc_panic::{PanicAction, PanicService};
 
 #[allow(improper_ctypes)]
 extern "C" {
@@ -18,29 +29,52 @@ extern "C" {
     static mut hwdt1: IWDG_HandleTypeDef;
 }
 
+const ALIVE_CYCLE: Duration = Duration::from_millis(300);
+
 #[global_allocator]
 static mut MEM: MemorySpace<CMSISOS, 256, 10, 512, 10, 1024, 10, 2048, 2> = MemorySpace::new();
 
+#[link_section = ".noinit"]
+static mut RETAINED: MaybeUninit<RetainedRing<2048>> = MaybeUninit::uninit();
+
+static mut CONSOLE_UART: Option<UartTransport> = None;
+static POWER_IDLE: PowerIdle<STM32> = PowerIdle::new(PowerPolicy::new());
+static CONSOLE_RETAINED: RetainedDump = RetainedDump;
 static mut SVC_CONSOLE: StaticCell<TaskSample<CMSISOS, NativeConsole<CMSISOS>>> = StaticCell::new();
+static mut SVC_CONSOLE_DRAIN: StaticCell<TaskSample<CMSISOS, NativeConsoleDrain<CMSISOS>>> =
+    StaticCell::new();
 static mut SVC_ALIVE: StaticCell<TaskSample<CMSISOS, NativeAliveWatch<CMSISOS>>> = StaticCell::new();
 
 #[allow(static_mut_refs)]
 pub unsafe fn app_main() -> RetValue<()>
 {
+    retain::install(RetainedRing::attach(&mut RETAINED));
     MEM.initialize()?;
     CMSISOS::initialize()?;
+    idle::install(&POWER_IDLE);
 
     SVC_ALIVE
-        .set(TaskSample::new(NativeAliveWatch::new(WatchDogQueue::alloc(&mut hwdt1)?, 300)?)?)
+        .set(TaskSample::new(NativeAliveWatch::new(
+            WatchDogQueue::alloc(&mut hwdt1)?,
+            ALIVE_CYCLE,
+        )?)?)
         .and_then(|x| x.active("AliveWatchService", 1024, TaskPriority::High))
         .and_then(|x| AliveWatchService::initialize(x.as_ref()))?;
 
+    let console_uart = CONSOLE_UART.insert(UartTransport::new(UartQueue::alloc(&mut huart1)?));
+
+    PanicService::initialize::<CMSISOS>(Some(UartQueue::search(&mut huart1)?), PanicAction::Halt);
+
     SVC_CONSOLE
-        .set(TaskSample::new(NativeConsole::new(UartQueue::alloc(&mut huart1)?)?)?)
+        .set(TaskSample::new(NativeConsole::new(console_uart)?)?)
         .and_then(|x| x.active("ConsoleService", 1024, TaskPriority::Normal))
-        .and_then(|x| ConsoleService::initialize(x.as_ref(), LevelFilter::Info))?;
+        .and_then(|x| ConsoleService::initialize(x.as_ref(), LevelFilter::Info).map(|()| x))
+        .and_then(|x| x.as_ref().accept_dispatch(&CONSOLE_RETAINED).map(|()| x))
+        .and_then(|x| SVC_CONSOLE_DRAIN.set(TaskSample::new(NativeConsoleDrain::new(x.as_ref()))?))
+        .and_then(|x| x.active("ConsoleDrain", 1024, TaskPriority::Low))?;
 
     app_print_trademark();
+    app_print_reset_reason();
 
     Ok(())
 }
@@ -53,3 +87,14 @@ fn app_print_trademark()
     info!("    |___/\\___\\___|_| |_| |_|___/\r");
     info!("");
 }
+
+fn app_print_reset_reason()
+{
+    info!("Reset cause {:?}, device {:02X?}.", System.reset_cause(), System.unique_id());
+    System.clean_reset_cause();
+
+    if let Some(x) = retain::retained().filter(|x| x.reset_reason() != ResetReason::PowerOn)
+    {
+        warn!("Reset by {:?}, run `retained` to show the last logs.", x.reset_reason());
+    }
+}
@@ -1,14 +1,21 @@
-use log::info;
+use core::mem::MaybeUninit;
+
+use log::{info, warn};
 use log::LevelFilter;
 use sces::cell::StaticCell;
+use sces::mcu::sys::SystemCtrl;
 use sces::os::task::{TaskPriority, TaskSample};
+use sces::retain::{self, ResetReason, RetainedLog, RetainedRing};
 use sces::os::RTOS;
 use sces::value::RetValue;
 use sces_cmw::os::MWOS;
+use sces_mcu_stm32::sys::System;
 use sces_mcu_stm32::uart::{UART_HandleTypeDef, UartQueue};
 use sces_mcu_stm32::wd::{IWDG_HandleTypeDef, WatchDogQueue};
 use sces_svc_alive::{AliveWatchService, NativeAliveWatch};
-use sces_svc_console::{ConsoleService, NativeConsole};
+use sces_svc_console::{Console, ConsoleService, NativeConsole, NativeConsoleDrain};
+use sces_svc_console::{RetainedDump, UartTransport};
+use sces_svc_panic::{PanicAction, PanicService};
 
 #[allow(improper_ctypes)]
 extern "C" {
@@ -16,25 +23,48 @@ extern "C" {
     // static mut hwdt1: IWDG_HandleTypeDef;
 }
 
+// The section is added by platform/nucleo-h563zi/noinit.ld before the linker script generated by
+// STM32CubeMX, which doesn't have it, so a `.noinit (NOLOAD)` section is needed when it's linked by
+// another project.
+#[link_section = ".noinit"]
+static mut RETAINED: MaybeUninit<RetainedRing<2048>> = MaybeUninit::uninit();
+
+static mut CONSOLE_UART: Option<UartTransport> = None;
+static CONSOLE_RETAINED: RetainedDump = RetainedDump;
 static mut SVC_CONSOLE: StaticCell<TaskSample<MWOS, NativeConsole<MWOS>>> = StaticCell::new();
+static mut SVC_CONSOLE_DRAIN: StaticCell<TaskSample<MWOS, NativeConsoleDrain<MWOS>>> =
+    StaticCell::new();
 // static mut SVC_ALIVE: StaticCell<TaskSample<MWOS, NativeAliveWatch<MWOS>>> = StaticCell::new();
 
 #[allow(static_mut_refs)]
 pub unsafe fn app_main() -> RetValue<()>
 {
+    retain::install(RetainedRing::attach(&mut RETAINED));
     MWOS::initialize()?;
 
     // SVC_ALIVE
-    //     .set(TaskSample::new(NativeAliveWatch::new(WatchDogQueue::alloc(&mut hwdt1)?, 300)?)?)
+    //     .set(TaskSample::new(NativeAliveWatch::new(WatchDogQueue::alloc(&mut hwdt1)?, Duration::from_millis(300))?)?)
     //     .and_then(|x| x.active("AliveWatchService", MWOS::TASK_STACK_1K, TaskPriority::High))
     //     .and_then(|x| AliveWatchService::initialize(x.as_ref()))?;
 
+    let console_uart =
+        CONSOLE_UART.insert(UartTransport::new(UartQueue::alloc(&mut hcom_uart[0])?));
+
+    PanicService::initialize::<MWOS>(
+        Some(UartQueue::search(&mut hcom_uart[0])?),
+        PanicAction::Halt,
+    );
+
     SVC_CONSOLE
-        .set(TaskSample::new(NativeConsole::new(UartQueue::alloc(&mut hcom_uart[0])?)?)?)
+        .set(TaskSample::new(NativeConsole::new(console_uart)?)?)
         .and_then(|x| x.active("ConsoleService", MWOS::TASK_STACK_1K, TaskPriority::Normal))
-        .and_then(|x| ConsoleService::initialize(x.as_ref(), LevelFilter::Info))?;
+        .and_then(|x| ConsoleService::initialize(x.as_ref(), LevelFilter::Info).map(|()| x))
+        .and_then(|x| x.as_ref().accept_dispatch(&CONSOLE_RETAINED).map(|()| x))
+        .and_then(|x| SVC_CONSOLE_DRAIN.set(TaskSample::new(NativeConsoleDrain::new(x.as_ref()))?))
+        .and_then(|x| x.active("ConsoleDrain", MWOS::TASK_STACK_1K, TaskPriority::Low))?;
 
     app_print_trademark();
+    app_print_reset_reason();
 
     Ok(())
 }
@@ -47,3 +77,14 @@ fn app_print_trademark()
     info!("    |___/\\___\\___|_| |_| |_|___/\r");
     info!("");
 }
+
+fn app_print_reset_reason()
+{
+    info!("Reset cause {:?}, device {:02X?}.", System.reset_cause(), System.unique_id());
+    System.clean_reset_cause();
+
+    if let Some(x) = retain::retained().filter(|x| x.reset_reason() != ResetReason::PowerOn)
+    {
+        warn!("Reset by {:?}, run `retained` to show the last logs.", x.reset_reason());
+    }
+}
@@ -54,6 +54,7 @@
 /* USER CODE BEGIN 0 */
   extern void configureTimerForRunTimeStats(void);
   extern unsigned long getRunTimeCounterValue(void);
+  extern uint32_t sces_cmsis_idle(uint32_t expected);
 /* USER CODE END 0 */
 #endif
 #ifndef CMSIS_device_header
@@ -180,6 +181,11 @@ standard names. */
 /* USER CODE BEGIN Defines */
 /* Section where parameter definitions can be added (for instance, to override default ones in FreeRTOS.h) */
 #define configAPPLICATION_ALLOCATED_HEAP 1
+/* The idle hook of sces decides the low power mode, see sces-os-cmsis */
+#if defined(APP)
+#define configUSE_TICKLESS_IDLE 1
+#define configPRE_SLEEP_PROCESSING(x) ((x) = sces_cmsis_idle(x))
+#endif /* APP */
 /* USER CODE END Defines */
 
 #endif /* FREERTOS_CONFIG_H */
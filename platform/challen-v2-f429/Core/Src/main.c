@@ -62,7 +62,9 @@ void MX_FREERTOS_Init(void);
 
 /* Private user code ---------------------------------------------------------*/
 /* USER CODE BEGIN 0 */
-
+#if defined(APP)
+extern void sces_tim_period_elapsed(TIM_HandleTypeDef *htim);
+#endif /* APP */
 /* USER CODE END 0 */
 
 /**
@@ -200,7 +202,9 @@ void HAL_TIM_PeriodElapsedCallback(TIM_HandleTypeDef *htim)
     HAL_IncTick();
   }
   /* USER CODE BEGIN Callback 1 */
-
+#if defined(APP)
+  sces_tim_period_elapsed(htim);
+#endif /* APP */
   /* USER CODE END Callback 1 */
 }
 
@@ -61,7 +61,9 @@ void SystemClock_Config(void);
 
 /* Private user code ---------------------------------------------------------*/
 /* USER CODE BEGIN 0 */
-
+#if defined(APP)
+extern void sces_tim_period_elapsed(TIM_HandleTypeDef *htim);
+#endif /* APP */
 /* USER CODE END 0 */
 
 /**
@@ -232,7 +234,9 @@ void HAL_TIM_PeriodElapsedCallback(TIM_HandleTypeDef *htim)
     HAL_IncTick();
   }
   /* USER CODE BEGIN Callback 1 */
-
+#if defined(APP)
+  sces_tim_period_elapsed(htim);
+#endif /* APP */
   /* USER CODE END Callback 1 */
 }
 
@@ -0,0 +1,221 @@
+use core::iter;
+
+use sces::mcu::spi::{SpiOperation, SpiSlave};
+use sces::os::tick::Duration;
+use sces::os::RTOS;
+use sces::value::{ErrValue, RetValue};
+
+pub const CMD_WRITE_STATUS: u8 = 0x01;
+pub const CMD_PAGE_PROGRAM: u8 = 0x02;
+pub const CMD_READ: u8 = 0x03;
+pub const CMD_READ_STATUS: u8 = 0x05;
+pub const CMD_WRITE_ENABLE: u8 = 0x06;
+pub const CMD_FAST_READ: u8 = 0x0B;
+pub const CMD_READ_SFDP: u8 = 0x5A;
+pub const CMD_READ_ID: u8 = 0x9F;
+pub const CMD_RELEASE_POWER_DOWN: u8 = 0xAB;
+pub const CMD_ENTER_4BYTE: u8 = 0xB7;
+pub const CMD_POWER_DOWN: u8 = 0xB9;
+pub const CMD_CHIP_ERASE: u8 = 0xC7;
+
+/// The chip is busy with a program, an erase or a write of the status register.
+pub const STATUS_BUSY: u8 = 0x01;
+
+/// The chip accepts a program, an erase or a write of the status register.
+pub const STATUS_WEL: u8 = 0x02;
+
+/// The block protection bits, BP0 to BP3 of MX25xx, or BP0 to BP2 and TB of W25Qxx.
+pub const STATUS_PROTECT: u8 = 0x3C;
+
+/// The largest size of a single receive, the STM32 HAL counts the data in 16 bits.
+const NOR_READ_CHUNK: usize = 0x8000;
+
+/// The commands of a SPI NOR flash, they are not serialized between the tasks.
+pub struct NorChip<'a, OS: RTOS>
+{
+    slave: SpiSlave<'a, OS>,
+    address_4byte: bool,
+    fast_read: bool,
+    asleep: bool,
+}
+
+impl<'a, OS: RTOS> NorChip<'a, OS>
+{
+    pub fn new(slave: SpiSlave<'a, OS>) -> Self
+    {
+        Self { slave, address_4byte: false, fast_read: true, asleep: false }
+    }
+
+    pub fn set_fast_read(&mut self, fast_read: bool)
+    {
+        self.fast_read = fast_read;
+    }
+
+    /// Send the addresses in four bytes, the chips which support both modes are switched into
+    /// the four bytes mode.
+    pub fn enter_4byte(&mut self) -> RetValue<()>
+    {
+        self.slave.write(&[CMD_ENTER_4BYTE])?;
+        self.address_4byte = true;
+        Ok(())
+    }
+
+    /// Build a command with an address, and return the size of the used part, the bytes after
+    /// it are zero.
+    fn command(&self, opcode: u8, address: u32) -> ([u8; 6], usize)
+    {
+        let [a3, a2, a1, a0] = address.to_be_bytes();
+
+        match self.address_4byte
+        {
+            true => ([opcode, a3, a2, a1, a0, 0], 5),
+            false => ([opcode, a2, a1, a0, 0, 0], 4),
+        }
+    }
+
+    fn check_awake(&self) -> RetValue<()>
+    {
+        (!self.asleep).then_some(()).ok_or(ErrValue::NotAvailable)
+    }
+
+    pub fn read_id(&self) -> RetValue<[u8; 3]>
+    {
+        self.check_awake()?;
+
+        let mut id = [0; 3];
+        self.slave.write_read(&[CMD_READ_ID], &mut id)?;
+        Ok(id)
+    }
+
+    /// Read the SFDP area, it's always addressed by three bytes with a dummy byte.
+    pub fn read_sfdp(&self, address: u32, data: &mut [u8]) -> RetValue<()>
+    {
+        self.check_awake()?;
+
+        let [_, a2, a1, a0] = address.to_be_bytes();
+        self.slave.write_read(&[CMD_READ_SFDP, a2, a1, a0, 0], data)
+    }
+
+    pub fn read(&self, address: u32, data: &mut [u8]) -> RetValue<()>
+    {
+        self.check_awake()?;
+
+        // The dummy byte of the fast read is the zero after the address.
+        let (command, size) = match self.fast_read
+        {
+            true => self.command(CMD_FAST_READ, address),
+            false => self.command(CMD_READ, address),
+        };
+        let size = size + self.fast_read as usize;
+
+        self.slave.transaction(
+            iter::once(SpiOperation::Write(&command[..size]))
+                .chain(data.chunks_mut(NOR_READ_CHUNK).map(SpiOperation::Read)),
+        )
+    }
+
+    pub fn read_status(&self) -> RetValue<u8>
+    {
+        self.check_awake()?;
+
+        let mut status = [0; 1];
+        self.slave.write_read(&[CMD_READ_STATUS], &mut status)?;
+        Ok(status[0])
+    }
+
+    pub fn write_enable(&self) -> RetValue<()>
+    {
+        self.check_awake()?;
+        self.slave.write(&[CMD_WRITE_ENABLE])?;
+
+        // The chip ignores the command when it's protected by the pin WP#.
+        match self.read_status()? & STATUS_WEL
+        {
+            0 => Err(ErrValue::Permission),
+            _ => Ok(()),
+        }
+    }
+
+    /// Wait until the chip finishes the last program or erase.
+    ///
+    /// It polls the status register continuously when `poll` is zero, or it sleeps `poll`
+    /// between the readings for a long operation.
+    pub fn wait_ready(&self, timeout: Duration, poll: Duration) -> RetValue<()>
+    {
+        let start = OS::now();
+
+        loop
+        {
+            if self.read_status()? & STATUS_BUSY == 0
+            {
+                return Ok(());
+            }
+
+            if !timeout.is_forever() && OS::now() - start > timeout
+            {
+                return Err(ErrValue::Timeout);
+            }
+
+            if poll != Duration::ZERO
+            {
+                OS::delay(poll);
+            }
+        }
+    }
+
+    /// Program a part of a page, the data must not cross the boundary of the page.
+    pub fn program_page(&self, address: u32, data: &[u8]) -> RetValue<()>
+    {
+        self.write_enable()?;
+
+        let (command, size) = self.command(CMD_PAGE_PROGRAM, address);
+        self.slave.transaction([SpiOperation::Write(&command[..size]), SpiOperation::Write(data)])
+    }
+
+    /// Start an erase, the chip erase is sent without any address.
+    pub fn erase(&self, opcode: u8, address: u32) -> RetValue<()>
+    {
+        self.write_enable()?;
+
+        match opcode
+        {
+            CMD_CHIP_ERASE => self.slave.write(&[opcode]),
+            _ =>
+            {
+                let (command, size) = self.command(opcode, address);
+                self.slave.write(&command[..size])
+            }
+        }
+    }
+
+    pub fn write_status(&self, status: &[u8]) -> RetValue<()>
+    {
+        self.write_enable()?;
+
+        self.slave
+            .transaction([SpiOperation::Write(&[CMD_WRITE_STATUS]), SpiOperation::Write(status)])
+    }
+
+    pub fn power_down(&mut self) -> RetValue<()>
+    {
+        self.check_awake()?;
+        self.slave.write(&[CMD_POWER_DOWN])?;
+        self.asleep = true;
+        Ok(())
+    }
+
+    /// Release the chip from the deep power down, it takes a few microseconds before the chip
+    /// accepts the next command, a tick is waited here.
+    pub fn wake_up(&mut self) -> RetValue<()>
+    {
+        self.slave.write(&[CMD_RELEASE_POWER_DOWN])?;
+        OS::delay(Duration::from_ticks(1));
+        self.asleep = false;
+        Ok(())
+    }
+
+    pub fn is_asleep(&self) -> bool
+    {
+        self.asleep
+    }
+}
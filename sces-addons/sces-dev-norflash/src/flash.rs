@@ -0,0 +1,555 @@
+use sces::mcu::flash::{FlashCtrl, FlashSector};
+use sces::mcu::spi::SpiSlave;
+use sces::os::mutex::MutexSample;
+use sces::os::tick::Duration;
+use sces::os::RTOS;
+use sces::value::{ErrValue, RetValue};
+
+use crate::chip::{NorChip, CMD_CHIP_ERASE, STATUS_PROTECT};
+use crate::sfdp::{JedecId, NorErase, NorGeometry};
+
+/// The time between the readings of the status when the chip is erasing.
+const NOR_ERASE_POLL: Duration = Duration::from_millis(1);
+
+/// The limit of a write of the status registers, it's 15 ms at most for W25Qxx.
+const NOR_STATUS_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// The limits of the operations, the chip is considered broken when it's still busy after them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NorTimeouts
+{
+    pub page_program: Duration,
+    pub sector_erase: Duration,
+    pub block_erase: Duration,
+    pub chip_erase: Duration,
+}
+
+impl NorTimeouts
+{
+    /// The limits which are longer than the maximum time of the W25Qxx and MX25xx chips.
+    pub const fn new() -> Self
+    {
+        Self {
+            page_program: Duration::from_millis(10),
+            sector_erase: Duration::from_millis(500),
+            block_erase: Duration::from_secs(3),
+            chip_erase: Duration::from_secs(400),
+        }
+    }
+}
+
+impl Default for NorTimeouts
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+/// A SPI NOR flash like W25Qxx and MX25xx on a shared SPI bus.
+///
+/// The chip is identified by its JEDEC ID and its geometry is read from SFDP when it's created,
+/// and the operations of the tasks are serialized, a program or an erase returns after the chip
+/// finishes it.
+///
+/// # Examples
+/// ```rust
+/// let slave = bus.slave(IoQueue::alloc(GPIOA, GPIO_Pin::P04)?, SpiConfig::new(SpiMode::Mode0, 20_000_000));
+/// let flash = SpiNorFlash::<MWOS>::new(slave)?;
+///
+/// flash.erase_sector_at(0x1000)?;
+/// flash.program(0x1000, b"Hello")?;
+/// ```
+pub struct SpiNorFlash<'a, OS: RTOS>
+{
+    chip: MutexSample<OS, NorChip<'a, OS>>,
+    id: JedecId,
+    geometry: NorGeometry,
+    timeouts: NorTimeouts,
+}
+
+impl<'a, OS: RTOS> SpiNorFlash<'a, OS>
+{
+    /// Identify the chip on `slave`, it fails with [`ErrValue::InstanceNotFound`] when no chip
+    /// answers.
+    ///
+    /// The chip is released from the deep power down at first, and it's switched into the four
+    /// bytes address mode when it's larger than 16 MiB.
+    pub fn new(slave: SpiSlave<'a, OS>) -> RetValue<Self>
+    {
+        let mut chip = NorChip::new(slave);
+        chip.wake_up()?;
+
+        let id = JedecId::new(chip.read_id()?);
+        if !id.is_valid()
+        {
+            return Err(ErrValue::InstanceNotFound);
+        }
+
+        let geometry = match NorGeometry::from_sfdp(|address, data| chip.read_sfdp(address, data))
+        {
+            Err(ErrValue::NotSupport) => NorGeometry::from_jedec(&id)?,
+            value => value?,
+        };
+
+        if geometry.address_4byte
+        {
+            chip.enter_4byte()?;
+        }
+
+        Ok(Self { chip: MutexSample::new(chip)?, id, geometry, timeouts: NorTimeouts::new() })
+    }
+
+    pub fn with_timeouts(mut self, timeouts: NorTimeouts) -> Self
+    {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Read by the fast read command with a dummy byte, which is the default one, or by the
+    /// normal read command, which is limited to a lower clock by most chips.
+    pub fn with_fast_read(self, fast_read: bool) -> Self
+    {
+        self.chip.lock().set_fast_read(fast_read);
+        self
+    }
+
+    pub fn id(&self) -> &JedecId
+    {
+        &self.id
+    }
+
+    pub fn geometry(&self) -> &NorGeometry
+    {
+        &self.geometry
+    }
+
+    pub fn read(&self, address: u32, data: &mut [u8]) -> RetValue<()>
+    {
+        self.check_range(address, data.len())?;
+        self.chip.lock().read(address, data)
+    }
+
+    /// Program the data, which is split at the boundaries of the pages.
+    ///
+    /// A program only clears the bits, so the area should be erased before.
+    pub fn program(&self, address: u32, data: &[u8]) -> RetValue<()>
+    {
+        self.check_range(address, data.len())?;
+
+        let chip = self.chip.lock();
+        let mut address = address;
+        let mut data = data;
+
+        while !data.is_empty()
+        {
+            let room = self.geometry.page_size - address % self.geometry.page_size;
+            let (page, rest) = data.split_at(data.len().min(room as usize));
+
+            chip.program_page(address, page)?;
+            chip.wait_ready(self.timeouts.page_program, Duration::ZERO)?;
+
+            address += page.len() as u32;
+            data = rest;
+        }
+
+        Ok(())
+    }
+
+    /// Erase the smallest erase unit, which is 4 KiB on almost all chips, at `address`.
+    ///
+    /// It takes the address in bytes, while [`FlashCtrl::erase_sector`] takes the number of the
+    /// sector.
+    pub fn erase_sector_at(&self, address: u32) -> RetValue<()>
+    {
+        self.erase_unit(&self.chip.lock(), self.geometry.sector(), address)
+    }
+
+    /// Erase the largest erase unit except the whole chip, which is 64 KiB on most chips, at
+    /// `address`.
+    pub fn erase_block(&self, address: u32) -> RetValue<()>
+    {
+        let block = self.geometry.erases().last().copied().ok_or(ErrValue::NotSupport)?;
+        self.erase_unit(&self.chip.lock(), block, address)
+    }
+
+    /// Erase `size` bytes from `address` with the largest units which fit, both of them must be
+    /// aligned to the smallest unit.
+    pub fn erase_range(&self, address: u32, size: u32) -> RetValue<()>
+    {
+        let sector = self.geometry.sector().size;
+        if !address.is_multiple_of(sector) || !size.is_multiple_of(sector)
+        {
+            return Err(ErrValue::Param);
+        }
+        self.check_range(address, size as usize)?;
+
+        let chip = self.chip.lock();
+        let end = address + size;
+        let mut address = address;
+
+        while address < end
+        {
+            let erase = self
+                .geometry
+                .erases()
+                .rev()
+                .find(|x| address.is_multiple_of(x.size) && end - address >= x.size)
+                .copied()
+                .ok_or(ErrValue::Param)?;
+
+            self.erase_unit(&chip, erase, address)?;
+            address += erase.size;
+        }
+
+        Ok(())
+    }
+
+    pub fn erase_chip(&self) -> RetValue<()>
+    {
+        let chip = self.chip.lock();
+        chip.erase(CMD_CHIP_ERASE, 0)?;
+        chip.wait_ready(self.timeouts.chip_erase, NOR_ERASE_POLL)
+    }
+
+    /// Read the status register 1.
+    pub fn status(&self) -> RetValue<u8>
+    {
+        self.chip.lock().read_status()
+    }
+
+    /// Write the status registers from the register 1, the layout after the bits of the block
+    /// protection depends on the chip.
+    ///
+    /// Some old W25Qxx chips clear the status register 2 when only the register 1 is written.
+    pub fn write_status(&self, status: &[u8]) -> RetValue<()>
+    {
+        let chip = self.chip.lock();
+        chip.write_status(status)?;
+        chip.wait_ready(NOR_STATUS_TIMEOUT, NOR_ERASE_POLL)
+    }
+
+    /// Protect the whole chip from the program and the erase by the block protection bits.
+    pub fn protect_all(&self) -> RetValue<()>
+    {
+        self.update_protection(STATUS_PROTECT)
+    }
+
+    pub fn unprotect_all(&self) -> RetValue<()>
+    {
+        self.update_protection(0)
+    }
+
+    /// Check whether any part of the chip is protected by the block protection bits.
+    pub fn is_protected(&self) -> RetValue<bool>
+    {
+        Ok(self.status()? & STATUS_PROTECT != 0)
+    }
+
+    /// Put the chip into the deep power down, all the operations fail with
+    /// [`ErrValue::NotAvailable`] until it's woken up.
+    pub fn power_down(&self) -> RetValue<()>
+    {
+        self.chip.lock().power_down()
+    }
+
+    pub fn wake_up(&self) -> RetValue<()>
+    {
+        self.chip.lock().wake_up()
+    }
+
+    pub fn is_asleep(&self) -> bool
+    {
+        self.chip.lock().is_asleep()
+    }
+
+    fn check_range(&self, address: u32, size: usize) -> RetValue<()>
+    {
+        match address as u64 + size as u64 <= self.geometry.size as u64
+        {
+            true => Ok(()),
+            false => Err(ErrValue::Param),
+        }
+    }
+
+    fn erase_unit(&self, chip: &NorChip<'a, OS>, erase: NorErase, address: u32) -> RetValue<()>
+    {
+        if erase.size == 0 || !address.is_multiple_of(erase.size)
+        {
+            return Err(ErrValue::Param);
+        }
+        self.check_range(address, erase.size as usize)?;
+
+        let timeout = match erase.size == self.geometry.sector().size
+        {
+            true => self.timeouts.sector_erase,
+            false => self.timeouts.block_erase,
+        };
+
+        chip.erase(erase.opcode, address)?;
+        chip.wait_ready(timeout, NOR_ERASE_POLL)
+    }
+
+    fn update_protection(&self, bits: u8) -> RetValue<()>
+    {
+        let chip = self.chip.lock();
+        let status = (chip.read_status()? & !STATUS_PROTECT) | bits;
+
+        chip.write_status(&[status])?;
+        chip.wait_ready(NOR_STATUS_TIMEOUT, NOR_ERASE_POLL)
+    }
+}
+
+unsafe impl<OS: RTOS> Send for SpiNorFlash<'_, OS> {}
+
+unsafe impl<OS: RTOS> Sync for SpiNorFlash<'_, OS> {}
+
+/// The sectors are the smallest erase units of the chip, and the addresses start from `0`.
+impl<OS: RTOS> FlashCtrl for SpiNorFlash<'_, OS>
+{
+    fn base(&self) -> u32
+    {
+        0
+    }
+
+    fn size(&self) -> u32
+    {
+        self.geometry.size
+    }
+
+    fn write_size(&self) -> u32
+    {
+        1
+    }
+
+    fn sector_count(&self) -> u32
+    {
+        self.geometry.size / self.geometry.sector().size
+    }
+
+    fn sector(&self, index: u32) -> RetValue<FlashSector>
+    {
+        let size = self.geometry.sector().size;
+
+        match index < self.sector_count()
+        {
+            true => Ok(FlashSector { index, bank: 0, address: index * size, size }),
+            false => Err(ErrValue::Param),
+        }
+    }
+
+    fn sector_at(&self, address: u32) -> RetValue<FlashSector>
+    {
+        self.sector(address / self.geometry.sector().size)
+    }
+
+    fn read(&self, address: u32, data: &mut [u8]) -> RetValue<()>
+    {
+        SpiNorFlash::read(self, address, data)
+    }
+
+    fn write(&self, address: u32, data: &[u8]) -> RetValue<()>
+    {
+        self.program(address, data)
+    }
+
+    fn erase_sector(&self, index: u32) -> RetValue<()>
+    {
+        self.erase_sector_at(self.sector(index)?.address)
+    }
+
+    /// The range is erased by the largest erase units which fit.
+    fn erase_range(&self, address: u32, size: u32) -> RetValue<()>
+    {
+        SpiNorFlash::erase_range(self, address, size)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::chip::*;
+    use crate::sim::{HostOs, NorSim};
+
+    fn mount(sim: &'static NorSim) -> SpiNorFlash<'static, HostOs>
+    {
+        SpiNorFlash::new(sim.slave()).unwrap()
+    }
+
+    fn pattern(size: usize) -> Vec<u8>
+    {
+        (0..size).map(|x| (x as u8).wrapping_mul(13).wrapping_add(7)).collect()
+    }
+
+    #[test]
+    fn identify_by_sfdp()
+    {
+        let sim = NorSim::w25q128();
+        let flash = mount(sim);
+
+        assert_eq!(*flash.id(), JedecId::new([0xEF, 0x40, 0x18]));
+        assert_eq!(flash.geometry().size, 0x100_0000);
+        assert_eq!(flash.sector_count(), 0x1000);
+        assert!(!flash.is_asleep());
+        assert_eq!(
+            sim.state().commands,
+            [CMD_RELEASE_POWER_DOWN, CMD_READ_ID, CMD_READ_SFDP, CMD_READ_SFDP]
+        );
+    }
+
+    #[test]
+    fn identify_by_jedec()
+    {
+        // A chip of 32 MiB without SFDP is switched into the four bytes address mode.
+        let sim = NorSim::new([0xC2, 0x20, 0x19], &[], 0x200_0000);
+        let flash = mount(sim);
+
+        assert_eq!(*flash.geometry(), NorGeometry::from_jedec(flash.id()).unwrap());
+        assert!(sim.state().address_4byte);
+        assert_eq!(sim.state().commands.last(), Some(&CMD_ENTER_4BYTE));
+
+        flash.program(0x180_0000, b"high").unwrap();
+        assert_eq!(&sim.state().memory[0x180_0000..0x180_0004], b"high");
+
+        let mut data = [0; 4];
+        flash.read(0x180_0000, &mut data).unwrap();
+        assert_eq!(&data, b"high");
+
+        let sim = NorSim::new([0xFF, 0xFF, 0xFF], &[], 0x1000);
+        assert!(matches!(SpiNorFlash::new(sim.slave()), Err(ErrValue::InstanceNotFound)));
+    }
+
+    #[test]
+    fn program_split_by_pages()
+    {
+        let sim = NorSim::w25q128();
+        let flash = mount(sim);
+        let data = pattern(600);
+
+        flash.program(0x1F0, &data).unwrap();
+        assert_eq!(sim.state().programs, [(0x1F0, 16), (0x200, 256), (0x300, 256), (0x400, 72)]);
+
+        let mut read = [0; 600];
+        flash.read(0x1F0, &mut read).unwrap();
+        assert_eq!(read[..], data[..]);
+
+        // A program at the end of the chip never goes past it.
+        assert!(matches!(flash.program(0xFF_FFFF, b"ab"), Err(ErrValue::Param)));
+        assert!(flash.with_fast_read(false).read(0x1F0, &mut read).is_ok());
+        assert_eq!(read[..], data[..]);
+    }
+
+    #[test]
+    fn erase_by_largest_units()
+    {
+        let sim = NorSim::w25q128();
+        let flash = mount(sim);
+
+        flash.erase_range(0x7000, 0x2_2000).unwrap();
+        assert_eq!(
+            sim.state().erases,
+            [(0x20, 0x7000), (0x52, 0x8000), (0xD8, 0x1_0000), (0x52, 0x2_0000), (0x20, 0x2_8000)]
+        );
+
+        // The sector is the number of the sector by the trait, and the address by the chip.
+        sim.state().erases.clear();
+        FlashCtrl::erase_sector(&flash, 3).unwrap();
+        flash.erase_sector_at(0x5000).unwrap();
+        flash.erase_block(0x3_0000).unwrap();
+        assert_eq!(sim.state().erases, [(0x20, 0x3000), (0x20, 0x5000), (0xD8, 0x3_0000)]);
+
+        assert!(matches!(flash.erase_range(0x7800, 0x1000), Err(ErrValue::Param)));
+        assert!(matches!(flash.erase_sector_at(0x5800), Err(ErrValue::Param)));
+        assert!(matches!(flash.erase_range(0xFF_F000, 0x2000), Err(ErrValue::Param)));
+    }
+
+    #[test]
+    fn erase_then_program()
+    {
+        let sim = NorSim::w25q128();
+        let flash = mount(sim);
+
+        flash.program(0x2000, b"old").unwrap();
+        FlashCtrl::erase_range(&flash, 0x2000, 0x1000).unwrap();
+        flash.write_verify(0x2000, b"new").unwrap();
+        assert_eq!(&sim.state().memory[0x2000..0x2004], b"new\xFF");
+    }
+
+    #[test]
+    fn busy_too_long()
+    {
+        let sim = NorSim::w25q128();
+        let flash = mount(sim);
+        sim.state().stuck = true;
+        assert!(matches!(flash.program(0x100, b"abc"), Err(ErrValue::Timeout)));
+
+        // The chip which is still busy ignores the write enable.
+        assert!(matches!(flash.program(0x100, b"abc"), Err(ErrValue::Permission)));
+
+        let sim = NorSim::w25q128();
+        let flash = mount(sim);
+        sim.state().stuck = true;
+        assert!(matches!(flash.erase_sector_at(0x1000), Err(ErrValue::Timeout)));
+    }
+
+    #[test]
+    fn write_enable_refused()
+    {
+        let sim = NorSim::w25q128();
+        let flash = mount(sim);
+        sim.state().write_protect = true;
+
+        assert!(matches!(flash.program(0x100, b"abc"), Err(ErrValue::Permission)));
+        assert!(matches!(flash.erase_sector_at(0x1000), Err(ErrValue::Permission)));
+        assert!(matches!(flash.protect_all(), Err(ErrValue::Permission)));
+
+        let state = sim.state();
+        assert!(state.programs.is_empty() && state.erases.is_empty());
+        assert!(!state.commands.contains(&CMD_PAGE_PROGRAM));
+        assert_eq!(state.memory[0x100], 0xFF);
+    }
+
+    #[test]
+    fn protection_bits()
+    {
+        let sim = NorSim::w25q128();
+        let flash = mount(sim);
+
+        flash.protect_all().unwrap();
+        assert!(flash.is_protected().unwrap());
+        assert_eq!(sim.state().status & STATUS_PROTECT, STATUS_PROTECT);
+
+        flash.unprotect_all().unwrap();
+        assert!(!flash.is_protected().unwrap());
+    }
+
+    #[test]
+    fn power_down()
+    {
+        let sim = NorSim::w25q128();
+        let flash = mount(sim);
+        let mut data = [0; 4];
+
+        flash.power_down().unwrap();
+        assert!(flash.is_asleep() && sim.state().asleep);
+        let sent = sim.state().commands.len();
+
+        assert!(matches!(flash.read(0, &mut data), Err(ErrValue::NotAvailable)));
+        assert!(matches!(flash.program(0, b"abc"), Err(ErrValue::NotAvailable)));
+        assert!(matches!(flash.erase_sector_at(0), Err(ErrValue::NotAvailable)));
+        assert!(matches!(flash.status(), Err(ErrValue::NotAvailable)));
+        assert!(matches!(flash.power_down(), Err(ErrValue::NotAvailable)));
+        assert_eq!(sim.state().commands.len(), sent);
+
+        flash.wake_up().unwrap();
+        assert!(!flash.is_asleep() && !sim.state().asleep);
+        flash.program(0, b"abc").unwrap();
+        flash.read(0, &mut data).unwrap();
+        assert_eq!(&data, b"abc\xFF");
+    }
+}
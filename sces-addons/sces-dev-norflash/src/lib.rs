@@ -0,0 +1,13 @@
+#![cfg_attr(not(test), no_std)]
+
+mod chip;
+mod flash;
+mod sfdp;
+#[cfg(test)]
+mod sim;
+
+pub use flash::NorTimeouts;
+pub use flash::SpiNorFlash;
+pub use sfdp::JedecId;
+pub use sfdp::NorErase;
+pub use sfdp::NorGeometry;
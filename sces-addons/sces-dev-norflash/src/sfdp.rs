@@ -0,0 +1,349 @@
+//! The identification and the geometry of a SPI NOR flash.
+//!
+//! The geometry is read from the basic parameter table of SFDP (JESD216) when the chip has it,
+//! or it's guessed from the capacity code of the JEDEC ID with the erase commands that almost all
+//! chips of W25Qxx and MX25xx support.
+
+use sces::value::{ErrValue, RetValue};
+
+/// The signature at the beginning of the SFDP area, `"SFDP"` in little endian.
+const SFDP_SIGNATURE: u32 = 0x5044_4653;
+
+/// The number of DWORDs of the basic parameter table which are used.
+const SFDP_BASIC_DWORDS: usize = 11;
+
+/// The largest flash which is addressed by three bytes.
+const NOR_3B_LIMIT: u32 = 0x100_0000;
+
+/// The manufacturer, the memory type and the capacity code which are read by the command `0x9F`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JedecId
+{
+    pub manufacturer: u8,
+    pub memory_type: u8,
+    pub capacity: u8,
+}
+
+impl JedecId
+{
+    pub const fn new(id: [u8; 3]) -> Self
+    {
+        Self { manufacturer: id[0], memory_type: id[1], capacity: id[2] }
+    }
+
+    /// Check whether a chip answers, the data line floats high or is pulled low without it.
+    pub const fn is_valid(&self) -> bool
+    {
+        !matches!(self.manufacturer, 0x00 | 0xFF)
+    }
+}
+
+/// An erase command with the size it erases, a size of `0` means the command doesn't exist.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NorErase
+{
+    pub size: u32,
+    pub opcode: u8,
+}
+
+impl NorErase
+{
+    pub const fn new(size: u32, opcode: u8) -> Self
+    {
+        Self { size, opcode }
+    }
+}
+
+/// The layout of a SPI NOR flash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NorGeometry
+{
+    /// The size of the whole chip in bytes.
+    pub size: u32,
+
+    /// A program never crosses the boundary of a page.
+    pub page_size: u32,
+
+    /// The erase commands sorted from the smallest to the largest, the unused ones are at the end
+    /// with the size `0`.
+    pub erases: [NorErase; 4],
+
+    /// Send the addresses in four bytes.
+    pub address_4byte: bool,
+}
+
+impl NorGeometry
+{
+    /// Guess the geometry from the capacity code, which is the power of two of the size in bytes.
+    pub fn from_jedec(id: &JedecId) -> RetValue<Self>
+    {
+        let size = 1u32.checked_shl(id.capacity as u32).ok_or(ErrValue::NotSupport)?;
+
+        Ok(Self {
+            size,
+            page_size: 256,
+            erases: [
+                NorErase::new(0x1000, 0x20),
+                NorErase::new(0x8000, 0x52),
+                NorErase::new(0x10000, 0xD8),
+                NorErase::default(),
+            ],
+            address_4byte: size > NOR_3B_LIMIT,
+        })
+    }
+
+    /// Read the geometry from SFDP, `read` reads the SFDP area at an address.
+    ///
+    /// It fails with [`ErrValue::NotSupport`] when the chip has no SFDP or it describes a layout
+    /// which is not supported, like a chip larger than 4 GiB.
+    pub fn from_sfdp<F>(mut read: F) -> RetValue<Self>
+    where
+        F: FnMut(u32, &mut [u8]) -> RetValue<()>,
+    {
+        let mut header = [0; 16];
+        read(0, &mut header)?;
+
+        // The first parameter header is always the basic one of JEDEC.
+        if u32::from_le_bytes([header[0], header[1], header[2], header[3]]) != SFDP_SIGNATURE
+            || header[8] != 0x00
+            || header[15] != 0xFF
+        {
+            return Err(ErrValue::NotSupport);
+        }
+
+        let length = (header[11] as usize).min(SFDP_BASIC_DWORDS);
+        let pointer = u32::from_le_bytes([header[12], header[13], header[14], 0]);
+
+        let mut table = [0; SFDP_BASIC_DWORDS * 4];
+        read(pointer, &mut table[..length * 4])?;
+
+        let mut dwords = [0u32; SFDP_BASIC_DWORDS];
+        dwords.iter_mut().zip(table.chunks_exact(4)).for_each(|(dword, x)| {
+            *dword = u32::from_le_bytes([x[0], x[1], x[2], x[3]]);
+        });
+
+        Self::from_basic_table(&dwords[..length])
+    }
+
+    /// Parse the DWORDs of the basic parameter table, whose first element is the DWORD 1.
+    pub fn from_basic_table(dwords: &[u32]) -> RetValue<Self>
+    {
+        // The JESD216 table has 9 DWORDs at least, the page size is added by JESD216A.
+        if dwords.len() < 9
+        {
+            return Err(ErrValue::NotSupport);
+        }
+
+        let density = dwords[1];
+        let bits = match density & 0x8000_0000
+        {
+            0 => density as u64 + 1,
+            _ => 1u64.checked_shl(density & 0x7FFF_FFFF).unwrap_or(0),
+        };
+        let size = u32::try_from(bits / 8).map_err(|_| ErrValue::NotSupport)?;
+        if size == 0
+        {
+            return Err(ErrValue::NotSupport);
+        }
+
+        let mut erases = [NorErase::default(); 4];
+        let types = [
+            dwords[7] as u16,
+            (dwords[7] >> 16) as u16,
+            dwords[8] as u16,
+            (dwords[8] >> 16) as u16,
+        ];
+        erases.iter_mut().zip(types).for_each(|(erase, x)| {
+            let shift = (x & 0xFF) as u32;
+            if shift != 0 && shift < 32
+            {
+                *erase = NorErase::new(1 << shift, (x >> 8) as u8);
+            }
+        });
+
+        // The old tables without the erase types still tell the 4 KiB erase command.
+        if erases.iter().all(|x| x.size == 0)
+        {
+            match dwords[0] & 0x3
+            {
+                0x1 => erases[0] = NorErase::new(0x1000, (dwords[0] >> 8) as u8),
+                _ => return Err(ErrValue::NotSupport),
+            }
+        }
+
+        erases.sort_unstable_by_key(|x| {
+            if x.size == 0
+            {
+                u32::MAX
+            }
+            else
+            {
+                x.size
+            }
+        });
+
+        let page_size = match dwords.get(10)
+        {
+            Some(x) => 1 << ((x >> 4) & 0xF),
+            None => 256,
+        };
+
+        let address_4byte = match (dwords[0] >> 17) & 0x3
+        {
+            0x0 if size > NOR_3B_LIMIT => return Err(ErrValue::NotSupport),
+            0x0 => false,
+            0x1 => size > NOR_3B_LIMIT,
+            0x2 => true,
+            _ => return Err(ErrValue::NotSupport),
+        };
+
+        Ok(Self { size, page_size, erases, address_4byte })
+    }
+
+    /// The smallest erase command.
+    pub fn sector(&self) -> NorErase
+    {
+        self.erases[0]
+    }
+
+    /// The erase commands which exist.
+    pub fn erases(&self) -> impl DoubleEndedIterator<Item = &NorErase>
+    {
+        self.erases.iter().filter(|x| x.size != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::sim::{MX25L12835F_SFDP, W25Q128JV_SFDP};
+
+    const ERASES_4K_32K_64K: [NorErase; 4] = [
+        NorErase::new(0x1000, 0x20),
+        NorErase::new(0x8000, 0x52),
+        NorErase::new(0x10000, 0xD8),
+        NorErase::new(0, 0),
+    ];
+
+    fn from_dump(dump: &[u8]) -> RetValue<NorGeometry>
+    {
+        NorGeometry::from_sfdp(|address, data| {
+            let at = address as usize;
+            data.copy_from_slice(dump.get(at..at + data.len()).ok_or(ErrValue::Param)?);
+            Ok(())
+        })
+    }
+
+    /// The DWORDs of the basic parameter table in a dump.
+    fn basic_table(dump: &[u8], pointer: usize, length: usize) -> [u32; 16]
+    {
+        let mut dwords = [0; 16];
+        dwords
+            .iter_mut()
+            .zip(dump[pointer..pointer + length * 4].chunks_exact(4))
+            .for_each(|(dword, x)| *dword = u32::from_le_bytes([x[0], x[1], x[2], x[3]]));
+        dwords
+    }
+
+    #[test]
+    fn sfdp_of_w25q128jv()
+    {
+        let geometry = from_dump(&W25Q128JV_SFDP).unwrap();
+
+        assert_eq!(geometry.size, 0x100_0000);
+        assert_eq!(geometry.page_size, 256);
+        assert_eq!(geometry.erases, ERASES_4K_32K_64K);
+        assert!(!geometry.address_4byte);
+        assert_eq!(geometry.sector(), NorErase::new(0x1000, 0x20));
+        assert_eq!(geometry.erases().count(), 3);
+    }
+
+    #[test]
+    fn sfdp_of_mx25l12835f()
+    {
+        // The table of JESD216 has no page size, the usual one is taken.
+        let geometry = from_dump(&MX25L12835F_SFDP).unwrap();
+
+        assert_eq!(geometry.size, 0x100_0000);
+        assert_eq!(geometry.page_size, 256);
+        assert_eq!(geometry.erases, ERASES_4K_32K_64K);
+        assert!(!geometry.address_4byte);
+    }
+
+    #[test]
+    fn sfdp_missing()
+    {
+        assert!(matches!(from_dump(&[0xFF; 64]), Err(ErrValue::NotSupport)));
+
+        // The first parameter header is not the basic one.
+        let mut dump = W25Q128JV_SFDP;
+        dump[8] = 0xEF;
+        assert!(matches!(from_dump(&dump), Err(ErrValue::NotSupport)));
+    }
+
+    #[test]
+    fn basic_table_with_4byte_address()
+    {
+        // The table of W25Q256JV is the one of W25Q128JV with 256 Mbit, which takes both address
+        // modes.
+        let mut dwords = basic_table(&W25Q128JV_SFDP, 0x80, 16);
+        dwords[0] = 0xFFF3_20E5;
+        dwords[1] = 0x0FFF_FFFF;
+
+        let geometry = NorGeometry::from_basic_table(&dwords).unwrap();
+        assert_eq!(geometry.size, 0x200_0000);
+        assert!(geometry.address_4byte);
+
+        // A chip of 3 bytes address only can't be larger than 16 MiB.
+        dwords[0] = 0xFFF1_20E5;
+        assert!(matches!(NorGeometry::from_basic_table(&dwords), Err(ErrValue::NotSupport)));
+
+        // The density over 2 Gbit is a power of two.
+        dwords[0] = 0xFFF5_20E5;
+        dwords[1] = 0x8000_0021;
+        let geometry = NorGeometry::from_basic_table(&dwords).unwrap();
+        assert_eq!(geometry.size, 0x4000_0000);
+        assert!(geometry.address_4byte);
+    }
+
+    #[test]
+    fn basic_table_without_erase_types()
+    {
+        let mut dwords = basic_table(&MX25L12835F_SFDP, 0x30, 9);
+        dwords[7] = 0;
+        dwords[8] = 0;
+
+        let geometry = NorGeometry::from_basic_table(&dwords[..9]).unwrap();
+        assert_eq!(
+            geometry.erases().copied().collect::<std::vec::Vec<_>>(),
+            [ERASES_4K_32K_64K[0]]
+        );
+
+        // Without the 4 KiB erase either, the chip can't be erased.
+        dwords[0] &= !0x3;
+        assert!(matches!(NorGeometry::from_basic_table(&dwords[..9]), Err(ErrValue::NotSupport)));
+        assert!(matches!(NorGeometry::from_basic_table(&dwords[..8]), Err(ErrValue::NotSupport)));
+    }
+
+    #[test]
+    fn geometry_from_jedec()
+    {
+        let geometry = NorGeometry::from_jedec(&JedecId::new([0xEF, 0x40, 0x18])).unwrap();
+        assert_eq!(geometry.size, 0x100_0000);
+        assert_eq!(geometry.page_size, 256);
+        assert_eq!(geometry.erases, ERASES_4K_32K_64K);
+        assert!(!geometry.address_4byte);
+
+        let geometry = NorGeometry::from_jedec(&JedecId::new([0xC2, 0x20, 0x19])).unwrap();
+        assert_eq!(geometry.size, 0x200_0000);
+        assert!(geometry.address_4byte);
+
+        assert!(matches!(
+            NorGeometry::from_jedec(&JedecId::new([0xEF, 0x40, 0x20])),
+            Err(ErrValue::NotSupport)
+        ));
+        assert!(!JedecId::new([0xFF, 0xFF, 0xFF]).is_valid());
+        assert!(!JedecId::new([0x00, 0x00, 0x00]).is_valid());
+    }
+}
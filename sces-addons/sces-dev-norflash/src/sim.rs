@@ -0,0 +1,633 @@
+//! A SPI NOR flash in RAM behind [`SpiCtrl`] for the tests, and an RTOS on the host whose clock
+//! moves a tick on every reading, so a busy loop with a timeout always ends.
+//!
+//! The chip answers the JEDEC ID, SFDP, the reads, the page program, the erases, the status
+//! registers and the deep power down. A page program wraps at the end of the page like a real
+//! chip, and the programs, the erases and the opcodes of all commands are logged to be checked.
+
+extern crate std;
+
+use core::cell::{Cell, RefCell, RefMut};
+use std::boxed::Box;
+use std::vec;
+use std::vec::Vec;
+
+use sces::mcu::io::{IoCtrl, IoCtrlEvent, IoState};
+use sces::mcu::spi::{SpiBus, SpiConfig, SpiCtrl, SpiCtrlEvent, SpiMode, SpiSlave};
+use sces::mcu::EventLaunch;
+use sces::os::events::IEvents;
+use sces::os::mem::IMemPool;
+use sces::os::message_queue::{IMessageQueue, MessageContent};
+use sces::os::mutex::IMutex;
+use sces::os::semaphore::ISemaphore;
+use sces::os::task::{ITask, ITaskMain, TaskPriority, TaskState};
+use sces::os::tick::{Duration, Instant};
+use sces::os::timer::{ITimer, ITimerEvent, TimerMode, TimerState};
+use sces::os::{OSState, RTOS};
+use sces::value::{ErrValue, RetValue};
+
+use crate::chip::*;
+
+/// The SFDP of W25Q128JV up to the end of its basic parameter table (JESD216B, 16 DWORDs).
+#[rustfmt::skip]
+pub const W25Q128JV_SFDP: [u8; 0xC0] = [
+    0x53, 0x46, 0x44, 0x50, 0x05, 0x01, 0x00, 0xFF, 0x00, 0x05, 0x01, 0x10, 0x80, 0x00, 0x00, 0xFF,
+    0x84, 0x00, 0x01, 0x02, 0xD0, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xE5, 0x20, 0xF9, 0xFF, 0xFF, 0xFF, 0xFF, 0x07, 0x44, 0xEB, 0x08, 0x6B, 0x08, 0x3B, 0x42, 0xBB,
+    0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0x40, 0xEB, 0x0C, 0x20, 0x0F, 0x52,
+    0x10, 0xD8, 0x00, 0x00, 0x36, 0x02, 0xA6, 0x00, 0x82, 0xEA, 0x14, 0xC9, 0xE9, 0x63, 0x76, 0x33,
+    0x7A, 0x75, 0x7A, 0x75, 0xF7, 0xA2, 0xD5, 0x5C, 0x19, 0xF7, 0x4D, 0xFF, 0xE9, 0x30, 0xF8, 0x80,
+];
+
+/// The SFDP of MX25L12835F up to the end of its basic parameter table (JESD216, 9 DWORDs).
+#[rustfmt::skip]
+pub const MX25L12835F_SFDP: [u8; 0x54] = [
+    0x53, 0x46, 0x44, 0x50, 0x00, 0x01, 0x01, 0xFF, 0x00, 0x00, 0x01, 0x09, 0x30, 0x00, 0x00, 0xFF,
+    0xC2, 0x00, 0x01, 0x04, 0x60, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xE5, 0x20, 0xF1, 0xFF, 0xFF, 0xFF, 0xFF, 0x07, 0x44, 0xEB, 0x08, 0x6B, 0x08, 0x3B, 0x04, 0xBB,
+    0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0xFF, 0xFF, 0xFF, 0x44, 0xEB, 0x0C, 0x20, 0x0F, 0x52,
+    0x10, 0xD8, 0x00, 0xFF,
+];
+
+/// The erase commands of the chip, like the ones of W25Qxx and MX25xx.
+const SIM_ERASES: [(u8, u32); 3] = [(0x20, 0x1000), (0x52, 0x8000), (0xD8, 0x10000)];
+
+/// The readings of the status which are busy after a program or an erase.
+const SIM_BUSY_READS: u32 = 2;
+
+std::thread_local! {
+    static TICKS: Cell<u32> = const { Cell::new(0) };
+}
+
+/// An RTOS with a single task on the host.
+pub struct HostOs;
+
+/// The objects of [`HostOs`], only the mutex works, as nothing runs at the same time.
+pub struct HostObject;
+
+impl RTOS for HostOs
+{
+    type Events = HostObject;
+    type MemPool = HostObject;
+    type MessageQueue = HostObject;
+    type Mutex = HostObject;
+    type Semaphore = HostObject;
+    type Task = HostObject;
+    type Timer = HostObject;
+
+    fn initialize() -> RetValue<()>
+    {
+        Ok(())
+    }
+
+    fn state() -> OSState
+    {
+        OSState::Running
+    }
+
+    fn ticks() -> u32
+    {
+        TICKS.with(|x| {
+            x.set(x.get().wrapping_add(1));
+            x.get()
+        })
+    }
+
+    fn task_count() -> u32
+    {
+        1
+    }
+
+    fn current_task() -> HostObject
+    {
+        HostObject
+    }
+
+    fn is_in_isr() -> bool
+    {
+        false
+    }
+
+    fn switch_next_task() {}
+
+    fn exit_current_task() {}
+
+    fn delay(time: Duration)
+    {
+        TICKS.with(|x| x.set(x.get().wrapping_add(time.ticks())));
+    }
+
+    fn delay_interval(time: Instant)
+    {
+        TICKS.with(|x| x.set(x.get().max(time.ticks())));
+    }
+}
+
+impl IMutex for HostObject
+{
+    fn new() -> RetValue<Self>
+    {
+        Ok(Self)
+    }
+
+    fn lock(&self) {}
+
+    fn attempt_lock(&self, _time: Duration) -> RetValue<()>
+    {
+        Ok(())
+    }
+
+    fn unlock(&self) {}
+}
+
+impl IEvents for HostObject
+{
+    fn new() -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn put(&self, _events: u32) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn wait(&self, _events: u32, _timeout: Duration) -> RetValue<u32>
+    {
+        Err(ErrValue::NotSupport)
+    }
+}
+
+impl IMemPool for HostObject
+{
+    fn new(
+        _name: &str, _buf: &'static mut [u8], _block_size: u32, _max_block_count: u32,
+    ) -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn name(&self) -> &str
+    {
+        ""
+    }
+
+    fn block_size(&self) -> u32
+    {
+        0
+    }
+
+    fn block_count(&self) -> u32
+    {
+        0
+    }
+
+    fn max_block_count(&self) -> u32
+    {
+        0
+    }
+
+    fn alloc(&self) -> *mut u8
+    {
+        core::ptr::null_mut()
+    }
+
+    fn free(&self, _mem: *mut u8) {}
+}
+
+impl IMessageQueue for HostObject
+{
+    fn new(_message_size: u32, _message_count: u32) -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn send(&self, _content: &dyn MessageContent, _timeout: Duration) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn receive(&self, _cache: &mut dyn MessageContent, _timeout: Duration) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+}
+
+impl ISemaphore for HostObject
+{
+    fn new(_max_count: u32) -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn take(&self) {}
+
+    fn attempt_take(&self, _timeout: Duration) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn release(&self) {}
+}
+
+impl ITask for HostObject
+{
+    fn new() -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn active(
+        &mut self, _name: &str, _stack: u32, _priority: TaskPriority, _main: &dyn ITaskMain,
+    ) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn name(&self) -> &str
+    {
+        "host"
+    }
+
+    fn stack_size(&self) -> u32
+    {
+        0
+    }
+
+    fn priority(&self) -> TaskPriority
+    {
+        TaskPriority::Normal
+    }
+
+    fn state(&self) -> TaskState
+    {
+        TaskState::Running
+    }
+
+    fn set_priority(&mut self, _priority: TaskPriority) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn suspend(&self) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn resume(&self) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn terminate(&mut self) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+}
+
+impl ITimer for HostObject
+{
+    fn new(_mode: TimerMode) -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn mode(&self) -> TimerMode
+    {
+        TimerMode::Once
+    }
+
+    fn state(&self) -> TimerState
+    {
+        TimerState::Idle
+    }
+
+    fn active(&mut self, _times: Duration, _event: &dyn ITimerEvent) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn terminate(&mut self) {}
+}
+
+/// The state of the chip in RAM.
+pub struct NorState
+{
+    pub id: [u8; 3],
+
+    /// The SFDP area, the bytes after it read as `0xFF` like a chip without SFDP.
+    pub sfdp: Vec<u8>,
+    pub memory: Vec<u8>,
+    pub page_size: u32,
+    pub status: u8,
+    pub address_4byte: bool,
+    pub asleep: bool,
+
+    /// The pin WP# is low and the status register is protected, so the write enable is ignored.
+    pub write_protect: bool,
+
+    /// The chip never finishes a program or an erase, and it ignores the commands after it.
+    pub stuck: bool,
+    busy: u32,
+
+    /// The address and the size of every page program.
+    pub programs: Vec<(u32, usize)>,
+
+    /// The opcode and the address of every erase.
+    pub erases: Vec<(u8, u32)>,
+
+    /// The opcode of every command.
+    pub commands: Vec<u8>,
+
+    selected: bool,
+    frame: Vec<u8>,
+    received: usize,
+}
+
+/// A SPI NOR flash in RAM, which is the chip select of its slave.
+pub struct NorSim
+{
+    state: RefCell<NorState>,
+    level: Cell<IoState>,
+}
+
+/// The SPI bus of a [`NorSim`].
+struct NorSimSpi(&'static NorSim);
+
+impl NorSim
+{
+    pub fn new(id: [u8; 3], sfdp: &[u8], size: u32) -> &'static Self
+    {
+        Box::leak(Box::new(Self {
+            state: RefCell::new(NorState {
+                id,
+                sfdp: sfdp.to_vec(),
+                memory: vec![0xFF; size as usize],
+                page_size: 256,
+                status: 0,
+                address_4byte: false,
+                asleep: true,
+                write_protect: false,
+                stuck: false,
+                busy: 0,
+                programs: Vec::new(),
+                erases: Vec::new(),
+                commands: Vec::new(),
+                selected: false,
+                frame: Vec::new(),
+                received: 0,
+            }),
+            level: Cell::new(IoState::Set),
+        }))
+    }
+
+    /// A W25Q128JV of 16 MiB.
+    pub fn w25q128() -> &'static Self
+    {
+        Self::new([0xEF, 0x40, 0x18], &W25Q128JV_SFDP, 0x100_0000)
+    }
+
+    pub fn state(&self) -> RefMut<'_, NorState>
+    {
+        self.state.borrow_mut()
+    }
+
+    /// Create a slave of the chip on a bus of its own.
+    pub fn slave(&'static self) -> SpiSlave<'static, HostOs>
+    {
+        let spi = Box::leak(Box::new(NorSimSpi(self)));
+        let bus = Box::leak(Box::new(SpiBus::new(spi, Duration::from_millis(10)).unwrap()));
+
+        bus.slave(self, SpiConfig::new(SpiMode::Mode0, 20_000_000))
+    }
+}
+
+impl NorState
+{
+    /// The address after the opcode, and the bytes after the address.
+    fn address(&self) -> (u32, &[u8])
+    {
+        let size = if self.address_4byte { 4 } else { 3 };
+        let bytes = self.frame.get(1..1 + size).unwrap_or(&[]);
+
+        (bytes.iter().fold(0, |x, y| x << 8 | *y as u32), self.frame.get(1 + size..).unwrap_or(&[]))
+    }
+
+    fn read_byte(&mut self) -> u8
+    {
+        let at = self.received;
+        self.received += 1;
+
+        if self.asleep
+        {
+            return 0xFF;
+        }
+
+        match self.frame[0]
+        {
+            CMD_READ_ID => self.id.get(at).copied().unwrap_or(0xFF),
+            CMD_READ_STATUS => match self.busy > 0
+            {
+                true =>
+                {
+                    self.busy = self.busy.saturating_sub(1);
+                    self.status | STATUS_BUSY
+                }
+                false => self.status,
+            },
+            CMD_READ_SFDP =>
+            {
+                let address = u32::from_be_bytes([0, self.frame[1], self.frame[2], self.frame[3]]);
+                self.sfdp.get(address as usize + at).copied().unwrap_or(0xFF)
+            }
+            CMD_READ | CMD_FAST_READ =>
+            {
+                let (address, _) = self.address();
+                self.memory[(address as usize + at) % self.memory.len()]
+            }
+            _ => 0xFF,
+        }
+    }
+
+    /// Run the command when the chip select goes inactive.
+    fn execute(&mut self)
+    {
+        let Some(opcode) = self.frame.first().copied()
+        else
+        {
+            return;
+        };
+        self.commands.push(opcode);
+
+        if self.asleep
+        {
+            self.asleep = opcode != CMD_RELEASE_POWER_DOWN;
+            return;
+        }
+
+        let busy = self.busy > 0;
+        let enabled = self.status & STATUS_WEL != 0 && !busy;
+
+        match opcode
+        {
+            CMD_WRITE_ENABLE if !self.write_protect && !busy => self.status |= STATUS_WEL,
+            CMD_PAGE_PROGRAM if enabled =>
+            {
+                let (address, data) = self.address();
+                let (page, offset) = (address - address % self.page_size, address % self.page_size);
+                let data = data.to_vec();
+
+                for (x, byte) in data.iter().enumerate()
+                {
+                    let at = page + (offset + x as u32) % self.page_size;
+                    self.memory[at as usize] &= byte;
+                }
+
+                self.programs.push((address, data.len()));
+                self.finish();
+            }
+            CMD_CHIP_ERASE if enabled =>
+            {
+                self.memory.fill(0xFF);
+                self.erases.push((opcode, 0));
+                self.finish();
+            }
+            CMD_WRITE_STATUS if enabled =>
+            {
+                self.status = self.status & (STATUS_BUSY | STATUS_WEL)
+                    | self.frame.get(1).unwrap_or(&0) & 0xFC;
+                self.finish();
+            }
+            CMD_ENTER_4BYTE => self.address_4byte = true,
+            CMD_POWER_DOWN => self.asleep = true,
+            _ if enabled =>
+            {
+                if let Some((_, size)) = SIM_ERASES.iter().find(|x| x.0 == opcode)
+                {
+                    let (address, _) = self.address();
+                    let start = (address - address % size) as usize;
+
+                    self.memory[start..start + *size as usize].fill(0xFF);
+                    self.erases.push((opcode, address));
+                    self.finish();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn finish(&mut self)
+    {
+        self.status &= !STATUS_WEL;
+        self.busy = if self.stuck { u32::MAX } else { SIM_BUSY_READS };
+    }
+}
+
+impl EventLaunch<dyn IoCtrlEvent> for NorSim
+{
+    fn set_event_agent(&mut self, _event_handle: &'static dyn IoCtrlEvent) {}
+
+    fn clean_event_agent(&mut self) {}
+}
+
+impl IoCtrl for NorSim
+{
+    fn state(&self) -> IoState
+    {
+        self.level.get()
+    }
+
+    fn set_state(&self, state: IoState)
+    {
+        self.level.set(state);
+        let mut chip = self.state.borrow_mut();
+
+        match (state, chip.selected)
+        {
+            (IoState::Reset, false) =>
+            {
+                chip.selected = true;
+                chip.frame.clear();
+                chip.received = 0;
+            }
+            (IoState::Set, true) =>
+            {
+                chip.selected = false;
+                chip.execute();
+            }
+            _ => (),
+        }
+    }
+
+    fn toggle(&self)
+    {
+        match self.level.get()
+        {
+            IoState::Set => self.set_state(IoState::Reset),
+            IoState::Reset => self.set_state(IoState::Set),
+        }
+    }
+}
+
+impl EventLaunch<dyn SpiCtrlEvent> for NorSimSpi
+{
+    fn set_event_agent(&mut self, _event_handle: &'static dyn SpiCtrlEvent) {}
+
+    fn clean_event_agent(&mut self) {}
+}
+
+impl SpiCtrl for NorSimSpi
+{
+    fn transmit(&self, data: &[u8], _timeout: Duration) -> RetValue<()>
+    {
+        self.0.state().frame.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn receive(&self, data: &mut [u8], _timeout: Duration) -> RetValue<()>
+    {
+        let mut chip = self.0.state();
+        data.iter_mut().for_each(|x| *x = chip.read_byte());
+        Ok(())
+    }
+
+    fn transmit_receive(
+        &self, tx_data: &[u8], rx_data: &mut [u8], timeout: Duration,
+    ) -> RetValue<()>
+    {
+        self.transmit(tx_data, timeout)?;
+        self.receive(rx_data, timeout)
+    }
+
+    fn async_transmit(&self, _data: &[u8]) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn async_receive(&self, _data: &mut [u8]) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn async_transmit_receive(&self, _tx_data: &[u8], _rx_data: &mut [u8]) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn abort(&self) -> RetValue<()>
+    {
+        Ok(())
+    }
+
+    fn configure(&mut self, _config: &SpiConfig) -> RetValue<()>
+    {
+        Ok(())
+    }
+}
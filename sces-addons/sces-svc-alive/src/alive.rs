@@ -1,34 +1,236 @@
-use core::ops::Deref;
-
+use log::warn;
+use sces::os::task::ITaskRestart;
+use sces::os::tick::Duration;
 use sces::value::RetValue;
 
+use crate::svc::AWS;
+
+/// The handle of a watched task.
+///
+/// It's a plain value which is copied to wherever the task reports alive, and it's rejected with
+/// [`ErrValue::InstanceNotFound`](sces::value::ErrValue::InstanceNotFound) after the task is
+/// unwatched, even if its slot is reused by another task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AliveWatchHandle
 {
     num: usize,
+    serial: u32,
 }
 
 impl AliveWatchHandle
 {
-    pub(crate) const fn new(num: usize) -> Self
+    pub(crate) const fn new(num: usize, serial: u32) -> Self
+    {
+        Self { num, serial }
+    }
+
+    pub(crate) const fn num(&self) -> usize
+    {
+        self.num
+    }
+
+    pub(crate) const fn serial(&self) -> u32
+    {
+        self.serial
+    }
+}
+
+/// The deadline of a watched task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AliveDeadline
+{
+    timeout: Duration,
+    window: Duration,
+    grace: Duration,
+}
+
+impl AliveDeadline
+{
+    /// The task must report alive within `timeout` since the last report.
+    pub const fn new(timeout: Duration) -> Self
+    {
+        Self { timeout, window: Duration::ZERO, grace: Duration::ZERO }
+    }
+
+    /// The task must not report alive within `window` since the last report, a too early report
+    /// means the task runs out of control like the timeout does.
+    pub const fn with_window(mut self, window: Duration) -> Self
+    {
+        self.window = window;
+        self
+    }
+
+    /// Give the task `grace` more time for its first report after it's watched or watched back,
+    /// like for a slow initialization.
+    pub const fn with_grace(mut self, grace: Duration) -> Self
+    {
+        self.grace = grace;
+        self
+    }
+
+    pub const fn timeout(&self) -> Duration
+    {
+        self.timeout
+    }
+
+    pub const fn window(&self) -> Duration
+    {
+        self.window
+    }
+
+    pub const fn grace(&self) -> Duration
     {
-        Self { num }
+        self.grace
     }
 }
 
-impl Deref for AliveWatchHandle
+/// How to escalate when a task misses its deadline.
+///
+/// The late task is notified by [`AliveWatchEvent::on_alive_late`] at first, and it's restarted
+/// if it's still late after `notify_cycles` check cycles and it's set by
+/// [`AliveWatch::set_restart`]. The watch dog is not refreshed anymore when the task can't be
+/// restarted or it has been restarted for `max_restarts` times, then the hardware resets the
+/// system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AliveEscalation
 {
-    type Target = usize;
+    notify_cycles: u32,
+    max_restarts: u32,
+}
+
+impl AliveEscalation
+{
+    /// Wait one check cycle after the notification, and never restart the tasks.
+    pub const fn new() -> Self
+    {
+        Self { notify_cycles: 1, max_restarts: 0 }
+    }
+
+    /// Wait `notify_cycles` check cycles after the notification before the next step, at least
+    /// one cycle.
+    pub const fn with_notify_cycles(mut self, notify_cycles: u32) -> Self
+    {
+        self.notify_cycles = if notify_cycles > 0 { notify_cycles } else { 1 };
+        self
+    }
+
+    /// Restart a late task `max_restarts` times at most before the watch dog expires, the count
+    /// is cleared when the task reports alive on time.
+    pub const fn with_max_restarts(mut self, max_restarts: u32) -> Self
+    {
+        self.max_restarts = max_restarts;
+        self
+    }
+
+    pub const fn notify_cycles(&self) -> u32
+    {
+        self.notify_cycles
+    }
 
-    fn deref(&self) -> &Self::Target
+    pub const fn max_restarts(&self) -> u32
+    {
+        self.max_restarts
+    }
+}
+
+impl Default for AliveEscalation
+{
+    fn default() -> Self
     {
-        &self.num
+        Self::new()
     }
 }
 
+/// The events of the escalation when a task misses its deadline, they are called in the task of
+/// the alive watch.
+pub trait AliveWatchEvent
+{
+    /// The task `name` has not reported alive for `past`, or it reported too early.
+    fn on_alive_late(&self, _name: &str, _past: Duration) {}
+
+    /// The task `name` has been restarted.
+    fn on_alive_restart(&self, _name: &str) {}
+
+    /// The watch dog will not be refreshed anymore because of the task `name`.
+    fn on_alive_expire(&self, _name: &str) {}
+}
+
 pub trait AliveWatch: Send + Sync
 {
-    fn watch(&self, name: &'static str) -> RetValue<AliveWatchHandle>;
+    /// Start to watch a task with its own deadline.
+    fn watch(&self, name: &'static str, deadline: AliveDeadline) -> RetValue<AliveWatchHandle>;
+
+    /// Stop to watch the task for good, the handle is invalid since then.
+    fn unwatch(&self, handle: AliveWatchHandle) -> RetValue<()>;
+
+    /// Watch the task again after [`AliveWatch::stop_watch`], the deadline restarts from now.
     fn watch_back(&self, handle: AliveWatchHandle) -> RetValue<()>;
+
+    /// Stop to watch the task, like before it waits for something for a long time.
     fn stop_watch(&self, handle: AliveWatchHandle) -> RetValue<()>;
-    fn update_alive_state(&self, handle: AliveWatchHandle);
+
+    /// Let the task be restarted by `task` when it's late, see [`AliveEscalation`].
+    fn set_restart(
+        &self, handle: AliveWatchHandle, task: &'static dyn ITaskRestart,
+    ) -> RetValue<()>;
+
+    /// Call `f` with the name and the time since the last report of every task which misses its
+    /// deadline now.
+    fn late_tasks(&self, f: &mut dyn FnMut(&str, Duration)) -> RetValue<()>;
+
+    /// Report the task is alive.
+    ///
+    /// It doesn't wait for any lock, so it could be called in an interrupt handler or in a loop
+    /// with a tight period.
+    fn update_alive_state(&self, handle: AliveWatchHandle) -> RetValue<()>;
+}
+
+/// Watch a task as long as the guard lives, the task is unwatched when the guard is dropped.
+///
+/// # Examples
+/// ```rust
+/// let guard = AliveWatchService::guard("Sampler", AliveDeadline::new(Duration::from_millis(50)))?;
+///
+/// loop
+/// {
+///     sample();
+///     guard.report()?;
+/// }
+/// ```
+pub struct AliveWatchGuard<'a>
+{
+    watch: &'a dyn AliveWatch,
+    handle: AliveWatchHandle,
+}
+
+impl<'a> AliveWatchGuard<'a>
+{
+    pub fn new(
+        watch: &'a dyn AliveWatch, name: &'static str, deadline: AliveDeadline,
+    ) -> RetValue<Self>
+    {
+        Ok(Self { watch, handle: watch.watch(name, deadline)? })
+    }
+
+    pub fn handle(&self) -> AliveWatchHandle
+    {
+        self.handle
+    }
+
+    /// Report the task is alive, see [`AliveWatch::update_alive_state`].
+    pub fn report(&self) -> RetValue<()>
+    {
+        self.watch.update_alive_state(self.handle)
+    }
+}
+
+impl<'a> Drop for AliveWatchGuard<'a>
+{
+    fn drop(&mut self)
+    {
+        #[allow(unused_must_use)]
+        self.watch
+            .unwatch(self.handle)
+            .inspect_err(|x| warn!("{AWS} Failed to unwatch {:?}: {x:?}.", self.handle));
+    }
 }
@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
@@ -6,7 +6,11 @@ mod alive;
 mod native;
 mod svc;
 
+pub use alive::AliveDeadline;
+pub use alive::AliveEscalation;
 pub use alive::AliveWatch;
+pub use alive::AliveWatchEvent;
+pub use alive::AliveWatchGuard;
 pub use alive::AliveWatchHandle;
 pub use native::NativeAliveWatch;
 pub use svc::AliveWatchService;
@@ -1,96 +1,228 @@
 mod queue;
+mod report;
 mod status;
 
 use core::marker::PhantomData;
 
-use log::error;
-use sces::value::RetValue;
+use log::{error, warn};
+use sces::retain::{retained, ResetReason};
+use sces::value::{ErrValue, RetValue};
 use sces::mcu::wd::WatchDogDevice;
+use sces::mcu::EventLaunch;
 use sces::os::mutex::MutexSample;
-use sces::os::task::ITaskMain;
+use sces::os::task::{ITaskMain, ITaskRestart};
+use sces::os::tick::Duration;
 use sces::os::RTOS;
 
-use crate::alive::{AliveWatch, AliveWatchHandle};
+use crate::alive::{AliveDeadline, AliveEscalation, AliveWatch, AliveWatchEvent, AliveWatchHandle};
 use crate::native::queue::AliveWatchQueue;
+use crate::native::report::AliveReport;
+use crate::native::status::AliveStep;
 use crate::svc::AWS;
 
-pub struct NativeAliveWatch<'a, OS>
+/// The alive watch which watches `N` tasks at most.
+pub struct NativeAliveWatch<'a, OS, const N: usize = 16>
 where
     OS: RTOS,
 {
     device: WatchDogDevice,
-    cycle_time: u32,
+    cycle_time: Duration,
+    escalation: AliveEscalation,
     watch_queue: MutexSample<OS, AliveWatchQueue<'a>>,
+    reports: [AliveReport; N],
+    event_handle: Option<&'static dyn AliveWatchEvent>,
     _marker: PhantomData<OS>,
 }
 
-impl<'a, OS> NativeAliveWatch<'a, OS>
+impl<'a, OS, const N: usize> NativeAliveWatch<'a, OS, N>
 where
     OS: RTOS,
 {
-    pub fn new(device: WatchDogDevice, cycle_time: u32) -> RetValue<Self>
+    /// Check the deadlines of all watched tasks and refresh the watch dog every `cycle_time`.
+    ///
+    /// The `cycle_time` should be shorter than the timeout of the watch dog, and the deadlines
+    /// are checked with the precision of it.
+    pub fn new(device: WatchDogDevice, cycle_time: Duration) -> RetValue<Self>
     {
         Ok(Self {
             device,
             cycle_time,
+            escalation: AliveEscalation::new(),
             watch_queue: MutexSample::new(AliveWatchQueue::new()?)?,
+            reports: [const { AliveReport::new() }; N],
+            event_handle: None,
             _marker: PhantomData,
         })
     }
+
+    /// Set how to escalate when a task misses its deadline, the default one only notifies the
+    /// late task, then lets the watch dog expire.
+    pub fn with_escalation(mut self, escalation: AliveEscalation) -> Self
+    {
+        self.escalation = escalation;
+        self
+    }
+
+    /// Take the step of the escalation for the task `name`, and return whether the watch dog
+    /// should expire.
+    fn take_step(&self, name: &str, step: AliveStep) -> bool
+    {
+        match step
+        {
+            AliveStep::Alive | AliveStep::Pending => false,
+            AliveStep::Late(past) =>
+            {
+                warn!("{AWS} {name} is late over {} ms.", past.as_millis());
+                self.event_handle.inspect(|x| x.on_alive_late(name, past));
+                false
+            }
+            AliveStep::Restart(task) =>
+            {
+                warn!("{AWS} {name} is still late, restart it.");
+
+                #[allow(unused_must_use)]
+                task.restart().inspect_err(|x| error!("{AWS} Failed to restart {name}: {x:?}."));
+                self.event_handle.inspect(|x| x.on_alive_restart(name));
+                false
+            }
+            AliveStep::Expire =>
+            {
+                error!("{AWS} {name} near death, don't refresh Watch Dog.");
+
+                if let Some(log) = retained()
+                {
+                    log.write(format_args!("{AWS} {name} near death, let Watch Dog expire."));
+                    log.mark_reset(ResetReason::WatchDog);
+                }
+
+                self.event_handle.inspect(|x| x.on_alive_expire(name));
+                true
+            }
+        }
+    }
+}
+
+impl<'a, OS, const N: usize> EventLaunch<dyn AliveWatchEvent> for NativeAliveWatch<'a, OS, N>
+where
+    OS: RTOS,
+{
+    fn set_event_agent(&mut self, event_handle: &'static dyn AliveWatchEvent)
+    {
+        self.event_handle = Some(event_handle);
+    }
+
+    fn clean_event_agent(&mut self)
+    {
+        self.event_handle = None;
+    }
 }
 
-unsafe impl<'a, OS> Send for NativeAliveWatch<'a, OS> where OS: RTOS {}
+unsafe impl<'a, OS, const N: usize> Send for NativeAliveWatch<'a, OS, N> where OS: RTOS {}
 
-unsafe impl<'a, OS> Sync for NativeAliveWatch<'a, OS> where OS: RTOS {}
+unsafe impl<'a, OS, const N: usize> Sync for NativeAliveWatch<'a, OS, N> where OS: RTOS {}
 
-impl<'a, OS> AliveWatch for NativeAliveWatch<'a, OS>
+impl<'a, OS, const N: usize> AliveWatch for NativeAliveWatch<'a, OS, N>
 where
     OS: RTOS,
 {
-    fn watch(&self, name: &'static str) -> RetValue<AliveWatchHandle>
+    fn watch(&self, name: &'static str, deadline: AliveDeadline) -> RetValue<AliveWatchHandle>
+    {
+        self.watch_queue
+            .attempt_lock_then(|x| x.attempt_push(name, deadline, OS::now(), &self.reports))
+    }
+
+    fn unwatch(&self, handle: AliveWatchHandle) -> RetValue<()>
     {
-        Ok(AliveWatchHandle::new(
-            self.watch_queue.attempt_lock_then(|x| x.attempt_push::<OS>(name))?,
-        ))
+        self.watch_queue.attempt_lock_then(|x| x.remove(handle, &self.reports))
     }
 
     fn watch_back(&self, handle: AliveWatchHandle) -> RetValue<()>
     {
-        self.watch_queue.attempt_lock()?[handle].set_enable(true);
-        Ok(())
+        self.watch_queue.attempt_lock_then(|x| {
+            // The reports when the task is not watched are not taken.
+            self.reports[handle.num()].discard();
+            x.status_mut(handle).map(|x| x.set_enable(true, OS::now()))
+        })
     }
 
     fn stop_watch(&self, handle: AliveWatchHandle) -> RetValue<()>
     {
-        self.watch_queue.attempt_lock()?[handle].set_enable(false);
-        Ok(())
+        self.watch_queue
+            .attempt_lock_then(|x| x.status_mut(handle).map(|x| x.set_enable(false, OS::now())))
     }
 
-    fn update_alive_state(&self, handle: AliveWatchHandle)
+    fn set_restart(&self, handle: AliveWatchHandle, task: &'static dyn ITaskRestart)
+        -> RetValue<()>
     {
-        self.watch_queue.lock()[handle].update_tick(OS::ticks());
+        self.watch_queue.attempt_lock_then(|x| x.status_mut(handle).map(|x| x.set_restart(task)))
+    }
+
+    fn late_tasks(&self, f: &mut dyn FnMut(&str, Duration)) -> RetValue<()>
+    {
+        self.watch_queue.attempt_lock_then(|x| {
+            x.update_reports(&self.reports);
+            x.late_tasks(OS::now(), f);
+            Ok(())
+        })
+    }
+
+    fn update_alive_state(&self, handle: AliveWatchHandle) -> RetValue<()>
+    {
+        self.reports
+            .get(handle.num())
+            .ok_or(ErrValue::InstanceNotFound)
+            .and_then(|x| x.report(handle.serial(), OS::now()))
     }
 }
 
-impl<'a, OS> ITaskMain for NativeAliveWatch<'a, OS>
+impl<'a, OS, const N: usize> ITaskMain for NativeAliveWatch<'a, OS, N>
 where
     OS: RTOS,
 {
     fn main(&mut self)
     {
         #[allow(unused_must_use)]
-        self.watch_queue.lock().update_all_ticks(OS::ticks());
+        self.watch_queue.lock().restart_all(OS::now(), &self.reports);
         self.device.refresh();
 
+        let mut expired = false;
+
         loop
         {
             OS::delay(self.cycle_time);
 
-            #[allow(unused_must_use)]
-            self.watch_queue
-                .attempt_lock_then(|x| x.check_alive_time(OS::ticks(), self.cycle_time))
-                .inspect(|()| self.device.refresh())
-                .inspect_err(|_| error!("{AWS} Some tasks near death, don't refresh Watch Dog."));
+            // Stop the escalation after the watch dog expires, the system will be reset soon.
+            if expired
+            {
+                continue;
+            }
+
+            // The steps are taken after the queue is unlocked, so a restarted task could watch
+            // itself again, and the event agent could call the alive watch.
+            let mut steps: [Option<(&str, AliveStep)>; N] = [const { None }; N];
+
+            let checked = self.watch_queue.attempt_lock_then(|x| {
+                let mut slots = steps.iter_mut();
+
+                x.update_reports(&self.reports);
+                x.escalate(OS::now(), &self.escalation, |name, step| {
+                    if let Some(slot) = slots.next()
+                    {
+                        *slot = Some((name, step));
+                    }
+                });
+                Ok(())
+            });
+
+            for (name, step) in steps.into_iter().flatten()
+            {
+                expired |= self.take_step(name, step);
+            }
+
+            if checked.is_ok() && !expired
+            {
+                self.device.refresh();
+            }
         }
     }
 }
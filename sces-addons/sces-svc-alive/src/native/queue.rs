@@ -1,62 +1,107 @@
-use core::ops::{Index, IndexMut};
-
 use alloc::vec::Vec;
 use sces::value::{ErrValue, RetValue};
 use sces::vec::SafeVec;
-use sces::os::RTOS;
+use sces::os::tick::{Duration, Instant};
 
-use crate::native::status::AliveStatus;
-use crate::AliveWatchHandle;
+use crate::native::report::AliveReport;
+use crate::native::status::{AliveStatus, AliveStep};
+use crate::{AliveDeadline, AliveEscalation, AliveWatchHandle};
 
 pub struct AliveWatchQueue<'a>
 {
-    queue: Vec<AliveStatus<'a>>,
+    queue: Vec<Option<AliveStatus<'a>>>,
+    serial: u32,
 }
 
 impl<'a> AliveWatchQueue<'a>
 {
     pub fn new() -> RetValue<Self>
     {
-        Ok(Self { queue: Vec::attempt_new()? })
+        Ok(Self { queue: Vec::attempt_new()?, serial: 0 })
     }
 
-    pub fn attempt_push<OS>(&mut self, name: &'a str) -> RetValue<usize>
-    where
-        OS: RTOS,
+    /// Watch the task in a vacant slot of `reports`, the slot is shared by the queue and the
+    /// reports with the same number.
+    pub fn attempt_push(
+        &mut self, name: &'a str, deadline: AliveDeadline, now: Instant, reports: &[AliveReport],
+    ) -> RetValue<AliveWatchHandle>
     {
-        (!self.queue.iter().any(|x| x.name() == name))
+        (!self.queue.iter().flatten().any(|x| x.name() == name))
             .then_some(())
             .ok_or(ErrValue::InstanceDuplicate)?;
 
-        self.queue.attempt_push(AliveStatus::new(name, OS::ticks()))?;
-        Ok(self.queue.len() - 1)
+        let num = match self.queue.iter().position(|x| x.is_none())
+        {
+            Some(num) => num,
+            None if self.queue.len() < reports.len() =>
+            {
+                self.queue.attempt_push(None)?;
+                self.queue.len() - 1
+            }
+            None => return Err(ErrValue::NotAvailable),
+        };
+
+        // The serial 0 means a vacant slot, so it's skipped when the serial wraps around.
+        self.serial = self.serial.checked_add(1).unwrap_or(1);
+
+        self.queue[num] = Some(AliveStatus::new(name, self.serial, deadline, now));
+        reports[num].claim(self.serial);
+
+        Ok(AliveWatchHandle::new(num, self.serial))
     }
 
-    pub fn update_all_ticks(&mut self, tick: u32)
+    /// Stop to watch the task and make its slot vacant.
+    pub fn remove(&mut self, handle: AliveWatchHandle, reports: &[AliveReport]) -> RetValue<()>
     {
-        self.queue.iter_mut().for_each(|x| x.update_tick(tick));
+        self.status_mut(handle)?;
+        self.queue[handle.num()] = None;
+        reports[handle.num()].release();
+        Ok(())
     }
 
-    pub fn check_alive_time(&self, now: u32, max_time: u32) -> RetValue<()>
+    pub fn status_mut(&mut self, handle: AliveWatchHandle) -> RetValue<&mut AliveStatus<'a>>
     {
-        self.queue.iter().try_for_each(|x| x.check_alive(now, max_time))
+        self.queue
+            .get_mut(handle.num())
+            .and_then(|x| x.as_mut())
+            .filter(|x| x.serial() == handle.serial())
+            .ok_or(ErrValue::InstanceNotFound)
     }
-}
 
-impl<'a> Index<AliveWatchHandle> for AliveWatchQueue<'a>
-{
-    type Output = AliveStatus<'a>;
+    pub fn restart_all(&mut self, now: Instant, reports: &[AliveReport])
+    {
+        self.queue.iter_mut().zip(reports).for_each(|(x, report)| {
+            report.discard();
+            x.iter_mut().for_each(|x| x.restart(now));
+        });
+    }
 
-    fn index(&self, index: AliveWatchHandle) -> &Self::Output
+    /// Take the reports of every task into its status.
+    pub fn update_reports(&mut self, reports: &[AliveReport])
     {
-        &self.queue[*index]
+        self.queue.iter_mut().zip(reports).for_each(|(x, report)| {
+            if let (Some(status), Some(taken)) = (x.as_mut(), report.take())
+            {
+                status.update_report(&taken);
+            }
+        });
     }
-}
 
-impl<'a> IndexMut<AliveWatchHandle> for AliveWatchQueue<'a>
-{
-    fn index_mut(&mut self, index: AliveWatchHandle) -> &mut Self::Output
+    /// Take the step of the escalation for every task, and give the steps to `f`.
+    pub fn escalate(
+        &mut self, now: Instant, escalation: &AliveEscalation,
+        mut f: impl FnMut(&'a str, AliveStep),
+    )
+    {
+        self.queue.iter_mut().flatten().for_each(|x| f(x.name(), x.escalate(now, escalation)));
+    }
+
+    pub fn late_tasks(&self, now: Instant, f: &mut dyn FnMut(&str, Duration))
     {
-        &mut self.queue[*index]
+        self.queue
+            .iter()
+            .flatten()
+            .filter(|x| x.check_alive(now).is_err())
+            .for_each(|x| f(x.name(), x.past(now)));
     }
 }
@@ -0,0 +1,94 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use sces::os::tick::{Duration, Instant};
+use sces::value::{ErrValue, RetValue};
+
+/// The slot of a watched task which is not owned by any task.
+const SERIAL_VACANT: u32 = 0;
+
+/// The reports of a watched task which are taken by the alive watch in a check cycle.
+pub struct AliveTaken
+{
+    pub first: Instant,
+    pub last: Instant,
+    pub gap: Duration,
+}
+
+/// The slot of the reports of a watched task.
+///
+/// The reports are written with the atomic operations only, so the task or an interrupt handler
+/// reports alive without locking the queue, and the alive watch takes them in every check cycle.
+pub struct AliveReport
+{
+    serial: AtomicU32,
+    count: AtomicU32,
+    first: AtomicU32,
+    last: AtomicU32,
+    gap: AtomicU32,
+}
+
+impl AliveReport
+{
+    pub const fn new() -> Self
+    {
+        Self {
+            serial: AtomicU32::new(SERIAL_VACANT),
+            count: AtomicU32::new(0),
+            first: AtomicU32::new(0),
+            last: AtomicU32::new(0),
+            gap: AtomicU32::new(u32::MAX),
+        }
+    }
+
+    /// Give the slot to the task with `serial`, the reports of the last owner are dropped.
+    pub fn claim(&self, serial: u32)
+    {
+        self.discard();
+        self.serial.store(serial, Ordering::Release);
+    }
+
+    /// Make the slot vacant, the reports with the old serial are rejected since then.
+    pub fn release(&self)
+    {
+        self.serial.store(SERIAL_VACANT, Ordering::Release);
+        self.discard();
+    }
+
+    /// Report alive at `now` by the owner with `serial`.
+    pub fn report(&self, serial: u32, now: Instant) -> RetValue<()>
+    {
+        (serial != SERIAL_VACANT && self.serial.load(Ordering::Acquire) == serial)
+            .then_some(())
+            .ok_or(ErrValue::InstanceNotFound)?;
+
+        let last = self.last.swap(now.ticks(), Ordering::AcqRel);
+
+        match self.count.fetch_add(1, Ordering::AcqRel)
+        {
+            0 => self.first.store(now.ticks(), Ordering::Release),
+            _ =>
+            {
+                self.gap.fetch_min(now.ticks().wrapping_sub(last), Ordering::AcqRel);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Take the reports since the last time, a report which races with it may be taken in the
+    /// next time.
+    pub fn take(&self) -> Option<AliveTaken>
+    {
+        (self.count.swap(0, Ordering::AcqRel) > 0).then(|| AliveTaken {
+            first: Instant::from_ticks(self.first.load(Ordering::Acquire)),
+            last: Instant::from_ticks(self.last.load(Ordering::Acquire)),
+            gap: Duration::from_ticks(self.gap.swap(u32::MAX, Ordering::AcqRel)),
+        })
+    }
+
+    /// Drop the reports since the last time.
+    pub fn discard(&self)
+    {
+        self.take();
+    }
+}
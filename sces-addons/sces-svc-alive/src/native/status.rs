@@ -1,20 +1,52 @@
 use log::error;
+use sces::os::task::ITaskRestart;
+use sces::os::tick::{Duration, Instant};
 use sces::value::{ErrValue, RetValue};
 
+use crate::native::report::AliveTaken;
 use crate::svc::AWS;
+use crate::{AliveDeadline, AliveEscalation};
+
+/// The step of the escalation which is taken for a task in a check cycle.
+pub enum AliveStep
+{
+    Alive,
+    Late(Duration),
+    Pending,
+    Restart(&'static dyn ITaskRestart),
+    Expire,
+}
 
 pub struct AliveStatus<'a>
 {
     name: &'a str,
+    serial: u32,
     enable: bool,
-    alive_tick: u32,
+    deadline: AliveDeadline,
+    alive_tick: Instant,
+    in_grace: bool,
+    too_early: bool,
+    late_cycles: u32,
+    restarts: u32,
+    task: Option<&'static dyn ITaskRestart>,
 }
 
 impl<'a> AliveStatus<'a>
 {
-    pub fn new(name: &'a str, alive_tick: u32) -> Self
+    pub fn new(name: &'a str, serial: u32, deadline: AliveDeadline, alive_tick: Instant) -> Self
     {
-        Self { name, enable: true, alive_tick }
+        Self {
+            name,
+            serial,
+            enable: true,
+            deadline,
+            alive_tick,
+            in_grace: true,
+            too_early: false,
+            late_cycles: 0,
+            restarts: 0,
+            task: None,
+        }
     }
 
     pub fn name(&self) -> &'a str
@@ -22,23 +54,241 @@ impl<'a> AliveStatus<'a>
         self.name
     }
 
-    pub fn set_enable(&mut self, enable: bool)
+    pub fn serial(&self) -> u32
+    {
+        self.serial
+    }
+
+    pub fn set_restart(&mut self, task: &'static dyn ITaskRestart)
+    {
+        self.task = Some(task);
+    }
+
+    /// Enable or disable the watch, the deadline restarts from `now` in both cases.
+    pub fn set_enable(&mut self, enable: bool, now: Instant)
     {
         self.enable = enable;
+        self.restart(now);
     }
 
-    pub fn update_tick(&mut self, tick: u32)
+    /// Restart the deadline from `now` with the grace period, and forget the too early report.
+    pub fn restart(&mut self, now: Instant)
     {
-        self.alive_tick = tick;
+        self.alive_tick = now;
+        self.in_grace = true;
+        self.too_early = false;
+        self.late_cycles = 0;
     }
 
-    pub fn check_alive(&self, tick: u32, max_time: u32) -> RetValue<()>
+    /// Take the reports of the task since the last check cycle.
+    pub fn update_report(&mut self, taken: &AliveTaken)
     {
-        let past = tick - self.alive_tick;
+        if !self.enable
+        {
+            return;
+        }
+
+        // The first report after the restart has no last report to compare with.
+        let gap = match self.in_grace
+        {
+            true => taken.gap,
+            false => taken.gap.min(self.past(taken.first)),
+        };
+
+        if gap < self.deadline.window()
+        {
+            error!("{AWS} {} reports alive too early after {} ms", self.name, gap.as_millis());
+            self.too_early = true;
+        }
+
+        // Only a report on time shows the restart has helped, a task which keeps reporting too
+        // early or too late must still run out of its restarts.
+        if self.check_alive(taken.first).is_ok()
+        {
+            self.restarts = 0;
+        }
+
+        self.alive_tick = taken.last;
+        self.in_grace = false;
+    }
+
+    /// Get the time since the last report, it's right across the wraparound of the tick counter.
+    pub fn past(&self, now: Instant) -> Duration
+    {
+        now - self.alive_tick
+    }
+
+    pub fn check_alive(&self, now: Instant) -> RetValue<()>
+    {
+        if !self.enable
+        {
+            return Ok(());
+        }
+
+        (!self.too_early).then_some(()).ok_or(ErrValue::Timeout)?;
+
+        let limit = match self.in_grace
+        {
+            true => self.deadline.timeout() + self.deadline.grace(),
+            false => self.deadline.timeout(),
+        };
+
+        (self.past(now) <= limit).then_some(()).ok_or(ErrValue::Timeout)
+    }
+
+    /// Check the deadline and take the next step of the escalation if the task is late.
+    pub fn escalate(&mut self, now: Instant, escalation: &AliveEscalation) -> AliveStep
+    {
+        if self.check_alive(now).is_ok()
+        {
+            self.late_cycles = 0;
+            return AliveStep::Alive;
+        }
+
+        self.late_cycles += 1;
+
+        if self.late_cycles == 1
+        {
+            return AliveStep::Late(self.past(now));
+        }
+
+        if self.late_cycles <= escalation.notify_cycles()
+        {
+            return AliveStep::Pending;
+        }
+
+        match self.task
+        {
+            Some(task) if self.restarts < escalation.max_restarts() =>
+            {
+                self.restarts += 1;
+                self.restart(now);
+                AliveStep::Restart(task)
+            }
+            _ => AliveStep::Expire,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    const CYCLE: Duration = Duration::from_millis(100);
+
+    struct Restarts(AtomicU32);
+
+    impl ITaskRestart for Restarts
+    {
+        fn restart(&self) -> RetValue<()>
+        {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    fn deadline() -> AliveDeadline
+    {
+        AliveDeadline::new(Duration::from_millis(150)).with_window(Duration::from_millis(50))
+    }
+
+    /// Run the check cycles with the reports made by `report` at the cycle, until the watch dog
+    /// expires or `cycles` have run, and return the cycles which have run.
+    fn run(
+        status: &mut AliveStatus, escalation: &AliveEscalation, cycles: u32,
+        mut report: impl FnMut(Instant) -> Option<AliveTaken>,
+    ) -> Option<u32>
+    {
+        let mut now = Instant::from_ticks(0);
+
+        for cycle in 1..=cycles
+        {
+            now += CYCLE;
+            if let Some(taken) = report(now)
+            {
+                status.update_report(&taken);
+            }
+
+            match status.escalate(now, escalation)
+            {
+                AliveStep::Restart(task) => task.restart().unwrap(),
+                AliveStep::Expire => return Some(cycle),
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    /// Two reports 10 ms apart in the cycle, which are too early for the window.
+    fn too_early(now: Instant) -> Option<AliveTaken>
+    {
+        let gap = Duration::from_millis(10);
+        Some(AliveTaken { first: now - gap - gap, last: now - gap, gap })
+    }
+
+    /// One report in the cycle.
+    fn on_time(now: Instant) -> Option<AliveTaken>
+    {
+        let last = now - Duration::from_millis(10);
+        Some(AliveTaken { first: last, last, gap: Duration::MAX })
+    }
+
+    #[test]
+    fn too_early_reports_expire()
+    {
+        static TASK: Restarts = Restarts(AtomicU32::new(0));
+
+        let escalation = AliveEscalation::new().with_max_restarts(2);
+        let mut status = AliveStatus::new("early", 1, deadline(), Instant::from_ticks(0));
+        status.set_restart(&TASK);
+
+        assert_eq!(run(&mut status, &escalation, 20, too_early), Some(6));
+        assert_eq!(TASK.0.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn on_time_reports_clear_restarts()
+    {
+        static TASK: Restarts = Restarts(AtomicU32::new(0));
+
+        let escalation = AliveEscalation::new().with_max_restarts(1);
+        let mut status = AliveStatus::new("recover", 1, deadline(), Instant::from_ticks(0));
+        status.set_restart(&TASK);
+
+        // Too early in the first cycle of every ten, and restarted in the next one, then the
+        // reports on time give the only restart back.
+        let result = run(&mut status, &escalation, 40, |now| match now.ticks() / 100 % 10
+        {
+            0 | 1 => too_early(now),
+            _ => on_time(now),
+        });
+
+        assert_eq!(result, None);
+        assert_eq!(TASK.0.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn deadline_across_wrap()
+    {
+        let deadline =
+            AliveDeadline::new(Duration::from_millis(100)).with_grace(Duration::from_millis(50));
+        let watched = Instant::from_ticks(u32::MAX - 9);
+        let mut status = AliveStatus::new("wrap", 1, deadline, watched);
+
+        // The grace period is given from the watch just before the wraparound.
+        assert_eq!(status.past(Instant::from_ticks(20)), Duration::from_millis(30));
+        assert!(status.check_alive(Instant::from_ticks(140)).is_ok());
+        assert!(status.check_alive(Instant::from_ticks(141)).is_err());
+
+        let report = watched + Duration::from_millis(5);
+        status.update_report(&AliveTaken { first: report, last: report, gap: Duration::MAX });
 
-        (past > max_time)
-            .then_some(())
-            .ok_or(ErrValue::Timeout)
-            .inspect_err(|_| error!("{AWS} {} is near death over {}", self.name, past))
+        assert_eq!(status.past(Instant::from_ticks(0)), Duration::from_millis(5));
+        assert!(status.check_alive(Instant::from_ticks(95)).is_ok());
+        assert!(status.check_alive(Instant::from_ticks(96)).is_err());
     }
 }
@@ -1,6 +1,6 @@
 use sces::value::{ErrValue, RetValue};
 
-use crate::alive::AliveWatch;
+use crate::alive::{AliveDeadline, AliveWatch, AliveWatchGuard};
 
 static mut SVC: Option<&'static dyn AliveWatch> = None;
 
@@ -24,4 +24,11 @@ impl AliveWatchService
     {
         unsafe { SVC.unwrap() }
     }
+
+    /// Watch a task by the service until the guard is dropped.
+    pub fn guard(name: &'static str, deadline: AliveDeadline)
+        -> RetValue<AliveWatchGuard<'static>>
+    {
+        AliveWatchGuard::new(Self::instance(), name, deadline)
+    }
 }
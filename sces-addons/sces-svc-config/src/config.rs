@@ -0,0 +1,159 @@
+use alloc::vec::Vec;
+use sces::value::{ErrValue, RetValue};
+
+use crate::value::ConfigValue;
+
+/// The type of a value, it's stored with the value to check the typed access and to show the
+/// value in the console.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigType
+{
+    Bytes = 0,
+    Bool = 1,
+    U8 = 2,
+    U16 = 3,
+    U32 = 4,
+    U64 = 5,
+    I8 = 6,
+    I16 = 7,
+    I32 = 8,
+    I64 = 9,
+    F32 = 10,
+    F64 = 11,
+    Str = 12,
+}
+
+impl ConfigType
+{
+    const ALL: [ConfigType; 13] = [
+        ConfigType::Bytes,
+        ConfigType::Bool,
+        ConfigType::U8,
+        ConfigType::U16,
+        ConfigType::U32,
+        ConfigType::U64,
+        ConfigType::I8,
+        ConfigType::I16,
+        ConfigType::I32,
+        ConfigType::I64,
+        ConfigType::F32,
+        ConfigType::F64,
+        ConfigType::Str,
+    ];
+
+    pub fn from_u8(value: u8) -> Option<Self>
+    {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self>
+    {
+        Self::ALL.into_iter().find(|x| x.name() == name)
+    }
+
+    pub const fn name(&self) -> &'static str
+    {
+        match self
+        {
+            ConfigType::Bytes => "bytes",
+            ConfigType::Bool => "bool",
+            ConfigType::U8 => "u8",
+            ConfigType::U16 => "u16",
+            ConfigType::U32 => "u32",
+            ConfigType::U64 => "u64",
+            ConfigType::I8 => "i8",
+            ConfigType::I16 => "i16",
+            ConfigType::I32 => "i32",
+            ConfigType::I64 => "i64",
+            ConfigType::F32 => "f32",
+            ConfigType::F64 => "f64",
+            ConfigType::Str => "str",
+        }
+    }
+}
+
+/// The usage and the wear of the store.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigStats
+{
+    pub sectors: u32,
+    pub keys: u32,
+
+    /// The size of all sectors in bytes.
+    pub capacity: u32,
+
+    /// The bytes written in all sectors, including the outdated records.
+    pub used: u32,
+
+    /// The bytes of the latest records, they are copied by the garbage collection.
+    pub live: u32,
+
+    /// The least and the most erase counts of the sectors.
+    pub min_erases: u32,
+    pub max_erases: u32,
+
+    /// The garbage collections since the store is mounted.
+    pub collections: u32,
+}
+
+/// A store of the values by their keys.
+///
+/// The keys are the strings of 32 bytes at most. The values are read and written as raw bytes
+/// with their types by this trait, and [`dyn ConfigStore`](ConfigStore) has the typed access:
+///
+/// ```rust
+/// let store = ConfigService::instance();
+///
+/// store.set("volume", &5u8)?;
+/// let volume: u8 = store.get("volume")?;
+/// ```
+pub trait ConfigStore: Send + Sync
+{
+    /// Get the type and the size of the value of `key`.
+    fn lookup(&self, key: &str) -> RetValue<(ConfigType, usize)>;
+
+    /// Read the value of `key` into `data`, it fails with [`ErrValue::Param`] when `data` is
+    /// shorter than the value.
+    fn read(&self, key: &str, data: &mut [u8]) -> RetValue<(ConfigType, usize)>;
+
+    /// Write the value of `key`, the old value is kept until the new one is written completely.
+    fn write(&self, key: &str, kind: ConfigType, data: &[u8]) -> RetValue<()>;
+
+    fn remove(&self, key: &str) -> RetValue<()>;
+
+    /// Call `f` with the key, the type and the size of every value, the store can't be accessed
+    /// in `f`.
+    fn keys(&self, f: &mut dyn FnMut(&str, ConfigType, usize)) -> RetValue<()>;
+
+    fn stats(&self) -> RetValue<ConfigStats>;
+
+    /// Start a new sector, and collect the oldest one if there is no spare sector left.
+    fn collect(&self) -> RetValue<()>;
+}
+
+impl dyn ConfigStore
+{
+    /// Get the value of `key`, it fails with [`ErrValue::InstanceInvalid`] when the value has
+    /// another type.
+    pub fn get<T: ConfigValue>(&self, key: &str) -> RetValue<T>
+    {
+        let (kind, size) = self.lookup(key)?;
+        if kind != T::TYPE
+        {
+            return Err(ErrValue::InstanceInvalid);
+        }
+
+        let mut data = Vec::new();
+        data.try_reserve_exact(size).or(Err(ErrValue::MemAllocFailure))?;
+        data.resize(size, 0);
+
+        let (_, size) = self.read(key, &mut data)?;
+        T::from_config(&data[..size])
+    }
+
+    pub fn set<T: ConfigValue>(&self, key: &str, value: &T) -> RetValue<()>
+    {
+        value.with_config(|x| self.write(key, T::TYPE, x))
+    }
+}
@@ -0,0 +1,181 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use log::info;
+use sces::value::{ErrValue, RetValue};
+use sces_svc_console::{ConsoleCommands, ConsoleExecute};
+
+use crate::config::{ConfigStore, ConfigType};
+use crate::svc::ConfigService;
+use crate::value::ConfigValue;
+
+/// The log target of the records printed by [`ConfigCommand`].
+pub const CONFIG_TARGET: &str = "config";
+
+/// The console command to access the store of [`ConfigService`].
+///
+/// * `config [list]`: Show all keys with their types and values.
+/// * `config get <key>`: Show the value of a key.
+/// * `config set <key> <value> [type]`: Set the value of a key, the value has the type of the
+///   old one or `type`, a new key is a string by default. The bytes are written in hex.
+/// * `config remove <key>`: Remove a key.
+/// * `config stats`: Show the usage and the wear of the store.
+/// * `config collect`: Start a new sector, and collect the oldest one if needed.
+pub struct ConfigCommand;
+
+impl ConsoleExecute for ConfigCommand
+{
+    fn exe_name(&self) -> &str
+    {
+        CONFIG_TARGET
+    }
+
+    fn exe_with_cmds(&self, cmds: &mut ConsoleCommands) -> RetValue<()>
+    {
+        let store = ConfigService::instance();
+        let mut text =
+            || cmds.next().map(core::str::from_utf8).transpose().or(Err(ErrValue::FormatFailure));
+
+        match text()?
+        {
+            None | Some("list") => list(store),
+            Some("get") => show(store, text()?.ok_or(ErrValue::Param)?),
+            Some("set") =>
+            {
+                let key = text()?.ok_or(ErrValue::Param)?;
+                let value = text()?.ok_or(ErrValue::Param)?;
+
+                let kind = match text()?
+                {
+                    Some(x) => ConfigType::from_name(x).ok_or(ErrValue::Param)?,
+                    None => store.lookup(key).map_or(ConfigType::Str, |(x, _)| x),
+                };
+
+                store.write(key, kind, &parse(kind, value)?)
+            }
+            Some("remove") => store.remove(text()?.ok_or(ErrValue::Param)?),
+            Some("stats") =>
+            {
+                let stats = store.stats()?;
+
+                info!(target: CONFIG_TARGET, "{} keys in {} sectors.", stats.keys, stats.sectors);
+                info!(target: CONFIG_TARGET, "Used {} of {} bytes, {} bytes live.", stats.used, stats.capacity, stats.live);
+                info!(target: CONFIG_TARGET, "Erased {} to {} times, collected {} times.", stats.min_erases, stats.max_erases, stats.collections);
+                Ok(())
+            }
+            Some("collect") => store.collect(),
+            Some(_) => Err(ErrValue::Param),
+        }
+    }
+}
+
+fn list(store: &dyn ConfigStore) -> RetValue<()>
+{
+    // The store is locked in the callback, so the keys are taken before their values are read.
+    let mut keys = Vec::new();
+    store.keys(&mut |key, _, _| keys.push(String::from(key)))?;
+
+    for key in keys
+    {
+        show(store, &key)?;
+    }
+
+    Ok(())
+}
+
+fn show(store: &dyn ConfigStore, key: &str) -> RetValue<()>
+{
+    let (_, size) = store.lookup(key)?;
+
+    let mut data = Vec::new();
+    data.try_reserve_exact(size).or(Err(ErrValue::MemAllocFailure))?;
+    data.resize(size, 0);
+
+    let (kind, size) = store.read(key, &mut data)?;
+    info!(target: CONFIG_TARGET, "{key}: {} = {}", kind.name(), ConfigShow(kind, &data[..size]));
+    Ok(())
+}
+
+macro_rules! parse_number {
+    ($t:ty, $text:expr) => {
+        $text.parse::<$t>().or(Err(ErrValue::FormatFailure))?.with_config(|x| x.to_vec())
+    };
+}
+
+fn parse(kind: ConfigType, text: &str) -> RetValue<Vec<u8>>
+{
+    let value = match kind
+    {
+        ConfigType::Bytes =>
+        {
+            if !text.len().is_multiple_of(2)
+            {
+                return Err(ErrValue::FormatFailure);
+            }
+
+            (0..text.len())
+                .step_by(2)
+                .map(|x| text.get(x..x + 2).and_then(|x| u8::from_str_radix(x, 16).ok()))
+                .collect::<Option<Vec<u8>>>()
+                .ok_or(ErrValue::FormatFailure)?
+        }
+        ConfigType::Bool => match text
+        {
+            "true" | "1" => true.with_config(|x| x.to_vec()),
+            "false" | "0" => false.with_config(|x| x.to_vec()),
+            _ => return Err(ErrValue::FormatFailure),
+        },
+        ConfigType::U8 => parse_number!(u8, text),
+        ConfigType::U16 => parse_number!(u16, text),
+        ConfigType::U32 => parse_number!(u32, text),
+        ConfigType::U64 => parse_number!(u64, text),
+        ConfigType::I8 => parse_number!(i8, text),
+        ConfigType::I16 => parse_number!(i16, text),
+        ConfigType::I32 => parse_number!(i32, text),
+        ConfigType::I64 => parse_number!(i64, text),
+        ConfigType::F32 => parse_number!(f32, text),
+        ConfigType::F64 => parse_number!(f64, text),
+        ConfigType::Str => text.as_bytes().to_vec(),
+    };
+
+    Ok(value)
+}
+
+/// Show a value by its type, the broken one is shown in hex.
+struct ConfigShow<'a>(ConfigType, &'a [u8]);
+
+impl Display for ConfigShow<'_>
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result
+    {
+        fn show<T: ConfigValue + Display>(
+            f: &mut Formatter<'_>, data: &[u8],
+        ) -> Option<core::fmt::Result>
+        {
+            T::from_config(data).ok().map(|x| write!(f, "{x}"))
+        }
+
+        let shown = match self.0
+        {
+            ConfigType::Bytes => None,
+            ConfigType::Bool => show::<bool>(f, self.1),
+            ConfigType::U8 => show::<u8>(f, self.1),
+            ConfigType::U16 => show::<u16>(f, self.1),
+            ConfigType::U32 => show::<u32>(f, self.1),
+            ConfigType::U64 => show::<u64>(f, self.1),
+            ConfigType::I8 => show::<i8>(f, self.1),
+            ConfigType::I16 => show::<i16>(f, self.1),
+            ConfigType::I32 => show::<i32>(f, self.1),
+            ConfigType::I64 => show::<i64>(f, self.1),
+            ConfigType::F32 => show::<f32>(f, self.1),
+            ConfigType::F64 => show::<f64>(f, self.1),
+            ConfigType::Str => core::str::from_utf8(self.1).ok().map(|x| write!(f, "\"{x}\"")),
+        };
+
+        match shown
+        {
+            Some(x) => x,
+            None => self.1.iter().try_for_each(|x| write!(f, "{x:02x}")),
+        }
+    }
+}
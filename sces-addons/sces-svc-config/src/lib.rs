@@ -0,0 +1,19 @@
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+mod config;
+#[cfg(feature = "console")]
+mod console;
+mod native;
+mod svc;
+mod value;
+
+pub use config::ConfigStats;
+pub use config::ConfigStore;
+pub use config::ConfigType;
+#[cfg(feature = "console")]
+pub use console::ConfigCommand;
+pub use native::NativeConfigStore;
+pub use svc::ConfigService;
+pub use value::ConfigValue;
@@ -0,0 +1,97 @@
+mod log;
+mod record;
+
+use core::marker::PhantomData;
+
+use sces::mcu::flash::FlashCtrl;
+use sces::os::mutex::MutexSample;
+use sces::os::RTOS;
+use sces::value::RetValue;
+
+use crate::config::{ConfigStats, ConfigStore, ConfigType};
+use crate::native::log::ConfigLog;
+
+/// The store which logs the records over two or more sectors of a flash.
+///
+/// The sectors are used in turn, so they are erased evenly, and one of them is always kept
+/// erased as the spare of the garbage collection, so all the live records should fit in one
+/// sector. Any [`FlashCtrl`] works, like the on chip flash or a SPI NOR flash, whether it
+/// programs single bytes or units which take only one program after the erase, like the ECC
+/// units of 16 bytes.
+///
+/// # Examples
+/// ```rust
+/// static mut STORE: Option<NativeConfigStore<MWOS>> = None;
+///
+/// let store = STORE.insert(NativeConfigStore::new(flash, &[6, 7])?);
+/// ConfigService::initialize(store)?;
+/// ```
+pub struct NativeConfigStore<OS>
+where
+    OS: RTOS,
+{
+    log: MutexSample<OS, ConfigLog>,
+    _marker: PhantomData<OS>,
+}
+
+impl<OS> NativeConfigStore<OS>
+where
+    OS: RTOS,
+{
+    /// Mount the store on the sectors `sectors` of `flash`, which are the numbers of
+    /// [`FlashCtrl::sector`].
+    ///
+    /// The sectors without the store are erased, so the new sectors should not have any other
+    /// data.
+    pub fn new(flash: &'static dyn FlashCtrl, sectors: &[u32]) -> RetValue<Self>
+    {
+        Ok(Self { log: MutexSample::new(ConfigLog::mount(flash, sectors)?)?, _marker: PhantomData })
+    }
+}
+
+unsafe impl<OS> Send for NativeConfigStore<OS> where OS: RTOS {}
+
+unsafe impl<OS> Sync for NativeConfigStore<OS> where OS: RTOS {}
+
+impl<OS> ConfigStore for NativeConfigStore<OS>
+where
+    OS: RTOS,
+{
+    fn lookup(&self, key: &str) -> RetValue<(ConfigType, usize)>
+    {
+        self.log.attempt_lock_then(|x| x.lookup(key).map(|x| (x.kind, x.value_size as usize)))
+    }
+
+    fn read(&self, key: &str, data: &mut [u8]) -> RetValue<(ConfigType, usize)>
+    {
+        self.log.attempt_lock_then(|x| x.read(key, data))
+    }
+
+    fn write(&self, key: &str, kind: ConfigType, data: &[u8]) -> RetValue<()>
+    {
+        self.log.attempt_lock_then(|x| x.write(key, kind, data))
+    }
+
+    fn remove(&self, key: &str) -> RetValue<()>
+    {
+        self.log.attempt_lock_then(|x| x.remove(key))
+    }
+
+    fn keys(&self, f: &mut dyn FnMut(&str, ConfigType, usize)) -> RetValue<()>
+    {
+        self.log.attempt_lock_then(|x| {
+            x.keys(f);
+            Ok(())
+        })
+    }
+
+    fn stats(&self) -> RetValue<ConfigStats>
+    {
+        self.log.attempt_lock_then(|x| Ok(x.stats()))
+    }
+
+    fn collect(&self) -> RetValue<()>
+    {
+        self.log.attempt_lock_then(|x| x.rotate())
+    }
+}
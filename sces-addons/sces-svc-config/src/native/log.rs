@@ -0,0 +1,944 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use log::warn;
+use sces::mcu::flash::{FlashCtrl, FlashSector};
+use sces::value::{ErrValue, RetValue};
+
+use crate::config::{ConfigStats, ConfigType};
+use crate::native::record::*;
+use crate::svc::CFS;
+
+/// The size of the buffer on the stack, which holds the data read to check the erased sectors.
+const BLANK_CHECK_SIZE: usize = 64;
+
+/// Where the latest record of a key is.
+#[derive(Clone, Copy)]
+pub struct ConfigEntry
+{
+    pub address: u32,
+    pub kind: ConfigType,
+    pub key_size: u8,
+    pub value_size: u16,
+}
+
+impl ConfigEntry
+{
+    pub fn value_address(&self) -> u32
+    {
+        self.address + (RECORD_HEADER + self.key_size as usize) as u32
+    }
+
+    pub fn size(&self, align: u32) -> u32
+    {
+        align_up((RECORD_HEADER + self.key_size as usize + self.value_size as usize) as u32, align)
+    }
+}
+
+struct ConfigSector
+{
+    info: FlashSector,
+
+    /// The order in which the sector was started, it's `None` when the sector is erased.
+    sequence: Option<u32>,
+    erases: u32,
+
+    /// The offset to write the next record.
+    end: u32,
+}
+
+/// The log of the records over the sectors.
+///
+/// The records are appended to the active sector, which is the one with the largest sequence,
+/// and the latest record of a key wins. When the active sector is full, the erased sector with
+/// the least erase count becomes the active one, and if no erased sector is left then, the live
+/// records of the oldest sector are copied into the active one before the oldest one is erased.
+///
+/// A record or a header is valid only with its CRC, so a write broken by a power failure is
+/// skipped, and the value before it is still found in the log.
+pub struct ConfigLog
+{
+    flash: &'static dyn FlashCtrl,
+    sectors: Vec<ConfigSector>,
+    index: BTreeMap<String, ConfigEntry>,
+    active: usize,
+    align: u32,
+    collections: u32,
+}
+
+impl ConfigLog
+{
+    /// Load the log from the sectors `sectors` of `flash`, the broken sectors are erased, and an
+    /// interrupted garbage collection is finished.
+    pub fn mount(flash: &'static dyn FlashCtrl, sectors: &[u32]) -> RetValue<Self>
+    {
+        if sectors.len() < 2
+        {
+            return Err(ErrValue::Param);
+        }
+
+        let mut list = Vec::new();
+        list.try_reserve_exact(sectors.len()).or(Err(ErrValue::MemAllocFailure))?;
+
+        for index in sectors
+        {
+            let info = flash.sector(*index)?;
+            list.push(ConfigSector { info, sequence: None, erases: 0, end: 0 });
+        }
+
+        let mut log = Self {
+            flash,
+            sectors: list,
+            index: BTreeMap::new(),
+            active: 0,
+            align: flash.write_size().max(4),
+            collections: 0,
+        };
+
+        let mut broken = Vec::new();
+
+        for num in 0..log.sectors.len()
+        {
+            let mut bytes = [0; SECTOR_HEADER];
+            log.flash.read(log.sectors[num].info.address, &mut bytes)?;
+
+            match SectorHeader::decode(&bytes)
+            {
+                Some(header) =>
+                {
+                    log.sectors[num].sequence = Some(header.sequence);
+                    log.sectors[num].erases = header.erases;
+                }
+                None if log.is_blank(num)? => (),
+                None => broken.push(num),
+            }
+        }
+
+        // The erase counts of the erased sectors are lost, the largest known one is taken.
+        let erases = log.sectors.iter().map(|x| x.erases).max().unwrap_or(0);
+        log.sectors.iter_mut().filter(|x| x.sequence.is_none()).for_each(|x| x.erases = erases);
+
+        for num in broken
+        {
+            warn!("{CFS} Sector {} has no valid header, erase it.", log.sectors[num].info.index);
+            log.erase(num)?;
+        }
+
+        let mut order: Vec<usize> = (0..log.sectors.len()).filter(|x| log.is_used(*x)).collect();
+        order.sort_unstable_by_key(|x| log.sectors[*x].sequence);
+
+        for num in order.iter()
+        {
+            log.scan(*num)?;
+        }
+
+        match order.last()
+        {
+            Some(num) => log.active = *num,
+            None =>
+            {
+                let num = log.pick_erased()?;
+                log.format(num, 0)?;
+                log.active = num;
+            }
+        }
+
+        while !log.has_erased()
+        {
+            log.collect_oldest()?;
+        }
+
+        Ok(log)
+    }
+
+    pub fn lookup(&self, key: &str) -> RetValue<ConfigEntry>
+    {
+        self.index.get(key).copied().ok_or(ErrValue::InstanceNotFound)
+    }
+
+    pub fn read(&self, key: &str, data: &mut [u8]) -> RetValue<(ConfigType, usize)>
+    {
+        let entry = self.lookup(key)?;
+        let size = entry.value_size as usize;
+
+        let data = data.get_mut(..size).ok_or(ErrValue::Param)?;
+        self.flash.read(entry.value_address(), data)?;
+        Ok((entry.kind, size))
+    }
+
+    pub fn write(&mut self, key: &str, kind: ConfigType, value: &[u8]) -> RetValue<()>
+    {
+        // The same value is not written again to save the flash.
+        if let Ok(entry) = self.lookup(key)
+        {
+            if entry.kind == kind
+                && entry.value_size as usize == value.len()
+                && self.flash.verify(entry.value_address(), value).is_ok()
+            {
+                return Ok(());
+            }
+        }
+
+        let entry = self.append(key, kind as u8, value)?;
+        self.index.insert(String::from(key), entry);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &str) -> RetValue<()>
+    {
+        self.lookup(key)?;
+        self.append(key, RECORD_REMOVED, &[])?;
+        self.index.remove(key);
+        Ok(())
+    }
+
+    pub fn keys(&self, f: &mut dyn FnMut(&str, ConfigType, usize))
+    {
+        self.index.iter().for_each(|(key, x)| f(key, x.kind, x.value_size as usize));
+    }
+
+    pub fn stats(&self) -> ConfigStats
+    {
+        let erases = self.sectors.iter().map(|x| x.erases);
+
+        ConfigStats {
+            sectors: self.sectors.len() as u32,
+            keys: self.index.len() as u32,
+            capacity: self.sectors.iter().map(|x| x.info.size).sum(),
+            used: self.sectors.iter().map(|x| x.end).sum(),
+            live: self.index.values().map(|x| x.size(self.align)).sum(),
+            min_erases: erases.clone().min().unwrap_or(0),
+            max_erases: erases.max().unwrap_or(0),
+            collections: self.collections,
+        }
+    }
+
+    /// Start a new sector, the oldest one is collected if no erased sector is left.
+    pub fn rotate(&mut self) -> RetValue<()>
+    {
+        let num = self.pick_erased()?;
+        let sequence = self.sectors[self.active].sequence.map_or(0, |x| x.wrapping_add(1));
+
+        self.format(num, sequence)?;
+        self.active = num;
+
+        while !self.has_erased()
+        {
+            self.collect_oldest()?;
+        }
+
+        Ok(())
+    }
+
+    /// Append a record to the active sector, a new sector is started when it's full.
+    fn append(&mut self, key: &str, kind: u8, value: &[u8]) -> RetValue<ConfigEntry>
+    {
+        if key.is_empty() || key.len() > KEY_MAX || value.len() > u16::MAX as usize
+        {
+            return Err(ErrValue::Param);
+        }
+
+        let header = RecordHeader::new(key.as_bytes(), kind, value);
+        let size = header.size(self.align);
+
+        // A record which can't fit in an empty sector is never written.
+        if self.sectors.iter().any(|x| x.info.size < self.header_size() + size)
+        {
+            return Err(ErrValue::Param);
+        }
+
+        if self.free() < size
+        {
+            self.rotate()?;
+        }
+
+        let mut record = Vec::new();
+        record.try_reserve_exact(size as usize).or(Err(ErrValue::MemAllocFailure))?;
+        record.extend_from_slice(&header.encode());
+        record.extend_from_slice(key.as_bytes());
+        record.extend_from_slice(value);
+        record.resize(size as usize, 0xFF);
+
+        let address = self.program(&record)?;
+
+        Ok(ConfigEntry {
+            address,
+            kind: ConfigType::from_u8(kind).unwrap_or(ConfigType::Bytes),
+            key_size: header.key_size,
+            value_size: header.value_size,
+        })
+    }
+
+    /// Program a whole record at the end of the active sector, and return its address.
+    fn program(&mut self, record: &[u8]) -> RetValue<u32>
+    {
+        if self.free() < record.len() as u32
+        {
+            return Err(ErrValue::StackOverflow);
+        }
+
+        let sector = &mut self.sectors[self.active];
+        let address = sector.info.address + sector.end;
+
+        // The broken record is left as the end of the sector.
+        self.flash.write_verify(address, record).inspect_err(|_| sector.end = sector.info.size)?;
+
+        sector.end += record.len() as u32;
+        Ok(address)
+    }
+
+    /// Copy the live records of the oldest sector into the active one, and erase it.
+    fn collect_oldest(&mut self) -> RetValue<()>
+    {
+        let oldest = (0..self.sectors.len())
+            .filter(|x| *x != self.active && self.is_used(*x))
+            .min_by_key(|x| self.sectors[*x].sequence)
+            .ok_or(ErrValue::StackOverflow)?;
+
+        let info = self.sectors[oldest].info;
+        let moves: Vec<(String, ConfigEntry)> = self
+            .index
+            .iter()
+            .filter(|(_, x)| info.contains(x.address))
+            .map(|(key, x)| (key.clone(), *x))
+            .collect();
+
+        for (key, entry) in moves
+        {
+            let mut record = Vec::new();
+            record
+                .try_reserve_exact(entry.size(self.align) as usize)
+                .or(Err(ErrValue::MemAllocFailure))?;
+            record.resize(entry.size(self.align) as usize, 0);
+
+            self.flash.read(entry.address, &mut record)?;
+            let address = self.program(&record)?;
+            self.index.insert(key, ConfigEntry { address, ..entry });
+        }
+
+        self.erase(oldest)?;
+        self.collections += 1;
+        Ok(())
+    }
+
+    /// Load the records of a sector into the index.
+    fn scan(&mut self, num: usize) -> RetValue<()>
+    {
+        let info = self.sectors[num].info;
+        let mut offset = self.header_size();
+
+        while offset + RECORD_HEADER as u32 <= info.size
+        {
+            let mut bytes = [0; RECORD_HEADER];
+            self.flash.read(info.address + offset, &mut bytes)?;
+
+            if bytes.iter().all(|x| *x == 0xFF)
+            {
+                break;
+            }
+
+            // A header with impossible sizes is the first unit of a record cut by a power loss,
+            // the next record starts after it.
+            match self.load(info.address + offset, &bytes, info.size - offset)?
+            {
+                Some(size) => offset += size,
+                None => offset += self.align,
+            }
+        }
+
+        self.sectors[num].end = offset.min(info.size);
+        Ok(())
+    }
+
+    /// Load a record into the index, and return its size, or `None` when its header is broken.
+    fn load(
+        &mut self, address: u32, bytes: &[u8; RECORD_HEADER], room: u32,
+    ) -> RetValue<Option<u32>>
+    {
+        let Some(header) = RecordHeader::decode(bytes)
+        else
+        {
+            return Ok(None);
+        };
+
+        let size = header.size(self.align);
+        if size > room
+        {
+            return Ok(None);
+        }
+
+        let mut body = Vec::new();
+        let length = header.key_size as usize + header.value_size as usize;
+        body.try_reserve_exact(length).or(Err(ErrValue::MemAllocFailure))?;
+        body.resize(length, 0);
+        self.flash.read(address + RECORD_HEADER as u32, &mut body)?;
+
+        let (key, value) = body.split_at(header.key_size as usize);
+        // The record which is cut by a power loss is skipped, its header tells where the next
+        // one is.
+        let key = match core::str::from_utf8(key)
+        {
+            Ok(key) if header.check(key.as_bytes(), value) => key,
+            _ => return Ok(Some(size)),
+        };
+
+        match (header.kind, ConfigType::from_u8(header.kind))
+        {
+            (RECORD_REMOVED, _) =>
+            {
+                self.index.remove(key);
+            }
+            (_, Some(kind)) =>
+            {
+                let entry = ConfigEntry {
+                    address,
+                    kind,
+                    key_size: header.key_size,
+                    value_size: header.value_size,
+                };
+                self.index.insert(String::from(key), entry);
+            }
+            (_, None) => (),
+        }
+
+        Ok(Some(size))
+    }
+
+    fn format(&mut self, num: usize, sequence: u32) -> RetValue<()>
+    {
+        let end = self.header_size();
+        let sector = &mut self.sectors[num];
+        let header = SectorHeader { sequence, erases: sector.erases }.encode();
+
+        self.flash.write_verify(sector.info.address, &header)?;
+        sector.sequence = Some(sequence);
+        sector.end = end;
+        Ok(())
+    }
+
+    fn erase(&mut self, num: usize) -> RetValue<()>
+    {
+        let sector = &mut self.sectors[num];
+
+        self.flash.erase_sector(sector.info.index)?;
+        sector.sequence = None;
+        sector.erases = sector.erases.saturating_add(1);
+        sector.end = 0;
+        Ok(())
+    }
+
+    fn is_blank(&self, num: usize) -> RetValue<bool>
+    {
+        let info = self.sectors[num].info;
+        let mut buffer = [0; BLANK_CHECK_SIZE];
+
+        for offset in (0..info.size).step_by(BLANK_CHECK_SIZE)
+        {
+            let read = &mut buffer[..(info.size - offset).min(BLANK_CHECK_SIZE as u32) as usize];
+            self.flash.read(info.address + offset, read)?;
+
+            if read.iter().any(|x| *x != 0xFF)
+            {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn is_used(&self, num: usize) -> bool
+    {
+        self.sectors[num].sequence.is_some()
+    }
+
+    fn has_erased(&self) -> bool
+    {
+        (0..self.sectors.len()).any(|x| !self.is_used(x))
+    }
+
+    /// Pick the erased sector with the least erase count to level the wear.
+    fn pick_erased(&self) -> RetValue<usize>
+    {
+        (0..self.sectors.len())
+            .filter(|x| !self.is_used(*x))
+            .min_by_key(|x| self.sectors[*x].erases)
+            .ok_or(ErrValue::StackOverflow)
+    }
+
+    fn free(&self) -> u32
+    {
+        let sector = &self.sectors[self.active];
+        sector.info.size - sector.end
+    }
+
+    fn header_size(&self) -> u32
+    {
+        align_up(SECTOR_HEADER as u32, self.align)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use core::cell::{Cell, RefCell};
+    use std::boxed::Box;
+    use std::vec;
+
+    use super::*;
+
+    const BASE: u32 = 0x0800_0000;
+    const SECTOR_SIZE: u32 = 256;
+    const SECTOR_COUNT: u32 = 4;
+
+    /// The records of the tests take 16 bytes, a sector has room for 15 of them.
+    const SECTOR_RECORDS: u32 = 15;
+
+    const KEYS: [&str; 4] = ["k0", "k1", "k2", "k3"];
+
+    /// A flash in RAM, whose power can be cut in the middle of a write or around an erase.
+    struct RamFlash
+    {
+        data: RefCell<Vec<u8>>,
+
+        /// The program units which are programmed since their sector was erased.
+        programmed: RefCell<Vec<bool>>,
+        erases: RefCell<Vec<u32>>,
+        writes: Cell<u32>,
+        write_size: u32,
+
+        /// The writes to finish before the power is cut, and the bytes of the next one to keep.
+        write_cut: Cell<Option<(u32, usize)>>,
+
+        /// The erases to finish before the power is cut, and whether the next one is finished.
+        erase_cut: Cell<Option<(u32, bool)>>,
+        off: Cell<bool>,
+    }
+
+    impl RamFlash
+    {
+        fn new(write_size: u32) -> &'static Self
+        {
+            let size = (SECTOR_SIZE * SECTOR_COUNT) as usize;
+
+            Box::leak(Box::new(Self {
+                data: RefCell::new(vec![0xFF; size]),
+                programmed: RefCell::new(vec![false; size / write_size as usize]),
+                erases: RefCell::new(vec![0; SECTOR_COUNT as usize]),
+                writes: Cell::new(0),
+                write_size,
+                write_cut: Cell::new(None),
+                erase_cut: Cell::new(None),
+                off: Cell::new(false),
+            }))
+        }
+
+        fn cut_write(&self, writes: u32, keep: usize)
+        {
+            self.write_cut.set(Some((writes, keep)));
+        }
+
+        fn cut_erase(&self, erases: u32, finish: bool)
+        {
+            self.erase_cut.set(Some((erases, finish)));
+        }
+
+        fn power_on(&self)
+        {
+            self.write_cut.set(None);
+            self.erase_cut.set(None);
+            self.off.set(false);
+        }
+
+        /// Change the bytes without the rules of the flash, like a damage.
+        fn corrupt(&self, address: u32, data: &[u8])
+        {
+            let offset = (address - BASE) as usize;
+            self.data.borrow_mut()[offset..offset + data.len()].copy_from_slice(data);
+        }
+
+        fn offset(&self, address: u32, size: usize) -> RetValue<usize>
+        {
+            match address >= BASE && (address - BASE) as usize + size <= self.size() as usize
+            {
+                true => Ok((address - BASE) as usize),
+                false => Err(ErrValue::Param),
+            }
+        }
+    }
+
+    impl FlashCtrl for RamFlash
+    {
+        fn base(&self) -> u32
+        {
+            BASE
+        }
+
+        fn size(&self) -> u32
+        {
+            SECTOR_SIZE * SECTOR_COUNT
+        }
+
+        fn write_size(&self) -> u32
+        {
+            self.write_size
+        }
+
+        fn sector_count(&self) -> u32
+        {
+            SECTOR_COUNT
+        }
+
+        fn sector(&self, index: u32) -> RetValue<FlashSector>
+        {
+            match index < SECTOR_COUNT
+            {
+                true => Ok(FlashSector {
+                    index,
+                    bank: 0,
+                    address: BASE + index * SECTOR_SIZE,
+                    size: SECTOR_SIZE,
+                }),
+                false => Err(ErrValue::Param),
+            }
+        }
+
+        fn read(&self, address: u32, data: &mut [u8]) -> RetValue<()>
+        {
+            let offset = self.offset(address, data.len())?;
+            data.copy_from_slice(&self.data.borrow()[offset..offset + data.len()]);
+            Ok(())
+        }
+
+        fn write(&self, address: u32, data: &[u8]) -> RetValue<()>
+        {
+            let offset = self.offset(address, data.len())?;
+            if self.off.get()
+            {
+                return Err(ErrValue::LowLevelFailure);
+            }
+
+            let keep = match self.write_cut.get()
+            {
+                Some((0, keep)) =>
+                {
+                    self.off.set(true);
+                    keep.min(data.len())
+                }
+                Some((writes, keep)) =>
+                {
+                    self.write_cut.set(Some((writes - 1, keep)));
+                    data.len()
+                }
+                None => data.len(),
+            };
+
+            // The units with ECC are programmed only once.
+            let unit = self.write_size as usize;
+            let units = offset / unit..(offset + keep).div_ceil(unit);
+            if unit > 1 && self.programmed.borrow()[units.clone()].iter().any(|x| *x)
+            {
+                return Err(ErrValue::LowLevelFailure);
+            }
+
+            self.programmed.borrow_mut()[units].fill(true);
+            self.data.borrow_mut()[offset..offset + keep]
+                .iter_mut()
+                .zip(data)
+                .for_each(|(x, y)| *x &= *y);
+
+            match keep == data.len()
+            {
+                true =>
+                {
+                    self.writes.set(self.writes.get() + 1);
+                    Ok(())
+                }
+                false => Err(ErrValue::LowLevelFailure),
+            }
+        }
+
+        fn erase_sector(&self, index: u32) -> RetValue<()>
+        {
+            let sector = self.sector(index)?;
+            if self.off.get()
+            {
+                return Err(ErrValue::LowLevelFailure);
+            }
+
+            match self.erase_cut.get()
+            {
+                Some((0, false)) =>
+                {
+                    self.off.set(true);
+                    return Err(ErrValue::LowLevelFailure);
+                }
+                Some((0, true)) => self.off.set(true),
+                Some((erases, finish)) => self.erase_cut.set(Some((erases - 1, finish))),
+                None => (),
+            }
+
+            let range = (sector.address - BASE) as usize..(sector.end() - BASE) as usize;
+            let unit = self.write_size as usize;
+
+            self.data.borrow_mut()[range.clone()].fill(0xFF);
+            self.programmed.borrow_mut()[range.start / unit..range.end / unit].fill(false);
+            self.erases.borrow_mut()[index as usize] += 1;
+            Ok(())
+        }
+    }
+
+    fn mount(flash: &'static RamFlash, sectors: &[u32]) -> ConfigLog
+    {
+        flash.power_on();
+        ConfigLog::mount(flash, sectors).unwrap()
+    }
+
+    fn set(log: &mut ConfigLog, key: &str, value: u32) -> RetValue<()>
+    {
+        log.write(key, ConfigType::U32, &value.to_le_bytes())
+    }
+
+    fn get(log: &ConfigLog, key: &str) -> Option<u32>
+    {
+        let mut data = [0; 4];
+        log.read(key, &mut data).ok().map(|_| u32::from_le_bytes(data))
+    }
+
+    /// Fill the active sector of a new log with the values `0..15` of the keys `k0` to `k3`,
+    /// so the next write starts a new sector.
+    fn fill(log: &mut ConfigLog) -> [u32; 4]
+    {
+        (0..SECTOR_RECORDS).for_each(|x| set(log, KEYS[x as usize % 4], x).unwrap());
+        [12, 13, 14, 11]
+    }
+
+    fn check(log: &ConfigLog, values: [u32; 4])
+    {
+        KEYS.iter().zip(values).for_each(|(key, x)| assert_eq!(get(log, key), Some(x), "{key}"));
+    }
+
+    fn is_formatted(flash: &RamFlash, index: u32) -> bool
+    {
+        let mut bytes = [0; SECTOR_HEADER];
+        flash.read(BASE + index * SECTOR_SIZE, &mut bytes).unwrap();
+        SectorHeader::decode(&bytes).is_some()
+    }
+
+    #[test]
+    fn mount_blank()
+    {
+        for write_size in [1, 16]
+        {
+            let flash = RamFlash::new(write_size);
+            let mut log = mount(flash, &[0, 1, 2]);
+
+            let stats = log.stats();
+            assert_eq!((stats.sectors, stats.keys, stats.used), (3, 0, 16));
+            assert_eq!((0..3).filter(|x| is_formatted(flash, *x)).count(), 1);
+
+            set(&mut log, "a", 1).unwrap();
+            assert_eq!(get(&mount(flash, &[0, 1, 2]), "a"), Some(1));
+        }
+    }
+
+    #[test]
+    fn mount_broken_header()
+    {
+        for write_size in [1, 16]
+        {
+            let flash = RamFlash::new(write_size);
+            set(&mut mount(flash, &[0, 1]), "a", 1).unwrap();
+
+            // A newer header whose CRC fails is not taken, its sector is erased.
+            let mut header = SectorHeader { sequence: 5, erases: 0 }.encode();
+            header[4] ^= 1;
+            flash.corrupt(BASE + SECTOR_SIZE, &header);
+
+            let log = mount(flash, &[0, 1]);
+            assert_eq!(get(&log, "a"), Some(1));
+            assert_eq!(flash.erases.borrow()[1], 1);
+            assert!(!is_formatted(flash, 1));
+        }
+    }
+
+    #[test]
+    fn mount_partial_record()
+    {
+        // The cut in the first unit leaves the sizes of the header unwritten, the later one
+        // leaves a whole header with a bad CRC.
+        for (write_size, keep) in [(1, 3), (1, 10), (16, 0), (16, 16)]
+        {
+            let flash = RamFlash::new(write_size);
+            let mut log = mount(flash, &[0, 1]);
+            set(&mut log, "a", 1).unwrap();
+            set(&mut log, "b", 2).unwrap();
+
+            flash.cut_write(0, keep);
+            log.write("a", ConfigType::U64, &[3; 8]).unwrap_err();
+
+            let mut log = mount(flash, &[0, 1]);
+            assert_eq!(get(&log, "a"), Some(1));
+            assert_eq!(get(&log, "b"), Some(2));
+
+            // The records after the broken one are found too.
+            set(&mut log, "a", 4).unwrap();
+            set(&mut log, "c", 5).unwrap();
+
+            let log = mount(flash, &[0, 1]);
+            assert_eq!(
+                (get(&log, "a"), get(&log, "b"), get(&log, "c")),
+                (Some(4), Some(2), Some(5))
+            );
+        }
+    }
+
+    #[test]
+    fn latest_wins_and_tombstones()
+    {
+        let flash = RamFlash::new(1);
+        let mut log = mount(flash, &[0, 1]);
+
+        set(&mut log, "a", 1).unwrap();
+        set(&mut log, "b", 2).unwrap();
+        set(&mut log, "a", 3).unwrap();
+        log.remove("b").unwrap();
+        set(&mut log, "c", 4).unwrap();
+        set(&mut log, "d", 5).unwrap();
+        log.remove("d").unwrap();
+        set(&mut log, "d", 6).unwrap();
+        assert!(matches!(log.remove("e"), Err(ErrValue::InstanceNotFound)));
+
+        let log = mount(flash, &[0, 1]);
+        let mut keys = Vec::new();
+        log.keys(&mut |key, _, _| keys.push(String::from(key)));
+
+        assert_eq!(keys, ["a", "c", "d"]);
+        assert_eq!((get(&log, "a"), get(&log, "b")), (Some(3), None));
+        assert_eq!((get(&log, "c"), get(&log, "d")), (Some(4), Some(6)));
+    }
+
+    #[test]
+    fn collect_interrupted_before_erase()
+    {
+        for write_size in [1, 16]
+        {
+            let flash = RamFlash::new(write_size);
+            let mut log = mount(flash, &[0, 1]);
+            let values = fill(&mut log);
+
+            flash.cut_erase(0, false);
+            set(&mut log, "k0", 100).unwrap_err();
+
+            // Both sectors have the live records, the mount finishes the collection.
+            let mut log = mount(flash, &[0, 1]);
+            check(&log, values);
+            assert_eq!(log.stats().collections, 1);
+            assert_eq!(flash.erases.borrow()[0], 1);
+
+            set(&mut log, "k0", 100).unwrap();
+            check(&mount(flash, &[0, 1]), [100, 13, 14, 11]);
+        }
+    }
+
+    #[test]
+    fn collect_interrupted_after_erase()
+    {
+        for write_size in [1, 16]
+        {
+            let flash = RamFlash::new(write_size);
+            let mut log = mount(flash, &[0, 1]);
+            let values = fill(&mut log);
+
+            flash.cut_erase(0, true);
+            set(&mut log, "k0", 100).unwrap_err();
+
+            let mut log = mount(flash, &[0, 1]);
+            check(&log, values);
+            assert_eq!(log.stats().collections, 0);
+            assert!(!is_formatted(flash, 0));
+
+            set(&mut log, "k0", 100).unwrap();
+            check(&mount(flash, &[0, 1]), [100, 13, 14, 11]);
+        }
+    }
+
+    #[test]
+    fn collect_interrupted_in_copy()
+    {
+        // The header of the new sector and the first copy are written, the second copy is cut.
+        for (write_size, keep) in [(1, 3), (1, 12), (16, 0)]
+        {
+            let flash = RamFlash::new(write_size);
+            let mut log = mount(flash, &[0, 1]);
+            let values = fill(&mut log);
+
+            flash.cut_write(2, keep);
+            set(&mut log, "k0", 100).unwrap_err();
+
+            let log = mount(flash, &[0, 1]);
+            check(&log, values);
+            assert_eq!(log.stats().collections, 1);
+            assert!(!is_formatted(flash, 0));
+        }
+    }
+
+    #[test]
+    fn pick_erased_by_wear()
+    {
+        let flash = RamFlash::new(1);
+        let mut log = mount(flash, &[0, 1, 2, 3]);
+
+        log.sectors[1].erases = 5;
+        log.sectors[2].erases = 2;
+        log.sectors[3].erases = 7;
+        assert_eq!(log.pick_erased().unwrap(), 2);
+
+        log.rotate().unwrap();
+        assert_eq!(log.active, 2);
+    }
+
+    #[test]
+    fn wear_is_levelled()
+    {
+        let flash = RamFlash::new(1);
+        let mut log = mount(flash, &[0, 1, 2, 3]);
+
+        for x in 0..1000
+        {
+            set(&mut log, KEYS[x as usize % 4], x).unwrap();
+
+            if x % 97 == 0
+            {
+                log = mount(flash, &[0, 1, 2, 3]);
+            }
+        }
+
+        let erases = flash.erases.borrow();
+        assert!(*erases.iter().min().unwrap() > 10);
+        assert!(erases.iter().max().unwrap() - erases.iter().min().unwrap() <= 1);
+        check(&log, [996, 997, 998, 999]);
+    }
+
+    #[test]
+    fn write_skips_identical()
+    {
+        let flash = RamFlash::new(16);
+        let mut log = mount(flash, &[0, 1]);
+
+        set(&mut log, "a", 1).unwrap();
+        let (writes, used) = (flash.writes.get(), log.stats().used);
+
+        set(&mut log, "a", 1).unwrap();
+        assert_eq!((flash.writes.get(), log.stats().used), (writes, used));
+
+        // The same bytes of another type are a new value.
+        log.write("a", ConfigType::I32, &1u32.to_le_bytes()).unwrap();
+        assert_eq!(flash.writes.get(), writes + 1);
+
+        set(&mut log, "a", 2).unwrap();
+        assert_eq!(flash.writes.get(), writes + 2);
+        assert_eq!(log.lookup("a").unwrap().kind, ConfigType::U32);
+    }
+}
@@ -0,0 +1,125 @@
+//! The layout of the store on the flash.
+//!
+//! Every sector starts with a header of 16 bytes, which has the magic number, the sequence of
+//! the sector, its erase count and the CRC of them. The records follow the header one by one,
+//! each of them is aligned to the program unit of the flash:
+//!
+//! | Offset | Size  | Content                                                   |
+//! |--------|-------|-----------------------------------------------------------|
+//! | 0      | 1     | The size of the key                                       |
+//! | 1      | 1     | The type of the value, or [`RECORD_REMOVED`]              |
+//! | 2      | 2     | The size of the value                                     |
+//! | 4      | 4     | The CRC of the first 4 bytes, the key and the value       |
+//! | 8      | n     | The key and then the value, padded with `0xFF`            |
+//!
+//! The first record whose header keeps the erased value ends the sector. A record whose CRC
+//! fails is skipped by the sizes in its header, and a header with impossible sizes is skipped by
+//! a program unit, as only the first unit of a record can be cut before its sizes are written.
+
+use sces::crc::{crc32, crc32_update};
+
+/// `"SCFG"` in little endian.
+pub const SECTOR_MAGIC: u32 = 0x4746_4353;
+pub const SECTOR_HEADER: usize = 16;
+
+pub const RECORD_HEADER: usize = 8;
+pub const KEY_MAX: usize = 32;
+
+/// The type of the record which removes its key.
+pub const RECORD_REMOVED: u8 = 0xFE;
+
+pub const fn align_up(size: u32, align: u32) -> u32
+{
+    size.div_ceil(align) * align
+}
+
+pub struct SectorHeader
+{
+    pub sequence: u32,
+    pub erases: u32,
+}
+
+impl SectorHeader
+{
+    pub fn encode(&self) -> [u8; SECTOR_HEADER]
+    {
+        let mut bytes = [0; SECTOR_HEADER];
+        bytes[0..4].copy_from_slice(&SECTOR_MAGIC.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.erases.to_le_bytes());
+
+        let crc = crc32(&bytes[..12]);
+        bytes[12..16].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8; SECTOR_HEADER]) -> Option<Self>
+    {
+        let word =
+            |x: usize| u32::from_le_bytes([bytes[x], bytes[x + 1], bytes[x + 2], bytes[x + 3]]);
+
+        (word(0) == SECTOR_MAGIC && word(12) == crc32(&bytes[..12]))
+            .then(|| Self { sequence: word(4), erases: word(8) })
+    }
+}
+
+pub struct RecordHeader
+{
+    pub key_size: u8,
+    pub kind: u8,
+    pub value_size: u16,
+    pub crc: u32,
+}
+
+impl RecordHeader
+{
+    pub fn new(key: &[u8], kind: u8, value: &[u8]) -> Self
+    {
+        let mut header =
+            Self { key_size: key.len() as u8, kind, value_size: value.len() as u16, crc: 0 };
+        header.crc = header.calculate(key, value);
+        header
+    }
+
+    /// Decode the header, it fails when the sizes are impossible.
+    pub fn decode(bytes: &[u8; RECORD_HEADER]) -> Option<Self>
+    {
+        let header = Self {
+            key_size: bytes[0],
+            kind: bytes[1],
+            value_size: u16::from_le_bytes([bytes[2], bytes[3]]),
+            crc: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        };
+
+        (header.key_size != 0 && header.key_size as usize <= KEY_MAX).then_some(header)
+    }
+
+    pub fn encode(&self) -> [u8; RECORD_HEADER]
+    {
+        let mut bytes = [0; RECORD_HEADER];
+        bytes[0] = self.key_size;
+        bytes[1] = self.kind;
+        bytes[2..4].copy_from_slice(&self.value_size.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.crc.to_le_bytes());
+        bytes
+    }
+
+    /// Check the key and the value which follow the header.
+    pub fn check(&self, key: &[u8], value: &[u8]) -> bool
+    {
+        self.crc == self.calculate(key, value)
+    }
+
+    /// Get the size of the whole record on the flash.
+    pub fn size(&self, align: u32) -> u32
+    {
+        align_up((RECORD_HEADER + self.key_size as usize + self.value_size as usize) as u32, align)
+    }
+
+    fn calculate(&self, key: &[u8], value: &[u8]) -> u32
+    {
+        let head = [self.key_size, self.kind, self.value_size as u8, (self.value_size >> 8) as u8];
+        let crc = crc32_update(0xFFFF_FFFF, &head);
+        !crc32_update(crc32_update(crc, key), value)
+    }
+}
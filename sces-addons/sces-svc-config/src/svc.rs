@@ -0,0 +1,42 @@
+use sces::value::{ErrValue, RetValue};
+
+use crate::config::ConfigStore;
+use crate::value::ConfigValue;
+
+static mut SVC: Option<&'static dyn ConfigStore> = None;
+
+pub const CFS: &str = "<ConfigService>";
+
+pub struct ConfigService;
+
+impl ConfigService
+{
+    pub fn initialize<T>(instance: &'static T) -> RetValue<()>
+    where
+        T: ConfigStore,
+    {
+        #[allow(static_mut_refs)]
+        unsafe {
+            SVC.is_none().then_some(()).ok_or(ErrValue::InstanceDuplicate)?
+        };
+        unsafe { SVC = Some(instance) };
+        Ok(())
+    }
+
+    pub fn instance() -> &'static dyn ConfigStore
+    {
+        unsafe { SVC.unwrap() }
+    }
+
+    /// Get the value of `key` from the store of the service.
+    pub fn get<T: ConfigValue>(key: &str) -> RetValue<T>
+    {
+        Self::instance().get(key)
+    }
+
+    /// Set the value of `key` in the store of the service.
+    pub fn set<T: ConfigValue>(key: &str, value: &T) -> RetValue<()>
+    {
+        Self::instance().set(key, value)
+    }
+}
@@ -0,0 +1,98 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use sces::value::{ErrValue, RetValue};
+
+use crate::config::ConfigType;
+
+/// A value which is kept in the store as its bytes, the numbers are in little endian.
+pub trait ConfigValue: Sized
+{
+    const TYPE: ConfigType;
+
+    /// Call `f` with the bytes of the value.
+    fn with_config<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R;
+
+    /// Get the value from its bytes, it fails with [`ErrValue::InstanceInvalid`] when they
+    /// are broken.
+    fn from_config(data: &[u8]) -> RetValue<Self>;
+}
+
+macro_rules! config_number {
+    ($($t:ty => $kind:ident),*) => {
+        $(
+            impl ConfigValue for $t
+            {
+                const TYPE: ConfigType = ConfigType::$kind;
+
+                fn with_config<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R
+                {
+                    f(&self.to_le_bytes())
+                }
+
+                fn from_config(data: &[u8]) -> RetValue<Self>
+                {
+                    data.try_into().map(<$t>::from_le_bytes).or(Err(ErrValue::InstanceInvalid))
+                }
+            }
+        )*
+    };
+}
+
+config_number!(
+    u8 => U8, u16 => U16, u32 => U32, u64 => U64,
+    i8 => I8, i16 => I16, i32 => I32, i64 => I64,
+    f32 => F32, f64 => F64
+);
+
+impl ConfigValue for bool
+{
+    const TYPE: ConfigType = ConfigType::Bool;
+
+    fn with_config<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R
+    {
+        f(&[*self as u8])
+    }
+
+    fn from_config(data: &[u8]) -> RetValue<Self>
+    {
+        match data
+        {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(ErrValue::InstanceInvalid),
+        }
+    }
+}
+
+impl ConfigValue for String
+{
+    const TYPE: ConfigType = ConfigType::Str;
+
+    fn with_config<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R
+    {
+        f(self.as_bytes())
+    }
+
+    fn from_config(data: &[u8]) -> RetValue<Self>
+    {
+        core::str::from_utf8(data).map(String::from).or(Err(ErrValue::InstanceInvalid))
+    }
+}
+
+impl ConfigValue for Vec<u8>
+{
+    const TYPE: ConfigType = ConfigType::Bytes;
+
+    fn with_config<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R
+    {
+        f(self)
+    }
+
+    fn from_config(data: &[u8]) -> RetValue<Self>
+    {
+        let mut value = Vec::new();
+        value.try_reserve_exact(data.len()).or(Err(ErrValue::MemAllocFailure))?;
+        value.extend_from_slice(data);
+        Ok(value)
+    }
+}
@@ -0,0 +1,196 @@
+use core::fmt::{Arguments, Display, Result, Write};
+
+use log::Level;
+
+/// The flag set in the level byte of a frame, when the frame carries a formatted text but not an
+/// interned format string.
+pub const FRAME_TEXT: u8 = 0x80;
+
+const ARG_UNSIGNED: u8 = 0x01;
+const ARG_SIGNED: u8 = 0x02;
+const ARG_F32: u8 = 0x03;
+const ARG_F64: u8 = 0x04;
+const ARG_BOOL: u8 = 0x05;
+const ARG_CHAR: u8 = 0x06;
+const ARG_STR: u8 = 0x07;
+
+/// The output of the binary log encoding.
+pub trait LogSink
+{
+    fn put(&mut self, bytes: &[u8]);
+}
+
+/// Trait for the values which could be an argument of [`crate::binlog`].
+///
+/// Every argument is encoded as a type tag and a compact value, so the host decoder could print
+/// it without knowing the types in the format string.
+/// The [`Display`] is used when the record is output by a text console.
+pub trait LogArg: Display
+{
+    fn encode(&self, sink: &mut dyn LogSink);
+}
+
+/// Encode the head of a frame: the sequence, the level, the ticks and the interned string address.
+///
+/// The frame content will be COBS encoded by the console, every frame ends with a `0x00`.
+pub fn encode_head(sink: &mut dyn LogSink, seq: u8, level: Level, ticks: u32, id: Option<usize>)
+{
+    sink.put(&[seq, level as u8 | id.map_or(FRAME_TEXT, |_| 0)]);
+    encode_varint(sink, ticks as u64);
+
+    if let Some(id) = id
+    {
+        encode_varint(sink, id as u64);
+    }
+}
+
+pub fn encode_varint(sink: &mut dyn LogSink, mut value: u64)
+{
+    while value >= 0x80
+    {
+        sink.put(&[value as u8 | 0x80]);
+        value >>= 7;
+    }
+
+    sink.put(&[value as u8]);
+}
+
+/// Encode a formatted text as a string argument.
+pub fn encode_fmt(sink: &mut dyn LogSink, args: &Arguments)
+{
+    let mut length = TextLength(0);
+    length.write_fmt(*args).ok();
+
+    sink.put(&[ARG_STR]);
+    encode_varint(sink, length.0 as u64);
+    TextSink(sink).write_fmt(*args).ok();
+}
+
+struct TextLength(usize);
+
+impl Write for TextLength
+{
+    fn write_str(&mut self, s: &str) -> Result
+    {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+struct TextSink<'a>(&'a mut dyn LogSink);
+
+impl<'a> Write for TextSink<'a>
+{
+    fn write_str(&mut self, s: &str) -> Result
+    {
+        self.0.put(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl<T> LogArg for &T
+where
+    T: LogArg + ?Sized,
+{
+    fn encode(&self, sink: &mut dyn LogSink)
+    {
+        (**self).encode(sink)
+    }
+}
+
+macro_rules! impl_log_arg_unsigned {
+    ($($ty:ty),*) => {
+        $(
+            impl LogArg for $ty
+            {
+                fn encode(&self, sink: &mut dyn LogSink)
+                {
+                    sink.put(&[ARG_UNSIGNED]);
+                    encode_varint(sink, *self as u64);
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_log_arg_signed {
+    ($($ty:ty),*) => {
+        $(
+            impl LogArg for $ty
+            {
+                fn encode(&self, sink: &mut dyn LogSink)
+                {
+                    let value = *self as i64;
+
+                    sink.put(&[ARG_SIGNED]);
+                    encode_varint(sink, ((value << 1) ^ (value >> 63)) as u64);
+                }
+            }
+        )*
+    };
+}
+
+impl_log_arg_unsigned!(u8, u16, u32, u64, usize);
+impl_log_arg_signed!(i8, i16, i32, i64, isize);
+
+impl LogArg for f32
+{
+    fn encode(&self, sink: &mut dyn LogSink)
+    {
+        sink.put(&[ARG_F32]);
+        sink.put(&self.to_le_bytes());
+    }
+}
+
+impl LogArg for f64
+{
+    fn encode(&self, sink: &mut dyn LogSink)
+    {
+        sink.put(&[ARG_F64]);
+        sink.put(&self.to_le_bytes());
+    }
+}
+
+impl LogArg for bool
+{
+    fn encode(&self, sink: &mut dyn LogSink)
+    {
+        sink.put(&[ARG_BOOL, *self as u8]);
+    }
+}
+
+impl LogArg for char
+{
+    fn encode(&self, sink: &mut dyn LogSink)
+    {
+        sink.put(&[ARG_CHAR]);
+        encode_varint(sink, *self as u64);
+    }
+}
+
+impl LogArg for str
+{
+    fn encode(&self, sink: &mut dyn LogSink)
+    {
+        sink.put(&[ARG_STR]);
+        encode_varint(sink, self.len() as u64);
+        sink.put(self.as_bytes());
+    }
+}
+
+/// Log a record in the binary format, the format string is interned and never be formatted on
+/// the device, all arguments must implement [`LogArg`].
+///
+/// ```ignore
+/// binlog!(Level::Info, "temperature {} of sensor {}", 23.5f32, id);
+/// ```
+#[macro_export]
+macro_rules! binlog {
+    ($level:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::ConsoleService::log_binary(
+            &$crate::log::Metadata::builder().level($level).target(module_path!()).build(),
+            $crate::log_intern!($fmt),
+            &[$(&$arg as &dyn $crate::LogArg),*],
+        )
+    };
+}
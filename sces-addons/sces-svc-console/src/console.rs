@@ -1,9 +1,64 @@
-use log::Log;
+use log::{LevelFilter, Log, Metadata};
 use sces::value::RetValue;
 
+use crate::{LogArg, LogFormat};
+
 pub trait Console: Send + Sync + Log
 {
     fn accept_dispatch(&self, exe: &'static dyn ConsoleExecute) -> RetValue<()>;
+
+    /// Set the level of the log records whose target starts with `target`, an empty `target`
+    /// sets the default level of all records.
+    fn set_log_level(&self, target: &str, level: LevelFilter) -> RetValue<()>;
+
+    /// Get the most verbose level of all log targets.
+    fn max_log_level(&self) -> LevelFilter;
+
+    fn set_log_format(&self, format: &'static dyn LogFormat) -> RetValue<()>;
+
+    /// Set how the log records are encoded in the output, it should be set before any logging.
+    fn set_log_encoding(&self, encoding: LogEncoding) -> RetValue<()>;
+
+    /// Log a record of [`crate::binlog`], whose format string is interned at the address `id`.
+    fn log_binary(&self, metadata: &Metadata, id: usize, args: &[&dyn LogArg]);
+}
+
+/// How the log records are encoded in the console output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogEncoding
+{
+    /// Human readable lines formatted by the [`LogFormat`].
+    Text,
+
+    /// COBS framed binary records, which should be decoded by the host tool `sces-logcat`.
+    Binary,
+}
+
+impl LogEncoding
+{
+    /// The byte to end every record in the output.
+    pub const fn delimiter(&self) -> u8
+    {
+        match self
+        {
+            LogEncoding::Text => b'\n',
+            LogEncoding::Binary => 0,
+        }
+    }
+}
+
+/// What to do when the log buffer has no space for a new record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogOverflow
+{
+    /// Drop the oldest buffered records until the new one fits.
+    DropOldest,
+
+    /// Drop the new record, keep the buffered ones.
+    DropNewest,
+
+    /// Wait until the buffered records have been output, only in task context.
+    Block,
 }
 
 pub trait ConsoleExecute
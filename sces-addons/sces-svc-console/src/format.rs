@@ -0,0 +1,66 @@
+use core::fmt::{Result, Write};
+
+use log::{Level, Record};
+
+/// The information collected by the console for every log record.
+pub struct LogContext<'a>
+{
+    /// The OS tick count when the record is logged, in milliseconds.
+    pub ticks: u32,
+
+    /// The name of the task who logs the record, `ISR` when logged in an interrupt handler.
+    pub task: &'a str,
+}
+
+/// Trait to decide how a log record looks like in the console output.
+///
+/// The line end will be appended by the console, so don't write it in [`LogFormat::format`].
+pub trait LogFormat: Send + Sync
+{
+    fn format(&self, out: &mut dyn Write, context: &LogContext, record: &Record) -> Result;
+}
+
+/// The default log format, as `[    1.234] [ INFO] (task) message`.
+pub struct DefaultLogFormat
+{
+    colored: bool,
+}
+
+impl DefaultLogFormat
+{
+    pub const fn new(colored: bool) -> Self
+    {
+        Self { colored }
+    }
+
+    const fn color(level: Level) -> &'static str
+    {
+        match level
+        {
+            Level::Error => "\x1b[31m",
+            Level::Warn => "\x1b[33m",
+            Level::Info => "\x1b[32m",
+            Level::Debug => "\x1b[36m",
+            Level::Trace => "\x1b[90m",
+        }
+    }
+}
+
+impl LogFormat for DefaultLogFormat
+{
+    fn format(&self, out: &mut dyn Write, context: &LogContext, record: &Record) -> Result
+    {
+        write!(out, "[{:>5}.{:03}] ", context.ticks / 1000, context.ticks % 1000)?;
+
+        if self.colored
+        {
+            write!(out, "{}[{:>5}]\x1b[0m ", Self::color(record.level()), record.level())?;
+        }
+        else
+        {
+            write!(out, "[{:>5}] ", record.level())?;
+        }
+
+        write!(out, "({}) {}", context.task, record.args())
+    }
+}
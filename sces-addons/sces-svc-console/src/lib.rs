@@ -1,13 +1,35 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
+mod binary;
 mod console;
+mod format;
 mod native;
+mod retained;
 mod svc;
+mod transport;
 
+pub use binary::LogArg;
+pub use binary::LogSink;
 pub use console::Console;
 pub use console::ConsoleCommands;
 pub use console::ConsoleExecute;
+pub use console::LogEncoding;
+pub use console::LogOverflow;
+pub use format::DefaultLogFormat;
+pub use format::LogContext;
+pub use format::LogFormat;
 pub use native::NativeConsole;
+pub use native::NativeConsoleDrain;
+pub use retained::RetainedDump;
 pub use svc::ConsoleService;
+pub use transport::ConsoleTransport;
+pub use transport::ConsoleTransportEvent;
+pub use transport::TransportDevice;
+pub use transport::UartTransport;
+
+#[doc(hidden)]
+pub use log;
+#[doc(hidden)]
+pub use sces_derive::log_intern;
@@ -1,22 +1,33 @@
-use log::Log;
+use core::ptr::NonNull;
+
+use log::{LevelFilter, Log, Metadata};
 use sces::value::RetValue;
-use sces::mcu::uart::{UartCtrl, UartCtrlEvent, UartDevice};
 use sces::os::task::ITaskMain;
+use sces::os::tick::Duration;
 use sces::os::RTOS;
 
 use crate::native::dispatch::ConsoleDispatchCore;
 use crate::native::print::ConsolePrintCore;
-use crate::{Console, ConsoleExecute};
+use crate::transport::{ConsoleTransport, ConsoleTransportEvent, TransportDevice};
+use crate::{Console, ConsoleExecute, LogArg, LogEncoding, LogFormat, LogOverflow};
 
 mod cache;
 mod dispatch;
+mod filter;
 mod print;
+mod ring;
+mod share;
+
+const LOG_CAPACITY: usize = 1024;
+const LOG_REPORT_PERIOD: Duration = Duration::from_secs(1);
 
+/// A console runs over a [`ConsoleTransport`], it becomes the event agent of the transport when
+/// its task starts running.
 pub struct NativeConsole<OS>
 where
     OS: RTOS,
 {
-    serial_port: UartDevice,
+    transport: TransportDevice,
     dispatcher: ConsoleDispatchCore<OS>,
     printer: ConsolePrintCore<OS>,
 }
@@ -25,14 +36,26 @@ impl<OS> NativeConsole<OS>
 where
     OS: RTOS,
 {
-    pub fn new(uart: &'static mut dyn UartCtrl) -> RetValue<Self>
+    pub fn new(transport: &'static mut dyn ConsoleTransport) -> RetValue<Self>
+    {
+        Self::with_log_buffer(transport, LOG_CAPACITY, LogOverflow::DropOldest)
+    }
+
+    pub fn with_log_buffer(
+        transport: &'static mut dyn ConsoleTransport, capacity: usize, overflow: LogOverflow,
+    ) -> RetValue<Self>
     {
         Ok(Self {
-            serial_port: UartDevice::new(uart),
+            transport: TransportDevice::new(transport),
             dispatcher: ConsoleDispatchCore::new()?,
-            printer: ConsolePrintCore::new()?,
+            printer: ConsolePrintCore::new(capacity, overflow)?,
         })
     }
+
+    pub fn dropped_logs(&self) -> u32
+    {
+        self.printer.dropped()
+    }
 }
 
 impl<OS> Console for NativeConsole<OS>
@@ -43,35 +66,71 @@ where
     {
         self.dispatcher.accept_dispatch(exe)
     }
+
+    fn set_log_level(&self, target: &str, level: LevelFilter) -> RetValue<()>
+    {
+        self.printer.set_level(target, level)
+    }
+
+    fn max_log_level(&self) -> LevelFilter
+    {
+        self.printer.max_level()
+    }
+
+    fn set_log_format(&self, format: &'static dyn LogFormat) -> RetValue<()>
+    {
+        self.printer.set_format(format)
+    }
+
+    fn set_log_encoding(&self, encoding: LogEncoding) -> RetValue<()>
+    {
+        self.printer.set_encoding(encoding)
+    }
+
+    fn log_binary(&self, metadata: &Metadata, id: usize, args: &[&dyn LogArg])
+    {
+        if self.enabled(metadata)
+        {
+            #[allow(unused_must_use)]
+            self.printer.writes_binary(metadata.level(), id, args);
+        }
+    }
 }
 
 impl<OS> ITaskMain for NativeConsole<OS>
 where
-    OS: Sized + RTOS,
+    OS: Sized + RTOS + 'static,
 {
     fn main(&mut self)
     {
+        let agent = NonNull::from(&*self);
+        self.transport.as_mut().set_event_agent(unsafe { agent.as_ref() });
+
         loop
         {
             #[allow(unused_must_use)]
-            self.dispatcher.wait_and_dispatch(&self.serial_port);
+            self.dispatcher.wait_and_dispatch(&self.transport);
         }
     }
 }
 
-impl<OS> UartCtrlEvent for NativeConsole<OS>
+impl<OS> ConsoleTransportEvent for NativeConsole<OS>
 where
     OS: Sized + RTOS,
 {
-    fn on_uart_rx_complete(&self, size: u32)
+    fn on_transport_written(&self)
     {
-        #[allow(unused_must_use)]
-        self.dispatcher.set_dispatch_signal(size as usize);
+        self.printer.set_drain_signal();
     }
 
-    fn on_uart_error(&self)
+    fn on_transport_read(&self, size: usize)
     {
-        self.on_uart_rx_complete(0);
+        self.dispatcher.set_dispatch_signal(size);
+    }
+
+    fn on_transport_error(&self)
+    {
+        self.on_transport_read(0);
     }
 }
 
@@ -83,17 +142,52 @@ impl<OS> Log for NativeConsole<OS>
 where
     OS: RTOS,
 {
-    #[allow(unused_variables)]
     fn enabled(&self, metadata: &log::Metadata) -> bool
     {
-        true
+        self.printer.enabled(metadata)
     }
 
     fn log(&self, record: &log::Record)
     {
-        #[allow(unused_must_use)]
-        self.printer.writes(&self.serial_port, record);
+        if self.enabled(record.metadata())
+        {
+            #[allow(unused_must_use)]
+            self.printer.writes(record);
+        }
     }
 
     fn flush(&self) {}
 }
+
+/// The task to output the buffered logs of a [`NativeConsole`], it should run with a lower
+/// priority than the tasks who print logs.
+pub struct NativeConsoleDrain<OS>
+where
+    OS: RTOS + 'static,
+{
+    console: &'static NativeConsole<OS>,
+}
+
+impl<OS> NativeConsoleDrain<OS>
+where
+    OS: RTOS + 'static,
+{
+    pub fn new(console: &'static NativeConsole<OS>) -> Self
+    {
+        Self { console }
+    }
+}
+
+impl<OS> ITaskMain for NativeConsoleDrain<OS>
+where
+    OS: Sized + RTOS + 'static,
+{
+    fn main(&mut self)
+    {
+        loop
+        {
+            #[allow(unused_must_use)]
+            self.console.printer.drain(&self.console.transport, LOG_REPORT_PERIOD);
+        }
+    }
+}
@@ -3,7 +3,6 @@ use core::cell::RefCell;
 use alloc::vec::Vec;
 use log::warn;
 use sces::value::{ErrValue, RetValue};
-use sces::mcu::uart::UartDevice;
 use sces::os::events::IEvents;
 use sces::vec::SafeVec;
 use sces::os::mutex::MutexSample;
@@ -11,6 +10,7 @@ use sces::os::RTOS;
 
 use crate::native::cache::ConsoleCache;
 use crate::svc::CS;
+use crate::transport::TransportDevice;
 use crate::ConsoleCommands;
 use crate::ConsoleExecute;
 
@@ -50,9 +50,9 @@ where
         self.exe_queue.attempt_lock_then(|x| x.attempt_push(exe))
     }
 
-    pub fn wait_and_dispatch(&self, serial_port: &UartDevice) -> RetValue<()>
+    pub fn wait_and_dispatch(&self, transport: &TransportDevice) -> RetValue<()>
     {
-        serial_port.as_ref().async_receive(self.cache.borrow_mut().as_bytes_mut())?;
+        transport.as_ref().async_read(self.cache.borrow_mut().as_bytes_mut())?;
         self.dispatch_event.wait(EVT_CMD_RX, OS::WAIT_MAX).or(Err(ErrValue::Timeout))?;
 
         let cache = self.cache.borrow();
@@ -0,0 +1,58 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use log::LevelFilter;
+use sces::value::{ErrValue, RetValue};
+use sces::vec::SafeVec;
+
+/// The log levels of modules, a module is matched by the prefix of the `log` target.
+pub struct LogFilter
+{
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter
+{
+    pub const fn new() -> Self
+    {
+        Self { default: LevelFilter::Trace, modules: Vec::new() }
+    }
+
+    /// Get the level of the target, the longest matched prefix wins.
+    pub fn level(&self, target: &str) -> LevelFilter
+    {
+        self.modules
+            .iter()
+            .filter(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// Set the level of the targets start with `prefix`, an empty `prefix` sets the default level.
+    pub fn set_level(&mut self, prefix: &str, level: LevelFilter) -> RetValue<()>
+    {
+        if prefix.is_empty()
+        {
+            self.default = level;
+        }
+        else if let Some(module) = self.modules.iter_mut().find(|(x, _)| x == prefix)
+        {
+            module.1 = level;
+        }
+        else
+        {
+            let mut name = String::new();
+            name.try_reserve_exact(prefix.len()).or(Err(ErrValue::MemAllocFailure))?;
+            name.push_str(prefix);
+            self.modules.attempt_push((name, level))?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the most verbose level of the default and all modules.
+    pub fn max_level(&self) -> LevelFilter
+    {
+        self.modules.iter().map(|(_, level)| *level).fold(self.default, |x, y| x.max(y))
+    }
+}
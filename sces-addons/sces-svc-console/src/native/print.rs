@@ -1,39 +1,472 @@
-use core::fmt::Write;
+use core::cell::RefCell;
+use core::fmt::{Display, Formatter, Result, Write};
+use core::sync::atomic::{AtomicU32, Ordering};
 
+use log::{Level, LevelFilter, Metadata, Record};
 use sces::value::{ErrValue, RetValue};
-use sces::mcu::uart::UartDevice;
-use sces::os::{mutex::MutexSample, RTOS};
+use sces::os::events::IEvents;
+use sces::os::task::ITask;
+use sces::os::tick::Duration;
+use sces::os::RTOS;
 
+use crate::binary::{encode_fmt, encode_head, LogArg, LogSink};
+use crate::console::{LogEncoding, LogOverflow};
+use crate::format::{DefaultLogFormat, LogContext, LogFormat};
 use crate::native::cache::ConsoleCache;
+use crate::native::filter::LogFilter;
+use crate::native::ring::{CobsWriter, LogLength, LogRing, LogWriter};
+use crate::native::share::ShareCell;
+use crate::svc::CS;
+use crate::transport::TransportDevice;
+
+const EVT_LOG_PUT: u32 = 0x01;
+const EVT_LOG_TX: u32 = 0x02;
+
+static FORMAT: DefaultLogFormat = DefaultLogFormat::new(false);
+
+struct PrintState
+{
+    ring: LogRing,
+    format: &'static dyn LogFormat,
+    encoding: LogEncoding,
+    seq: u8,
+}
+
+/// Where a record is encoded to, according to the [`LogEncoding`] of the console.
+enum LogOutput<'a>
+{
+    Text(&'a mut dyn Write),
+    Binary(&'a mut dyn LogSink),
+}
+
+/// Display a record of [`crate::binlog`] without its format string.
+struct BinaryArgs<'a>(usize, &'a [&'a dyn LogArg]);
+
+impl<'a> Display for BinaryArgs<'a>
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result
+    {
+        write!(f, "<{:#x}>", self.0)?;
+        self.1.iter().try_for_each(|x| write!(f, " {x}"))
+    }
+}
+
+impl PrintState
+{
+    /// Put one record into the ring with the overflow policy, and count the dropped records.
+    ///
+    /// It's `Ok(false)` when the record should wait for the space, which is only with
+    /// [`LogOverflow::Block`] when `wait` is set. A text record larger than the ring is
+    /// truncated, but a binary one is dropped.
+    fn put<F>(
+        &mut self, overflow: LogOverflow, wait: bool, dropped: &AtomicU32, encode: &F,
+    ) -> RetValue<bool>
+    where
+        F: Fn(&dyn LogFormat, u8, LogOutput) -> Option<()>,
+    {
+        match self.try_put(overflow, dropped, encode)
+        {
+            Ok(true) => Ok(true),
+            Ok(false) if overflow == LogOverflow::Block && wait => Ok(false),
+            _ =>
+            {
+                dropped.fetch_add(1, Ordering::Relaxed);
+                Err(ErrValue::StackOverflow)
+            }
+        }
+    }
+
+    fn try_put<F>(
+        &mut self, overflow: LogOverflow, dropped: &AtomicU32, encode: &F,
+    ) -> RetValue<bool>
+    where
+        F: Fn(&dyn LogFormat, u8, LogOutput) -> Option<()>,
+    {
+        let PrintState { ring, format, encoding, seq } = self;
+        let mut length = LogLength(0);
+
+        let size = match encoding
+        {
+            LogEncoding::Text =>
+            {
+                encode(*format, *seq, LogOutput::Text(&mut length));
+                (length.0 + 1).min(ring.capacity())
+            }
+            LogEncoding::Binary =>
+            {
+                encode(*format, *seq, LogOutput::Binary(&mut length))
+                    .ok_or(ErrValue::FormatFailure)?;
+                CobsWriter::bound(length.0)
+            }
+        };
+
+        if size > ring.capacity()
+        {
+            return Err(ErrValue::StackOverflow);
+        }
+
+        if ring.space() < size && overflow == LogOverflow::DropOldest
+        {
+            while ring.space() < size && ring.drop_oldest(encoding.delimiter())
+            {
+                dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        if ring.space() < size
+        {
+            return Ok(false);
+        }
+
+        if *encoding == LogEncoding::Text
+        {
+            encode(*format, *seq, LogOutput::Text(&mut LogWriter::new(ring, size - 1)));
+            ring.push(b"\n");
+        }
+        else
+        {
+            let mut writer = CobsWriter::new(ring);
+            encode(*format, *seq, LogOutput::Binary(&mut writer));
+            writer.finish();
+        }
+
+        *seq = seq.wrapping_add(1);
+        Ok(true)
+    }
+}
 
 pub struct ConsolePrintCore<OS>
 where
     OS: RTOS,
 {
-    cache: MutexSample<OS, ConsoleCache>,
+    state: ShareCell<OS, PrintState>,
+    filter: ShareCell<OS, LogFilter>,
+    overflow: LogOverflow,
+    dropped: AtomicU32,
+    reported: AtomicU32,
+    cache: RefCell<ConsoleCache>,
+    drain_event: OS::Events,
 }
 
 impl<OS> ConsolePrintCore<OS>
 where
     OS: RTOS,
 {
-    pub fn new() -> RetValue<Self>
+    pub fn new(capacity: usize, overflow: LogOverflow) -> RetValue<Self>
+    {
+        Ok(Self {
+            state: ShareCell::new(PrintState {
+                ring: LogRing::new(capacity)?,
+                format: &FORMAT,
+                encoding: LogEncoding::Text,
+                seq: 0,
+            }),
+            filter: ShareCell::new(LogFilter::new()),
+            overflow,
+            dropped: AtomicU32::new(0),
+            reported: AtomicU32::new(0),
+            cache: RefCell::new(ConsoleCache::new()),
+            drain_event: OS::Events::new()?,
+        })
+    }
+
+    pub fn set_format(&self, format: &'static dyn LogFormat) -> RetValue<()>
+    {
+        self.state.access(|x| {
+            x.format = format;
+            Ok(())
+        })
+    }
+
+    pub fn set_level(&self, target: &str, level: LevelFilter) -> RetValue<()>
+    {
+        self.filter.access(|x| x.set_level(target, level))
+    }
+
+    pub fn max_level(&self) -> LevelFilter
+    {
+        self.filter.access(|x| Ok(x.max_level())).unwrap_or(LevelFilter::Trace)
+    }
+
+    pub fn enabled(&self, metadata: &Metadata) -> bool
+    {
+        self.filter.access(|x| Ok(metadata.level() <= x.level(metadata.target()))).unwrap_or(true)
+    }
+
+    pub fn set_encoding(&self, encoding: LogEncoding) -> RetValue<()>
     {
-        Ok(Self { cache: MutexSample::new(ConsoleCache::new())? })
+        self.state.access(|x| {
+            x.encoding = encoding;
+            Ok(())
+        })
     }
 
-    pub fn writes(&self, serial_port: &UartDevice, record: &log::Record) -> RetValue<()>
+    /// Format one record into the log ring, the real output is done by [`Self::drain`].
+    ///
+    /// In interrupt context this function never waits, the record will be dropped when the ring
+    /// is in use or full, whatever the overflow policy is.
+    pub fn writes(&self, record: &Record) -> RetValue<()>
     {
-        if let Ok(mut x) = self.cache.attempt_lock()
+        let task = (!OS::is_in_isr()).then(OS::current_task);
+        let context =
+            LogContext { ticks: OS::ticks(), task: task.as_ref().map_or("ISR", |x| x.name()) };
+
+        self.store(|format, seq, output| match output
         {
-            x.clean();
+            LogOutput::Text(out) => format.format(out, &context, record).ok(),
+            LogOutput::Binary(out) =>
+            {
+                encode_head(out, seq, record.level(), context.ticks, None);
+                encode_fmt(out, record.args());
+                Some(())
+            }
+        })
+    }
+
+    /// Encode one record of [`crate::binlog`] into the log ring.
+    ///
+    /// When the console outputs text, the record is printed as the interned address and the
+    /// arguments, because the format string is not on the device.
+    pub fn writes_binary(&self, level: Level, id: usize, args: &[&dyn LogArg]) -> RetValue<()>
+    {
+        if self.state.access(|x| Ok(x.encoding))? == LogEncoding::Text
+        {
+            return self.writes(
+                &Record::builder()
+                    .level(level)
+                    .target(module_path!())
+                    .args(format_args!("{}", BinaryArgs(id, args)))
+                    .build(),
+            );
+        }
+
+        let ticks = OS::ticks();
+
+        self.store(|_, seq, output| match output
+        {
+            LogOutput::Text(_) => None,
+            LogOutput::Binary(out) =>
+            {
+                encode_head(out, seq, level, ticks, Some(id));
+                args.iter().for_each(|x| x.encode(out));
+                Some(())
+            }
+        })
+    }
+
+    /// Store one record into the ring with the overflow policy.
+    ///
+    /// The `encode` is called twice, once to measure the size and once to write the record, so
+    /// it must output the same content in both calls.
+    fn store<F>(&self, encode: F) -> RetValue<()>
+    where
+        F: Fn(&dyn LogFormat, u8, LogOutput) -> Option<()>,
+    {
+        let wait = !OS::is_in_isr();
 
+        loop
+        {
+            match self.state.access(|x| Ok(x.put(self.overflow, wait, &self.dropped, &encode)))
+            {
+                Ok(Ok(true)) => return self.drain_event.put(EVT_LOG_PUT),
+                Ok(Ok(false)) => OS::delay(Duration::from_millis(1)),
+                Ok(Err(x)) => return Err(x),
+                Err(_) =>
+                {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Err(ErrValue::StackOverflow);
+                }
+            }
+        }
+    }
+
+    /// Move the buffered records to the transport, and report the dropped records count.
+    ///
+    /// This function should be called in a loop by a dedicated task, it will wait new records at
+    /// most `period`.
+    pub fn drain(&self, transport: &TransportDevice, period: Duration) -> RetValue<()>
+    {
+        #[allow(unused_must_use)]
+        self.drain_event.wait(EVT_LOG_PUT, period);
+        self.report_dropped();
+
+        let mut cache = self.cache.try_borrow_mut()?;
+
+        loop
+        {
+            cache.clean();
+            let size = self.state.access(|x| Ok(x.ring.pop_into(cache.as_bytes_mut())))?;
+
+            if size == 0
+            {
+                return Ok(());
+            }
+
+            cache.set_length(size);
+            transport.as_ref().async_write(cache.as_bytes())?;
+
+            self.drain_event
+                .wait(EVT_LOG_TX, OS::WAIT_500)
+                .map(|_| ())
+                .or_else(|_| transport.as_ref().abort())?;
+        }
+    }
+
+    pub fn set_drain_signal(&self)
+    {
+        #[allow(unused_must_use)]
+        self.drain_event.put(EVT_LOG_TX);
+    }
+
+    pub fn dropped(&self) -> u32
+    {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn report_dropped(&self)
+    {
+        let dropped = self.dropped.load(Ordering::Relaxed);
+        let count = dropped.wrapping_sub(self.reported.swap(dropped, Ordering::Relaxed));
+
+        if count > 0
+        {
             #[allow(unused_must_use)]
-            writeln!(x, "[{:>5}] {}", record.level(), record.args())
-                .map_err(|_| ErrValue::FormatFailure)
-                .and_then(|()| serial_port.as_ref().transmit(x.as_bytes(), 100));
+            self.writes(
+                &Record::builder()
+                    .level(Level::Warn)
+                    .target(module_path!())
+                    .args(format_args!("{CS} {count} log records have been dropped."))
+                    .build(),
+            );
         }
+    }
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use std::string::String;
+
+    use super::*;
+
+    fn new_state(capacity: usize, encoding: LogEncoding) -> PrintState
+    {
+        PrintState { ring: LogRing::new(capacity).unwrap(), format: &FORMAT, encoding, seq: 0 }
+    }
+
+    /// Put a record of `text` in the encoding of the state.
+    fn put(
+        state: &mut PrintState, overflow: LogOverflow, wait: bool, dropped: &AtomicU32, text: &str,
+    ) -> RetValue<bool>
+    {
+        state.put(overflow, wait, dropped, &|_, seq, output| match output
+        {
+            LogOutput::Text(out) => out.write_str(text).ok(),
+            LogOutput::Binary(out) =>
+            {
+                encode_head(out, seq, Level::Info, 0, None);
+                encode_fmt(out, &format_args!("{text}"));
+                Some(())
+            }
+        })
+    }
+
+    fn contents(state: &mut PrintState) -> String
+    {
+        let mut data = vec![0; state.ring.capacity()];
+        let size = state.ring.pop_into(&mut data);
+        String::from_utf8(data[..size].to_vec()).unwrap()
+    }
+
+    /// A ring of 16 bytes, which has the records `aaaa`, `bbbb` and `cccc`.
+    fn full(overflow: LogOverflow, dropped: &AtomicU32) -> PrintState
+    {
+        let mut state = new_state(16, LogEncoding::Text);
+
+        for text in ["aaaa", "bbbb", "cccc"]
+        {
+            assert_eq!(put(&mut state, overflow, true, dropped, text).ok(), Some(true));
+        }
+
+        state
+    }
+
+    #[test]
+    fn drop_oldest()
+    {
+        let dropped = AtomicU32::new(0);
+        let mut state = full(LogOverflow::DropOldest, &dropped);
+
+        assert_eq!(put(&mut state, LogOverflow::DropOldest, true, &dropped, "dd").ok(), Some(true));
+        assert_eq!(
+            put(&mut state, LogOverflow::DropOldest, false, &dropped, "eeeeeee").ok(),
+            Some(true)
+        );
+        assert_eq!(dropped.load(Ordering::Relaxed), 2);
+        assert_eq!(state.seq, 5);
+        assert_eq!(contents(&mut state), "cccc\ndd\neeeeeee\n");
+    }
+
+    #[test]
+    fn drop_newest()
+    {
+        let dropped = AtomicU32::new(0);
+        let mut state = full(LogOverflow::DropNewest, &dropped);
+
+        assert!(put(&mut state, LogOverflow::DropNewest, true, &dropped, "dd").is_err());
+        assert!(put(&mut state, LogOverflow::DropNewest, true, &dropped, "d").is_err());
+        assert_eq!(dropped.load(Ordering::Relaxed), 2);
+        assert_eq!(state.seq, 3);
+        assert_eq!(contents(&mut state), "aaaa\nbbbb\ncccc\n");
+
+        assert_eq!(put(&mut state, LogOverflow::DropNewest, true, &dropped, "dd").ok(), Some(true));
+        assert_eq!(contents(&mut state), "dd\n");
+    }
+
+    #[test]
+    fn block()
+    {
+        let dropped = AtomicU32::new(0);
+        let mut state = full(LogOverflow::Block, &dropped);
+
+        // A task waits for the space, but an interrupt handler drops the record.
+        assert_eq!(put(&mut state, LogOverflow::Block, true, &dropped, "dd").ok(), Some(false));
+        assert_eq!(dropped.load(Ordering::Relaxed), 0);
+        assert!(put(&mut state, LogOverflow::Block, false, &dropped, "dd").is_err());
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+
+        state.ring.pop_into(&mut [0; 5]);
+        assert_eq!(put(&mut state, LogOverflow::Block, true, &dropped, "dd").ok(), Some(true));
+        assert_eq!(contents(&mut state), "bbbb\ncccc\ndd\n");
+    }
+
+    #[test]
+    fn oversized_records()
+    {
+        let dropped = AtomicU32::new(0);
+
+        // A text record is truncated to the ring, and the old records are dropped for it.
+        let mut state = full(LogOverflow::DropOldest, &dropped);
+        let text = "0123456789abcdefghij";
+        assert_eq!(put(&mut state, LogOverflow::DropOldest, true, &dropped, text).ok(), Some(true));
+        assert_eq!(dropped.load(Ordering::Relaxed), 3);
+        assert_eq!(contents(&mut state), "0123456789abcde\n");
+
+        // A binary record can't be truncated, so it's dropped with any policy.
+        for overflow in [LogOverflow::DropOldest, LogOverflow::DropNewest, LogOverflow::Block]
+        {
+            let dropped = AtomicU32::new(0);
+            let mut state = new_state(16, LogEncoding::Binary);
+
+            assert_eq!(put(&mut state, overflow, true, &dropped, "a").ok(), Some(true));
+            let space = state.ring.space();
+
+            assert!(put(&mut state, overflow, true, &dropped, text).is_err());
+            assert_eq!(dropped.load(Ordering::Relaxed), 1);
+            assert_eq!(state.seq, 1);
+            assert_eq!(state.ring.space(), space);
+        }
     }
 }
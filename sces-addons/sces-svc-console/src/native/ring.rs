@@ -0,0 +1,269 @@
+use core::fmt::Write;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use sces::value::{ErrValue, RetValue};
+
+use crate::binary::LogSink;
+
+pub struct LogRing
+{
+    data: Box<[u8]>,
+    head: usize,
+    length: usize,
+}
+
+impl LogRing
+{
+    pub fn new(capacity: usize) -> RetValue<Self>
+    {
+        let mut data = Vec::new();
+        data.try_reserve_exact(capacity).or(Err(ErrValue::MemAllocFailure))?;
+        data.resize(capacity, 0);
+
+        Ok(Self { data: data.into_boxed_slice(), head: 0, length: 0 })
+    }
+
+    pub fn capacity(&self) -> usize
+    {
+        self.data.len()
+    }
+
+    pub fn space(&self) -> usize
+    {
+        self.data.len() - self.length
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> usize
+    {
+        let size = bytes.len().min(self.space());
+        let tail = self.tail();
+        let first = size.min(self.data.len() - tail);
+
+        self.data[tail..tail + first].copy_from_slice(&bytes[..first]);
+        self.data[..size - first].copy_from_slice(&bytes[first..size]);
+        self.length += size;
+
+        size
+    }
+
+    pub fn pop_into(&mut self, buf: &mut [u8]) -> usize
+    {
+        let size = buf.len().min(self.length);
+        let first = size.min(self.data.len() - self.head);
+
+        buf[..first].copy_from_slice(&self.data[self.head..self.head + first]);
+        buf[first..size].copy_from_slice(&self.data[..size - first]);
+        self.consume(size);
+
+        size
+    }
+
+    /// Get the position where the next byte will be pushed to.
+    pub fn tail(&self) -> usize
+    {
+        (self.head + self.length) % self.data.len().max(1)
+    }
+
+    /// Overwrite a byte which has been pushed, at the position got by [`Self::tail`].
+    pub fn set(&mut self, position: usize, value: u8)
+    {
+        self.data[position] = value;
+    }
+
+    /// Drop the oldest record, which is all bytes until the first `delimiter`.
+    pub fn drop_oldest(&mut self, delimiter: u8) -> bool
+    {
+        if self.length == 0
+        {
+            return false;
+        }
+
+        let size = (0..self.length)
+            .find(|x| self.data[(self.head + x) % self.data.len()] == delimiter)
+            .map_or(self.length, |x| x + 1);
+
+        self.consume(size);
+        true
+    }
+
+    fn consume(&mut self, size: usize)
+    {
+        self.head = (self.head + size) % self.data.len().max(1);
+        self.length -= size;
+
+        if self.length == 0
+        {
+            self.head = 0;
+        }
+    }
+}
+
+/// Write a formatted record into the ring, limited to the space reserved for it.
+pub struct LogWriter<'a>
+{
+    ring: &'a mut LogRing,
+    left: usize,
+}
+
+impl<'a> LogWriter<'a>
+{
+    pub fn new(ring: &'a mut LogRing, limit: usize) -> Self
+    {
+        Self { ring, left: limit }
+    }
+}
+
+impl<'a> Write for LogWriter<'a>
+{
+    fn write_str(&mut self, s: &str) -> core::fmt::Result
+    {
+        let content = s.as_bytes();
+        self.left -= self.ring.push(&content[..content.len().min(self.left)]);
+        Ok(())
+    }
+}
+
+/// Count the length of a formatted content without storing it.
+pub struct LogLength(pub usize);
+
+impl Write for LogLength
+{
+    fn write_str(&mut self, s: &str) -> core::fmt::Result
+    {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+impl LogSink for LogLength
+{
+    fn put(&mut self, bytes: &[u8])
+    {
+        self.0 += bytes.len();
+    }
+}
+
+/// Write a frame into the ring with the COBS encoding, so the frame never contains `0x00` and
+/// could be ended by it.
+///
+/// The code byte of every block is pushed as a placeholder and patched when the block ends.
+pub struct CobsWriter<'a>
+{
+    ring: &'a mut LogRing,
+    code: usize,
+    count: u8,
+}
+
+impl<'a> CobsWriter<'a>
+{
+    /// The most bytes the COBS encoding of `size` bytes takes, including the frame end.
+    pub const fn bound(size: usize) -> usize
+    {
+        size + size / 254 + 2
+    }
+
+    pub fn new(ring: &'a mut LogRing) -> Self
+    {
+        let code = ring.tail();
+        ring.push(&[0]);
+
+        Self { ring, code, count: 1 }
+    }
+
+    pub fn finish(self)
+    {
+        self.ring.set(self.code, self.count);
+        self.ring.push(&[0]);
+    }
+}
+
+impl<'a> LogSink for CobsWriter<'a>
+{
+    fn put(&mut self, bytes: &[u8])
+    {
+        for &x in bytes
+        {
+            if x != 0
+            {
+                self.ring.push(&[x]);
+                self.count += 1;
+            }
+
+            if x == 0 || self.count == u8::MAX
+            {
+                self.ring.set(self.code, self.count);
+                self.code = self.ring.tail();
+                self.ring.push(&[0]);
+                self.count = 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use std::string::String;
+
+    use log::Level;
+
+    use super::*;
+    use crate::binary::{encode_fmt, encode_head, LogArg};
+
+    /// The frames decoded by the tests of `sces-logcat`: an interned record with `0x00` in its
+    /// head and arguments, and a text record longer than a COBS block.
+    const FRAMES: &[u8] = include_bytes!("../../testdata/frames.bin");
+
+    /// The text of the second frame, 300 letters of `a` to `z`.
+    fn long_text() -> String
+    {
+        (0..300).map(|x| (b'a' + (x % 26) as u8) as char).collect()
+    }
+
+    fn store(ring: &mut LogRing, encode: &dyn Fn(&mut dyn LogSink))
+    {
+        let mut length = LogLength(0);
+        encode(&mut length);
+        assert!(ring.space() >= CobsWriter::bound(length.0));
+
+        let mut writer = CobsWriter::new(ring);
+        encode(&mut writer);
+        writer.finish();
+    }
+
+    fn frames() -> Vec<u8>
+    {
+        let mut ring = LogRing::new(1024).unwrap();
+        let args: [&dyn LogArg; 8] =
+            [&0u32, &-1i32, &1.5f32, &true, &'é', &"a\0b", &2.25f64, &u64::MAX];
+
+        store(&mut ring, &|sink| {
+            encode_head(sink, 0, Level::Info, 0x80, Some(0x0800_1000));
+            args.iter().for_each(|x| x.encode(sink));
+        });
+
+        let text = long_text();
+        store(&mut ring, &|sink| {
+            encode_head(sink, 1, Level::Warn, 1_234_567, None);
+            encode_fmt(sink, &format_args!("{text}"));
+        });
+
+        let mut data = vec![0; ring.capacity()];
+        let size = ring.pop_into(&mut data);
+        data.truncate(size);
+        data
+    }
+
+    #[test]
+    fn cobs_frames()
+    {
+        let data = frames();
+
+        assert_eq!(data, FRAMES);
+        assert_eq!(data.iter().filter(|x| **x == 0).count(), 2);
+        assert_eq!(data.last(), Some(&0));
+    }
+}
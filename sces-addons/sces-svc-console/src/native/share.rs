@@ -0,0 +1,50 @@
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use sces::os::tick::Duration;
+use sces::os::RTOS;
+use sces::value::{ErrValue, RetValue};
+
+/// A cell shared by tasks and interrupt handlers.
+///
+/// Tasks wait until the cell is free, but interrupt handlers never wait, they get
+/// `ErrValue::Busy` when the cell is in use by the interrupted task.
+pub struct ShareCell<OS, T>
+where
+    OS: RTOS,
+{
+    busy: AtomicBool,
+    value: UnsafeCell<T>,
+    _marker: PhantomData<OS>,
+}
+
+impl<OS, T> ShareCell<OS, T>
+where
+    OS: RTOS,
+{
+    pub const fn new(value: T) -> Self
+    {
+        Self { busy: AtomicBool::new(false), value: UnsafeCell::new(value), _marker: PhantomData }
+    }
+
+    pub fn access<R, F>(&self, f: F) -> RetValue<R>
+    where
+        F: FnOnce(&mut T) -> RetValue<R>,
+    {
+        while self.busy.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_err()
+        {
+            if OS::is_in_isr()
+            {
+                return Err(ErrValue::Busy);
+            }
+
+            OS::delay(Duration::from_millis(1));
+        }
+
+        let value = f(unsafe { &mut *self.value.get() });
+        self.busy.store(false, Ordering::Release);
+
+        value
+    }
+}
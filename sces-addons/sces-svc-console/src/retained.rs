@@ -0,0 +1,61 @@
+use alloc::vec::Vec;
+use log::info;
+use sces::retain::retained;
+use sces::value::{ErrValue, RetValue};
+
+use crate::{ConsoleCommands, ConsoleExecute};
+
+/// The log target of the records printed by [`RetainedDump`], they are not written to the retained
+/// log again.
+pub const RETAINED_TARGET: &str = "retained";
+
+const DUMP_COUNT: usize = 20;
+const DUMP_SIZE: usize = 1024;
+
+/// The console command to show the records in the retained log of `sces::retain`.
+///
+/// * `retained [count]`: Show the last `count` records, 20 records by default.
+/// * `retained clean`: Remove all records.
+pub struct RetainedDump;
+
+impl ConsoleExecute for RetainedDump
+{
+    fn exe_name(&self) -> &str
+    {
+        RETAINED_TARGET
+    }
+
+    fn exe_with_cmds(&self, cmds: &mut ConsoleCommands) -> RetValue<()>
+    {
+        let log = retained().ok_or(ErrValue::NotAvailable)?;
+
+        let count = match cmds.next()
+        {
+            Some(b"clean") =>
+            {
+                log.clean();
+                return Ok(());
+            }
+            Some(x) => core::str::from_utf8(x)
+                .ok()
+                .and_then(|x| x.parse().ok())
+                .ok_or(ErrValue::FormatFailure)?,
+            None => DUMP_COUNT,
+        };
+
+        let mut buf = Vec::new();
+        buf.try_reserve_exact(DUMP_SIZE).or(Err(ErrValue::MemAllocFailure))?;
+        buf.resize(DUMP_SIZE, 0);
+
+        let size = log.read_last(count, &mut buf);
+
+        info!(target: RETAINED_TARGET, "Reset by {:?}, boot {}.", log.reset_reason(), log.boot_count());
+
+        for record in buf[..size].split(|x| *x == b'\n').filter(|x| !x.is_empty())
+        {
+            info!(target: RETAINED_TARGET, "{}", core::str::from_utf8(record).unwrap_or("?"));
+        }
+
+        Ok(())
+    }
+}
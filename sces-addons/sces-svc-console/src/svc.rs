@@ -1,29 +1,105 @@
-use log::LevelFilter;
+use log::{LevelFilter, Log, Metadata, Record};
+use sces::retain::retained;
 use sces::value::{ErrValue, RetValue};
 
-use crate::Console;
+use crate::retained::RETAINED_TARGET;
+use crate::{Console, LogArg};
 
 pub const CS: &str = "<ConsoleService>";
 
-static mut SVC: Option<&'static dyn Console> = None;
+const CONSOLE_MAX: usize = 4;
+
+static mut SVC: [Option<&'static dyn Console>; CONSOLE_MAX] = [None; CONSOLE_MAX];
+static LOGGER: ConsoleLogger = ConsoleLogger;
 
 pub struct ConsoleService;
 
 impl ConsoleService
 {
+    /// Initialize the service with the first console, it becomes the logger of the `log` crate.
     pub fn initialize<T>(instance: &'static T, level: LevelFilter) -> RetValue<()>
     where
         T: Console,
     {
-        log::set_logger(instance).or(Err(ErrValue::InstanceDuplicate))?;
-        log::set_max_level(level);
+        log::set_logger(&LOGGER).or(Err(ErrValue::InstanceDuplicate))?;
+        Self::append(instance, level)
+    }
 
-        unsafe { SVC = Some(instance) };
+    /// Add one more console as a sink of the log records, after the service has been initialized.
+    ///
+    /// A console which is only used to receive commands could be added with `LevelFilter::Off`.
+    #[allow(static_mut_refs)]
+    pub fn append<T>(instance: &'static T, level: LevelFilter) -> RetValue<()>
+    where
+        T: Console,
+    {
+        instance.set_log_level("", level)?;
+
+        unsafe { SVC.iter_mut() }
+            .find(|x| x.is_none())
+            .map(|x| *x = Some(instance))
+            .ok_or(ErrValue::StackOverflow)?;
+
+        log::set_max_level(Self::max_log_level());
         Ok(())
     }
 
+    /// Set the log level of the targets start with `target` for all consoles.
+    pub fn set_log_level(target: &str, level: LevelFilter) -> RetValue<()>
+    {
+        Self::consoles().try_for_each(|x| x.set_log_level(target, level))?;
+
+        log::set_max_level(Self::max_log_level());
+        Ok(())
+    }
+
+    /// Send a record of [`crate::binlog`] to all consoles.
+    pub fn log_binary(metadata: &Metadata, id: usize, args: &[&dyn LogArg])
+    {
+        Self::consoles().for_each(|x| x.log_binary(metadata, id, args));
+    }
+
+    /// Get the first console, the one used to initialize the service.
     pub fn instance() -> &'static dyn Console
     {
-        unsafe { SVC.unwrap() }
+        unsafe { SVC[0].unwrap() }
+    }
+
+    #[allow(static_mut_refs)]
+    pub fn consoles() -> impl Iterator<Item = &'static dyn Console>
+    {
+        unsafe { SVC.iter() }.map_while(|x| *x)
+    }
+
+    fn max_log_level() -> LevelFilter
+    {
+        Self::consoles().map(|x| x.max_log_level()).max().unwrap_or(LevelFilter::Off)
+    }
+}
+
+/// The logger of the `log` crate, to dispatch the log records to all consoles, and keep them in
+/// the retained log if it's installed.
+struct ConsoleLogger;
+
+impl Log for ConsoleLogger
+{
+    fn enabled(&self, metadata: &Metadata) -> bool
+    {
+        ConsoleService::consoles().any(|x| x.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record)
+    {
+        ConsoleService::consoles().for_each(|x| x.log(record));
+
+        if record.target() != RETAINED_TARGET
+        {
+            retained().inspect(|x| x.write(format_args!("[{}] {}", record.level(), record.args())));
+        }
+    }
+
+    fn flush(&self)
+    {
+        ConsoleService::consoles().for_each(|x| x.flush());
     }
 }
@@ -0,0 +1,78 @@
+//! Provide a common trait for the byte streams which a console runs over.
+
+use sces::mcu::EventLaunch;
+use sces::value::RetValue;
+
+mod uart;
+
+pub use uart::UartTransport;
+
+/// A common trait for a byte stream that a console reads commands from and writes logs to, like
+/// an UART, a SEGGER RTT channel, an USB CDC port, a TCP socket or a host pipe.
+///
+/// Both reading and writing are asynchronous, the results are notified via the event agent
+/// [`ConsoleTransportEvent`], and the functions of the agent may be called in the interrupt
+/// context.
+pub trait ConsoleTransport
+where
+    Self: EventLaunch<dyn ConsoleTransportEvent>,
+{
+    /// Start to read some bytes into `data`.
+    ///
+    /// [`ConsoleTransportEvent::on_transport_read`] will be called when something has been read,
+    /// the `data` must not be touched until then.
+    fn async_read(&self, data: &mut [u8]) -> RetValue<()>;
+
+    /// Start to write all bytes in `data`.
+    ///
+    /// [`ConsoleTransportEvent::on_transport_written`] will be called when all bytes have been
+    /// written, the `data` must not be touched until then.
+    fn async_write(&self, data: &[u8]) -> RetValue<()>;
+
+    /// Abort the reading and writing in progress.
+    fn abort(&self) -> RetValue<()>;
+}
+
+/// The agent to handle the events sent from a [`ConsoleTransport`].
+///
+/// All functions of this trait have an empty default implementation.
+pub trait ConsoleTransportEvent
+{
+    /// Will be called when the reading has been completed, `_size` is the count of read bytes.
+    fn on_transport_read(&self, _size: usize) {}
+
+    /// Will be called when the writing has been completed.
+    fn on_transport_written(&self) {}
+
+    /// Will be called when the byte stream has some errors.
+    fn on_transport_error(&self) {}
+}
+
+pub struct TransportDevice
+{
+    instance: *mut dyn ConsoleTransport,
+}
+
+impl TransportDevice
+{
+    pub const fn new(instance: &'static mut dyn ConsoleTransport) -> Self
+    {
+        Self { instance }
+    }
+}
+
+impl AsRef<dyn ConsoleTransport> for TransportDevice
+{
+    fn as_ref(&self) -> &'static dyn ConsoleTransport
+    {
+        unsafe { &*self.instance }
+    }
+}
+
+impl AsMut<dyn ConsoleTransport> for TransportDevice
+{
+    fn as_mut(&mut self) -> &'static mut dyn ConsoleTransport
+    {
+        unsafe { &mut *self.instance }
+    }
+}
@@ -0,0 +1,78 @@
+use core::ptr::NonNull;
+
+use sces::mcu::uart::{UartCtrl, UartCtrlEvent, UartDevice, UartError};
+use sces::mcu::EventLaunch;
+use sces::value::RetValue;
+
+use crate::transport::{ConsoleTransport, ConsoleTransportEvent};
+
+/// The console transport over an UART.
+///
+/// It becomes the event agent of the UART when an agent is set to it, so it should be placed in
+/// a static place, and not be moved after that.
+pub struct UartTransport
+{
+    uart: UartDevice,
+    event_handle: Option<&'static dyn ConsoleTransportEvent>,
+}
+
+impl UartTransport
+{
+    pub fn new(uart: &'static mut dyn UartCtrl) -> Self
+    {
+        Self { uart: UartDevice::new(uart), event_handle: None }
+    }
+}
+
+impl EventLaunch<dyn ConsoleTransportEvent> for UartTransport
+{
+    fn set_event_agent(&mut self, event_handle: &'static dyn ConsoleTransportEvent)
+    {
+        self.event_handle = Some(event_handle);
+
+        let agent = NonNull::from(&*self);
+        self.uart.as_mut().set_event_agent(unsafe { agent.as_ref() });
+    }
+
+    fn clean_event_agent(&mut self)
+    {
+        self.uart.as_mut().clean_event_agent();
+        self.event_handle = None;
+    }
+}
+
+impl ConsoleTransport for UartTransport
+{
+    fn async_read(&self, data: &mut [u8]) -> RetValue<()>
+    {
+        self.uart.as_ref().async_receive(data)
+    }
+
+    fn async_write(&self, data: &[u8]) -> RetValue<()>
+    {
+        self.uart.as_ref().async_transmit(data)
+    }
+
+    fn abort(&self) -> RetValue<()>
+    {
+        self.uart.as_ref().abort()
+    }
+}
+
+impl UartCtrlEvent for UartTransport
+{
+    fn on_uart_tx_complete(&self)
+    {
+        self.event_handle.inspect(|x| x.on_transport_written());
+    }
+
+    fn on_uart_rx_complete(&self, size: u32)
+    {
+        self.event_handle.inspect(|x| x.on_transport_read(size as usize));
+    }
+
+    fn on_uart_error(&self, _error: UartError)
+    {
+        self.event_handle.inspect(|x| x.on_transport_error());
+    }
+}
@@ -0,0 +1,114 @@
+use log::warn;
+use sces::os::tick::Duration;
+use sces::value::RetValue;
+use sces_svc_alive::{AliveWatch, AliveWatchEvent};
+
+use crate::dfu::DfuManager;
+use crate::svc::{DfuService, DFS};
+
+/// The tie of the trial image to the alive watch of [`sces_svc_alive`].
+///
+/// The trial image is confirmed when all the watched tasks keep alive, and it's rejected when
+/// the watch dog is let expire, so the next boot rolls back to the active image. The events are
+/// passed on to the next handler, if there's one.
+///
+/// # Examples
+/// ```rust
+/// static CHECK: DfuAliveCheck = DfuAliveCheck::new();
+///
+/// watch.set_event_agent(&CHECK);
+///
+/// // After the application runs for a while.
+/// CHECK.confirm_if_alive(AliveWatchService::instance())?;
+/// ```
+pub struct DfuAliveCheck
+{
+    next: Option<&'static (dyn AliveWatchEvent + Sync)>,
+}
+
+impl DfuAliveCheck
+{
+    pub const fn new() -> Self
+    {
+        Self { next: None }
+    }
+
+    pub const fn with_next(mut self, next: &'static (dyn AliveWatchEvent + Sync)) -> Self
+    {
+        self.next = Some(next);
+        self
+    }
+
+    /// Confirm the running trial image if no watched task is late, and return whether it's
+    /// confirmed.
+    pub fn confirm_if_alive(&self, watch: &dyn AliveWatch) -> RetValue<bool>
+    {
+        let dfu = DfuService::instance();
+        if !is_trial(dfu)?
+        {
+            return Ok(false);
+        }
+
+        let mut late = false;
+        watch.late_tasks(&mut |_, _| late = true)?;
+        if late
+        {
+            return Ok(false);
+        }
+
+        dfu.confirm()?;
+        Ok(true)
+    }
+}
+
+impl Default for DfuAliveCheck
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl AliveWatchEvent for DfuAliveCheck
+{
+    fn on_alive_late(&self, name: &str, past: Duration)
+    {
+        if let Some(x) = self.next
+        {
+            x.on_alive_late(name, past);
+        }
+    }
+
+    fn on_alive_restart(&self, name: &str)
+    {
+        if let Some(x) = self.next
+        {
+            x.on_alive_restart(name);
+        }
+    }
+
+    fn on_alive_expire(&self, name: &str)
+    {
+        let dfu = DfuService::instance();
+        if is_trial(dfu).unwrap_or(false)
+        {
+            warn!("{DFS} The trial image is rejected because of the task {name}.");
+            if dfu.reject().is_err()
+            {
+                warn!("{DFS} Failed to reject the trial image.");
+            }
+        }
+
+        if let Some(x) = self.next
+        {
+            x.on_alive_expire(name);
+        }
+    }
+}
+
+/// Check whether the running image is a trial one.
+fn is_trial(dfu: &dyn DfuManager) -> RetValue<bool>
+{
+    let status = dfu.status()?;
+    Ok(status.trial.is_some() && status.attempts > 0)
+}
@@ -0,0 +1,103 @@
+use log::warn;
+use sces::mcu::flash::FlashCtrl;
+use sces::mcu::sys::SystemCtrl;
+use sces::value::{ErrValue, RetValue};
+
+use crate::dfu::DfuLayout;
+use crate::header::DfuHeader;
+use crate::state::{DfuState, StateLog};
+use crate::svc::DFS;
+
+/// The boots of a trial image before it's rolled back, if it's not confirmed.
+pub(crate) const BOOT_MAX_ATTEMPTS: u8 = 3;
+
+/// The choice of the image to start in a bootloader, which has the same layout as the
+/// application.
+///
+/// The trial image is started for a limited count of boots, and every boot is recorded before
+/// it's started, so a trial image which hangs or resets before it's confirmed is rolled back to
+/// the active one. An image whose CRC-32 or SHA-256 is wrong is never started.
+///
+/// # Examples
+/// ```rust
+/// let mut boot = DfuBoot::new(flash, layout)?.with_max_attempts(2);
+///
+/// let (slot, header) = boot.select()?;
+/// boot.start(system, slot, &header)?;
+/// ```
+pub struct DfuBoot
+{
+    flash: &'static dyn FlashCtrl,
+    layout: DfuLayout,
+    log: StateLog,
+    max_attempts: u8,
+}
+
+impl DfuBoot
+{
+    pub fn new(flash: &'static dyn FlashCtrl, layout: DfuLayout) -> RetValue<Self>
+    {
+        layout.check(flash)?;
+
+        let log = StateLog::mount(flash, layout.state)?;
+        Ok(Self { flash, layout, log, max_attempts: BOOT_MAX_ATTEMPTS })
+    }
+
+    /// Set the boots of a trial image before it's rolled back, `0` rolls back at once.
+    pub const fn with_max_attempts(mut self, max_attempts: u8) -> Self
+    {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Choose the image to start and record the boot, it fails with
+    /// [`ErrValue::InstanceNotFound`] when no slot has a valid image.
+    pub fn select(&mut self) -> RetValue<(usize, DfuHeader)>
+    {
+        let mut state = self.log.state();
+
+        if let Some(trial) = state.trial
+        {
+            if state.attempts < self.max_attempts
+            {
+                if let Ok(header) = self.check(trial)
+                {
+                    self.log.save(DfuState { attempts: state.attempts + 1, ..state })?;
+                    return Ok((trial, header));
+                }
+            }
+
+            warn!("{DFS} The trial image in slot {trial} is rolled back.");
+            state = DfuState { trial: None, attempts: 0, rolled_back: Some(trial), ..state };
+            self.log.save(state)?;
+        }
+
+        if let Ok(header) = self.check(state.active)
+        {
+            return Ok((state.active, header));
+        }
+
+        let other = 1 - state.active;
+        let header = self.check(other).or(Err(ErrValue::InstanceNotFound))?;
+
+        warn!("{DFS} The active image is broken, the image in slot {other} is used.");
+        self.log.save(DfuState { active: other, ..state })?;
+        Ok((other, header))
+    }
+
+    /// Start the image in `slot`, it never returns if it succeeds.
+    pub fn start(&self, system: &dyn SystemCtrl, slot: usize, header: &DfuHeader) -> RetValue<()>
+    {
+        let slot = self.layout.slots.get(slot).ok_or(ErrValue::Param)?;
+        system.start_image(slot.address + header.header_size as u32)
+    }
+
+    fn check(&self, slot: usize) -> RetValue<DfuHeader>
+    {
+        let address = self.layout.slots[slot].address;
+
+        let header = DfuHeader::read(self.flash, address)?;
+        header.verify(self.flash, address)?;
+        Ok(header)
+    }
+}
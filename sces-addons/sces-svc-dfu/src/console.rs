@@ -0,0 +1,227 @@
+use log::info;
+use sces::value::{ErrValue, RetValue};
+use sces_svc_console::{ConsoleCommands, ConsoleExecute};
+
+use crate::dfu::DfuManager;
+use crate::svc::DfuService;
+
+/// The log target of the records printed by [`DfuCommand`].
+pub const DFU_TARGET: &str = "dfu";
+
+/// The most bytes in a line of `dfu write`.
+const WRITE_LINE: usize = 256;
+
+/// The console command to update the firmware by [`DfuService`] through the console, the image
+/// file is sent in hex by the lines of `sces-dfupack script`.
+///
+/// * `dfu [status]`: Show the slots, their images and the state of the updates.
+/// * `dfu begin`: Start to receive an image file into the slot which is not active.
+/// * `dfu write <hex>`: Write the next bytes of the image file, at most 256 bytes a line. A line
+///   with a bad hex writes nothing, so it could be sent again.
+/// * `dfu finish`: Check the received image and make it the trial image of the next boot.
+/// * `dfu abort`: Drop the received part of the image.
+/// * `dfu confirm`: Keep the running trial image.
+/// * `dfu reject`: Give up the trial image, the next boot rolls back.
+pub struct DfuCommand;
+
+impl ConsoleExecute for DfuCommand
+{
+    fn exe_name(&self) -> &str
+    {
+        DFU_TARGET
+    }
+
+    fn exe_with_cmds(&self, cmds: &mut ConsoleCommands) -> RetValue<()>
+    {
+        let dfu = DfuService::instance();
+        let mut text =
+            || cmds.next().map(core::str::from_utf8).transpose().or(Err(ErrValue::FormatFailure));
+
+        match text()?
+        {
+            None | Some("status") => status(dfu),
+            Some("begin") =>
+            {
+                let slot = dfu.begin()?;
+                info!(target: DFU_TARGET, "Receiving into slot {slot}.");
+                Ok(())
+            }
+            Some("write") => write(dfu, text()?.ok_or(ErrValue::Param)?),
+            Some("finish") =>
+            {
+                let header = dfu.finish()?;
+                info!(target: DFU_TARGET, "Received {} build {}, reset to try it.", header.version, header.build);
+                Ok(())
+            }
+            Some("abort") => dfu.abort(),
+            Some("confirm") => dfu.confirm(),
+            Some("reject") => dfu.reject(),
+            Some(_) => Err(ErrValue::Param),
+        }
+    }
+}
+
+fn status(dfu: &dyn DfuManager) -> RetValue<()>
+{
+    let status = dfu.status()?;
+
+    for slot in 0..2
+    {
+        let mark = match slot
+        {
+            x if x == status.running() => "*",
+            x if Some(x) == status.trial => "?",
+            _ => " ",
+        };
+
+        match dfu.header(slot)
+        {
+            Ok(x) =>
+            {
+                info!(target: DFU_TARGET, "{mark} slot {slot}: {} build {}, {} bytes", x.version, x.build, x.size)
+            }
+            Err(_) => info!(target: DFU_TARGET, "{mark} slot {slot}: empty"),
+        }
+    }
+
+    info!(target: DFU_TARGET, "Active slot {}, trial slot {:?} booted {} times.", status.active, status.trial, status.attempts);
+    if let Some(x) = status.rolled_back
+    {
+        info!(target: DFU_TARGET, "Slot {x} was rolled back.");
+    }
+    if let Some((slot, size)) = status.receiving
+    {
+        info!(target: DFU_TARGET, "Receiving into slot {slot}, {size} bytes.");
+    }
+
+    Ok(())
+}
+
+fn write(dfu: &dyn DfuManager, text: &str) -> RetValue<()>
+{
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(2)
+    {
+        return Err(ErrValue::FormatFailure);
+    }
+    if text.len() > WRITE_LINE * 2
+    {
+        return Err(ErrValue::Param);
+    }
+
+    let mut line = [0; WRITE_LINE];
+    let data = &mut line[..text.len() / 2];
+
+    for (byte, hex) in data.iter_mut().zip(text.chunks(2))
+    {
+        *byte = (digit(hex[0])? << 4) | digit(hex[1])?;
+    }
+
+    dfu.write(data)
+}
+
+fn digit(hex: u8) -> RetValue<u8>
+{
+    match hex
+    {
+        b'0'..=b'9' => Ok(hex - b'0'),
+        b'a'..=b'f' => Ok(hex - b'a' + 10),
+        b'A'..=b'F' => Ok(hex - b'A' + 10),
+        _ => Err(ErrValue::FormatFailure),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use std::sync::Mutex;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::dfu::DfuStatus;
+    use crate::header::DfuHeader;
+
+    #[derive(Default)]
+    struct RamDfu
+    {
+        image: Mutex<Vec<u8>>,
+        writes: Mutex<usize>,
+    }
+
+    impl DfuManager for RamDfu
+    {
+        fn begin(&self) -> RetValue<usize>
+        {
+            Ok(1)
+        }
+
+        fn write(&self, chunk: &[u8]) -> RetValue<()>
+        {
+            *self.writes.lock().unwrap() += 1;
+            self.image.lock().unwrap().extend_from_slice(chunk);
+            Ok(())
+        }
+
+        fn finish(&self) -> RetValue<DfuHeader>
+        {
+            Err(ErrValue::NotSupport)
+        }
+
+        fn abort(&self) -> RetValue<()>
+        {
+            Ok(())
+        }
+
+        fn status(&self) -> RetValue<DfuStatus>
+        {
+            Err(ErrValue::NotSupport)
+        }
+
+        fn header(&self, _slot: usize) -> RetValue<DfuHeader>
+        {
+            Err(ErrValue::NotSupport)
+        }
+
+        fn confirm(&self) -> RetValue<()>
+        {
+            Ok(())
+        }
+
+        fn reject(&self) -> RetValue<()>
+        {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_whole_line()
+    {
+        let dfu = RamDfu::default();
+        let text: std::string::String = (0..=255u8).map(|x| std::format!("{x:02X}")).collect();
+
+        write(&dfu, "00a5Ff").unwrap();
+        write(&dfu, &text).unwrap();
+
+        assert_eq!(*dfu.writes.lock().unwrap(), 2);
+        assert_eq!(dfu.image.lock().unwrap()[..3], [0x00, 0xA5, 0xFF]);
+        assert!(dfu.image.lock().unwrap()[3..].iter().copied().eq(0..=255u8));
+    }
+
+    #[test]
+    fn write_bad_line_writes_nothing()
+    {
+        let dfu = RamDfu::default();
+        let long = "00".repeat(WRITE_LINE + 1);
+        let late = std::format!("{}0g", "00".repeat(40));
+
+        assert!(matches!(write(&dfu, "123"), Err(ErrValue::FormatFailure)));
+        assert!(matches!(write(&dfu, "+1"), Err(ErrValue::FormatFailure)));
+        assert!(matches!(write(&dfu, " 1"), Err(ErrValue::FormatFailure)));
+        assert!(matches!(write(&dfu, &late), Err(ErrValue::FormatFailure)));
+        assert!(matches!(write(&dfu, &long), Err(ErrValue::Param)));
+        assert_eq!(*dfu.writes.lock().unwrap(), 0);
+        assert!(dfu.image.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,154 @@
+use sces::mcu::flash::FlashCtrl;
+use sces::value::{ErrValue, RetValue};
+
+use crate::header::DfuHeader;
+
+/// A range of the flash which holds an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DfuSlot
+{
+    pub address: u32,
+    pub size: u32,
+}
+
+impl DfuSlot
+{
+    pub const fn new(address: u32, size: u32) -> Self
+    {
+        Self { address, size }
+    }
+
+    pub const fn end(&self) -> u32
+    {
+        self.address + self.size
+    }
+}
+
+/// Where the images and the state of the updates are on the flash.
+///
+/// The image in a slot is linked at the address after its header, so the two slots hold the
+/// different builds of the same firmware, and the bootloader which starts them is in another
+/// place of the flash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DfuLayout
+{
+    pub slots: [DfuSlot; 2],
+
+    /// Two sectors which keep the state, by their numbers of [`FlashCtrl::sector`].
+    pub state: [u32; 2],
+}
+
+impl DfuLayout
+{
+    pub const fn new(slots: [DfuSlot; 2], state: [u32; 2]) -> Self
+    {
+        Self { slots, state }
+    }
+
+    /// Check the slots are at the boundaries of the sectors and don't overlap anything.
+    pub fn check(&self, flash: &dyn FlashCtrl) -> RetValue<()>
+    {
+        let [a, b] = self.slots;
+        let state = [flash.sector(self.state[0])?, flash.sector(self.state[1])?];
+
+        for slot in self.slots
+        {
+            if slot.size == 0
+                || flash.sector_at(slot.address)?.address != slot.address
+                || flash.sector_at(slot.end() - 1)?.end() != slot.end()
+                || state.iter().any(|x| x.address < slot.end() && slot.address < x.end())
+            {
+                return Err(ErrValue::Param);
+            }
+        }
+
+        match a.address < b.end() && b.address < a.end() || self.state[0] == self.state[1]
+        {
+            true => Err(ErrValue::Param),
+            false => Ok(()),
+        }
+    }
+}
+
+/// The state of the updates.
+///
+/// A new image is a trial one after it's received, the bootloader starts it for a limited count
+/// of boots, and it becomes the active one only when it's confirmed by itself. Otherwise the
+/// bootloader rolls back to the active image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DfuStatus
+{
+    /// The slot of the confirmed image.
+    pub active: usize,
+
+    /// The slot of the image which is on trial.
+    pub trial: Option<usize>,
+
+    /// The boots of the trial image, it's running when it's not `0`.
+    pub attempts: u8,
+
+    /// The slot of the last trial image which was rolled back.
+    pub rolled_back: Option<usize>,
+
+    /// The slot which is being received and the bytes received.
+    pub receiving: Option<(usize, u32)>,
+}
+
+impl DfuStatus
+{
+    /// The slot of the running image, if it's started by the bootloader of [`crate::DfuBoot`].
+    pub const fn running(&self) -> usize
+    {
+        match self.trial
+        {
+            Some(x) if self.attempts > 0 => x,
+            _ => self.active,
+        }
+    }
+}
+
+/// The updates of the firmware, the images are received in chunks from any transport like the
+/// console, the CAN bus or the SPI bus.
+///
+/// ```rust
+/// let dfu = DfuService::instance();
+///
+/// dfu.begin()?;
+/// while let Some(chunk) = receive()?
+/// {
+///     dfu.write(&chunk)?;
+/// }
+/// let header = dfu.finish()?;
+/// info!("Reset to try {}.", header.version);
+/// ```
+pub trait DfuManager: Send + Sync
+{
+    /// Start to receive an image file into the slot of the image which is not active, and return
+    /// the slot.
+    ///
+    /// It fails with [`ErrValue::InstanceInUse`] when the trial image is running, which must be
+    /// confirmed or rejected first.
+    fn begin(&self) -> RetValue<usize>;
+
+    /// Write the next chunk of the image file, the chunks could have any size.
+    fn write(&self, chunk: &[u8]) -> RetValue<()>;
+
+    /// Check the received image by its CRC-32 and SHA-256, and make it the trial image of the
+    /// next boot.
+    fn finish(&self) -> RetValue<DfuHeader>;
+
+    /// Drop the received part of the image.
+    fn abort(&self) -> RetValue<()>;
+
+    fn status(&self) -> RetValue<DfuStatus>;
+
+    /// Get the header of the image in a slot.
+    fn header(&self, slot: usize) -> RetValue<DfuHeader>;
+
+    /// Keep the running trial image as the active one, it does nothing when no trial image is
+    /// running, so it could be called at every boot.
+    fn confirm(&self) -> RetValue<()>;
+
+    /// Give up the trial image, the next boot rolls back to the active one if it's running.
+    fn reject(&self) -> RetValue<()>;
+}
@@ -0,0 +1,236 @@
+//! The header of an image, which is at the start of a slot.
+//!
+//! The image file made by `sces-dfupack` is the header, the padding up to `header_size` and the
+//! image which is linked at `header_size` in a slot. All the numbers are in little endian:
+//!
+//! | Offset | Size  | Content                                                   |
+//! |--------|-------|-----------------------------------------------------------|
+//! | 0      | 4     | The magic number                                          |
+//! | 4      | 2     | The offset of the image in the slot                       |
+//! | 6      | 2     | The flags, which are `0` for now                          |
+//! | 8      | 4     | The version, see [`DfuVersion`]                           |
+//! | 12     | 4     | The build number                                          |
+//! | 16     | 4     | The size of the image                                     |
+//! | 20     | 4     | The CRC-32 of the image                                   |
+//! | 24     | 32    | The SHA-256 of the image                                  |
+//! | 56     | 4     | The address the image is linked at, or `0` for any slot   |
+//! | 60     | 4     | The CRC-32 of the first 60 bytes                          |
+
+use core::fmt::{Display, Formatter};
+use sces::crc::{crc32, crc32_update};
+use sces::mcu::flash::FlashCtrl;
+use sces::value::{ErrValue, RetValue};
+
+use crate::sha256::Sha256;
+
+/// `"SDFU"` in little endian.
+pub const DFU_MAGIC: u32 = 0x5546_4453;
+pub const DFU_HEADER_SIZE: usize = 64;
+
+/// The version of an image, it's compared by the major, the minor and the patch in turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DfuVersion
+{
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u16,
+}
+
+impl DfuVersion
+{
+    pub const fn new(major: u8, minor: u8, patch: u16) -> Self
+    {
+        Self { major, minor, patch }
+    }
+
+    /// The major is in the highest byte, so the raw values are in the same order.
+    pub const fn to_u32(&self) -> u32
+    {
+        (self.major as u32) << 24 | (self.minor as u32) << 16 | self.patch as u32
+    }
+
+    pub const fn from_u32(value: u32) -> Self
+    {
+        Self { major: (value >> 24) as u8, minor: (value >> 16) as u8, patch: value as u16 }
+    }
+}
+
+impl Display for DfuVersion
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result
+    {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DfuHeader
+{
+    pub header_size: u16,
+    pub version: DfuVersion,
+    pub build: u32,
+    pub size: u32,
+    pub crc: u32,
+    pub sha256: [u8; 32],
+    pub load_address: u32,
+}
+
+impl DfuHeader
+{
+    /// Decode the header, it fails when the magic number or the CRC is wrong.
+    pub fn decode(bytes: &[u8; DFU_HEADER_SIZE]) -> Option<Self>
+    {
+        let word =
+            |x: usize| u32::from_le_bytes([bytes[x], bytes[x + 1], bytes[x + 2], bytes[x + 3]]);
+
+        if word(0) != DFU_MAGIC || word(60) != crc32(&bytes[..60])
+        {
+            return None;
+        }
+
+        let mut sha256 = [0; 32];
+        sha256.copy_from_slice(&bytes[24..56]);
+
+        let header = Self {
+            header_size: u16::from_le_bytes([bytes[4], bytes[5]]),
+            version: DfuVersion::from_u32(word(8)),
+            build: word(12),
+            size: word(16),
+            crc: word(20),
+            sha256,
+            load_address: word(56),
+        };
+
+        (header.header_size as usize >= DFU_HEADER_SIZE).then_some(header)
+    }
+
+    pub fn encode(&self) -> [u8; DFU_HEADER_SIZE]
+    {
+        let mut bytes = [0; DFU_HEADER_SIZE];
+        bytes[0..4].copy_from_slice(&DFU_MAGIC.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.header_size.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.version.to_u32().to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.build.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.size.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.crc.to_le_bytes());
+        bytes[24..56].copy_from_slice(&self.sha256);
+        bytes[56..60].copy_from_slice(&self.load_address.to_le_bytes());
+
+        let crc = crc32(&bytes[..60]);
+        bytes[60..64].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// Read the header at `address`, it fails with [`ErrValue::InstanceNotFound`] when there's
+    /// no valid header.
+    pub fn read(flash: &dyn FlashCtrl, address: u32) -> RetValue<Self>
+    {
+        let mut bytes = [0; DFU_HEADER_SIZE];
+        flash.read(address, &mut bytes)?;
+        Self::decode(&bytes).ok_or(ErrValue::InstanceNotFound)
+    }
+
+    /// Check the CRC-32 and the SHA-256 of the image after the header at `address`, it fails
+    /// with [`ErrValue::InstanceInvalid`] when any of them is wrong.
+    pub fn verify(&self, flash: &dyn FlashCtrl, address: u32) -> RetValue<()>
+    {
+        let mut buffer = [0; 256];
+        let mut crc = 0xFFFF_FFFF;
+        let mut sha = Sha256::new();
+        let start = address + self.header_size as u32;
+
+        for offset in (0..self.size).step_by(buffer.len())
+        {
+            let read = &mut buffer[..(self.size - offset).min(256) as usize];
+            flash.read(start + offset, read)?;
+            crc = crc32_update(crc, read);
+            sha.update(read);
+        }
+
+        match !crc == self.crc && sha.finish() == self.sha256
+        {
+            true => Ok(()),
+            false => Err(ErrValue::InstanceInvalid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::sim::{RamFlash, BASE, FILE};
+
+    fn header() -> DfuHeader
+    {
+        DfuHeader::decode(FILE[..DFU_HEADER_SIZE].try_into().unwrap()).unwrap()
+    }
+
+    /// The image file is made by `sces-dfupack`, whose tests check the same bytes.
+    #[test]
+    fn decode_shared_file()
+    {
+        let header = header();
+        let image = &FILE[0x80..];
+
+        assert_eq!(header.header_size, 0x80);
+        assert_eq!(header.version, DfuVersion::new(1, 2, 3));
+        assert_eq!((header.build, header.size, header.load_address), (7, 600, 0));
+        assert_eq!(header.size as usize, image.len());
+        assert_eq!(header.crc, crc32(image));
+
+        let mut sha = Sha256::new();
+        sha.update(image);
+        assert_eq!(header.sha256, sha.finish());
+
+        assert_eq!(header.encode(), FILE[..DFU_HEADER_SIZE]);
+    }
+
+    #[test]
+    fn decode_broken()
+    {
+        let bytes: [u8; DFU_HEADER_SIZE] = FILE[..DFU_HEADER_SIZE].try_into().unwrap();
+
+        for (offset, bit) in [(0, 0x01), (16, 0x80), (63, 0x10)]
+        {
+            let mut broken = bytes;
+            broken[offset] ^= bit;
+            assert_eq!(DfuHeader::decode(&broken), None);
+        }
+
+        let small = DfuHeader { header_size: 32, ..header() };
+        assert_eq!(DfuHeader::decode(&small.encode()), None);
+    }
+
+    #[test]
+    fn version_order()
+    {
+        let versions = [(0, 9, 999), (1, 0, 0), (1, 0, 1), (1, 2, 0), (2, 0, 0)]
+            .map(|(major, minor, patch)| DfuVersion::new(major, minor, patch));
+
+        for pair in versions.windows(2)
+        {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].to_u32() < pair[1].to_u32());
+        }
+        assert_eq!(DfuVersion::from_u32(versions[3].to_u32()), versions[3]);
+    }
+
+    #[test]
+    fn verify_crc_and_sha()
+    {
+        let flash = RamFlash::new(1);
+        flash.write(BASE, FILE).unwrap();
+
+        let header = DfuHeader::read(flash, BASE).unwrap();
+        header.verify(flash, BASE).unwrap();
+
+        // A wrong SHA-256 is found even if the CRC-32 is right.
+        let forged = DfuHeader { sha256: [0; 32], ..header };
+        assert!(matches!(forged.verify(flash, BASE), Err(ErrValue::InstanceInvalid)));
+
+        flash.corrupt(BASE + 0x80 + 300, &[0]);
+        assert!(matches!(header.verify(flash, BASE), Err(ErrValue::InstanceInvalid)));
+        assert!(matches!(DfuHeader::read(flash, BASE + 0x400), Err(ErrValue::InstanceNotFound)));
+    }
+}
@@ -0,0 +1,32 @@
+#![cfg_attr(not(test), no_std)]
+
+#[cfg(feature = "alive")]
+mod alive;
+mod boot;
+#[cfg(feature = "console")]
+mod console;
+mod dfu;
+mod header;
+mod native;
+mod sha256;
+#[cfg(test)]
+mod sim;
+mod state;
+mod svc;
+
+#[cfg(feature = "alive")]
+pub use alive::DfuAliveCheck;
+pub use boot::DfuBoot;
+#[cfg(feature = "console")]
+pub use console::DfuCommand;
+pub use dfu::DfuLayout;
+pub use dfu::DfuManager;
+pub use dfu::DfuSlot;
+pub use dfu::DfuStatus;
+pub use header::DfuHeader;
+pub use header::DfuVersion;
+pub use header::DFU_HEADER_SIZE;
+pub use header::DFU_MAGIC;
+pub use native::NativeDfu;
+pub use sha256::Sha256;
+pub use svc::DfuService;
@@ -0,0 +1,449 @@
+mod writer;
+
+use core::marker::PhantomData;
+
+use sces::mcu::flash::FlashCtrl;
+use sces::os::mutex::MutexSample;
+use sces::os::RTOS;
+use sces::value::{ErrValue, RetValue};
+
+use crate::dfu::{DfuLayout, DfuManager, DfuStatus};
+use crate::header::DfuHeader;
+use crate::native::writer::ImageWriter;
+use crate::state::{DfuState, StateLog};
+
+struct DfuCore
+{
+    flash: &'static dyn FlashCtrl,
+    layout: DfuLayout,
+    log: StateLog,
+    writer: Option<ImageWriter>,
+}
+
+impl DfuCore
+{
+    fn begin(&mut self) -> RetValue<usize>
+    {
+        let mut state = self.log.state();
+        if state.trial.is_some() && state.attempts > 0
+        {
+            return Err(ErrValue::InstanceInUse);
+        }
+
+        // The trial image which has not been started is replaced.
+        let slot = 1 - state.active;
+        if state.trial == Some(slot)
+        {
+            state.trial = None;
+            self.log.save(state)?;
+        }
+
+        self.writer = None;
+        self.writer = Some(ImageWriter::begin(self.flash, slot, self.layout.slots[slot])?);
+        Ok(slot)
+    }
+
+    fn write(&mut self, chunk: &[u8]) -> RetValue<()>
+    {
+        let writer = self.writer.as_mut().ok_or(ErrValue::InstanceNotFound)?;
+
+        // A broken image file is dropped, it should be sent again from the start.
+        let result = writer.write(self.flash, chunk);
+        if result.is_err()
+        {
+            self.writer = None;
+        }
+
+        result
+    }
+
+    fn finish(&mut self) -> RetValue<DfuHeader>
+    {
+        let writer = self.writer.take().ok_or(ErrValue::InstanceNotFound)?;
+        let slot = writer.slot();
+        let header = writer.finish(self.flash)?;
+
+        let state = self.log.state();
+        self.log.save(DfuState { trial: Some(slot), attempts: 0, ..state })?;
+        Ok(header)
+    }
+
+    fn status(&self) -> DfuStatus
+    {
+        let state = self.log.state();
+
+        DfuStatus {
+            active: state.active,
+            trial: state.trial,
+            attempts: state.attempts,
+            rolled_back: state.rolled_back,
+            receiving: self.writer.as_ref().map(|x| (x.slot(), x.received())),
+        }
+    }
+
+    fn header(&self, slot: usize) -> RetValue<DfuHeader>
+    {
+        let slot = self.layout.slots.get(slot).ok_or(ErrValue::Param)?;
+        DfuHeader::read(self.flash, slot.address)
+    }
+
+    fn confirm(&mut self) -> RetValue<()>
+    {
+        let state = self.log.state();
+
+        match state.trial
+        {
+            Some(x) if state.attempts > 0 =>
+            {
+                self.log.save(DfuState { active: x, trial: None, attempts: 0, rolled_back: None })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn reject(&mut self) -> RetValue<()>
+    {
+        let state = self.log.state();
+
+        match state.trial
+        {
+            Some(x) => self.log.save(DfuState {
+                active: state.active,
+                trial: None,
+                attempts: 0,
+                rolled_back: Some(x),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The updates of the images in the slots of a flash, which are started by [`crate::DfuBoot`].
+///
+/// # Examples
+/// ```rust
+/// static mut DFU: Option<NativeDfu<MWOS>> = None;
+///
+/// let layout = DfuLayout::new([DfuSlot::new(0x0802_0000, 0x60000), DfuSlot::new(0x0808_0000, 0x60000)], [2, 3]);
+/// let dfu = DFU.insert(NativeDfu::new(flash, layout)?);
+/// DfuService::initialize(dfu)?;
+/// ```
+pub struct NativeDfu<OS>
+where
+    OS: RTOS,
+{
+    core: MutexSample<OS, DfuCore>,
+    _marker: PhantomData<OS>,
+}
+
+impl<OS> NativeDfu<OS>
+where
+    OS: RTOS,
+{
+    /// Mount the state of the updates on `flash`, which has the slots and the state of `layout`.
+    pub fn new(flash: &'static dyn FlashCtrl, layout: DfuLayout) -> RetValue<Self>
+    {
+        layout.check(flash)?;
+
+        let log = StateLog::mount(flash, layout.state)?;
+        let core = DfuCore { flash, layout, log, writer: None };
+        Ok(Self { core: MutexSample::new(core)?, _marker: PhantomData })
+    }
+}
+
+unsafe impl<OS> Send for NativeDfu<OS> where OS: RTOS {}
+
+unsafe impl<OS> Sync for NativeDfu<OS> where OS: RTOS {}
+
+impl<OS> DfuManager for NativeDfu<OS>
+where
+    OS: RTOS,
+{
+    fn begin(&self) -> RetValue<usize>
+    {
+        self.core.attempt_lock_then(|x| x.begin())
+    }
+
+    fn write(&self, chunk: &[u8]) -> RetValue<()>
+    {
+        self.core.attempt_lock_then(|x| x.write(chunk))
+    }
+
+    fn finish(&self) -> RetValue<DfuHeader>
+    {
+        self.core.attempt_lock_then(|x| x.finish())
+    }
+
+    fn abort(&self) -> RetValue<()>
+    {
+        self.core.attempt_lock_then(|x| {
+            x.writer = None;
+            Ok(())
+        })
+    }
+
+    fn status(&self) -> RetValue<DfuStatus>
+    {
+        self.core.attempt_lock_then(|x| Ok(x.status()))
+    }
+
+    fn header(&self, slot: usize) -> RetValue<DfuHeader>
+    {
+        self.core.attempt_lock_then(|x| x.header(slot))
+    }
+
+    fn confirm(&self) -> RetValue<()>
+    {
+        self.core.attempt_lock_then(|x| x.confirm())
+    }
+
+    fn reject(&self) -> RetValue<()>
+    {
+        self.core.attempt_lock_then(|x| x.reject())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::boot::{DfuBoot, BOOT_MAX_ATTEMPTS};
+    use crate::header::DFU_HEADER_SIZE;
+    use crate::sim::{layout, RamFlash, BASE, FILE, SECTOR_SIZE};
+
+    /// The address of the slot 1.
+    const SLOT1: u32 = BASE + SECTOR_SIZE * 4;
+
+    /// The shared image file with another build number.
+    fn file(build: u32) -> Vec<u8>
+    {
+        let header = DfuHeader::decode(FILE[..DFU_HEADER_SIZE].try_into().unwrap()).unwrap();
+
+        let mut file = FILE.to_vec();
+        file[..DFU_HEADER_SIZE].copy_from_slice(&DfuHeader { build, ..header }.encode());
+        file
+    }
+
+    /// A flash with the build 1 in the slot 0, which is the active one.
+    fn setup() -> &'static RamFlash
+    {
+        let flash = RamFlash::new(16);
+        flash.write(BASE, &file(1)).unwrap();
+        flash
+    }
+
+    fn core(flash: &'static RamFlash) -> DfuCore
+    {
+        flash.power_on();
+        let log = StateLog::mount(flash, layout().state).unwrap();
+        DfuCore { flash, layout: layout(), log, writer: None }
+    }
+
+    /// Reset and let the bootloader choose, it's the slot and the build.
+    fn boot(flash: &'static RamFlash) -> RetValue<(usize, u32)>
+    {
+        flash.power_on();
+        let (slot, header) = DfuBoot::new(flash, layout())?.select()?;
+        Ok((slot, header.build))
+    }
+
+    fn send(core: &mut DfuCore, file: &[u8]) -> RetValue<DfuHeader>
+    {
+        core.begin()?;
+        for chunk in file.chunks(37)
+        {
+            core.write(chunk)?;
+        }
+        core.finish()
+    }
+
+    #[test]
+    fn update_and_confirm()
+    {
+        let flash = setup();
+        assert_eq!(boot(flash).ok(), Some((0, 1)));
+
+        let mut dfu = core(flash);
+        assert_eq!(send(&mut dfu, &file(2)).unwrap().build, 2);
+        assert_eq!(dfu.header(1).unwrap().build, 2);
+        assert_eq!((dfu.status().trial, dfu.status().attempts), (Some(1), 0));
+
+        assert_eq!(boot(flash).ok(), Some((1, 2)));
+
+        let mut dfu = core(flash);
+        assert_eq!(dfu.status().running(), 1);
+        assert!(matches!(dfu.begin(), Err(ErrValue::InstanceInUse)));
+
+        dfu.confirm().unwrap();
+        let status = dfu.status();
+        assert_eq!((status.active, status.trial, status.running()), (1, None, 1));
+
+        for _ in 0..5
+        {
+            assert_eq!(boot(flash).ok(), Some((1, 2)));
+        }
+
+        // The next update goes into the slot 0.
+        let mut dfu = core(flash);
+        send(&mut dfu, &file(3)).unwrap();
+        assert_eq!(boot(flash).ok(), Some((0, 3)));
+    }
+
+    #[test]
+    fn trial_rolled_back()
+    {
+        let flash = setup();
+        send(&mut core(flash), &file(2)).unwrap();
+
+        for _ in 0..BOOT_MAX_ATTEMPTS
+        {
+            assert_eq!(boot(flash).ok(), Some((1, 2)));
+        }
+        assert_eq!(boot(flash).ok(), Some((0, 1)));
+        assert_eq!(boot(flash).ok(), Some((0, 1)));
+
+        let status = core(flash).status();
+        assert_eq!((status.active, status.trial, status.rolled_back), (0, None, Some(1)));
+
+        // It's rolled back at once without any attempt.
+        send(&mut core(flash), &file(3)).unwrap();
+        flash.power_on();
+        let mut boot = DfuBoot::new(flash, layout()).unwrap().with_max_attempts(0);
+        assert_eq!(boot.select().map(|(x, y)| (x, y.build)).ok(), Some((0, 1)));
+    }
+
+    #[test]
+    fn trial_rejected()
+    {
+        let flash = setup();
+        send(&mut core(flash), &file(2)).unwrap();
+        assert_eq!(boot(flash).ok(), Some((1, 2)));
+
+        let mut dfu = core(flash);
+        dfu.reject().unwrap();
+        assert_eq!(dfu.status().rolled_back, Some(1));
+
+        assert_eq!(boot(flash).ok(), Some((0, 1)));
+
+        // The confirmation of the active image changes nothing.
+        let mut dfu = core(flash);
+        dfu.confirm().unwrap();
+        assert_eq!(dfu.status().active, 0);
+    }
+
+    #[test]
+    fn broken_images()
+    {
+        let flash = setup();
+        let mut dfu = core(flash);
+
+        let mut broken = file(2);
+        broken[0x80 + 500] ^= 0x40;
+        assert!(matches!(send(&mut dfu, &broken), Err(ErrValue::InstanceInvalid)));
+        assert!(matches!(dfu.header(1), Err(ErrValue::InstanceNotFound)));
+        assert_eq!(dfu.status().trial, None);
+
+        // An image linked at the other slot is refused by its header, and dropped.
+        let header = DfuHeader::decode(FILE[..DFU_HEADER_SIZE].try_into().unwrap()).unwrap();
+        let linked = DfuHeader { load_address: BASE + 0x80, ..header }.encode();
+        dfu.begin().unwrap();
+        assert!(matches!(dfu.write(&linked), Err(ErrValue::InstanceInvalid)));
+        assert!(matches!(dfu.write(&FILE[DFU_HEADER_SIZE..]), Err(ErrValue::InstanceNotFound)));
+
+        // A trial image damaged in the flash is rolled back.
+        send(&mut dfu, &file(2)).unwrap();
+        flash.corrupt(SLOT1 + 0x80 + 10, &[0]);
+        assert_eq!(boot(flash).ok(), Some((0, 1)));
+
+        // The other image is used when the active one is damaged.
+        send(&mut core(flash), &file(3)).unwrap();
+        assert_eq!(boot(flash).ok(), Some((1, 3)));
+        core(flash).confirm().unwrap();
+        flash.corrupt(SLOT1 + 0x80 + 10, &[0]);
+        flash.corrupt(BASE + 0x80 + 10, &[0]);
+        assert!(matches!(boot(flash), Err(ErrValue::InstanceNotFound)));
+    }
+
+    #[test]
+    fn active_damaged()
+    {
+        let flash = setup();
+        send(&mut core(flash), &file(2)).unwrap();
+        assert_eq!(boot(flash).ok(), Some((1, 2)));
+        core(flash).reject().unwrap();
+
+        flash.corrupt(BASE + 0x80 + 10, &[0]);
+        assert_eq!(boot(flash).ok(), Some((1, 2)));
+        assert_eq!(core(flash).status().active, 1);
+    }
+
+    /// Cut the power at every write and erase of an update, the bootloader starts the old
+    /// image, or the new one as a trial, and the update could be done again.
+    #[test]
+    fn power_cut_in_update()
+    {
+        for count in 0..
+        {
+            let mut cut = false;
+            for keep in [Some(0), Some(5), None]
+            {
+                let flash = setup();
+                let mut dfu = core(flash);
+                match keep
+                {
+                    Some(keep) => flash.cut_write(count, keep),
+                    None => flash.cut_erase(count, false),
+                }
+
+                let result = send(&mut dfu, &file(2));
+                if !flash.is_off()
+                {
+                    assert!(result.is_ok());
+                    continue;
+                }
+
+                cut = true;
+                match boot(flash)
+                {
+                    Ok((0, 1)) => (),
+                    Ok((1, 2)) => core(flash).reject().unwrap(),
+                    x => panic!("{count} {keep:?}: {x:?}"),
+                }
+
+                send(&mut core(flash), &file(3)).unwrap();
+                assert_eq!(boot(flash).ok(), Some((1, 3)), "{count} {keep:?}");
+            }
+
+            if !cut
+            {
+                break;
+            }
+        }
+    }
+
+    /// Cut the power in the record of a boot, the trial image is not started by that boot, and
+    /// the boots before the roll back are still counted.
+    #[test]
+    fn power_cut_in_boot()
+    {
+        for keep in [0, 5, 15]
+        {
+            let flash = setup();
+            send(&mut core(flash), &file(2)).unwrap();
+
+            let mut cut = DfuBoot::new(flash, layout()).unwrap();
+            flash.cut_write(0, keep);
+            assert!(cut.select().is_err());
+
+            for _ in 0..BOOT_MAX_ATTEMPTS
+            {
+                assert_eq!(boot(flash).ok(), Some((1, 2)));
+            }
+            assert_eq!(boot(flash).ok(), Some((0, 1)));
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use sces::crc::crc32_update;
+use sces::mcu::flash::FlashCtrl;
+use sces::value::{ErrValue, RetValue};
+
+use crate::dfu::DfuSlot;
+use crate::header::{DfuHeader, DFU_HEADER_SIZE};
+use crate::sha256::Sha256;
+
+/// The size of the buffer which gathers the chunks into the program units of the flash.
+const WRITE_BUFFER: usize = 256;
+
+/// The writer of an image file into a slot, the chunks are written as they come.
+///
+/// The header is checked as soon as it's received, and it's programmed only after the whole
+/// image is verified, so a slot with a valid header always has the image of it.
+pub struct ImageWriter
+{
+    slot: usize,
+    area: DfuSlot,
+    header: Option<DfuHeader>,
+    head: [u8; DFU_HEADER_SIZE],
+    received: u32,
+    crc: u32,
+    sha: Sha256,
+    buffer: [u8; WRITE_BUFFER],
+    filled: usize,
+    written: u32,
+    erased: u32,
+}
+
+impl ImageWriter
+{
+    /// Erase the header of the slot `slot` and start to receive an image file into it.
+    pub fn begin(flash: &dyn FlashCtrl, slot: usize, area: DfuSlot) -> RetValue<Self>
+    {
+        if !WRITE_BUFFER.is_multiple_of(flash.write_size() as usize)
+        {
+            return Err(ErrValue::NotSupport);
+        }
+
+        let first = flash.sector_at(area.address)?;
+        flash.erase_sector(first.index)?;
+
+        Ok(Self {
+            slot,
+            area,
+            header: None,
+            head: [0; DFU_HEADER_SIZE],
+            received: 0,
+            crc: 0xFFFF_FFFF,
+            sha: Sha256::new(),
+            buffer: [0; WRITE_BUFFER],
+            filled: 0,
+            written: 0,
+            erased: first.end(),
+        })
+    }
+
+    pub const fn slot(&self) -> usize
+    {
+        self.slot
+    }
+
+    /// The bytes of the image file which are received.
+    pub const fn received(&self) -> u32
+    {
+        self.received
+    }
+
+    pub fn write(&mut self, flash: &dyn FlashCtrl, chunk: &[u8]) -> RetValue<()>
+    {
+        let mut chunk = chunk;
+
+        while !chunk.is_empty()
+        {
+            let Some(header) = self.header
+            else
+            {
+                let start = self.received as usize;
+                let size = chunk.len().min(DFU_HEADER_SIZE - start);
+
+                self.head[start..start + size].copy_from_slice(&chunk[..size]);
+                self.received += size as u32;
+                chunk = &chunk[size..];
+
+                if self.received as usize == DFU_HEADER_SIZE
+                {
+                    self.header = Some(self.check(flash, &self.head)?);
+                }
+                continue;
+            };
+
+            // The padding after the header is not kept, the header is programmed at the end.
+            let offset = header.header_size as u32;
+            if self.received < offset
+            {
+                let size = chunk.len().min((offset - self.received) as usize);
+                self.received += size as u32;
+                chunk = &chunk[size..];
+                continue;
+            }
+
+            if self.received - offset + chunk.len() as u32 > header.size
+            {
+                return Err(ErrValue::InstanceInvalid);
+            }
+
+            let size = chunk.len().min(WRITE_BUFFER - self.filled);
+            let data = &chunk[..size];
+
+            self.crc = crc32_update(self.crc, data);
+            self.sha.update(data);
+            self.buffer[self.filled..self.filled + size].copy_from_slice(data);
+            self.filled += size;
+            self.received += size as u32;
+            chunk = &chunk[size..];
+
+            if self.filled == WRITE_BUFFER
+            {
+                self.flush(flash)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the whole image, and program the header to make the slot valid.
+    pub fn finish(mut self, flash: &dyn FlashCtrl) -> RetValue<DfuHeader>
+    {
+        let header = self.header.ok_or(ErrValue::InstanceInvalid)?;
+        if self.received != header.header_size as u32 + header.size
+        {
+            return Err(ErrValue::InstanceInvalid);
+        }
+
+        self.flush(flash)?;
+        if !self.crc != header.crc || self.sha.clone().finish() != header.sha256
+        {
+            return Err(ErrValue::InstanceInvalid);
+        }
+
+        // The image is read back, so a bad program of the flash is found before the header.
+        header.verify(flash, self.area.address)?;
+        flash.write_verify(self.area.address, &self.head)?;
+        Ok(header)
+    }
+
+    fn check(&self, flash: &dyn FlashCtrl, bytes: &[u8; DFU_HEADER_SIZE]) -> RetValue<DfuHeader>
+    {
+        let header = DfuHeader::decode(bytes).ok_or(ErrValue::InstanceInvalid)?;
+        let image = self.area.address + header.header_size as u32;
+
+        if header.header_size as u64 + header.size as u64 > self.area.size as u64
+            || !(header.header_size as u32).is_multiple_of(flash.write_size())
+            || (header.load_address != 0 && header.load_address != image)
+        {
+            return Err(ErrValue::InstanceInvalid);
+        }
+
+        Ok(header)
+    }
+
+    fn flush(&mut self, flash: &dyn FlashCtrl) -> RetValue<()>
+    {
+        let Some(header) = self.header
+        else
+        {
+            return Ok(());
+        };
+
+        if self.filled == 0
+        {
+            return Ok(());
+        }
+
+        let address = self.area.address + header.header_size as u32 + self.written;
+        let end = address + self.filled as u32;
+
+        while self.erased < end
+        {
+            let sector = flash.sector_at(self.erased)?;
+            flash.erase_sector(sector.index)?;
+            self.erased = sector.end();
+        }
+
+        flash.write(address, &self.buffer[..self.filled])?;
+        self.written += self.filled as u32;
+        self.filled = 0;
+        Ok(())
+    }
+}
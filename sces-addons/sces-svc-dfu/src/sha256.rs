@@ -0,0 +1,194 @@
+//! The SHA-256 of FIPS 180-4, which is calculated piece by piece as the image is received.
+
+#[rustfmt::skip]
+const K: [u32; 64] = [
+    0x428A_2F98, 0x7137_4491, 0xB5C0_FBCF, 0xE9B5_DBA5, 0x3956_C25B, 0x59F1_11F1, 0x923F_82A4,
+    0xAB1C_5ED5, 0xD807_AA98, 0x1283_5B01, 0x2431_85BE, 0x550C_7DC3, 0x72BE_5D74, 0x80DE_B1FE,
+    0x9BDC_06A7, 0xC19B_F174, 0xE49B_69C1, 0xEFBE_4786, 0x0FC1_9DC6, 0x240C_A1CC, 0x2DE9_2C6F,
+    0x4A74_84AA, 0x5CB0_A9DC, 0x76F9_88DA, 0x983E_5152, 0xA831_C66D, 0xB003_27C8, 0xBF59_7FC7,
+    0xC6E0_0BF3, 0xD5A7_9147, 0x06CA_6351, 0x1429_2967, 0x27B7_0A85, 0x2E1B_2138, 0x4D2C_6DFC,
+    0x5338_0D13, 0x650A_7354, 0x766A_0ABB, 0x81C2_C92E, 0x9272_2C85, 0xA2BF_E8A1, 0xA81A_664B,
+    0xC24B_8B70, 0xC76C_51A3, 0xD192_E819, 0xD699_0624, 0xF40E_3585, 0x106A_A070, 0x19A4_C116,
+    0x1E37_6C08, 0x2748_774C, 0x34B0_BCB5, 0x391C_0CB3, 0x4ED8_AA4A, 0x5B9C_CA4F, 0x682E_6FF3,
+    0x748F_82EE, 0x78A5_636F, 0x84C8_7814, 0x8CC7_0208, 0x90BE_FFFA, 0xA450_6CEB, 0xBEF9_A3F7,
+    0xC671_78F2,
+];
+
+#[rustfmt::skip]
+const H: [u32; 8] = [
+    0x6A09_E667, 0xBB67_AE85, 0x3C6E_F372, 0xA54F_F53A, 0x510E_527F, 0x9B05_688C, 0x1F83_D9AB,
+    0x5BE0_CD19,
+];
+
+/// The SHA-256 of the data given by [`Sha256::update`].
+///
+/// ```ignore
+/// let mut sha = Sha256::new();
+/// sha.update(b"a");
+/// sha.update(b"bc");
+/// assert_eq!(sha.finish()[..4], [0xBA, 0x78, 0x16, 0xBF]);
+/// ```
+#[derive(Clone)]
+pub struct Sha256
+{
+    state: [u32; 8],
+    block: [u8; 64],
+    size: u64,
+}
+
+impl Sha256
+{
+    pub const fn new() -> Self
+    {
+        Self { state: H, block: [0; 64], size: 0 }
+    }
+
+    pub fn update(&mut self, data: &[u8])
+    {
+        for byte in data
+        {
+            self.block[(self.size % 64) as usize] = *byte;
+            self.size += 1;
+
+            if self.size.is_multiple_of(64)
+            {
+                self.compress();
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> [u8; 32]
+    {
+        let bits = self.size * 8;
+
+        self.update(&[0x80]);
+        while self.size % 64 != 56
+        {
+            self.update(&[0]);
+        }
+        self.update(&bits.to_be_bytes());
+
+        let mut digest = [0; 32];
+        for (chunk, word) in digest.chunks_mut(4).zip(self.state)
+        {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self)
+    {
+        let mut w = [0u32; 64];
+        for (i, chunk) in self.block.chunks(4).enumerate()
+        {
+            w[i] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        for i in 16..64
+        {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+
+        for i in 0..64
+        {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (state, x) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h])
+        {
+            *state = state.wrapping_add(x);
+        }
+    }
+}
+
+impl Default for Sha256
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use super::*;
+
+    fn hex(text: &str) -> [u8; 32]
+    {
+        core::array::from_fn(|x| u8::from_str_radix(&text[x * 2..x * 2 + 2], 16).unwrap())
+    }
+
+    fn sha256(data: &[u8]) -> [u8; 32]
+    {
+        let mut sha = Sha256::new();
+        sha.update(data);
+        sha.finish()
+    }
+
+    /// The examples of FIPS 180-4.
+    #[test]
+    fn known_answers()
+    {
+        let vectors = [
+            (&b""[..], "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            (b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+        ];
+
+        for (data, digest) in vectors
+        {
+            assert_eq!(sha256(data), hex(digest));
+        }
+    }
+
+    #[test]
+    fn update_in_pieces()
+    {
+        let data: std::vec::Vec<u8> = (0..200).collect();
+        for split in [0, 1, 55, 56, 63, 64, 65, 128, 199, 200]
+        {
+            let mut sha = Sha256::new();
+            sha.update(&data[..split]);
+            sha.update(&data[split..]);
+            assert_eq!(sha.finish(), sha256(&data));
+        }
+
+        // The million `a` of FIPS 180-4, in the pieces of 1 to 997 bytes.
+        let mut sha = Sha256::new();
+        let (mut left, mut size) = (1_000_000, 1);
+        while left > 0
+        {
+            let piece = size.min(left);
+            sha.update(&[b'a'; 1000][..piece]);
+            (left, size) = (left - piece, size % 997 + 1);
+        }
+
+        assert_eq!(
+            sha.finish(),
+            hex("cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0")
+        );
+    }
+}
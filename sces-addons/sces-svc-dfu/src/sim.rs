@@ -0,0 +1,222 @@
+//! A flash in RAM for the tests, whose power can be cut in the middle of a write or around an
+//! erase, and the image file shared with the tests of `sces-dfupack`.
+
+extern crate std;
+
+use core::cell::{Cell, RefCell};
+use std::boxed::Box;
+use std::vec;
+use std::vec::Vec;
+
+use sces::mcu::flash::{FlashCtrl, FlashSector};
+use sces::value::{ErrValue, RetValue};
+
+use crate::dfu::{DfuLayout, DfuSlot};
+
+pub const BASE: u32 = 0x0800_0000;
+pub const SECTOR_SIZE: u32 = 256;
+pub const SECTOR_COUNT: u32 = 10;
+
+/// The image file made by `sces-dfupack pack` of the 600 bytes `(x * 31 + 7) % 251`, with the
+/// version 1.2.3, the build 7 and the header size 0x80.
+pub const FILE: &[u8] = include_bytes!("../testdata/image.dfu");
+
+/// Two slots of 4 sectors and the state in the sectors 8 and 9.
+pub fn layout() -> DfuLayout
+{
+    DfuLayout::new(
+        [
+            DfuSlot::new(BASE, SECTOR_SIZE * 4),
+            DfuSlot::new(BASE + SECTOR_SIZE * 4, SECTOR_SIZE * 4),
+        ],
+        [8, 9],
+    )
+}
+
+pub struct RamFlash
+{
+    data: RefCell<Vec<u8>>,
+
+    /// The program units which are programmed since their sector was erased.
+    programmed: RefCell<Vec<bool>>,
+    write_size: u32,
+
+    /// The writes to finish before the power is cut, and the bytes of the next one to keep.
+    write_cut: Cell<Option<(u32, usize)>>,
+
+    /// The erases to finish before the power is cut, and whether the next one is finished.
+    erase_cut: Cell<Option<(u32, bool)>>,
+    off: Cell<bool>,
+}
+
+impl RamFlash
+{
+    pub fn new(write_size: u32) -> &'static Self
+    {
+        let size = (SECTOR_SIZE * SECTOR_COUNT) as usize;
+
+        Box::leak(Box::new(Self {
+            data: RefCell::new(vec![0xFF; size]),
+            programmed: RefCell::new(vec![false; size / write_size as usize]),
+            write_size,
+            write_cut: Cell::new(None),
+            erase_cut: Cell::new(None),
+            off: Cell::new(false),
+        }))
+    }
+
+    pub fn cut_write(&self, writes: u32, keep: usize)
+    {
+        self.write_cut.set(Some((writes, keep)));
+    }
+
+    pub fn cut_erase(&self, erases: u32, finish: bool)
+    {
+        self.erase_cut.set(Some((erases, finish)));
+    }
+
+    /// Whether the power was cut.
+    pub fn is_off(&self) -> bool
+    {
+        self.off.get()
+    }
+
+    pub fn power_on(&self)
+    {
+        self.write_cut.set(None);
+        self.erase_cut.set(None);
+        self.off.set(false);
+    }
+
+    /// Change the bytes without the rules of the flash, like a damage.
+    pub fn corrupt(&self, address: u32, data: &[u8])
+    {
+        let offset = (address - BASE) as usize;
+        self.data.borrow_mut()[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    fn offset(&self, address: u32, size: usize) -> RetValue<usize>
+    {
+        match address >= BASE && (address - BASE) as usize + size <= self.size() as usize
+        {
+            true => Ok((address - BASE) as usize),
+            false => Err(ErrValue::Param),
+        }
+    }
+}
+
+impl FlashCtrl for RamFlash
+{
+    fn base(&self) -> u32
+    {
+        BASE
+    }
+
+    fn size(&self) -> u32
+    {
+        SECTOR_SIZE * SECTOR_COUNT
+    }
+
+    fn write_size(&self) -> u32
+    {
+        self.write_size
+    }
+
+    fn sector_count(&self) -> u32
+    {
+        SECTOR_COUNT
+    }
+
+    fn sector(&self, index: u32) -> RetValue<FlashSector>
+    {
+        match index < SECTOR_COUNT
+        {
+            true => Ok(FlashSector {
+                index,
+                bank: 0,
+                address: BASE + index * SECTOR_SIZE,
+                size: SECTOR_SIZE,
+            }),
+            false => Err(ErrValue::Param),
+        }
+    }
+
+    fn read(&self, address: u32, data: &mut [u8]) -> RetValue<()>
+    {
+        let offset = self.offset(address, data.len())?;
+        data.copy_from_slice(&self.data.borrow()[offset..offset + data.len()]);
+        Ok(())
+    }
+
+    fn write(&self, address: u32, data: &[u8]) -> RetValue<()>
+    {
+        let offset = self.offset(address, data.len())?;
+        if self.off.get()
+        {
+            return Err(ErrValue::LowLevelFailure);
+        }
+
+        let keep = match self.write_cut.get()
+        {
+            Some((0, keep)) =>
+            {
+                self.off.set(true);
+                keep.min(data.len())
+            }
+            Some((writes, keep)) =>
+            {
+                self.write_cut.set(Some((writes - 1, keep)));
+                data.len()
+            }
+            None => data.len(),
+        };
+
+        // The units with ECC are programmed only once.
+        let unit = self.write_size as usize;
+        let units = offset / unit..(offset + keep).div_ceil(unit);
+        if unit > 1 && self.programmed.borrow()[units.clone()].iter().any(|x| *x)
+        {
+            return Err(ErrValue::LowLevelFailure);
+        }
+
+        self.programmed.borrow_mut()[units].fill(true);
+        self.data.borrow_mut()[offset..offset + keep]
+            .iter_mut()
+            .zip(data)
+            .for_each(|(x, y)| *x &= *y);
+
+        match keep == data.len()
+        {
+            true => Ok(()),
+            false => Err(ErrValue::LowLevelFailure),
+        }
+    }
+
+    fn erase_sector(&self, index: u32) -> RetValue<()>
+    {
+        let sector = self.sector(index)?;
+        if self.off.get()
+        {
+            return Err(ErrValue::LowLevelFailure);
+        }
+
+        match self.erase_cut.get()
+        {
+            Some((0, false)) =>
+            {
+                self.off.set(true);
+                return Err(ErrValue::LowLevelFailure);
+            }
+            Some((0, true)) => self.off.set(true),
+            Some((erases, finish)) => self.erase_cut.set(Some((erases - 1, finish))),
+            None => (),
+        }
+
+        let range = (sector.address - BASE) as usize..(sector.end() - BASE) as usize;
+        let unit = self.write_size as usize;
+
+        self.data.borrow_mut()[range.clone()].fill(0xFF);
+        self.programmed.borrow_mut()[range.start / unit..range.end / unit].fill(false);
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+//! The state of the updates, which is shared by the application and the bootloader.
+//!
+//! The state is logged over two sectors, a change appends a record of 16 bytes which is aligned
+//! to the program unit of the flash, and the record with the highest sequence is the current
+//! one. When a sector is full, the other one is erased and the log goes on there, so the last
+//! record is kept whenever the power is lost:
+//!
+//! | Offset | Size  | Content                                                   |
+//! |--------|-------|-----------------------------------------------------------|
+//! | 0      | 4     | The magic number                                          |
+//! | 4      | 4     | The sequence                                              |
+//! | 8      | 1     | The slot of the active image                              |
+//! | 9      | 1     | The slot of the trial image, or `0xFF`                    |
+//! | 10     | 1     | The boots of the trial image                              |
+//! | 11     | 1     | The slot of the image rolled back, or `0xFF`              |
+//! | 12     | 4     | The CRC-32 of the first 12 bytes                          |
+
+use sces::crc::crc32;
+use sces::mcu::flash::{FlashCtrl, FlashSector};
+use sces::value::{ErrValue, RetValue};
+
+/// `"SDFS"` in little endian.
+const STATE_MAGIC: u32 = 0x5346_4453;
+const STATE_SIZE: usize = 16;
+const STATE_NONE: u8 = 0xFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DfuState
+{
+    pub active: usize,
+    pub trial: Option<usize>,
+    pub attempts: u8,
+    pub rolled_back: Option<usize>,
+}
+
+impl DfuState
+{
+    fn encode(&self, sequence: u32) -> [u8; STATE_SIZE]
+    {
+        let slot = |x: Option<usize>| x.map_or(STATE_NONE, |x| x as u8);
+
+        let mut bytes = [0; STATE_SIZE];
+        bytes[0..4].copy_from_slice(&STATE_MAGIC.to_le_bytes());
+        bytes[4..8].copy_from_slice(&sequence.to_le_bytes());
+        bytes[8] = self.active as u8;
+        bytes[9] = slot(self.trial);
+        bytes[10] = self.attempts;
+        bytes[11] = slot(self.rolled_back);
+
+        let crc = crc32(&bytes[..12]);
+        bytes[12..16].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    fn decode(bytes: &[u8; STATE_SIZE]) -> Option<(u32, Self)>
+    {
+        let word =
+            |x: usize| u32::from_le_bytes([bytes[x], bytes[x + 1], bytes[x + 2], bytes[x + 3]]);
+        let slot = |x: u8| match x
+        {
+            0 | 1 => Some(Some(x as usize)),
+            STATE_NONE => Some(None),
+            _ => None,
+        };
+
+        if word(0) != STATE_MAGIC || word(12) != crc32(&bytes[..12]) || bytes[8] > 1
+        {
+            return None;
+        }
+
+        let state = Self {
+            active: bytes[8] as usize,
+            trial: slot(bytes[9])?,
+            attempts: bytes[10],
+            rolled_back: slot(bytes[11])?,
+        };
+
+        Some((word(4), state))
+    }
+}
+
+pub struct StateLog
+{
+    flash: &'static dyn FlashCtrl,
+    sectors: [FlashSector; 2],
+    align: u32,
+    current: usize,
+    offset: u32,
+    sequence: u32,
+    state: DfuState,
+}
+
+impl StateLog
+{
+    /// Find the last record in the sectors `sectors`, the state is the default one if there's
+    /// no record.
+    pub fn mount(flash: &'static dyn FlashCtrl, sectors: [u32; 2]) -> RetValue<Self>
+    {
+        let sectors = [flash.sector(sectors[0])?, flash.sector(sectors[1])?];
+        let align = flash.write_size().max(STATE_SIZE as u32);
+        if sectors.iter().any(|x| x.size < align)
+        {
+            return Err(ErrValue::Param);
+        }
+
+        let mut log = Self {
+            flash,
+            sectors,
+            align,
+            current: 0,
+            offset: 0,
+            sequence: 0,
+            state: DfuState::default(),
+        };
+        let mut found = false;
+        let mut used = [0; 2];
+
+        for (index, sector) in sectors.iter().enumerate()
+        {
+            let mut bytes = [0; STATE_SIZE];
+            let mut offset = 0;
+
+            while offset < sector.size
+            {
+                flash.read(sector.address + offset, &mut bytes)?;
+                if bytes.iter().all(|x| *x == 0xFF)
+                {
+                    break;
+                }
+
+                // A record which is broken by a lost power is skipped.
+                offset += align;
+                if let Some((sequence, state)) = DfuState::decode(&bytes)
+                {
+                    if !found || sequence > log.sequence
+                    {
+                        (log.current, log.sequence, log.state) = (index, sequence, state);
+                        found = true;
+                    }
+                }
+            }
+
+            used[index] = offset;
+        }
+
+        log.offset = used[log.current];
+        Ok(log)
+    }
+
+    pub const fn state(&self) -> DfuState
+    {
+        self.state
+    }
+
+    /// Append the record of `state`.
+    pub fn save(&mut self, state: DfuState) -> RetValue<()>
+    {
+        if state == self.state
+        {
+            return Ok(());
+        }
+
+        if self.offset + self.align > self.sectors[self.current].size
+        {
+            let next = 1 - self.current;
+            self.flash.erase_sector(self.sectors[next].index)?;
+            (self.current, self.offset) = (next, 0);
+        }
+
+        let bytes = state.encode(self.sequence.wrapping_add(1));
+        let address = self.sectors[self.current].address + self.offset;
+
+        // The unit is used even if the write fails, it's not written twice.
+        self.offset += self.align;
+        self.flash.write_verify(address, &bytes)?;
+
+        self.sequence = self.sequence.wrapping_add(1);
+        self.state = state;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::sim::{RamFlash, SECTOR_SIZE};
+
+    const SECTORS: [u32; 2] = [8, 9];
+
+    /// The records in a sector.
+    const RECORDS: u32 = SECTOR_SIZE / STATE_SIZE as u32;
+
+    /// A different state for every `x` up to 255.
+    fn state(x: u32) -> DfuState
+    {
+        DfuState {
+            active: x as usize % 2,
+            trial: (!x.is_multiple_of(3)).then_some(1 - x as usize % 2),
+            attempts: x as u8,
+            rolled_back: x.is_multiple_of(5).then_some(x as usize % 2),
+        }
+    }
+
+    fn mount(flash: &'static RamFlash) -> StateLog
+    {
+        flash.power_on();
+        StateLog::mount(flash, SECTORS).unwrap()
+    }
+
+    #[test]
+    fn mount_blank()
+    {
+        let flash = RamFlash::new(1);
+        assert_eq!(mount(flash).state(), DfuState::default());
+
+        let mut log = mount(flash);
+        log.save(state(1)).unwrap();
+        assert_eq!(mount(flash).state(), state(1));
+    }
+
+    #[test]
+    fn latest_across_sectors()
+    {
+        for write_size in [1, 16]
+        {
+            let flash = RamFlash::new(write_size);
+            let mut log = mount(flash);
+
+            for x in 1..=RECORDS * 5
+            {
+                log.save(state(x)).unwrap();
+                assert_eq!(mount(flash).state(), state(x), "{write_size} {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn save_same_skips()
+    {
+        let flash = RamFlash::new(1);
+        let mut log = mount(flash);
+
+        log.save(state(1)).unwrap();
+        let offset = log.offset;
+        log.save(state(1)).unwrap();
+        assert_eq!((log.offset, log.sequence), (offset, 1));
+    }
+
+    #[test]
+    fn broken_record_skipped()
+    {
+        let flash = RamFlash::new(1);
+        let mut log = mount(flash);
+        log.save(state(1)).unwrap();
+        log.save(state(2)).unwrap();
+
+        let address = flash.sector(SECTORS[0]).unwrap().address + STATE_SIZE as u32;
+        flash.corrupt(address + 9, &[0x00]);
+
+        let mut log = mount(flash);
+        assert_eq!(log.state(), state(1));
+        log.save(state(3)).unwrap();
+        assert_eq!(mount(flash).state(), state(3));
+    }
+
+    /// Cut the power in every write and around every erase of a save, the state is the old one
+    /// or the new one, and the log still works.
+    #[test]
+    fn power_cut_in_save()
+    {
+        for write_size in [1, 16]
+        {
+            for saved in [1, 2, RECORDS - 1, RECORDS, RECORDS + 1, RECORDS * 2]
+            {
+                let cuts = [0, 1, 8, 15].map(|x| (Some(x), false)).into_iter();
+                for (keep, finish) in cuts.chain([(None, false), (None, true)])
+                {
+                    let flash = RamFlash::new(write_size);
+                    let mut log = mount(flash);
+                    (1..=saved).for_each(|x| log.save(state(x)).unwrap());
+
+                    match keep
+                    {
+                        Some(keep) => flash.cut_write(0, keep),
+                        None => flash.cut_erase(0, finish),
+                    }
+
+                    let (old, new) = (state(saved), state(saved + 1));
+                    let result = log.save(new);
+                    let found = mount(flash).state();
+
+                    let case = (write_size, saved, keep, finish);
+                    assert!(found == old || found == new, "{case:?}");
+                    if result.is_ok()
+                    {
+                        assert_eq!(found, new, "{case:?}");
+                    }
+
+                    let mut log = mount(flash);
+                    log.save(state(200)).unwrap();
+                    log.save(state(201)).unwrap();
+                    assert_eq!(mount(flash).state(), state(201), "{case:?}");
+                }
+            }
+        }
+    }
+}
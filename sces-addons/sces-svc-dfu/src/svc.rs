@@ -0,0 +1,29 @@
+use sces::value::{ErrValue, RetValue};
+
+use crate::dfu::DfuManager;
+
+static mut SVC: Option<&'static dyn DfuManager> = None;
+
+pub const DFS: &str = "<DfuService>";
+
+pub struct DfuService;
+
+impl DfuService
+{
+    pub fn initialize<T>(instance: &'static T) -> RetValue<()>
+    where
+        T: DfuManager,
+    {
+        #[allow(static_mut_refs)]
+        unsafe {
+            SVC.is_none().then_some(()).ok_or(ErrValue::InstanceDuplicate)?
+        };
+        unsafe { SVC = Some(instance) };
+        Ok(())
+    }
+
+    pub fn instance() -> &'static dyn DfuManager
+    {
+        unsafe { SVC.unwrap() }
+    }
+}
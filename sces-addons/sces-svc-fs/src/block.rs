@@ -0,0 +1,159 @@
+use core::ops::Range;
+use sces::mcu::flash::FlashCtrl;
+#[cfg(feature = "norflash")]
+use sces::os::RTOS;
+use sces::value::{ErrValue, RetValue};
+#[cfg(feature = "norflash")]
+use sces_dev_norflash::SpiNorFlash;
+
+/// A flash which is split into the blocks of the same size, a block is the unit of the erase.
+pub trait BlockDevice: Send + Sync
+{
+    fn block_size(&self) -> u32;
+
+    fn block_count(&self) -> u32;
+
+    /// The unit of the program in bytes, every program starts at a boundary of the units and
+    /// covers whole units.
+    fn prog_size(&self) -> u32;
+
+    fn read(&self, block: u32, offset: u32, data: &mut [u8]) -> RetValue<()>;
+
+    /// Program `data` into the erased area of `block`.
+    fn prog(&self, block: u32, offset: u32, data: &[u8]) -> RetValue<()>;
+
+    fn erase(&self, block: u32) -> RetValue<()>;
+}
+
+/// The blocks on the sectors of a [`FlashCtrl`], like the on chip flash.
+pub struct FlashBlocks
+{
+    flash: &'static dyn FlashCtrl,
+    first: u32,
+    count: u32,
+    address: u32,
+    size: u32,
+}
+
+impl FlashBlocks
+{
+    /// Use the sectors `sectors` of `flash` as the blocks, they must be next to each other and
+    /// have the same size.
+    pub fn new(flash: &'static dyn FlashCtrl, sectors: Range<u32>) -> RetValue<Self>
+    {
+        let first = flash.sector(sectors.start)?;
+
+        for index in sectors.clone()
+        {
+            let sector = flash.sector(index)?;
+
+            if sector.size != first.size
+                || sector.address != first.address + (index - sectors.start) * first.size
+            {
+                return Err(ErrValue::Param);
+            }
+        }
+
+        Ok(Self {
+            flash,
+            first: sectors.start,
+            count: sectors.len() as u32,
+            address: first.address,
+            size: first.size,
+        })
+    }
+
+    fn address(&self, block: u32, offset: u32, size: usize) -> RetValue<u32>
+    {
+        match block < self.count && offset as usize + size <= self.size as usize
+        {
+            true => Ok(self.address + block * self.size + offset),
+            false => Err(ErrValue::Param),
+        }
+    }
+}
+
+unsafe impl Send for FlashBlocks {}
+
+unsafe impl Sync for FlashBlocks {}
+
+impl BlockDevice for FlashBlocks
+{
+    fn block_size(&self) -> u32
+    {
+        self.size
+    }
+
+    fn block_count(&self) -> u32
+    {
+        self.count
+    }
+
+    fn prog_size(&self) -> u32
+    {
+        self.flash.write_size()
+    }
+
+    fn read(&self, block: u32, offset: u32, data: &mut [u8]) -> RetValue<()>
+    {
+        self.flash.read(self.address(block, offset, data.len())?, data)
+    }
+
+    fn prog(&self, block: u32, offset: u32, data: &[u8]) -> RetValue<()>
+    {
+        self.flash.write(self.address(block, offset, data.len())?, data)
+    }
+
+    fn erase(&self, block: u32) -> RetValue<()>
+    {
+        self.address(block, 0, 0)?;
+        self.flash.erase_sector(self.first + block)
+    }
+}
+
+/// The blocks are the smallest erase units of the chip, which are 4 KiB on almost all chips.
+#[cfg(feature = "norflash")]
+impl<OS> BlockDevice for SpiNorFlash<'_, OS>
+where
+    OS: RTOS,
+{
+    fn block_size(&self) -> u32
+    {
+        self.geometry().sector().size
+    }
+
+    fn block_count(&self) -> u32
+    {
+        self.geometry().size / self.block_size()
+    }
+
+    fn prog_size(&self) -> u32
+    {
+        1
+    }
+
+    fn read(&self, block: u32, offset: u32, data: &mut [u8]) -> RetValue<()>
+    {
+        if offset as usize + data.len() > self.block_size() as usize
+        {
+            return Err(ErrValue::Param);
+        }
+
+        SpiNorFlash::read(self, block * self.block_size() + offset, data)
+    }
+
+    fn prog(&self, block: u32, offset: u32, data: &[u8]) -> RetValue<()>
+    {
+        if offset as usize + data.len() > self.block_size() as usize
+        {
+            return Err(ErrValue::Param);
+        }
+
+        SpiNorFlash::program(self, block * self.block_size() + offset, data)
+    }
+
+    fn erase(&self, block: u32) -> RetValue<()>
+    {
+        self.erase_sector_at(block * self.block_size())
+    }
+}
@@ -0,0 +1,146 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use log::info;
+use sces::value::{ErrValue, RetValue};
+use sces_svc_console::{ConsoleCommands, ConsoleExecute};
+
+use crate::fs::{FsKind, FsOpen, FsStat};
+use crate::svc::FileSystemService;
+
+/// The log target of the records printed by the commands of the filesystem.
+pub const FS_TARGET: &str = "fs";
+
+/// The longest line printed by [`FsCat`], a longer one is split.
+const CAT_LINE: usize = 128;
+
+fn path<'a>(cmds: &mut ConsoleCommands<'a>) -> RetValue<Option<&'a str>>
+{
+    cmds.next().map(core::str::from_utf8).transpose().or(Err(ErrValue::FormatFailure))
+}
+
+/// The console command to show the entries in a directory of [`FileSystemService`].
+///
+/// * `ls [path]`: Show the kind, the size and the name of every entry, the root by default.
+pub struct FsList;
+
+impl ConsoleExecute for FsList
+{
+    fn exe_name(&self) -> &str
+    {
+        "ls"
+    }
+
+    fn exe_with_cmds(&self, cmds: &mut ConsoleCommands) -> RetValue<()>
+    {
+        // The filesystem is locked in the callback, so the entries are taken before they are
+        // printed.
+        let mut entries: Vec<(String, FsStat)> = Vec::new();
+        FileSystemService::instance().read_dir(path(cmds)?.unwrap_or("/"), &mut |name, stat| {
+            entries.push((String::from(name), stat))
+        })?;
+
+        for (name, stat) in entries
+        {
+            match stat.kind
+            {
+                FsKind::Dir => info!(target: FS_TARGET, "d {:>10} {name}/", "-"),
+                FsKind::File => info!(target: FS_TARGET, "- {:>10} {name}", stat.size),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The console command to print a file of [`FileSystemService`] line by line.
+///
+/// * `cat <path>`
+pub struct FsCat;
+
+impl ConsoleExecute for FsCat
+{
+    fn exe_name(&self) -> &str
+    {
+        "cat"
+    }
+
+    fn exe_with_cmds(&self, cmds: &mut ConsoleCommands) -> RetValue<()>
+    {
+        let file = FileSystemService::open(path(cmds)?.ok_or(ErrValue::Param)?, FsOpen::read())?;
+
+        let mut line = Vec::new();
+        line.try_reserve_exact(CAT_LINE).or(Err(ErrValue::MemAllocFailure))?;
+
+        let mut buffer = [0; 64];
+
+        loop
+        {
+            let size = file.read(&mut buffer)?;
+
+            for byte in &buffer[..size]
+            {
+                if *byte != b'\n'
+                {
+                    line.push(*byte);
+                }
+
+                if *byte == b'\n' || line.len() == CAT_LINE
+                {
+                    info!(target: FS_TARGET, "{}", String::from_utf8_lossy(&line));
+                    line.clear();
+                }
+            }
+
+            if size == 0
+            {
+                break;
+            }
+        }
+
+        if !line.is_empty()
+        {
+            info!(target: FS_TARGET, "{}", String::from_utf8_lossy(&line));
+        }
+
+        file.close()
+    }
+}
+
+/// The console command to remove a file or an empty directory of [`FileSystemService`].
+///
+/// * `rm <path>`
+pub struct FsRemove;
+
+impl ConsoleExecute for FsRemove
+{
+    fn exe_name(&self) -> &str
+    {
+        "rm"
+    }
+
+    fn exe_with_cmds(&self, cmds: &mut ConsoleCommands) -> RetValue<()>
+    {
+        FileSystemService::instance().remove(path(cmds)?.ok_or(ErrValue::Param)?)
+    }
+}
+
+/// The console command to show the usage of [`FileSystemService`].
+///
+/// * `df`
+pub struct FsFree;
+
+impl ConsoleExecute for FsFree
+{
+    fn exe_name(&self) -> &str
+    {
+        "df"
+    }
+
+    fn exe_with_cmds(&self, _cmds: &mut ConsoleCommands) -> RetValue<()>
+    {
+        let usage = FileSystemService::instance().usage()?;
+
+        info!(target: FS_TARGET, "Used {} of {} blocks of {} bytes, {} bytes free.", usage.used, usage.block_count, usage.block_size, usage.free_bytes());
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use sces::value::RetValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsKind
+{
+    File,
+    Dir,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsStat
+{
+    pub kind: FsKind,
+
+    /// The size of a file in bytes, and `0` for a directory.
+    pub size: u32,
+}
+
+/// The usage of the blocks, including the blocks of the superblock and the metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsUsage
+{
+    pub block_size: u32,
+    pub block_count: u32,
+    pub used: u32,
+}
+
+impl FsUsage
+{
+    pub const fn free_bytes(&self) -> u32
+    {
+        (self.block_count - self.used) * self.block_size
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsSeek
+{
+    Start(u32),
+    End(i32),
+    Current(i32),
+}
+
+/// How a file is opened.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FsOpen
+{
+    pub read: bool,
+    pub write: bool,
+
+    /// Create the file when it doesn't exist.
+    pub create: bool,
+
+    /// Drop the data of the file when it's opened.
+    pub truncate: bool,
+
+    /// Write at the end of the file whatever the position is.
+    pub append: bool,
+}
+
+impl FsOpen
+{
+    /// Open an existing file to read.
+    pub const fn read() -> Self
+    {
+        Self { read: true, write: false, create: false, truncate: false, append: false }
+    }
+
+    /// Open a file to write from the start, it's created or truncated.
+    pub const fn write() -> Self
+    {
+        Self { read: false, write: true, create: true, truncate: true, append: false }
+    }
+
+    /// Open a file to write at the end, it's created when it doesn't exist.
+    pub const fn append() -> Self
+    {
+        Self { read: false, write: true, create: true, truncate: false, append: true }
+    }
+
+    pub const fn with_read(mut self, read: bool) -> Self
+    {
+        self.read = read;
+        self
+    }
+
+    pub const fn with_write(mut self, write: bool) -> Self
+    {
+        self.write = write;
+        self
+    }
+
+    pub const fn with_create(mut self, create: bool) -> Self
+    {
+        self.create = create;
+        self
+    }
+
+    pub const fn with_truncate(mut self, truncate: bool) -> Self
+    {
+        self.truncate = truncate;
+        self
+    }
+
+    pub const fn with_append(mut self, append: bool) -> Self
+    {
+        self.append = append;
+        self
+    }
+}
+
+/// The handle of an opened file in a [`FileSystem`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsHandle(pub(crate) u16);
+
+/// A filesystem of files in directories.
+///
+/// The paths are split by `/`, and the names in them are 32 bytes at most. A file is opened only
+/// once at a time, the second open fails with [`ErrValue::InstanceInUse`](sces::value::ErrValue).
+/// The written data is kept by the filesystem after the file is synced or closed, so a power loss
+/// leaves either the old data or the new data. [`dyn FileSystem`](FileSystem) opens a file as a
+/// [`FsFile`] which is closed when it's dropped:
+///
+/// ```rust
+/// let fs = FileSystemService::instance();
+///
+/// fs.mkdir("/log")?;
+/// let file = fs.open_file("/log/boot.txt", FsOpen::append())?;
+/// file.write(b"Boot.\n")?;
+/// file.close()?;
+/// ```
+pub trait FileSystem: Send + Sync
+{
+    fn open(&self, path: &str, options: FsOpen) -> RetValue<FsHandle>;
+
+    /// Sync the file, and release the handle even if the sync fails.
+    fn close(&self, handle: FsHandle) -> RetValue<()>;
+
+    /// Read from the position of the file, and return the count of bytes read, which is `0` at
+    /// the end of the file.
+    fn read(&self, handle: FsHandle, data: &mut [u8]) -> RetValue<usize>;
+
+    /// Write at the position of the file, and return the count of bytes written.
+    fn write(&self, handle: FsHandle, data: &[u8]) -> RetValue<usize>;
+
+    /// Move the position of the file, which can't be beyond the end of it, and return the new
+    /// position.
+    fn seek(&self, handle: FsHandle, pos: FsSeek) -> RetValue<u32>;
+
+    /// Keep the written data of the file on the flash.
+    fn sync(&self, handle: FsHandle) -> RetValue<()>;
+
+    fn mkdir(&self, path: &str) -> RetValue<()>;
+
+    /// Remove a file or an empty directory.
+    fn remove(&self, path: &str) -> RetValue<()>;
+
+    /// Move a file or a directory, it fails with
+    /// [`ErrValue::InstanceDuplicate`](sces::value::ErrValue) when `to` exists.
+    fn rename(&self, from: &str, to: &str) -> RetValue<()>;
+
+    fn stat(&self, path: &str) -> RetValue<FsStat>;
+
+    /// Call `f` with the name and the state of every entry in the directory, the filesystem
+    /// can't be accessed in `f`.
+    fn read_dir(&self, path: &str, f: &mut dyn FnMut(&str, FsStat)) -> RetValue<()>;
+
+    fn usage(&self) -> RetValue<FsUsage>;
+}
+
+impl dyn FileSystem
+{
+    pub fn open_file(&self, path: &str, options: FsOpen) -> RetValue<FsFile<'_>>
+    {
+        Ok(FsFile { fs: self, handle: self.open(path, options)? })
+    }
+}
+
+/// An opened file, it's closed when it's dropped.
+pub struct FsFile<'a>
+{
+    fs: &'a dyn FileSystem,
+    handle: FsHandle,
+}
+
+impl FsFile<'_>
+{
+    pub fn handle(&self) -> FsHandle
+    {
+        self.handle
+    }
+
+    pub fn read(&self, data: &mut [u8]) -> RetValue<usize>
+    {
+        self.fs.read(self.handle, data)
+    }
+
+    pub fn write(&self, data: &[u8]) -> RetValue<usize>
+    {
+        self.fs.write(self.handle, data)
+    }
+
+    pub fn seek(&self, pos: FsSeek) -> RetValue<u32>
+    {
+        self.fs.seek(self.handle, pos)
+    }
+
+    pub fn sync(&self) -> RetValue<()>
+    {
+        self.fs.sync(self.handle)
+    }
+
+    /// Close the file and get the result of the last sync, which is dropped by [`Drop`].
+    pub fn close(self) -> RetValue<()>
+    {
+        let result = self.fs.close(self.handle);
+        core::mem::forget(self);
+        result
+    }
+}
+
+impl Drop for FsFile<'_>
+{
+    fn drop(&mut self)
+    {
+        self.fs.close(self.handle).ok();
+    }
+}
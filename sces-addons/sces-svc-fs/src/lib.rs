@@ -0,0 +1,31 @@
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+mod block;
+#[cfg(feature = "console")]
+mod console;
+mod fs;
+mod native;
+mod svc;
+
+pub use block::BlockDevice;
+pub use block::FlashBlocks;
+#[cfg(feature = "console")]
+pub use console::FsCat;
+#[cfg(feature = "console")]
+pub use console::FsFree;
+#[cfg(feature = "console")]
+pub use console::FsList;
+#[cfg(feature = "console")]
+pub use console::FsRemove;
+pub use fs::FileSystem;
+pub use fs::FsFile;
+pub use fs::FsHandle;
+pub use fs::FsKind;
+pub use fs::FsOpen;
+pub use fs::FsSeek;
+pub use fs::FsStat;
+pub use fs::FsUsage;
+pub use native::NativeFileSystem;
+pub use svc::FileSystemService;
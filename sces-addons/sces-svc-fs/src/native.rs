@@ -0,0 +1,135 @@
+mod blocks;
+mod file;
+mod layout;
+mod meta;
+mod table;
+mod volume;
+
+use core::marker::PhantomData;
+
+use sces::os::mutex::MutexSample;
+use sces::os::RTOS;
+use sces::value::RetValue;
+
+use crate::block::BlockDevice;
+use crate::fs::{FileSystem, FsHandle, FsOpen, FsSeek, FsStat, FsUsage};
+use crate::native::volume::FsVolume;
+
+/// The filesystem which keeps the data in the chains of blocks and the table of the files in two
+/// blocks, and shares them by the mutex of the OS.
+///
+/// The whole table is copied on every commit, and it must fit in one block: every file and
+/// directory takes 14 bytes and its name, after 28 bytes of the headers. A change which makes it
+/// larger, like [`open`](FileSystem::open) of a new file or [`mkdir`](FileSystem::mkdir), fails
+/// with [`ErrValue::StackOverflow`](sces::value::ErrValue), so a block of 4 KiB holds about 150
+/// entries with names of 12 bytes.
+///
+/// The free blocks are found when the filesystem is mounted, and they are taken in turn to level
+/// the wear. The two blocks of the table take every commit, so they move to two free blocks after
+/// 100 erases. The blocks 0 and 1 point to them, and they are written only when the table moves.
+///
+/// # Examples
+/// ```rust
+/// static mut FS: Option<NativeFileSystem<MWOS>> = None;
+///
+/// let fs = match NativeFileSystem::mount(device)
+/// {
+///     Ok(fs) => fs,
+///     Err(_) => NativeFileSystem::format(device)?,
+/// };
+/// FileSystemService::initialize(FS.insert(fs))?;
+/// ```
+pub struct NativeFileSystem<OS>
+where
+    OS: RTOS,
+{
+    volume: MutexSample<OS, FsVolume>,
+    _marker: PhantomData<OS>,
+}
+
+impl<OS> NativeFileSystem<OS>
+where
+    OS: RTOS,
+{
+    /// Load the filesystem on `device`, it fails with
+    /// [`ErrValue::InstanceNotFound`](sces::value::ErrValue) when the device is not formatted.
+    pub fn mount(device: &'static dyn BlockDevice) -> RetValue<Self>
+    {
+        Ok(Self { volume: MutexSample::new(FsVolume::mount(device)?)?, _marker: PhantomData })
+    }
+
+    /// Make an empty filesystem on `device`.
+    pub fn format(device: &'static dyn BlockDevice) -> RetValue<Self>
+    {
+        Ok(Self { volume: MutexSample::new(FsVolume::format(device)?)?, _marker: PhantomData })
+    }
+}
+
+unsafe impl<OS> Send for NativeFileSystem<OS> where OS: RTOS {}
+
+unsafe impl<OS> Sync for NativeFileSystem<OS> where OS: RTOS {}
+
+impl<OS> FileSystem for NativeFileSystem<OS>
+where
+    OS: RTOS,
+{
+    fn open(&self, path: &str, options: FsOpen) -> RetValue<FsHandle>
+    {
+        self.volume.attempt_lock_then(|x| x.open(path, options))
+    }
+
+    fn close(&self, handle: FsHandle) -> RetValue<()>
+    {
+        self.volume.attempt_lock_then(|x| x.close(handle))
+    }
+
+    fn read(&self, handle: FsHandle, data: &mut [u8]) -> RetValue<usize>
+    {
+        self.volume.attempt_lock_then(|x| x.read(handle, data))
+    }
+
+    fn write(&self, handle: FsHandle, data: &[u8]) -> RetValue<usize>
+    {
+        self.volume.attempt_lock_then(|x| x.write(handle, data))
+    }
+
+    fn seek(&self, handle: FsHandle, pos: FsSeek) -> RetValue<u32>
+    {
+        self.volume.attempt_lock_then(|x| x.seek(handle, pos))
+    }
+
+    fn sync(&self, handle: FsHandle) -> RetValue<()>
+    {
+        self.volume.attempt_lock_then(|x| x.sync(handle))
+    }
+
+    fn mkdir(&self, path: &str) -> RetValue<()>
+    {
+        self.volume.attempt_lock_then(|x| x.mkdir(path))
+    }
+
+    fn remove(&self, path: &str) -> RetValue<()>
+    {
+        self.volume.attempt_lock_then(|x| x.remove(path))
+    }
+
+    fn rename(&self, from: &str, to: &str) -> RetValue<()>
+    {
+        self.volume.attempt_lock_then(|x| x.rename(from, to))
+    }
+
+    fn stat(&self, path: &str) -> RetValue<FsStat>
+    {
+        self.volume.attempt_lock_then(|x| x.stat(path))
+    }
+
+    fn read_dir(&self, path: &str, f: &mut dyn FnMut(&str, FsStat)) -> RetValue<()>
+    {
+        self.volume.attempt_lock_then(|x| x.read_dir(path, f))
+    }
+
+    fn usage(&self) -> RetValue<FsUsage>
+    {
+        self.volume.attempt_lock_then(|x| Ok(x.usage()))
+    }
+}
@@ -0,0 +1,84 @@
+use alloc::vec::Vec;
+use sces::value::{ErrValue, RetValue};
+
+/// The map of the used blocks, which is built by walking all files when the filesystem is
+/// mounted.
+pub struct BlockMap
+{
+    bits: Vec<u32>,
+    count: u32,
+    cursor: u32,
+
+    /// The blocks which are still used by the committed table, they are released after the
+    /// next commit.
+    deferred: Vec<u32>,
+}
+
+impl BlockMap
+{
+    pub fn new(count: u32) -> RetValue<Self>
+    {
+        let mut bits = Vec::new();
+        bits.try_reserve_exact(count.div_ceil(32) as usize).or(Err(ErrValue::MemAllocFailure))?;
+        bits.resize(count.div_ceil(32) as usize, 0);
+
+        Ok(Self { bits, count, cursor: 0, deferred: Vec::new() })
+    }
+
+    /// Start the search of the free blocks at `seed`, so the blocks after a reset are not always
+    /// taken from the first one.
+    pub fn seed(&mut self, seed: u32)
+    {
+        self.cursor = seed % self.count;
+    }
+
+    /// Mark a block as used, and return `false` when it's already used.
+    pub fn mark(&mut self, block: u32) -> bool
+    {
+        let (word, bit) = ((block / 32) as usize, 1 << (block % 32));
+        let free = self.bits[word] & bit == 0;
+        self.bits[word] |= bit;
+        free
+    }
+
+    pub fn release(&mut self, block: u32)
+    {
+        self.bits[(block / 32) as usize] &= !(1 << (block % 32));
+    }
+
+    pub fn defer(&mut self, blocks: &[u32]) -> RetValue<()>
+    {
+        self.deferred.try_reserve(blocks.len()).or(Err(ErrValue::MemAllocFailure))?;
+        self.deferred.extend_from_slice(blocks);
+        Ok(())
+    }
+
+    /// Release the deferred blocks after the commit, or keep them when the commit fails.
+    pub fn settle(&mut self, committed: bool)
+    {
+        let deferred = core::mem::take(&mut self.deferred);
+
+        if committed
+        {
+            deferred.into_iter().for_each(|x| self.release(x));
+        }
+    }
+
+    /// Take a free block, the search goes on from the last taken one to spread the wear.
+    pub fn take(&mut self) -> RetValue<u32>
+    {
+        let block = (0..self.count)
+            .map(|x| (self.cursor + x) % self.count)
+            .find(|x| self.bits[(x / 32) as usize] & (1 << (x % 32)) == 0)
+            .ok_or(ErrValue::StackOverflow)?;
+
+        self.mark(block);
+        self.cursor = (block + 1) % self.count;
+        Ok(block)
+    }
+
+    pub fn used(&self) -> u32
+    {
+        self.bits.iter().map(|x| x.count_ones()).sum()
+    }
+}
@@ -0,0 +1,42 @@
+use alloc::vec::Vec;
+
+use crate::fs::FsOpen;
+
+/// A file which is opened.
+pub struct FsOpenFile
+{
+    pub id: u16,
+    pub options: FsOpen,
+    pub pos: u32,
+
+    /// The size and the blocks of the file in the committed table.
+    pub size: u32,
+    pub chain: Vec<u32>,
+
+    pub writer: Option<FsWriter>,
+}
+
+/// The data written since the last sync, which goes into the new blocks.
+///
+/// The new blocks take the place of the blocks from `index` in the chain, they start with the
+/// data before the first written byte in the block at `index`, and they end with the data after
+/// the last written byte when the file is synced.
+pub struct FsWriter
+{
+    pub index: usize,
+
+    /// The block before the first new one, or `BLOCK_NONE`.
+    pub prev: u32,
+    pub blocks: Vec<u32>,
+
+    /// The position in the file after the written data.
+    pub pos: u32,
+
+    /// The size of the data in the last new block.
+    pub fill: u32,
+
+    /// The bytes of the last new block which are not programmed yet, from the offset `cached` of
+    /// the block.
+    pub cache: Vec<u8>,
+    pub cached: u32,
+}
@@ -0,0 +1,237 @@
+//! The blocks 0 and 1 are the superblock, which keeps where the metadata is, and the metadata,
+//! which is the table of all files and directories, is in a pair of other blocks. Both pairs
+//! keep snapshots in the same way. A commit appends a snapshot to the active block of the pair,
+//! and when it's full, the other one is erased and takes the snapshot. The valid snapshot with
+//! the largest revision is the one in use, so a commit which is cut by a power loss leaves the
+//! last one. A snapshot is padded with `0xFF` to the program unit:
+//!
+//! | Offset | Size  | Content                                                   |
+//! |--------|-------|-----------------------------------------------------------|
+//! | 0      | 4     | The magic number                                          |
+//! | 4      | 4     | The revision                                              |
+//! | 8      | 4     | The erases of the pair since it's used                    |
+//! | 12     | 4     | The size of the data                                      |
+//! | 16     | 4     | The CRC of the 3 fields before it and the data            |
+//! | 20     | n     | The data, which is the superblock or the table            |
+//!
+//! The pair of the metadata takes every commit, so after [`META_CYCLES`] erases it moves to two
+//! free blocks, and a snapshot of the superblock points to them. The old pair is used until the
+//! superblock is written, and the superblock is written only when the metadata moves. The
+//! superblock is the two blocks of the metadata:
+//!
+//! | Offset | Size  | Content                                                   |
+//! |--------|-------|-----------------------------------------------------------|
+//! | 0      | 4     | The first block                                           |
+//! | 4      | 4     | The second block                                          |
+//!
+//! The whole table is in one snapshot, so it's not larger than a block. It starts with the size
+//! and the count of the blocks, and then the entries:
+//!
+//! | Offset | Size  | Content                                                   |
+//! |--------|-------|-----------------------------------------------------------|
+//! | 0      | 2     | The ID, the root directory is `0` and has no entry        |
+//! | 2      | 2     | The ID of the parent directory                            |
+//! | 4      | 1     | The kind, `0` for a file and `1` for a directory          |
+//! | 5      | 1     | The size of the name                                      |
+//! | 6      | 4     | The size of the file                                      |
+//! | 10     | 4     | The last block of the file                                |
+//! | 14     | n     | The name                                                  |
+//!
+//! The data of a file is in a chain of blocks, each of them starts with the number of the block
+//! before it, which is padded to the program unit, and the data follows. A block is never
+//! programmed again before it's erased, so a write copies the block it starts in and all the
+//! blocks after it into new blocks, and the file switches to them by the next commit.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use sces::crc::crc32_update;
+
+use crate::fs::FsKind;
+
+/// `"SCFS"` in little endian.
+pub const FS_MAGIC: u32 = 0x5346_4353;
+pub const SNAPSHOT_HEADER: usize = 20;
+pub const SUPER_SIZE: usize = 8;
+pub const TABLE_HEADER: usize = 8;
+pub const ENTRY_HEADER: usize = 14;
+pub const NAME_MAX: usize = 32;
+
+/// The blocks of the superblock, which are the first ones.
+pub const SUPER_BLOCKS: u32 = 2;
+
+/// The erases of the pair of the metadata before it moves to other blocks.
+pub const META_CYCLES: u32 = 100;
+
+pub const BLOCK_NONE: u32 = u32::MAX;
+pub const ROOT_ID: u16 = 0;
+
+pub const fn align_up(size: u32, align: u32) -> u32
+{
+    size.div_ceil(align) * align
+}
+
+fn word(bytes: &[u8], offset: usize) -> u32
+{
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+pub struct SnapshotHeader
+{
+    pub revision: u32,
+    pub cycles: u32,
+    pub size: u32,
+    pub crc: u32,
+}
+
+impl SnapshotHeader
+{
+    pub fn new(revision: u32, cycles: u32, data: &[u8]) -> Self
+    {
+        let mut header = Self { revision, cycles, size: data.len() as u32, crc: 0 };
+        header.crc = header.calculate(data);
+        header
+    }
+
+    pub fn decode(bytes: &[u8; SNAPSHOT_HEADER]) -> Option<Self>
+    {
+        (word(bytes, 0) == FS_MAGIC).then(|| Self {
+            revision: word(bytes, 4),
+            cycles: word(bytes, 8),
+            size: word(bytes, 12),
+            crc: word(bytes, 16),
+        })
+    }
+
+    pub fn encode(&self) -> [u8; SNAPSHOT_HEADER]
+    {
+        let mut bytes = [0; SNAPSHOT_HEADER];
+        bytes[0..4].copy_from_slice(&FS_MAGIC.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.revision.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.cycles.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.size.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.crc.to_le_bytes());
+        bytes
+    }
+
+    pub fn check(&self, data: &[u8]) -> bool
+    {
+        self.crc == self.calculate(data)
+    }
+
+    fn calculate(&self, data: &[u8]) -> u32
+    {
+        let mut head = [0; 12];
+        head[0..4].copy_from_slice(&self.revision.to_le_bytes());
+        head[4..8].copy_from_slice(&self.cycles.to_le_bytes());
+        head[8..12].copy_from_slice(&self.size.to_le_bytes());
+        !crc32_update(crc32_update(0xFFFF_FFFF, &head), data)
+    }
+}
+
+pub fn encode_super(blocks: [u32; 2]) -> [u8; SUPER_SIZE]
+{
+    let mut bytes = [0; SUPER_SIZE];
+    bytes[0..4].copy_from_slice(&blocks[0].to_le_bytes());
+    bytes[4..8].copy_from_slice(&blocks[1].to_le_bytes());
+    bytes
+}
+
+/// Decode the superblock, it fails when the blocks of the metadata are not valid for
+/// `block_count`.
+pub fn decode_super(bytes: &[u8], block_count: u32) -> Option<[u32; 2]>
+{
+    let blocks = [word(bytes.get(..SUPER_SIZE)?, 0), word(bytes, 4)];
+    let valid = |x: u32| x >= SUPER_BLOCKS && x < block_count;
+
+    (bytes.len() == SUPER_SIZE && valid(blocks[0]) && valid(blocks[1]) && blocks[0] != blocks[1])
+        .then_some(blocks)
+}
+
+#[derive(Clone)]
+pub struct FsEntry
+{
+    pub id: u16,
+    pub parent: u16,
+    pub kind: FsKind,
+    pub name: String,
+    pub size: u32,
+    pub tail: u32,
+}
+
+/// Encode the table for the blocks of `block_size` and `block_count`.
+pub fn encode_table(entries: &[FsEntry], block_size: u32, block_count: u32) -> Option<Vec<u8>>
+{
+    let size = TABLE_HEADER + entries.iter().map(|x| ENTRY_HEADER + x.name.len()).sum::<usize>();
+
+    let mut bytes = Vec::new();
+    bytes.try_reserve_exact(size).ok()?;
+    bytes.extend_from_slice(&block_size.to_le_bytes());
+    bytes.extend_from_slice(&block_count.to_le_bytes());
+
+    for entry in entries
+    {
+        bytes.extend_from_slice(&entry.id.to_le_bytes());
+        bytes.extend_from_slice(&entry.parent.to_le_bytes());
+        bytes.push(matches!(entry.kind, FsKind::Dir) as u8);
+        bytes.push(entry.name.len() as u8);
+        bytes.extend_from_slice(&entry.size.to_le_bytes());
+        bytes.extend_from_slice(&entry.tail.to_le_bytes());
+        bytes.extend_from_slice(entry.name.as_bytes());
+    }
+
+    Some(bytes)
+}
+
+/// Decode the table, it fails when it's for other blocks or any entry is broken.
+pub fn decode_table(bytes: &[u8], block_size: u32, block_count: u32) -> Option<Vec<FsEntry>>
+{
+    if bytes.len() < TABLE_HEADER || word(bytes, 0) != block_size || word(bytes, 4) != block_count
+    {
+        return None;
+    }
+
+    let mut entries = Vec::new();
+    let mut rest = &bytes[TABLE_HEADER..];
+
+    while !rest.is_empty()
+    {
+        let head = rest.get(..ENTRY_HEADER)?;
+        let name_size = head[5] as usize;
+        let name = rest.get(ENTRY_HEADER..ENTRY_HEADER + name_size)?;
+
+        let kind = match head[4]
+        {
+            0 => FsKind::File,
+            1 => FsKind::Dir,
+            _ => return None,
+        };
+
+        if name_size == 0 || name_size > NAME_MAX
+        {
+            return None;
+        }
+
+        entries.try_reserve(1).ok()?;
+        entries.push(FsEntry {
+            id: u16::from_le_bytes([head[0], head[1]]),
+            parent: u16::from_le_bytes([head[2], head[3]]),
+            kind,
+            name: String::from(core::str::from_utf8(name).ok()?),
+            size: word(head, 6),
+            tail: word(head, 10),
+        });
+
+        rest = &rest[ENTRY_HEADER + name_size..];
+    }
+
+    Some(entries)
+}
+
+/// Encode the header of a data block, which points to the block before it.
+pub fn encode_link(prev: u32, header: u32) -> Vec<u8>
+{
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&prev.to_le_bytes());
+    bytes.resize(header as usize, 0xFF);
+    bytes
+}
@@ -0,0 +1,189 @@
+use alloc::vec::Vec;
+use log::warn;
+use sces::value::{ErrValue, RetValue};
+
+use crate::block::BlockDevice;
+use crate::native::layout::{align_up, SnapshotHeader, SNAPSHOT_HEADER};
+use crate::native::volume::FS_COPY_SIZE;
+use crate::svc::FSS;
+
+/// The last valid snapshot in a block.
+struct Snapshot
+{
+    header: SnapshotHeader,
+    data: Vec<u8>,
+}
+
+/// A pair of blocks which keeps the snapshots of the table or of the superblock.
+pub struct MetaPair
+{
+    pub blocks: [u32; 2],
+
+    /// The active block, and the end of the snapshots in it.
+    pub active: usize,
+    pub end: u32,
+
+    /// The revision of the last snapshot, and the erases of the blocks since the pair is used.
+    pub revision: u32,
+    pub cycles: u32,
+}
+
+impl MetaPair
+{
+    /// A pair of erased blocks, the first snapshot goes to the first one.
+    pub const fn new(blocks: [u32; 2], revision: u32) -> Self
+    {
+        Self { blocks, active: 0, end: 0, revision, cycles: 0 }
+    }
+
+    /// Load the last valid snapshot of the pair, it's `None` when there's no snapshot.
+    pub fn load(device: &dyn BlockDevice, blocks: [u32; 2]) -> RetValue<Option<(Self, Vec<u8>)>>
+    {
+        let (first, first_end) = scan(device, blocks[0])?;
+        let (second, second_end) = scan(device, blocks[1])?;
+
+        let (active, end, Snapshot { header, data }) = match (first, second)
+        {
+            (Some(x), Some(y)) if y.header.revision > x.header.revision => (1, second_end, y),
+            (Some(x), _) => (0, first_end, x),
+            (None, Some(y)) => (1, second_end, y),
+            (None, None) => return Ok(None),
+        };
+
+        let pair = Self { blocks, active, end, revision: header.revision, cycles: header.cycles };
+        Ok(Some((pair, data)))
+    }
+
+    /// Check whether a snapshot of `size` bytes is appended to the active block without an
+    /// erase.
+    pub fn fits(&self, device: &dyn BlockDevice, size: usize) -> bool
+    {
+        let size = align_up((SNAPSHOT_HEADER + size) as u32, device.prog_size());
+        self.end.saturating_add(size) <= device.block_size()
+    }
+
+    /// Write a snapshot of `data`, it fails with [`ErrValue::StackOverflow`] when the snapshot
+    /// is larger than a block.
+    pub fn commit(&mut self, device: &dyn BlockDevice, data: &[u8]) -> RetValue<()>
+    {
+        let revision = self.revision.wrapping_add(1);
+
+        if self.fits(device, data.len())
+        {
+            let bytes = encode_snapshot(device, revision, self.cycles, data)?;
+
+            match program(device, self.blocks[self.active], self.end, &bytes)
+            {
+                Ok(()) =>
+                {
+                    self.end += bytes.len() as u32;
+                    self.revision = revision;
+                    return Ok(());
+                }
+                Err(x) => warn!("{FSS} Failed to append the metadata: {x:?}."),
+            }
+        }
+
+        // The active block is full or has a broken snapshot, so the other one takes the
+        // snapshot, and the old snapshots are kept until it's written.
+        let cycles = self.cycles.saturating_add(1);
+        let bytes = encode_snapshot(device, revision, cycles, data)?;
+        let other = 1 - self.active;
+        self.end = device.block_size();
+
+        device.erase(self.blocks[other])?;
+        program(device, self.blocks[other], 0, &bytes)?;
+
+        self.active = other;
+        self.end = bytes.len() as u32;
+        self.revision = revision;
+        self.cycles = cycles;
+        Ok(())
+    }
+}
+
+/// Find the last valid snapshot in a block, and where the next one goes.
+fn scan(device: &dyn BlockDevice, block: u32) -> RetValue<(Option<Snapshot>, u32)>
+{
+    let block_size = device.block_size();
+    let mut offset = 0;
+    let mut last = None;
+
+    while offset + SNAPSHOT_HEADER as u32 <= block_size
+    {
+        let mut bytes = [0; SNAPSHOT_HEADER];
+        device.read(block, offset, &mut bytes)?;
+
+        if bytes.iter().all(|x| *x == 0xFF)
+        {
+            return Ok((last, offset));
+        }
+
+        let Some(header) = SnapshotHeader::decode(&bytes)
+        else
+        {
+            break;
+        };
+
+        if header.size > block_size - offset - SNAPSHOT_HEADER as u32
+        {
+            break;
+        }
+
+        let mut data = Vec::new();
+        data.try_reserve_exact(header.size as usize).or(Err(ErrValue::MemAllocFailure))?;
+        data.resize(header.size as usize, 0);
+        device.read(block, offset + SNAPSHOT_HEADER as u32, &mut data)?;
+
+        if !header.check(&data)
+        {
+            break;
+        }
+
+        offset += align_up(SNAPSHOT_HEADER as u32 + header.size, device.prog_size());
+        last = Some(Snapshot { header, data });
+    }
+
+    // The rest of the block after a broken snapshot is not used.
+    Ok((last, block_size))
+}
+
+fn encode_snapshot(
+    device: &dyn BlockDevice, revision: u32, cycles: u32, data: &[u8],
+) -> RetValue<Vec<u8>>
+{
+    let header = SnapshotHeader::new(revision, cycles, data);
+    let size = align_up((SNAPSHOT_HEADER + data.len()) as u32, device.prog_size());
+
+    if size > device.block_size()
+    {
+        return Err(ErrValue::StackOverflow);
+    }
+
+    let mut bytes = Vec::new();
+    bytes.try_reserve_exact(size as usize).or(Err(ErrValue::MemAllocFailure))?;
+    bytes.extend_from_slice(&header.encode());
+    bytes.extend_from_slice(data);
+    bytes.resize(size as usize, 0xFF);
+    Ok(bytes)
+}
+
+fn program(device: &dyn BlockDevice, block: u32, offset: u32, bytes: &[u8]) -> RetValue<()>
+{
+    device.prog(block, offset, bytes)?;
+
+    let mut buffer = [0; FS_COPY_SIZE];
+
+    for (index, chunk) in bytes.chunks(FS_COPY_SIZE).enumerate()
+    {
+        let read = &mut buffer[..chunk.len()];
+        device.read(block, offset + (index * FS_COPY_SIZE) as u32, read)?;
+
+        if read != chunk
+        {
+            return Err(ErrValue::LowLevelFailure);
+        }
+    }
+
+    Ok(())
+}
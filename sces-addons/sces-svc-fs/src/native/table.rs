@@ -0,0 +1,103 @@
+use alloc::vec::Vec;
+use sces::value::{ErrValue, RetValue};
+
+use crate::fs::{FsKind, FsStat};
+use crate::native::layout::{FsEntry, NAME_MAX, ROOT_ID};
+
+/// The files and the directories, which are found by their paths.
+#[derive(Clone)]
+pub struct FsTable
+{
+    pub entries: Vec<FsEntry>,
+}
+
+impl FsTable
+{
+    /// Find the entry of `path`, `None` is the root directory.
+    pub fn resolve(&self, path: &str) -> RetValue<Option<usize>>
+    {
+        let mut found = None;
+
+        for name in path.split('/').filter(|x| !x.is_empty() && *x != ".")
+        {
+            let parent = self.dir_id(found)?;
+            found = Some(self.find(parent, name).ok_or(ErrValue::InstanceNotFound)?);
+        }
+
+        Ok(found)
+    }
+
+    /// Split `path` into the ID of the directory which has it and its name.
+    pub fn split<'p>(&self, path: &'p str) -> RetValue<(u16, &'p str)>
+    {
+        let path = path.trim_end_matches('/');
+        let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+
+        if name.is_empty() || name == "." || name == ".." || name.len() > NAME_MAX
+        {
+            return Err(ErrValue::Param);
+        }
+
+        Ok((self.dir_id(self.resolve(dir)?)?, name))
+    }
+
+    pub fn find(&self, parent: u16, name: &str) -> Option<usize>
+    {
+        self.entries.iter().position(|x| x.parent == parent && x.name == name)
+    }
+
+    pub fn index(&self, id: u16) -> Option<usize>
+    {
+        self.entries.iter().position(|x| x.id == id)
+    }
+
+    /// Get the ID of a directory, it fails with [`ErrValue::InstanceInvalid`] when it's a file.
+    pub fn dir_id(&self, found: Option<usize>) -> RetValue<u16>
+    {
+        match found.map(|x| &self.entries[x])
+        {
+            None => Ok(ROOT_ID),
+            Some(entry) if entry.kind == FsKind::Dir => Ok(entry.id),
+            Some(_) => Err(ErrValue::InstanceInvalid),
+        }
+    }
+
+    pub fn stat(&self, found: Option<usize>) -> FsStat
+    {
+        match found.map(|x| &self.entries[x])
+        {
+            None => FsStat { kind: FsKind::Dir, size: 0 },
+            Some(entry) => FsStat { kind: entry.kind, size: entry.size },
+        }
+    }
+
+    pub fn has_children(&self, id: u16) -> bool
+    {
+        self.entries.iter().any(|x| x.parent == id)
+    }
+
+    /// Check whether `id` is `ancestor` or in it.
+    pub fn is_within(&self, id: u16, ancestor: u16) -> bool
+    {
+        let mut id = id;
+
+        loop
+        {
+            if id == ancestor
+            {
+                return true;
+            }
+
+            match self.index(id)
+            {
+                Some(x) => id = self.entries[x].parent,
+                None => return false,
+            }
+        }
+    }
+
+    pub fn new_id(&self) -> RetValue<u16>
+    {
+        (1..=u16::MAX).find(|x| self.index(*x).is_none()).ok_or(ErrValue::StackOverflow)
+    }
+}
@@ -0,0 +1,1184 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use sces::value::{ErrValue, RetValue};
+
+use crate::block::BlockDevice;
+use crate::fs::{FsHandle, FsKind, FsOpen, FsSeek, FsStat, FsUsage};
+use crate::native::blocks::BlockMap;
+use crate::native::file::{FsOpenFile, FsWriter};
+use crate::native::layout::*;
+use crate::native::meta::MetaPair;
+use crate::native::table::FsTable;
+
+/// The smallest block which is supported.
+const FS_BLOCK_MIN: u32 = 128;
+
+/// The size of the buffer of the new data in a block, it's programmed when it's full.
+const FS_CACHE_SIZE: u32 = 256;
+
+/// The size of the buffer on the stack, which holds the data copied or read back.
+pub const FS_COPY_SIZE: usize = 64;
+
+/// The filesystem on a [`BlockDevice`], which is not shared.
+pub struct FsVolume
+{
+    device: &'static dyn BlockDevice,
+    table: FsTable,
+    blocks: BlockMap,
+    files: Vec<Option<FsOpenFile>>,
+
+    /// The superblock, and the pair of blocks of the table which it points to.
+    anchor: MetaPair,
+    meta: MetaPair,
+
+    block_size: u32,
+    prog_size: u32,
+    header: u32,
+    cache_size: usize,
+}
+
+impl FsVolume
+{
+    /// Load the filesystem, it fails with [`ErrValue::InstanceNotFound`] when there's no
+    /// filesystem on the device, and with [`ErrValue::InstanceInvalid`] when it's broken.
+    pub fn mount(device: &'static dyn BlockDevice) -> RetValue<Self>
+    {
+        let mut volume = Self::new(device)?;
+
+        let (anchor, record) =
+            MetaPair::load(device, volume.anchor.blocks)?.ok_or(ErrValue::InstanceNotFound)?;
+        let blocks =
+            decode_super(&record, device.block_count()).ok_or(ErrValue::InstanceInvalid)?;
+        let (meta, table) = MetaPair::load(device, blocks)?.ok_or(ErrValue::InstanceInvalid)?;
+
+        volume.table.entries = decode_table(&table, volume.block_size, device.block_count())
+            .ok_or(ErrValue::InstanceInvalid)?;
+        volume.anchor = anchor;
+        volume.meta = meta;
+
+        for block in blocks
+        {
+            volume.blocks.mark(block);
+        }
+
+        for index in 0..volume.table.entries.len()
+        {
+            let entry = &volume.table.entries[index];
+
+            for block in volume.chain(entry.tail, entry.size)?
+            {
+                if !volume.blocks.mark(block)
+                {
+                    return Err(ErrValue::InstanceInvalid);
+                }
+            }
+        }
+
+        volume.blocks.seed(volume.meta.revision);
+        Ok(volume)
+    }
+
+    /// Make an empty filesystem on the device, all the data on it is lost.
+    pub fn format(device: &'static dyn BlockDevice) -> RetValue<Self>
+    {
+        let mut volume = Self::new(device)?;
+        let blocks = [volume.blocks.take()?, volume.blocks.take()?];
+
+        // The superblock is written at last, so the device is not formatted until it's done.
+        for block in volume.anchor.blocks.into_iter().chain(blocks)
+        {
+            device.erase(block)?;
+        }
+
+        volume.meta = MetaPair::new(blocks, 0);
+        volume.commit()?;
+        volume.anchor.commit(device, &encode_super(blocks))?;
+
+        Ok(volume)
+    }
+
+    pub fn open(&mut self, path: &str, options: FsOpen) -> RetValue<FsHandle>
+    {
+        if !options.write && (options.truncate || !options.read)
+        {
+            return Err(ErrValue::Param);
+        }
+
+        let index = match self.table.resolve(path)
+        {
+            Ok(Some(x)) => x,
+            Ok(None) => return Err(ErrValue::InstanceInvalid),
+            Err(ErrValue::InstanceNotFound) if options.create && options.write =>
+            {
+                self.create(path, FsKind::File)?
+            }
+            Err(x) => return Err(x),
+        };
+
+        let entry = &self.table.entries[index];
+        let id = entry.id;
+
+        if entry.kind != FsKind::File
+        {
+            return Err(ErrValue::InstanceInvalid);
+        }
+
+        if self.is_open(id)
+        {
+            return Err(ErrValue::InstanceInUse);
+        }
+
+        let mut size = entry.size;
+        let mut chain = self.chain(entry.tail, entry.size)?;
+
+        if options.truncate && size > 0
+        {
+            self.blocks.defer(&chain)?;
+            self.update(|x| {
+                x.entries[index].size = 0;
+                x.entries[index].tail = BLOCK_NONE;
+                Ok(())
+            })?;
+
+            size = 0;
+            chain.clear();
+        }
+
+        let file = FsOpenFile { id, options, pos: 0, size, chain, writer: None };
+
+        let slot = match self.files.iter().position(Option::is_none)
+        {
+            Some(x) => x,
+            None =>
+            {
+                self.files.try_reserve(1).or(Err(ErrValue::MemAllocFailure))?;
+                self.files.push(None);
+                self.files.len() - 1
+            }
+        };
+
+        self.files[slot] = Some(file);
+        Ok(FsHandle(slot as u16))
+    }
+
+    pub fn close(&mut self, handle: FsHandle) -> RetValue<()>
+    {
+        let result = self.with_file(handle, |x, file| x.sync_file(file));
+
+        if let Some(x) = self.files.get_mut(handle.0 as usize)
+        {
+            *x = None;
+        }
+
+        result
+    }
+
+    pub fn read(&mut self, handle: FsHandle, data: &mut [u8]) -> RetValue<usize>
+    {
+        self.with_file(handle, |x, file| {
+            if !file.options.read
+            {
+                return Err(ErrValue::Permission);
+            }
+
+            x.sync_file(file)?;
+
+            let size = x.read_at(&file.chain, file.size, file.pos, data)?;
+            file.pos += size as u32;
+            Ok(size)
+        })
+    }
+
+    pub fn write(&mut self, handle: FsHandle, data: &[u8]) -> RetValue<usize>
+    {
+        self.with_file(handle, |x, file| {
+            if !file.options.write
+            {
+                return Err(ErrValue::Permission);
+            }
+
+            if data.is_empty()
+            {
+                return Ok(0);
+            }
+
+            if file.pos.checked_add(data.len() as u32).is_none()
+            {
+                return Err(ErrValue::Param);
+            }
+
+            let mut writer = match file.writer.take()
+            {
+                Some(writer) => writer,
+                None =>
+                {
+                    if file.options.append
+                    {
+                        file.pos = file.size;
+                    }
+
+                    x.begin(file)?
+                }
+            };
+
+            // The data written since the last sync is dropped when a write fails.
+            if let Err(err) = x.push(&mut writer, data)
+            {
+                x.abort(writer);
+                file.pos = file.pos.min(file.size);
+                return Err(err);
+            }
+
+            file.pos = writer.pos;
+            file.writer = Some(writer);
+            Ok(data.len())
+        })
+    }
+
+    pub fn seek(&mut self, handle: FsHandle, pos: FsSeek) -> RetValue<u32>
+    {
+        self.with_file(handle, |x, file| {
+            if pos == FsSeek::Current(0)
+            {
+                return Ok(file.pos);
+            }
+
+            x.sync_file(file)?;
+
+            let pos = match pos
+            {
+                FsSeek::Start(pos) => pos as i64,
+                FsSeek::End(offset) => file.size as i64 + offset as i64,
+                FsSeek::Current(offset) => file.pos as i64 + offset as i64,
+            };
+
+            if pos < 0 || pos > file.size as i64
+            {
+                return Err(ErrValue::Param);
+            }
+
+            file.pos = pos as u32;
+            Ok(file.pos)
+        })
+    }
+
+    pub fn sync(&mut self, handle: FsHandle) -> RetValue<()>
+    {
+        self.with_file(handle, |x, file| x.sync_file(file))
+    }
+
+    pub fn mkdir(&mut self, path: &str) -> RetValue<()>
+    {
+        self.create(path, FsKind::Dir).map(|_| ())
+    }
+
+    pub fn remove(&mut self, path: &str) -> RetValue<()>
+    {
+        let index = self.table.resolve(path)?.ok_or(ErrValue::Permission)?;
+        let entry = &self.table.entries[index];
+
+        if self.is_open(entry.id) || self.table.has_children(entry.id)
+        {
+            return Err(ErrValue::InstanceInUse);
+        }
+
+        let chain = self.chain(entry.tail, entry.size)?;
+        self.blocks.defer(&chain)?;
+
+        self.update(|x| {
+            x.entries.remove(index);
+            Ok(())
+        })
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) -> RetValue<()>
+    {
+        let index = self.table.resolve(from)?.ok_or(ErrValue::Permission)?;
+        let (parent, name) = self.table.split(to)?;
+
+        match self.table.find(parent, name)
+        {
+            Some(x) if x == index => return Ok(()),
+            Some(_) => return Err(ErrValue::InstanceDuplicate),
+            None => (),
+        }
+
+        // A directory can't be moved into itself.
+        let entry = &self.table.entries[index];
+        if entry.kind == FsKind::Dir && self.table.is_within(parent, entry.id)
+        {
+            return Err(ErrValue::Param);
+        }
+
+        let name = String::from(name);
+
+        self.update(|x| {
+            x.entries[index].parent = parent;
+            x.entries[index].name = name;
+            Ok(())
+        })
+    }
+
+    pub fn stat(&self, path: &str) -> RetValue<FsStat>
+    {
+        Ok(self.table.stat(self.table.resolve(path)?))
+    }
+
+    pub fn read_dir(&self, path: &str, f: &mut dyn FnMut(&str, FsStat)) -> RetValue<()>
+    {
+        let id = self.table.dir_id(self.table.resolve(path)?)?;
+
+        for entry in self.table.entries.iter().filter(|x| x.parent == id)
+        {
+            f(&entry.name, FsStat { kind: entry.kind, size: entry.size });
+        }
+
+        Ok(())
+    }
+
+    pub fn usage(&self) -> FsUsage
+    {
+        FsUsage {
+            block_size: self.block_size,
+            block_count: self.device.block_count(),
+            used: self.blocks.used(),
+        }
+    }
+
+    fn new(device: &'static dyn BlockDevice) -> RetValue<Self>
+    {
+        let block_size = device.block_size();
+        let prog_size = device.prog_size();
+
+        if prog_size == 0
+            || block_size < FS_BLOCK_MIN
+            || !block_size.is_multiple_of(prog_size)
+            || device.block_count() < SUPER_BLOCKS + 4
+        {
+            return Err(ErrValue::Param);
+        }
+
+        let mut blocks = BlockMap::new(device.block_count())?;
+        for block in 0..SUPER_BLOCKS
+        {
+            blocks.mark(block);
+        }
+
+        let header = align_up(4, prog_size);
+        if header * 2 > block_size
+        {
+            return Err(ErrValue::Param);
+        }
+
+        Ok(Self {
+            device,
+            table: FsTable { entries: Vec::new() },
+            blocks,
+            files: Vec::new(),
+            anchor: MetaPair::new([0, 1], 0),
+            meta: MetaPair::new([BLOCK_NONE; 2], 0),
+            block_size,
+            prog_size,
+            header,
+            cache_size: align_up(FS_CACHE_SIZE, prog_size).min(block_size) as usize,
+        })
+    }
+
+    /// Write a snapshot of the table, it fails with [`ErrValue::StackOverflow`] when the table
+    /// is larger than a block.
+    fn commit(&mut self) -> RetValue<()>
+    {
+        let table = encode_table(&self.table.entries, self.block_size, self.device.block_count())
+            .ok_or(ErrValue::MemAllocFailure)?;
+
+        if self.meta.cycles < META_CYCLES || self.meta.fits(self.device, table.len())
+        {
+            return self.meta.commit(self.device, &table);
+        }
+
+        self.relocate(&table)
+    }
+
+    /// Move the metadata to two free blocks with the snapshot of `table`, so its blocks are not
+    /// worn out before the others. It stays in its blocks when there are no free blocks.
+    fn relocate(&mut self, table: &[u8]) -> RetValue<()>
+    {
+        let device = self.device;
+
+        let Ok(first) = self.blocks.take()
+        else
+        {
+            return self.meta.commit(device, table);
+        };
+
+        let Ok(second) = self.blocks.take()
+        else
+        {
+            self.blocks.release(first);
+            return self.meta.commit(device, table);
+        };
+
+        let mut meta = MetaPair::new([first, second], self.meta.revision);
+        let result = device
+            .erase(first)
+            .and_then(|_| device.erase(second))
+            .and_then(|_| meta.commit(device, table))
+            .and_then(|_| self.anchor.commit(device, &encode_super(meta.blocks)));
+
+        // The blocks of the old pair are free after the superblock points to the new one.
+        let unused = match result
+        {
+            Ok(()) => core::mem::replace(&mut self.meta, meta).blocks,
+            Err(_) => meta.blocks,
+        };
+
+        unused.into_iter().for_each(|x| self.blocks.release(x));
+        result
+    }
+
+    /// Change the table and commit it, the table is restored when the commit fails.
+    fn update<R>(&mut self, f: impl FnOnce(&mut FsTable) -> RetValue<R>) -> RetValue<R>
+    {
+        let backup = self.table.clone();
+        let result = f(&mut self.table).and_then(|x| self.commit().map(|_| x));
+
+        if result.is_err()
+        {
+            self.table = backup;
+        }
+
+        self.blocks.settle(result.is_ok());
+        result
+    }
+
+    fn create(&mut self, path: &str, kind: FsKind) -> RetValue<usize>
+    {
+        let (parent, name) = self.table.split(path)?;
+
+        if self.table.find(parent, name).is_some()
+        {
+            return Err(ErrValue::InstanceDuplicate);
+        }
+
+        let entry = FsEntry {
+            id: self.table.new_id()?,
+            parent,
+            kind,
+            name: String::from(name),
+            size: 0,
+            tail: BLOCK_NONE,
+        };
+
+        self.update(|x| {
+            x.entries.try_reserve(1).or(Err(ErrValue::MemAllocFailure))?;
+            x.entries.push(entry);
+            Ok(x.entries.len() - 1)
+        })
+    }
+
+    fn is_open(&self, id: u16) -> bool
+    {
+        self.files.iter().flatten().any(|x| x.id == id)
+    }
+
+    fn with_file<R>(
+        &mut self, handle: FsHandle, f: impl FnOnce(&mut Self, &mut FsOpenFile) -> RetValue<R>,
+    ) -> RetValue<R>
+    {
+        let slot = handle.0 as usize;
+        let mut file =
+            self.files.get_mut(slot).and_then(Option::take).ok_or(ErrValue::InstanceNotFound)?;
+
+        let result = f(self, &mut file);
+        self.files[slot] = Some(file);
+        result
+    }
+
+    /// The size of the data in a block.
+    fn block_data(&self) -> u32
+    {
+        self.block_size - self.header
+    }
+
+    /// Get the blocks of a file from the first one by following the links from the last one.
+    fn chain(&self, tail: u32, size: u32) -> RetValue<Vec<u32>>
+    {
+        let count = size.div_ceil(self.block_data()) as usize;
+
+        let mut chain = Vec::new();
+        chain.try_reserve_exact(count).or(Err(ErrValue::MemAllocFailure))?;
+
+        let mut block = tail;
+
+        for index in 0..count
+        {
+            if block < SUPER_BLOCKS || block >= self.device.block_count()
+            {
+                return Err(ErrValue::InstanceInvalid);
+            }
+
+            chain.push(block);
+
+            if index + 1 < count
+            {
+                let mut link = [0; 4];
+                self.device.read(block, 0, &mut link)?;
+                block = u32::from_le_bytes(link);
+            }
+        }
+
+        chain.reverse();
+        Ok(chain)
+    }
+
+    fn read_at(&self, chain: &[u32], size: u32, pos: u32, data: &mut [u8]) -> RetValue<usize>
+    {
+        let block_data = self.block_data();
+        let end = size.min(pos.saturating_add(data.len() as u32));
+        let mut at = pos;
+
+        while at < end
+        {
+            let offset = at % block_data;
+            let count = (block_data - offset).min(end - at);
+            let done = (at - pos) as usize;
+
+            let block = chain[(at / block_data) as usize];
+            self.device.read(
+                block,
+                self.header + offset,
+                &mut data[done..done + count as usize],
+            )?;
+
+            at += count;
+        }
+
+        Ok(end.saturating_sub(pos) as usize)
+    }
+
+    /// Start to write at the position of the file.
+    fn begin(&mut self, file: &FsOpenFile) -> RetValue<FsWriter>
+    {
+        let block_data = self.block_data();
+        let index = (file.pos / block_data) as usize;
+        let prefix = file.pos % block_data;
+
+        let mut writer = FsWriter {
+            index,
+            prev: index.checked_sub(1).map_or(BLOCK_NONE, |x| file.chain[x]),
+            blocks: Vec::new(),
+            pos: file.pos - prefix,
+            fill: 0,
+            cache: Vec::new(),
+            cached: 0,
+        };
+
+        if let Err(err) = self.copy(&mut writer, file, prefix)
+        {
+            self.abort(writer);
+            return Err(err);
+        }
+
+        Ok(writer)
+    }
+
+    /// Copy the committed data of the file from the position of the writer.
+    fn copy(&mut self, writer: &mut FsWriter, file: &FsOpenFile, size: u32) -> RetValue<()>
+    {
+        let mut buffer = [0; FS_COPY_SIZE];
+        let end = writer.pos + size;
+
+        while writer.pos < end
+        {
+            let read = &mut buffer[..FS_COPY_SIZE.min((end - writer.pos) as usize)];
+            self.read_at(&file.chain, file.size, writer.pos, read)?;
+            self.push(writer, read)?;
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, writer: &mut FsWriter, data: &[u8]) -> RetValue<()>
+    {
+        let mut data = data;
+
+        loop
+        {
+            if writer.cache.len() >= self.cache_size
+            {
+                self.program_cache(writer)?;
+            }
+
+            if data.is_empty()
+            {
+                return Ok(());
+            }
+
+            if writer.blocks.is_empty() || writer.fill == self.block_data()
+            {
+                self.next_block(writer)?;
+            }
+
+            let count = ((self.block_data() - writer.fill) as usize)
+                .min(self.cache_size - writer.cache.len())
+                .min(data.len());
+
+            writer.cache.extend_from_slice(&data[..count]);
+            writer.fill += count as u32;
+            writer.pos += count as u32;
+            data = &data[count..];
+        }
+    }
+
+    /// Take a new block, which starts with the link to the last one.
+    fn next_block(&mut self, writer: &mut FsWriter) -> RetValue<()>
+    {
+        self.flush_cache(writer)?;
+
+        writer.blocks.try_reserve(1).or(Err(ErrValue::MemAllocFailure))?;
+        if writer.cache.capacity() < self.cache_size
+        {
+            writer.cache.try_reserve_exact(self.cache_size).or(Err(ErrValue::MemAllocFailure))?;
+        }
+
+        let block = self.blocks.take()?;
+        if let Err(err) = self.device.erase(block)
+        {
+            self.blocks.release(block);
+            return Err(err);
+        }
+
+        let prev = writer.blocks.last().copied().unwrap_or(writer.prev);
+        writer.blocks.push(block);
+
+        writer.cache.extend_from_slice(&encode_link(prev, self.header));
+        writer.cached = 0;
+        writer.fill = 0;
+        Ok(())
+    }
+
+    fn program_cache(&mut self, writer: &mut FsWriter) -> RetValue<()>
+    {
+        let block = *writer.blocks.last().ok_or(ErrValue::Unknown)?;
+
+        self.device.prog(block, writer.cached, &writer.cache)?;
+        writer.cached += writer.cache.len() as u32;
+        writer.cache.clear();
+        Ok(())
+    }
+
+    /// Program the rest of the last block, it's padded to the program unit, so nothing is
+    /// written into it again.
+    fn flush_cache(&mut self, writer: &mut FsWriter) -> RetValue<()>
+    {
+        if writer.cache.is_empty()
+        {
+            return Ok(());
+        }
+
+        let size = align_up(writer.cache.len() as u32, self.prog_size) as usize;
+        writer.cache.resize(size, 0xFF);
+        self.program_cache(writer)
+    }
+
+    fn abort(&mut self, writer: FsWriter)
+    {
+        writer.blocks.into_iter().for_each(|x| self.blocks.release(x));
+    }
+
+    /// Finish the writer of the file, and commit the new blocks of it.
+    fn sync_file(&mut self, file: &mut FsOpenFile) -> RetValue<()>
+    {
+        let Some(mut writer) = file.writer.take()
+        else
+        {
+            return Ok(());
+        };
+
+        match self.finish(file, &mut writer)
+        {
+            Ok(()) => Ok(()),
+            Err(err) =>
+            {
+                self.abort(writer);
+                file.pos = file.pos.min(file.size);
+                Err(err)
+            }
+        }
+    }
+
+    fn finish(&mut self, file: &mut FsOpenFile, writer: &mut FsWriter) -> RetValue<()>
+    {
+        if writer.pos < file.size
+        {
+            self.copy(writer, file, file.size - writer.pos)?;
+        }
+
+        self.flush_cache(writer)?;
+
+        let mut chain = Vec::new();
+        chain
+            .try_reserve_exact(writer.index + writer.blocks.len())
+            .or(Err(ErrValue::MemAllocFailure))?;
+        chain.extend_from_slice(&file.chain[..writer.index]);
+        chain.extend_from_slice(&writer.blocks);
+
+        let size = writer.pos;
+        let tail = chain.last().copied().unwrap_or(BLOCK_NONE);
+        let id = file.id;
+
+        self.blocks.defer(&file.chain[writer.index..])?;
+        self.update(|x| {
+            let index = x.index(id).ok_or(ErrValue::InstanceNotFound)?;
+            x.entries[index].size = size;
+            x.entries[index].tail = tail;
+            Ok(())
+        })?;
+
+        file.chain = chain;
+        file.size = size;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use std::boxed::Box;
+    use std::sync::Mutex;
+    use std::vec;
+
+    use super::*;
+
+    const BLOCK_SIZE: u32 = 256;
+    const BLOCK_COUNT: u32 = 16;
+
+    struct RamState
+    {
+        data: Vec<u8>,
+
+        /// The program units which are programmed since their block was erased.
+        programmed: Vec<bool>,
+        erases: Vec<u32>,
+
+        /// The programs to finish before the power is cut, and the units of the next one to keep.
+        prog_cut: Option<(u32, usize)>,
+
+        /// The erases to finish before the power is cut, and whether the next one is finished.
+        erase_cut: Option<(u32, bool)>,
+        off: bool,
+    }
+
+    /// A block device in RAM, whose power can be cut in the middle of a program or around an
+    /// erase. A unit is programmed only once after its block is erased.
+    struct RamBlocks
+    {
+        prog_size: u32,
+        state: Mutex<RamState>,
+    }
+
+    impl RamBlocks
+    {
+        fn new(prog_size: u32) -> &'static Self
+        {
+            let size = (BLOCK_SIZE * BLOCK_COUNT) as usize;
+
+            Box::leak(Box::new(Self {
+                prog_size,
+                state: Mutex::new(RamState {
+                    data: vec![0xFF; size],
+                    programmed: vec![false; size / prog_size as usize],
+                    erases: vec![0; BLOCK_COUNT as usize],
+                    prog_cut: None,
+                    erase_cut: None,
+                    off: false,
+                }),
+            }))
+        }
+
+        fn cut_prog(&self, progs: u32, keep: usize)
+        {
+            self.state.lock().unwrap().prog_cut = Some((progs, keep));
+        }
+
+        fn cut_erase(&self, erases: u32, finish: bool)
+        {
+            self.state.lock().unwrap().erase_cut = Some((erases, finish));
+        }
+
+        /// Turn the power on again, and tell whether it was cut.
+        fn power_on(&self) -> bool
+        {
+            let mut state = self.state.lock().unwrap();
+            state.prog_cut = None;
+            state.erase_cut = None;
+            core::mem::take(&mut state.off)
+        }
+
+        fn offset(&self, block: u32, offset: u32, size: usize) -> RetValue<usize>
+        {
+            match block < BLOCK_COUNT && offset as usize + size <= BLOCK_SIZE as usize
+            {
+                true => Ok((block * BLOCK_SIZE + offset) as usize),
+                false => Err(ErrValue::Param),
+            }
+        }
+    }
+
+    impl BlockDevice for RamBlocks
+    {
+        fn block_size(&self) -> u32
+        {
+            BLOCK_SIZE
+        }
+
+        fn block_count(&self) -> u32
+        {
+            BLOCK_COUNT
+        }
+
+        fn prog_size(&self) -> u32
+        {
+            self.prog_size
+        }
+
+        fn read(&self, block: u32, offset: u32, data: &mut [u8]) -> RetValue<()>
+        {
+            let at = self.offset(block, offset, data.len())?;
+            data.copy_from_slice(&self.state.lock().unwrap().data[at..at + data.len()]);
+            Ok(())
+        }
+
+        fn prog(&self, block: u32, offset: u32, data: &[u8]) -> RetValue<()>
+        {
+            let at = self.offset(block, offset, data.len())?;
+            let unit = self.prog_size as usize;
+
+            if !at.is_multiple_of(unit) || !data.len().is_multiple_of(unit)
+            {
+                return Err(ErrValue::Param);
+            }
+
+            let mut state = self.state.lock().unwrap();
+            if state.off
+            {
+                return Err(ErrValue::LowLevelFailure);
+            }
+
+            let keep = match state.prog_cut
+            {
+                Some((0, keep)) =>
+                {
+                    state.off = true;
+                    (keep * unit).min(data.len())
+                }
+                Some((progs, keep)) =>
+                {
+                    state.prog_cut = Some((progs - 1, keep));
+                    data.len()
+                }
+                None => data.len(),
+            };
+
+            let units = at / unit..(at + keep) / unit;
+            if state.programmed[units.clone()].iter().any(|x| *x)
+            {
+                return Err(ErrValue::LowLevelFailure);
+            }
+
+            state.programmed[units].fill(true);
+            state.data[at..at + keep].copy_from_slice(&data[..keep]);
+
+            match keep == data.len()
+            {
+                true => Ok(()),
+                false => Err(ErrValue::LowLevelFailure),
+            }
+        }
+
+        fn erase(&self, block: u32) -> RetValue<()>
+        {
+            let at = self.offset(block, 0, BLOCK_SIZE as usize)?;
+            let unit = self.prog_size as usize;
+
+            let mut state = self.state.lock().unwrap();
+            if state.off
+            {
+                return Err(ErrValue::LowLevelFailure);
+            }
+
+            match state.erase_cut
+            {
+                Some((0, false)) =>
+                {
+                    state.off = true;
+                    return Err(ErrValue::LowLevelFailure);
+                }
+                Some((0, true)) => state.off = true,
+                Some((erases, finish)) => state.erase_cut = Some((erases - 1, finish)),
+                None => (),
+            }
+
+            state.erases[block as usize] += 1;
+            state.data[at..at + BLOCK_SIZE as usize].fill(0xFF);
+            state.programmed[at / unit..(at + BLOCK_SIZE as usize) / unit].fill(false);
+            Ok(())
+        }
+    }
+
+    fn pattern(seed: u8, size: usize) -> Vec<u8>
+    {
+        (0..size).map(|x| (x as u8).wrapping_mul(31).wrapping_add(seed)).collect()
+    }
+
+    fn write_at(
+        volume: &mut FsVolume, path: &str, options: FsOpen, pos: u32, data: &[u8],
+    ) -> RetValue<()>
+    {
+        let file = volume.open(path, options)?;
+
+        let result = volume
+            .seek(file, FsSeek::Start(pos))
+            .and_then(|_| volume.write(file, data))
+            .and_then(|_| volume.sync(file));
+
+        volume.close(file).and(result)
+    }
+
+    fn read_all(volume: &mut FsVolume, path: &str) -> Vec<u8>
+    {
+        let file = volume.open(path, FsOpen::read()).unwrap();
+        let mut data = vec![0; volume.stat(path).unwrap().size as usize];
+
+        assert_eq!(volume.read(file, &mut data).unwrap(), data.len());
+        volume.close(file).unwrap();
+        data
+    }
+
+    /// Get all the files with their data, and check that the blocks in use are just the ones of
+    /// the files, the superblock and the metadata.
+    fn files(volume: &mut FsVolume) -> Vec<(String, Vec<u8>)>
+    {
+        let mut paths = Vec::new();
+
+        for dir in ["", "dir"]
+        {
+            volume
+                .read_dir(dir, &mut |name, stat| {
+                    if stat.kind == FsKind::File
+                    {
+                        paths.push(std::format!("{dir}/{name}"));
+                    }
+                })
+                .unwrap();
+        }
+
+        let files: Vec<_> = paths.into_iter().map(|x| (x.clone(), read_all(volume, &x))).collect();
+        let blocks: u32 =
+            files.iter().map(|(_, x)| (x.len() as u32).div_ceil(volume.block_data())).sum();
+
+        assert_eq!(volume.usage().used, SUPER_BLOCKS + 2 + blocks);
+        files
+    }
+
+    fn setup(prog_size: u32) -> (&'static RamBlocks, FsVolume)
+    {
+        let device = RamBlocks::new(prog_size);
+        let mut volume = FsVolume::format(device).unwrap();
+
+        volume.mkdir("dir").unwrap();
+        write_at(&mut volume, "dir/log", FsOpen::write(), 0, &pattern(1, 600)).unwrap();
+        write_at(&mut volume, "a", FsOpen::write(), 0, &pattern(2, 10)).unwrap();
+        (device, volume)
+    }
+
+    /// Cut the power at every program and erase of `op`, and check that the mount finds either
+    /// all the files before it or all the files after it.
+    fn sweep(op: fn(&mut FsVolume) -> RetValue<()>)
+    {
+        for prog_size in [1, 16]
+        {
+            let (device, mut volume) = setup(prog_size);
+            let before = files(&mut volume);
+            op(&mut volume).unwrap();
+            let after = files(&mut FsVolume::mount(device).unwrap());
+            assert!(before != after);
+
+            let cuts = [
+                |x: &RamBlocks, n| x.cut_prog(n, 0),
+                |x: &RamBlocks, n| x.cut_prog(n, 1),
+                |x: &RamBlocks, n| x.cut_erase(n, false),
+                |x: &RamBlocks, n| x.cut_erase(n, true),
+            ];
+
+            for cut in cuts
+            {
+                for n in 0..
+                {
+                    let (device, mut volume) = setup(prog_size);
+                    cut(device, n);
+                    let result = op(&mut volume);
+
+                    if !device.power_on()
+                    {
+                        result.unwrap();
+                        break;
+                    }
+
+                    let mut volume = FsVolume::mount(device).unwrap();
+                    let found = files(&mut volume);
+                    assert!(found == before || found == after, "{prog_size} {n}");
+
+                    // The filesystem is still writable.
+                    op(&mut volume).unwrap();
+                    assert_eq!(files(&mut FsVolume::mount(device).unwrap()), after);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mount_and_format()
+    {
+        for prog_size in [1, 16]
+        {
+            let device = RamBlocks::new(prog_size);
+            assert!(matches!(FsVolume::mount(device), Err(ErrValue::InstanceNotFound)));
+
+            let mut volume = FsVolume::format(device).unwrap();
+            volume.mkdir("dir").unwrap();
+            write_at(&mut volume, "dir/log", FsOpen::write(), 0, &pattern(1, 1000)).unwrap();
+            write_at(&mut volume, "a", FsOpen::write(), 0, &pattern(2, 10)).unwrap();
+
+            let mut volume = FsVolume::mount(device).unwrap();
+            assert_eq!(volume.stat("dir").unwrap().kind, FsKind::Dir);
+            assert_eq!(
+                files(&mut volume),
+                [(String::from("/a"), pattern(2, 10)), (String::from("dir/log"), pattern(1, 1000))]
+            );
+
+            volume.remove("dir/log").unwrap();
+            let mut volume = FsVolume::mount(device).unwrap();
+            assert_eq!(files(&mut volume), [(String::from("/a"), pattern(2, 10))]);
+        }
+    }
+
+    #[test]
+    fn commits_fill_the_meta_blocks()
+    {
+        for prog_size in [1, 16]
+        {
+            let (device, mut volume) = setup(prog_size);
+
+            // Each rename is a commit, so the metadata moves between its two blocks many times.
+            for x in 0..40
+            {
+                volume.rename("a", "b").unwrap();
+                volume.rename("b", "a").unwrap();
+                write_at(&mut volume, "a", FsOpen::append(), 0, &[x]).unwrap();
+            }
+
+            let mut volume = FsVolume::mount(device).unwrap();
+            let mut data = pattern(2, 10);
+            data.extend(0..40);
+            assert_eq!(read_all(&mut volume, "a"), data);
+        }
+    }
+
+    #[test]
+    fn metadata_moves()
+    {
+        for prog_size in [1, 16]
+        {
+            let (device, mut volume) = setup(prog_size);
+            let mut pairs = Vec::new();
+
+            for x in 0..1500
+            {
+                volume.rename("a", "b").unwrap();
+                volume.rename("b", "a").unwrap();
+
+                if x % 100 == 0
+                {
+                    write_at(&mut volume, "a", FsOpen::append(), 0, &[x as u8]).unwrap();
+                }
+
+                if !pairs.contains(&volume.meta.blocks)
+                {
+                    pairs.push(volume.meta.blocks);
+                }
+            }
+
+            // Without the moves, the blocks 2 and 3 would take nearly all the erases.
+            let erases = device.state.lock().unwrap().erases.clone();
+            let total: u32 = erases.iter().sum();
+            assert!(pairs.len() >= 5, "{pairs:?}");
+            assert!(erases[..2].iter().all(|x| *x <= 3), "{erases:?}");
+            assert!(erases.iter().all(|x| *x * 4 <= total), "{erases:?}");
+
+            let blocks = volume.meta.blocks;
+            let mut volume = FsVolume::mount(device).unwrap();
+            assert_eq!(volume.meta.blocks, blocks);
+            assert_eq!(read_all(&mut volume, "a").len(), 10 + 15);
+            files(&mut volume);
+        }
+    }
+
+    #[test]
+    fn table_larger_than_block()
+    {
+        let (device, mut volume) = setup(1);
+
+        let mut count = 0;
+        let err = loop
+        {
+            match volume.mkdir(&std::format!("dir/{count:02}"))
+            {
+                Ok(()) => count += 1,
+                Err(x) => break x,
+            }
+        };
+
+        assert!(matches!(err, ErrValue::StackOverflow));
+        assert!(count > 0);
+        assert_eq!(files(&mut FsVolume::mount(device).unwrap()).len(), 2);
+    }
+
+    #[test]
+    fn power_cut_in_move()
+    {
+        sweep(|x| {
+            x.meta.cycles = META_CYCLES;
+            x.meta.end = BLOCK_SIZE;
+            x.rename("dir/log", "log")
+        });
+
+        // The superblock takes the other block of it too.
+        sweep(|x| {
+            x.meta.cycles = META_CYCLES;
+            x.meta.end = BLOCK_SIZE;
+            x.anchor.end = BLOCK_SIZE;
+            x.rename("dir/log", "log")
+        });
+    }
+
+    #[test]
+    fn power_cut_in_rewrite()
+    {
+        sweep(|x| write_at(x, "dir/log", FsOpen::append().with_append(false), 100, &[0xA5; 300]));
+    }
+
+    #[test]
+    fn power_cut_in_append()
+    {
+        sweep(|x| write_at(x, "a", FsOpen::append(), 0, &pattern(3, 500)));
+    }
+
+    #[test]
+    fn power_cut_in_rename()
+    {
+        sweep(|x| x.rename("dir/log", "log"));
+    }
+}
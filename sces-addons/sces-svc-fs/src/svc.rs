@@ -0,0 +1,35 @@
+use sces::value::{ErrValue, RetValue};
+
+use crate::fs::{FileSystem, FsFile, FsOpen};
+
+static mut SVC: Option<&'static dyn FileSystem> = None;
+
+pub const FSS: &str = "<FileSystemService>";
+
+pub struct FileSystemService;
+
+impl FileSystemService
+{
+    pub fn initialize<T>(instance: &'static T) -> RetValue<()>
+    where
+        T: FileSystem,
+    {
+        #[allow(static_mut_refs)]
+        unsafe {
+            SVC.is_none().then_some(()).ok_or(ErrValue::InstanceDuplicate)?
+        };
+        unsafe { SVC = Some(instance) };
+        Ok(())
+    }
+
+    pub fn instance() -> &'static dyn FileSystem
+    {
+        unsafe { SVC.unwrap() }
+    }
+
+    /// Open a file in the filesystem of the service.
+    pub fn open(path: &str, options: FsOpen) -> RetValue<FsFile<'static>>
+    {
+        Self::instance().open_file(path, options)
+    }
+}
@@ -0,0 +1,110 @@
+//! The CPU operations used when a panic or a fault occurs.
+
+use crate::fault::FaultStatus;
+#[cfg(all(feature = "fault", target_arch = "arm", target_os = "none"))]
+use crate::{ExceptionFrame, FaultReport, PanicService};
+
+/// Disable all maskable interrupts, so the task switch and the interrupt handlers stop.
+pub fn disable_interrupts()
+{
+    #[cfg(all(target_arch = "arm", target_os = "none"))]
+    unsafe {
+        core::arch::asm!("cpsid i", options(nomem, nostack, preserves_flags))
+    };
+}
+
+/// Request a system reset by the `SYSRESETREQ` bit of the Cortex-M `AIRCR` register.
+pub fn system_reset() -> !
+{
+    #[cfg(all(target_arch = "arm", target_os = "none"))]
+    unsafe {
+        const AIRCR: *mut u32 = 0xE000_ED0C as *mut u32;
+        const AIRCR_SYSRESETREQ: u32 = 0x05FA_0004;
+
+        core::arch::asm!("dsb", options(nostack, preserves_flags));
+        core::ptr::write_volatile(AIRCR, AIRCR_SYSRESETREQ);
+        core::arch::asm!("dsb", options(nostack, preserves_flags));
+    }
+
+    loop
+    {
+        core::hint::spin_loop();
+    }
+}
+
+/// Read the fault status registers of the System Control Block.
+pub fn read_fault_status() -> FaultStatus
+{
+    #[cfg(all(target_arch = "arm", target_os = "none"))]
+    unsafe {
+        use core::ptr::read_volatile;
+
+        FaultStatus {
+            cfsr: read_volatile(0xE000_ED28 as *const u32),
+            hfsr: read_volatile(0xE000_ED2C as *const u32),
+            mmfar: read_volatile(0xE000_ED34 as *const u32),
+            bfar: read_volatile(0xE000_ED38 as *const u32),
+        }
+    }
+
+    #[cfg(not(all(target_arch = "arm", target_os = "none")))]
+    FaultStatus::default()
+}
+
+/// Enable the MemManage, BusFault and UsageFault exceptions by `SHCSR`, and the divide by zero
+/// trap by `CCR`, otherwise they are escalated to the hard fault or ignored.
+pub fn enable_fault_traps()
+{
+    #[cfg(all(target_arch = "arm", target_os = "none"))]
+    unsafe {
+        use core::ptr::{read_volatile, write_volatile};
+
+        const SHCSR: *mut u32 = 0xE000_ED24 as *mut u32;
+        const SHCSR_FAULTENA: u32 = (1 << 16) | (1 << 17) | (1 << 18);
+        const CCR: *mut u32 = 0xE000_ED14 as *mut u32;
+        const CCR_DIV_0_TRP: u32 = 1 << 4;
+
+        write_volatile(SHCSR, read_volatile(SHCSR) | SHCSR_FAULTENA);
+        write_volatile(CCR, read_volatile(CCR) | CCR_DIV_0_TRP);
+        core::arch::asm!("dsb", "isb", options(nostack, preserves_flags));
+    }
+}
+
+// Pass the stack with the exception frame, the exception number and `EXC_RETURN` to
+// `sces_fault_handler`, the stack is selected by the bit `SPSEL` of `EXC_RETURN`.
+#[cfg(all(feature = "fault", target_arch = "arm", target_os = "none"))]
+core::arch::global_asm!(
+    ".section .text.sces_fault_trampoline, \"ax\", %progbits",
+    ".global sces_fault_trampoline",
+    ".type sces_fault_trampoline, %function",
+    ".thumb_func",
+    "sces_fault_trampoline:",
+    "    mov r2, lr",
+    "    tst r2, #4",
+    "    ite eq",
+    "    mrseq r0, msp",
+    "    mrsne r0, psp",
+    "    mrs r1, ipsr",
+    "    b sces_fault_handler",
+    ".size sces_fault_trampoline, . - sces_fault_trampoline",
+    ".global HardFault_Handler",
+    ".thumb_set HardFault_Handler, sces_fault_trampoline",
+    ".global MemManage_Handler",
+    ".thumb_set MemManage_Handler, sces_fault_trampoline",
+    ".global BusFault_Handler",
+    ".thumb_set BusFault_Handler, sces_fault_trampoline",
+    ".global UsageFault_Handler",
+    ".thumb_set UsageFault_Handler, sces_fault_trampoline",
+);
+
+#[cfg(all(feature = "fault", target_arch = "arm", target_os = "none"))]
+#[no_mangle]
+unsafe extern "C" fn sces_fault_handler(
+    stack: *const ExceptionFrame, ipsr: u32, exc_return: u32,
+) -> !
+{
+    let report =
+        FaultReport::capture(ipsr, exc_return, FaultStatus::read(), || stack.read_volatile());
+
+    PanicService::fault(&report)
+}
@@ -0,0 +1,468 @@
+//! Decode the fault exceptions of the Cortex-M CPU.
+//!
+//! The decoding only depends on the register values, so it works on a dump got by a debugger or
+//! from the retained log as well.
+
+use core::fmt::{self, Display, Formatter};
+
+use sces_derive::EnumAsU32;
+
+use crate::arch;
+
+const HFSR_VECTTBL: u32 = 1 << 1;
+const HFSR_FORCED: u32 = 1 << 30;
+const HFSR_DEBUGEVT: u32 = 1 << 31;
+
+const CFSR_MMARVALID: u32 = 1 << 7;
+const CFSR_BFARVALID: u32 = 1 << 15;
+const CFSR_STACKING: u32 = (1 << 4) | (1 << 12) | (1 << 20);
+
+const EXC_RETURN_SPSEL: u32 = 1 << 2;
+const IPSR_EXCEPTION: u32 = 0x1FF;
+
+const CFSR_CAUSES: [(u32, FaultCause); 18] = [
+    (1 << 0, FaultCause::InstructionAccess),
+    (1 << 1, FaultCause::DataAccess),
+    (1 << 3, FaultCause::Unstacking),
+    (1 << 4, FaultCause::StackOverflow),
+    (1 << 5, FaultCause::LazyStacking),
+    (1 << 8, FaultCause::InstructionBus),
+    (1 << 9, FaultCause::PreciseBus),
+    (1 << 10, FaultCause::ImpreciseBus),
+    (1 << 11, FaultCause::Unstacking),
+    (1 << 12, FaultCause::StackOverflow),
+    (1 << 13, FaultCause::LazyStacking),
+    (1 << 16, FaultCause::UndefinedInstruction),
+    (1 << 17, FaultCause::InvalidState),
+    (1 << 18, FaultCause::InvalidReturn),
+    (1 << 19, FaultCause::NoCoprocessor),
+    (1 << 20, FaultCause::StackOverflow),
+    (1 << 24, FaultCause::UnalignedAccess),
+    (1 << 25, FaultCause::DivideByZero),
+];
+
+const HFSR_CAUSES: [(u32, FaultCause); 3] = [
+    (HFSR_VECTTBL, FaultCause::VectorTable),
+    (HFSR_FORCED, FaultCause::Escalated),
+    (HFSR_DEBUGEVT, FaultCause::DebugEvent),
+];
+
+/// The fault exception which is taken, the value is the exception number in `IPSR`.
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumAsU32)]
+pub enum FaultKind
+{
+    HardFault = 3,
+    MemManage = 4,
+    BusFault = 5,
+    UsageFault = 6,
+    SecureFault = 7,
+    Unknown = 0,
+}
+
+/// The cause of a fault which is decoded from the fault status registers.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FaultCause
+{
+    /// `HFSR.VECTTBL`: A bus fault occurred when reading the vector table.
+    VectorTable,
+
+    /// `HFSR.FORCED`: A configurable fault is escalated to the hard fault, because it's disabled
+    /// or it can't be taken by the priority.
+    Escalated,
+
+    /// `HFSR.DEBUGEVT`: A debug event occurred when the debugger is not enabled.
+    DebugEvent,
+
+    /// `IACCVIOL`: Execute from a region which is not executable.
+    InstructionAccess,
+
+    /// `DACCVIOL`: Read or write a region which is not accessible, like a null pointer.
+    DataAccess,
+
+    /// `MSTKERR`, `STKERR` or `STKOF`: The exception frame can't be pushed to the stack, it's
+    /// almost always a stack overflow.
+    StackOverflow,
+
+    /// `MUNSTKERR` or `UNSTKERR`: The exception frame can't be popped from the stack.
+    Unstacking,
+
+    /// `MLSPERR` or `LSPERR`: The lazy stacking of the floating point registers failed.
+    LazyStacking,
+
+    /// `IBUSERR`: A bus error occurred when fetching an instruction.
+    InstructionBus,
+
+    /// `PRECISERR`: A bus error occurred on a data access, the address is known.
+    PreciseBus,
+
+    /// `IMPRECISERR`: A bus error occurred on a data access, the `PC` may point after the faulting
+    /// instruction.
+    ImpreciseBus,
+
+    /// `UNDEFINSTR`: Execute an undefined instruction.
+    UndefinedInstruction,
+
+    /// `INVSTATE`: Execute with an invalid `EPSR`, like a jump to an even address.
+    InvalidState,
+
+    /// `INVPC`: Return from an exception with an invalid `EXC_RETURN`.
+    InvalidReturn,
+
+    /// `NOCP`: Access a coprocessor which is disabled or absent, like the FPU.
+    NoCoprocessor,
+
+    /// `UNALIGNED`: An unaligned access when the trap is enabled, or by `LDM`, `STM`, `LDRD` and
+    /// `STRD`.
+    UnalignedAccess,
+
+    /// `DIVBYZERO`: Divide by zero when the trap is enabled.
+    DivideByZero,
+}
+
+impl FaultCause
+{
+    pub fn description(&self) -> &'static str
+    {
+        match self
+        {
+            Self::VectorTable => "vector table read",
+            Self::Escalated => "escalated fault",
+            Self::DebugEvent => "debug event",
+            Self::InstructionAccess => "instruction access violation",
+            Self::DataAccess => "data access violation",
+            Self::StackOverflow => "stack overflow",
+            Self::Unstacking => "unstacking error",
+            Self::LazyStacking => "floating point lazy stacking error",
+            Self::InstructionBus => "instruction bus error",
+            Self::PreciseBus => "precise data bus error",
+            Self::ImpreciseBus => "imprecise data bus error",
+            Self::UndefinedInstruction => "undefined instruction",
+            Self::InvalidState => "invalid state",
+            Self::InvalidReturn => "invalid exception return",
+            Self::NoCoprocessor => "no coprocessor",
+            Self::UnalignedAccess => "unaligned access",
+            Self::DivideByZero => "divide by zero",
+        }
+    }
+}
+
+impl Display for FaultCause
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// The registers which are pushed to the stack by the CPU when an exception is taken.
+#[repr(C)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ExceptionFrame
+{
+    pub r0: u32,
+    pub r1: u32,
+    pub r2: u32,
+    pub r3: u32,
+    pub r12: u32,
+    pub lr: u32,
+    pub pc: u32,
+    pub xpsr: u32,
+}
+
+impl Display for ExceptionFrame
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result
+    {
+        write!(
+            f,
+            "R0={:08X} R1={:08X} R2={:08X} R3={:08X} R12={:08X} LR={:08X} PC={:08X} xPSR={:08X}",
+            self.r0, self.r1, self.r2, self.r3, self.r12, self.lr, self.pc, self.xpsr
+        )
+    }
+}
+
+/// The fault status registers of the System Control Block.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct FaultStatus
+{
+    /// The Configurable Fault Status Register, which combines `MMFSR`, `BFSR` and `UFSR`.
+    pub cfsr: u32,
+
+    /// The HardFault Status Register.
+    pub hfsr: u32,
+
+    /// The MemManage Fault Address Register.
+    pub mmfar: u32,
+
+    /// The BusFault Address Register.
+    pub bfar: u32,
+}
+
+impl FaultStatus
+{
+    /// Read the registers of the running CPU, for the fault handlers of yours when the feature
+    /// `fault` is disabled.
+    pub fn read() -> Self
+    {
+        arch::read_fault_status()
+    }
+
+    /// Get all causes which are marked in the registers, from the hard fault ones to the
+    /// configurable ones.
+    pub fn causes(&self) -> impl Iterator<Item = FaultCause> + '_
+    {
+        let hard = HFSR_CAUSES.iter().filter(|(bit, _)| self.hfsr & bit != 0);
+        let configurable = CFSR_CAUSES.iter().filter(|(bit, _)| self.cfsr & bit != 0);
+
+        hard.chain(configurable).map(|(_, cause)| *cause)
+    }
+
+    /// Get the address which caused the fault, if the CPU has recorded it.
+    pub fn fault_address(&self) -> Option<u32>
+    {
+        if self.cfsr & CFSR_MMARVALID != 0
+        {
+            Some(self.mmfar)
+        }
+        else if self.cfsr & CFSR_BFARVALID != 0
+        {
+            Some(self.bfar)
+        }
+        else
+        {
+            None
+        }
+    }
+
+    /// Check whether the exception frame failed to be pushed, the stack should not be read then.
+    pub fn is_stacking_failed(&self) -> bool
+    {
+        self.cfsr & CFSR_STACKING != 0
+    }
+}
+
+impl Display for FaultStatus
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result
+    {
+        write!(
+            f,
+            "CFSR={:08X} HFSR={:08X} MMFAR={:08X} BFAR={:08X}",
+            self.cfsr, self.hfsr, self.mmfar, self.bfar
+        )
+    }
+}
+
+/// All information of a fault which is captured by the fault handler.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FaultReport
+{
+    pub kind: FaultKind,
+
+    /// The exception frame, which is `None` when it failed to be pushed.
+    pub frame: Option<ExceptionFrame>,
+
+    pub status: FaultStatus,
+
+    /// The value of `LR` when the exception is taken.
+    pub exc_return: u32,
+}
+
+impl FaultReport
+{
+    /// Decode the fault from the registers got by the fault handler, `ipsr` has the exception
+    /// number and `exc_return` is the value of `LR`.
+    ///
+    /// `frame` reads the exception frame from the stack, it's not called when the frame failed to
+    /// be pushed, because reading the stack may fault again then.
+    pub fn capture(
+        ipsr: u32, exc_return: u32, status: FaultStatus, frame: impl FnOnce() -> ExceptionFrame,
+    ) -> Self
+    {
+        Self {
+            kind: (ipsr & IPSR_EXCEPTION).into(),
+            frame: if status.is_stacking_failed() { None } else { Some(frame()) },
+            status,
+            exc_return,
+        }
+    }
+
+    /// Check whether the fault occurred in a task, or in an interrupt handler otherwise.
+    ///
+    /// The tasks run with the process stack `PSP`, and the interrupt handlers run with the main
+    /// stack `MSP`.
+    pub fn is_in_task(&self) -> bool
+    {
+        self.exc_return & EXC_RETURN_SPSEL != 0
+    }
+}
+
+/// Print the fault in one line, like `HardFault at 0800123A: escalated fault, divide by zero`.
+impl Display for FaultReport
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{:?}", self.kind)?;
+
+        if let Some(frame) = &self.frame
+        {
+            write!(f, " at {:08X}", frame.pc)?;
+        }
+
+        f.write_str(":")?;
+
+        for (i, cause) in self.status.causes().enumerate()
+        {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, cause)?;
+        }
+
+        if let Some(address) = self.status.fault_address()
+        {
+            write!(f, ", address {:08X}", address)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use std::format;
+    use std::vec::Vec;
+
+    use super::*;
+
+    const MSTKERR: u32 = 1 << 4;
+    const PRECISERR: u32 = 1 << 9;
+    const STKERR: u32 = 1 << 12;
+    const UNALIGNED: u32 = 1 << 24;
+    const DIVBYZERO: u32 = 1 << 25;
+
+    /// `EXC_RETURN` of a thread with the process stack and the one of a handler.
+    const EXC_RETURN_PSP: u32 = 0xFFFF_FFFD;
+    const EXC_RETURN_MSP: u32 = 0xFFFF_FFF1;
+
+    fn causes(status: &FaultStatus) -> Vec<FaultCause>
+    {
+        status.causes().collect()
+    }
+
+    /// The exception frame as the words pushed to the stack, from `R0` to `xPSR`.
+    fn stack(pc: u32) -> [u32; 8]
+    {
+        [0x11, 0x22, 0x33, 0x44, 0xCC, 0x0800_0F01, pc, 0x0100_0000]
+    }
+
+    fn read(stack: &[u32; 8]) -> ExceptionFrame
+    {
+        unsafe { (stack.as_ptr() as *const ExceptionFrame).read() }
+    }
+
+    #[test]
+    fn unaligned_access()
+    {
+        let status = FaultStatus { cfsr: UNALIGNED, ..Default::default() };
+        let report = FaultReport::capture(6, EXC_RETURN_PSP, status, || read(&stack(0x0800_1000)));
+
+        assert_eq!(causes(&status), [FaultCause::UnalignedAccess]);
+        assert_eq!(status.fault_address(), None);
+        assert_eq!(report.kind, FaultKind::UsageFault);
+        assert!(report.is_in_task());
+        assert_eq!(format!("{report}"), "UsageFault at 08001000: unaligned access");
+    }
+
+    #[test]
+    fn divide_by_zero()
+    {
+        let status = FaultStatus { cfsr: DIVBYZERO, ..Default::default() };
+        let report = FaultReport::capture(6, EXC_RETURN_MSP, status, || read(&stack(0x0800_2002)));
+
+        assert_eq!(causes(&status), [FaultCause::DivideByZero]);
+        assert!(!report.is_in_task());
+        assert_eq!(format!("{report}"), "UsageFault at 08002002: divide by zero");
+    }
+
+    #[test]
+    fn precise_bus_error()
+    {
+        let status = FaultStatus {
+            cfsr: PRECISERR | CFSR_BFARVALID,
+            mmfar: 0xE000_0000,
+            bfar: 0x6000_0004,
+            ..Default::default()
+        };
+        let report = FaultReport::capture(5, EXC_RETURN_PSP, status, || read(&stack(0x0800_3000)));
+
+        assert_eq!(causes(&status), [FaultCause::PreciseBus]);
+        assert_eq!(status.fault_address(), Some(0x6000_0004));
+        assert_eq!(report.kind, FaultKind::BusFault);
+        assert_eq!(
+            format!("{report}"),
+            "BusFault at 08003000: precise data bus error, address 60000004"
+        );
+
+        // The address register is stale without `BFARVALID`.
+        let status = FaultStatus { cfsr: PRECISERR, ..status };
+        assert_eq!(status.fault_address(), None);
+    }
+
+    #[test]
+    fn forced_hard_fault()
+    {
+        let status = FaultStatus { cfsr: DIVBYZERO, hfsr: HFSR_FORCED, ..Default::default() };
+        let report = FaultReport::capture(3, EXC_RETURN_PSP, status, || read(&stack(0x0800_123A)));
+
+        assert_eq!(causes(&status), [FaultCause::Escalated, FaultCause::DivideByZero]);
+        assert_eq!(report.kind, FaultKind::HardFault);
+        assert_eq!(format!("{report}"), "HardFault at 0800123A: escalated fault, divide by zero");
+    }
+
+    #[test]
+    fn stacking_error()
+    {
+        let status = FaultStatus { cfsr: STKERR, hfsr: HFSR_FORCED, ..Default::default() };
+        let report = FaultReport::capture(3, EXC_RETURN_PSP, status, || panic!("stack read"));
+
+        assert!(status.is_stacking_failed());
+        assert_eq!(causes(&status), [FaultCause::Escalated, FaultCause::StackOverflow]);
+        assert_eq!(report.frame, None);
+        assert_eq!(format!("{report}"), "HardFault: escalated fault, stack overflow");
+
+        let status = FaultStatus {
+            cfsr: MSTKERR | CFSR_MMARVALID,
+            mmfar: 0x2000_0FE0,
+            ..Default::default()
+        };
+        let report = FaultReport::capture(4, EXC_RETURN_PSP, status, || panic!("stack read"));
+
+        assert!(status.is_stacking_failed());
+        assert_eq!(report.frame, None);
+        assert_eq!(format!("{report}"), "MemManage: stack overflow, address 20000FE0");
+    }
+
+    #[test]
+    fn exception_frame()
+    {
+        let words = stack(0x0800_4000);
+        let report =
+            FaultReport::capture(0x0100_0005, EXC_RETURN_PSP, FaultStatus::default(), || {
+                read(&words)
+            });
+        let frame = report.frame.unwrap();
+
+        assert_eq!(report.kind, FaultKind::BusFault);
+        assert_eq!((frame.r0, frame.r1, frame.r2, frame.r3), (0x11, 0x22, 0x33, 0x44));
+        assert_eq!(
+            (frame.r12, frame.lr, frame.pc, frame.xpsr),
+            (0xCC, 0x0800_0F01, 0x0800_4000, 0x0100_0000)
+        );
+        assert_eq!(
+            FaultReport::capture(11, 0, FaultStatus::default(), || frame).kind,
+            FaultKind::Unknown
+        );
+    }
+}
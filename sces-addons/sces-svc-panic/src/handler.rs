@@ -0,0 +1,9 @@
+use core::panic::PanicInfo;
+
+use crate::PanicService;
+
+#[panic_handler]
+fn sces_panic(info: &PanicInfo) -> !
+{
+    PanicService::panic(info)
+}
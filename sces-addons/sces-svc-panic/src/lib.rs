@@ -0,0 +1,16 @@
+#![cfg_attr(not(test), no_std)]
+
+mod arch;
+mod fault;
+#[cfg(all(feature = "handler", not(test)))]
+mod handler;
+mod svc;
+
+pub use fault::ExceptionFrame;
+pub use fault::FaultCause;
+pub use fault::FaultKind;
+pub use fault::FaultReport;
+pub use fault::FaultStatus;
+pub use svc::PanicAction;
+pub use svc::PanicHook;
+pub use svc::PanicService;
@@ -0,0 +1,268 @@
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use log::error;
+use sces::mcu::uart::UartCtrl;
+use sces::os::task::ITask;
+use sces::os::tick::Duration;
+use sces::os::RTOS;
+use sces::retain::{retained, ResetReason};
+
+use crate::arch;
+use crate::fault::FaultReport;
+
+const PANIC_TEXT_SIZE: usize = 384;
+const PANIC_TX_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// What to do after the panic has been reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicAction
+{
+    /// Stay with all interrupts disabled, so the watch dog is not refreshed anymore and it will
+    /// reset the system, or stay forever if there is no watch dog.
+    Halt,
+
+    /// Reset the system immediately.
+    Reset,
+}
+
+/// The hook to do some board specific actions when a panic occurs.
+///
+/// All functions are called with all interrupts disabled, so don't use any OS functions or
+/// interrupt based drivers in them.
+pub trait PanicHook
+{
+    /// Will be called after the panic has been reported, and before the [`PanicAction`].
+    fn on_panic(&self, _info: &PanicInfo) {}
+
+    /// Will be called after the fault exception has been reported, and before the
+    /// [`PanicAction`].
+    fn on_fault(&self, _report: &FaultReport) {}
+
+    /// Will be called repeatedly when the [`PanicAction::Halt`] is taken, like to blink a LED.
+    fn on_halt(&self) {}
+}
+
+struct PanicSetting
+{
+    uart: Option<&'static dyn UartCtrl>,
+    action: PanicAction,
+    hook: Option<&'static dyn PanicHook>,
+    is_in_isr: fn() -> bool,
+    task_name: fn(&mut dyn Write) -> fmt::Result,
+}
+
+static mut SVC: PanicSetting = PanicSetting {
+    uart: None,
+    action: PanicAction::Halt,
+    hook: None,
+    is_in_isr: || false,
+    task_name: |_| Ok(()),
+};
+
+static PANICKING: AtomicBool = AtomicBool::new(false);
+static mut PANIC_TEXT: PanicText = PanicText { data: [0; PANIC_TEXT_SIZE], length: 0 };
+
+pub struct PanicService;
+
+impl PanicService
+{
+    /// Set how to report a panic, the report is printed via `uart` synchronously if it's given.
+    ///
+    /// The `uart` could be the same one used by the console, the asynchronous transmission of the
+    /// console will be aborted when a panic occurs.
+    ///
+    /// The MemManage, BusFault, UsageFault exceptions and the divide by zero trap are enabled
+    /// when the feature `fault` is enabled, so they are reported with the exact causes.
+    pub fn initialize<OS>(uart: Option<&'static dyn UartCtrl>, action: PanicAction)
+    where
+        OS: RTOS,
+    {
+        unsafe {
+            SVC.uart = uart;
+            SVC.action = action;
+            SVC.is_in_isr = OS::is_in_isr;
+            SVC.task_name = Self::write_task_name::<OS>;
+        }
+
+        #[cfg(feature = "fault")]
+        arch::enable_fault_traps();
+    }
+
+    pub fn set_hook(hook: &'static dyn PanicHook)
+    {
+        unsafe { SVC.hook = Some(hook) };
+    }
+
+    /// Report the panic and take the [`PanicAction`], it's called by the `#[panic_handler]` of
+    /// this crate, or by yours when the feature `handler` is disabled.
+    ///
+    /// The report is printed via the UART, and written to the retained log of `sces::retain`.
+    #[allow(static_mut_refs)]
+    pub fn panic(info: &PanicInfo) -> !
+    {
+        arch::disable_interrupts();
+
+        let setting = unsafe { &SVC };
+
+        // Report only once, a panic in the report itself goes to the action directly.
+        if !PANICKING.swap(true, Ordering::Relaxed)
+        {
+            let text = unsafe { &mut PANIC_TEXT };
+
+            Self::write_head(text, "PANIC", (setting.is_in_isr)());
+
+            if let Some(location) = info.location()
+            {
+                write!(text, "{}:{}: ", location.file(), location.line()).ok();
+            }
+
+            write!(text, "{}\r\n", info.message()).ok();
+
+            Self::report(text, ResetReason::Panic);
+            setting.hook.inspect(|x| x.on_panic(info));
+        }
+
+        Self::take_action()
+    }
+
+    /// Report the fault exception and take the [`PanicAction`], it's called by the fault handlers
+    /// of this crate, or by yours when the feature `fault` is disabled.
+    ///
+    /// The report is printed via the UART, written to the retained log of `sces::retain`, then
+    /// logged via `log` if the logger could still work with all interrupts disabled.
+    #[allow(static_mut_refs)]
+    pub fn fault(report: &FaultReport) -> !
+    {
+        arch::disable_interrupts();
+
+        let setting = unsafe { &SVC };
+
+        if !PANICKING.swap(true, Ordering::Relaxed)
+        {
+            let text = unsafe { &mut PANIC_TEXT };
+
+            Self::write_head(text, "FAULT", !report.is_in_task());
+            write!(text, "{}\r\n", report).ok();
+
+            if let Some(frame) = &report.frame
+            {
+                write!(text, "{}\r\n", frame).ok();
+            }
+
+            write!(text, "{}\r\n", report.status).ok();
+
+            Self::report(text, ResetReason::Fault);
+
+            error!("{}", report);
+            report.frame.inspect(|x| error!("{}", x));
+            error!("{}", report.status);
+
+            setting.hook.inspect(|x| x.on_fault(report));
+        }
+
+        Self::take_action()
+    }
+
+    #[allow(static_mut_refs)]
+    fn write_head(text: &mut PanicText, title: &str, in_isr: bool)
+    {
+        let setting = unsafe { &SVC };
+
+        write!(text, "\r\n[{}] (", title).ok();
+
+        if in_isr
+        {
+            text.write_str("ISR").ok();
+        }
+        else
+        {
+            (setting.task_name)(text).ok();
+        }
+
+        write!(text, ") ").ok();
+    }
+
+    /// Print the report via the UART synchronously, and record it for the post-mortem.
+    fn report(text: &PanicText, reason: ResetReason)
+    {
+        if let Some(uart) = unsafe { SVC.uart }
+        {
+            #[allow(unused_must_use)]
+            uart.abort();
+            #[allow(unused_must_use)]
+            uart.transmit(text.as_bytes(), PANIC_TX_TIMEOUT);
+        }
+
+        if let Some(log) = retained()
+        {
+            for line in text.as_str().lines().filter(|x| !x.is_empty())
+            {
+                log.write(format_args!("{}", line));
+            }
+
+            log.mark_reset(reason);
+        }
+    }
+
+    #[allow(static_mut_refs)]
+    fn take_action() -> !
+    {
+        let setting = unsafe { &SVC };
+
+        match setting.action
+        {
+            PanicAction::Reset => arch::system_reset(),
+            PanicAction::Halt => loop
+            {
+                setting.hook.inspect(|x| x.on_halt());
+            },
+        }
+    }
+
+    fn write_task_name<OS>(out: &mut dyn Write) -> fmt::Result
+    where
+        OS: RTOS,
+    {
+        out.write_str(OS::current_task().name())
+    }
+}
+
+/// The text of the panic report, it's static because the stack of the panic task may be nearly
+/// used up.
+struct PanicText
+{
+    data: [u8; PANIC_TEXT_SIZE],
+    length: usize,
+}
+
+impl PanicText
+{
+    fn as_bytes(&self) -> &[u8]
+    {
+        &self.data[..self.length]
+    }
+
+    fn as_str(&self) -> &str
+    {
+        core::str::from_utf8(self.as_bytes()).unwrap_or_default()
+    }
+}
+
+impl Write for PanicText
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result
+    {
+        let mut size = s.len().min(PANIC_TEXT_SIZE - self.length);
+
+        while !s.is_char_boundary(size)
+        {
+            size -= 1;
+        }
+
+        self.data[self.length..self.length + size].copy_from_slice(&s.as_bytes()[..size]);
+        self.length += size;
+        Ok(())
+    }
+}
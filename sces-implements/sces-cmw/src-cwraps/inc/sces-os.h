@@ -11,6 +11,7 @@
 /// @author Khose-ie<khose-ie@outlook.com>
 
 #include <sces.h>
+#include <stdbool.h>
 #include <stdint.h>
 
 #define SCES_OS_WAIT_NO      0
@@ -137,6 +138,10 @@ uint32_t sces_os_task_count(void);
 /// @return Handle to the currently running task
 scesTaskHandle_t sces_os_current_task(void);
 
+/// @brief  Check whether the caller is running in an interrupt handler
+/// @return true if called from an interrupt service routine, false otherwise
+bool sces_os_is_in_isr(void);
+
 /// @brief  Yield the processor from the current task
 /// @details This function allows the current task to yield the processor, allowing other tasks to
 /// run.
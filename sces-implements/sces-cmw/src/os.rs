@@ -1,7 +1,9 @@
+use sces::os::tick::{Duration, Instant};
 use sces::os::RTOS;
 use sces::value::RetValue;
 
 mod events;
+mod idle;
 mod mem;
 mod message_queue;
 mod mutex;
@@ -55,6 +57,11 @@ impl RTOS for MWOS
         unsafe { Self::Task::from(native::sces_os_current_task()) }
     }
 
+    fn is_in_isr() -> bool
+    {
+        unsafe { native::sces_os_is_in_isr() }
+    }
+
     fn switch_next_task()
     {
         unsafe { native::sces_os_yield() };
@@ -65,13 +72,13 @@ impl RTOS for MWOS
         unsafe { native::sces_os_exit_task() };
     }
 
-    fn delay(time: u32)
+    fn delay(time: Duration)
     {
-        unsafe { native::sces_os_delay(time) };
+        unsafe { native::sces_os_delay(time.ticks()) };
     }
 
-    fn delay_interval(time: u32)
+    fn delay_interval(time: Instant)
     {
-        unsafe { native::sces_os_delay_interval(time) };
+        unsafe { native::sces_os_delay_interval(time.ticks()) };
     }
 }
@@ -2,6 +2,7 @@ use core::ptr::null;
 
 use sces::value::{ErrValue, RetValue};
 use sces::os::events::IEvents;
+use sces::os::tick::Duration;
 
 use crate::os::native::*;
 
@@ -31,7 +32,7 @@ impl IEvents for Events
         unsafe { sces_event_put(self.handle, events).map(()) }
     }
 
-    fn wait(&self, events: u32, timeout: u32) -> RetValue<u32>
+    fn wait(&self, events: u32, timeout: Duration) -> RetValue<u32>
     {
         let waited_events = SCES_EVENT_NONE;
 
@@ -40,7 +41,7 @@ impl IEvents for Events
                 self.handle,
                 events,
                 &waited_events as *const u32 as *mut u32,
-                timeout,
+                timeout.ticks(),
             )
             .map(waited_events)
         }
@@ -0,0 +1,21 @@
+//! The idle hook of MWOS, which runs the one installed by [`sces::os::idle::install`].
+//!
+//! The kernel runs without the timer, so it doesn't know the next timer deadline, and the hook is
+//! called with `Duration::MAX`, which keeps [`PowerIdle`](sces::mcu::power::PowerIdle) out of the
+//! standby mode. It's called from the low power entry of the kernel, which needs `TX_LOW_POWER`
+//! and the low power utility of ThreadX in the platform project:
+//!
+//! ```c
+//! extern uint32_t sces_cmw_idle(void);
+//! #define TX_LOW_POWER_USER_ENTER sces_cmw_idle()
+//! ```
+
+use sces::os::idle;
+use sces::os::tick::Duration;
+
+/// Run the idle hook, it returns `1` if the MCU has slept, otherwise it returns `0`.
+#[no_mangle]
+pub extern "C" fn sces_cmw_idle() -> u32
+{
+    idle::idle(Duration::MAX) as u32
+}
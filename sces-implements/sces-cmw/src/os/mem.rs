@@ -74,7 +74,7 @@ impl IMemPool for MemPool
 
     fn alloc(&self) -> *mut u8
     {
-        unsafe { sces_mem_pool_alloc(self.handle, MWOS::WAIT_500) as *mut u8 }
+        unsafe { sces_mem_pool_alloc(self.handle, MWOS::WAIT_500.ticks()) as *mut u8 }
     }
 
     fn free(&self, mem: *mut u8)
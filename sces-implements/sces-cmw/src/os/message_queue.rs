@@ -2,6 +2,7 @@ use core::ptr::null;
 
 use sces::value::RetValue;
 use sces::os::message_queue::{IMessageQueue, MessageContent};
+use sces::os::tick::Duration;
 
 use crate::os::native::*;
 
@@ -30,13 +31,13 @@ impl IMessageQueue for MessageQueue
             .ok_or(sces::value::ErrValue::InstanceCreateFailure)
     }
 
-    fn send(&self, content: &dyn MessageContent, timeout: u32) -> RetValue<()>
+    fn send(&self, content: &dyn MessageContent, timeout: Duration) -> RetValue<()>
     {
-        unsafe { sces_mq_send(self.handle, content.as_ptr(), timeout).map(()) }
+        unsafe { sces_mq_send(self.handle, content.as_ptr(), timeout.ticks()).map(()) }
     }
 
-    fn receive(&self, cache: &mut dyn MessageContent, timeout: u32) -> RetValue<()>
+    fn receive(&self, cache: &mut dyn MessageContent, timeout: Duration) -> RetValue<()>
     {
-        unsafe { sces_mq_receive(self.handle, cache.as_mut_ptr(), timeout).map(()) }
+        unsafe { sces_mq_receive(self.handle, cache.as_mut_ptr(), timeout.ticks()).map(()) }
     }
 }
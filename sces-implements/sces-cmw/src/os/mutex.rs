@@ -2,6 +2,7 @@ use core::ptr::null;
 
 use sces::value::{ErrValue, RetValue};
 use sces::os::mutex::IMutex;
+use sces::os::tick::Duration;
 
 use crate::os::native::*;
 
@@ -31,9 +32,9 @@ impl IMutex for Mutex
         unsafe { sces_mutex_lock(self.handle, SCES_OS_WAIT_FOREVER) };
     }
 
-    fn attempt_lock(&self, time: u32) -> RetValue<()>
+    fn attempt_lock(&self, time: Duration) -> RetValue<()>
     {
-        unsafe { sces_mutex_lock(self.handle, time).map(()) }
+        unsafe { sces_mutex_lock(self.handle, time.ticks()).map(()) }
     }
 
     fn unlock(&self)
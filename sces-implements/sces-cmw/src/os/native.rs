@@ -238,6 +238,9 @@ extern "C" {
     /// Get the currently running task
     pub fn sces_os_current_task() -> ScesTaskHandle;
 
+    /// Check whether the caller is running in an interrupt handler
+    pub fn sces_os_is_in_isr() -> bool;
+
     /// Yield the processor from the current task
     pub fn sces_os_yield();
 
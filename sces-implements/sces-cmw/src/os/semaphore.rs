@@ -2,6 +2,7 @@ use core::ptr::null;
 
 use sces::value::{ErrValue, RetValue};
 use sces::os::semaphore::ISemaphore;
+use sces::os::tick::Duration;
 
 use crate::os::native::*;
 
@@ -31,9 +32,9 @@ impl ISemaphore for Semaphore
         unsafe { sces_semaphore_take(self.handle, SCES_OS_WAIT_FOREVER) };
     }
 
-    fn attempt_take(&self, timeout: u32) -> RetValue<()>
+    fn attempt_take(&self, timeout: Duration) -> RetValue<()>
     {
-        unsafe { sces_semaphore_take(self.handle, timeout).map(()) }
+        unsafe { sces_semaphore_take(self.handle, timeout.ticks()).map(()) }
     }
 
     fn release(&self)
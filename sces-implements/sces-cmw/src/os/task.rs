@@ -84,4 +84,12 @@ impl ITask for Task
     {
         unsafe { sces_task_resume(self.handle).map(()) }
     }
+
+    fn terminate(&mut self) -> RetValue<()>
+    {
+        (!self.handle.is_null()).then_some(()).ok_or(ErrValue::InstanceInvalid)?;
+        unsafe { sces_task_delete(self.handle) };
+        self.handle = null_mut();
+        Ok(())
+    }
 }
@@ -1,6 +1,7 @@
 use core::ptr::{null, null_mut};
 
 use sces::value::{ErrValue, RetValue};
+use sces::os::tick::Duration;
 use sces::os::timer::{ITimer, ITimerEvent, TimerEventAgent, TimerMode, TimerState};
 
 use crate::os::native::*;
@@ -39,13 +40,13 @@ impl ITimer for Timer
         self.mode
     }
 
-    fn active(&mut self, times: u32, event: &dyn ITimerEvent) -> RetValue<()>
+    fn active(&mut self, times: Duration, event: &dyn ITimerEvent) -> RetValue<()>
     {
         if !self.handle.is_null()
         {
             if unsafe { sces_timer_state(self.handle) } != ScesTimerState::Active
             {
-                unsafe { sces_timer_start(self.handle, times) };
+                unsafe { sces_timer_start(self.handle, times.ticks()) };
                 return Ok(());
             }
             else
@@ -82,7 +83,7 @@ impl ITimer for Timer
             }
 
             (!self.handle.is_null()).then_some(()).ok_or(ErrValue::InstanceCreateFailure)?;
-            return unsafe { sces_timer_start(self.handle, times).map(()) };
+            return unsafe { sces_timer_start(self.handle, times.ticks()).map(()) };
         }
     }
 
@@ -6,6 +6,8 @@ pub mod flash;
 pub mod i2c;
 pub mod io;
 pub mod spi;
+pub mod sys;
+pub mod tim;
 pub mod uart;
 pub mod wd;
 
@@ -3,6 +3,7 @@ use core::ptr::NonNull;
 use sces::value::{ErrValue, RetValue};
 use sces::mcu::can::{CanCtrl, CanCtrlEvent, CanMessage};
 use sces::mcu::EventLaunch;
+use sces::os::tick::{Duration, Instant};
 
 use crate::device::Handle;
 use crate::native::can::*;
@@ -71,16 +72,16 @@ impl CanCtrl for Can
         unsafe { HAL_CAN_Stop(self.handle.as_ptr()).into() }
     }
 
-    fn transmit(&self, can_message: &CanMessage, timeout: u32) -> RetValue<()>
+    fn transmit(&self, can_message: &CanMessage, timeout: Duration) -> RetValue<()>
     {
         let mut status;
         let mut can_status;
-        let mut duration: u32;
+        let mut duration: Duration;
         let mut mail_box: u32 = 0;
 
         let tx_head = CAN_TxHeaderTypeDef::from(&can_message.head);
 
-        let tick = unsafe { HAL_GetTick() };
+        let tick = Instant::from_ticks(unsafe { HAL_GetTick() });
 
         loop
         {
@@ -98,7 +99,7 @@ impl CanCtrl for Can
                 break;
             }
 
-            duration = unsafe { HAL_GetTick() - tick };
+            duration = Instant::from_ticks(unsafe { HAL_GetTick() }) - tick;
 
             if duration > timeout
             {
@@ -111,7 +112,7 @@ impl CanCtrl for Can
             return Err(ErrValue::Busy);
         }
 
-        let tick = unsafe { HAL_GetTick() };
+        let tick = Instant::from_ticks(unsafe { HAL_GetTick() });
 
         loop
         {
@@ -122,7 +123,7 @@ impl CanCtrl for Can
                 break;
             }
 
-            duration = unsafe { HAL_GetTick() - tick };
+            duration = Instant::from_ticks(unsafe { HAL_GetTick() }) - tick;
 
             if duration > timeout
             {
@@ -139,13 +140,13 @@ impl CanCtrl for Can
         Ok(())
     }
 
-    fn receive(&self, can_message: &mut CanMessage, timeout: u32) -> RetValue<()>
+    fn receive(&self, can_message: &mut CanMessage, timeout: Duration) -> RetValue<()>
     {
         let mut status;
-        let mut duration: u32;
+        let mut duration: Duration;
         let mut rx_head: CAN_RxHeaderTypeDef = Default::default();
 
-        let tick = unsafe { HAL_GetTick() };
+        let tick = Instant::from_ticks(unsafe { HAL_GetTick() });
 
         loop
         {
@@ -163,7 +164,7 @@ impl CanCtrl for Can
                 break;
             }
 
-            duration = unsafe { HAL_GetTick() - tick };
+            duration = Instant::from_ticks(unsafe { HAL_GetTick() }) - tick;
 
             if duration > timeout
             {
@@ -273,7 +274,7 @@ pub unsafe extern "C" fn HAL_CAN_RxFifo0MsgPendingCallback(can: *mut CAN_HandleT
     {
         if let Some(mut async_cache) = sample.async_cache
         {
-            if sample.receive(async_cache.as_mut(), 0).is_ok()
+            if sample.receive(async_cache.as_mut(), Duration::ZERO).is_ok()
             {
                 sample.event_handle.inspect(|event_handle| event_handle.on_can_message_receive());
             }
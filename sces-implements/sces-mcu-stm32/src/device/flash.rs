@@ -1,12 +1,22 @@
-use sces::value::RetValue;
-use sces::mcu::flash::FlashCtrl;
+use core::ptr::{copy_nonoverlapping, read_volatile, write_volatile};
+
+use sces::value::{ErrValue, RetValue};
+use sces::mcu::flash::{FlashCtrl, FlashEccError, FlashReadProtection, FlashSector};
 
 use crate::native::flash::*;
+use crate::native::sys::FLASHSIZE_BASE;
 
 /////////////////////////////////////////////////////////////////////////////
 // FLASH Class
 /////////////////////////////////////////////////////////////////////////////
 
+/// The on chip flash.
+///
+/// STM32F4 programs a word for each aligned 4 bytes and a byte for the rest, it needs the supply
+/// voltage from 2.7 V to 3.6 V. STM32H5 programs the 128 bits flash words with ECC, a part of a
+/// flash word is padded with `0xFF`, and a flash word is programmed only once after it's erased.
+///
+/// The banks are never swapped, the sector `0` is always at the address `0x0800_0000`.
 pub struct OnChipFlash {}
 
 impl OnChipFlash
@@ -19,43 +29,279 @@ impl OnChipFlash
 
 impl OnChipFlash
 {
-    unsafe fn write_common(&self, kind: u32, address: u32, data: u64) -> RetValue<()>
+    /// Get the size of a bank and the number of the sectors in it.
+    fn bank_layout(&self) -> (u32, u32)
     {
-        HAL_FLASH_Lock().ok()?;
-        let value = HAL_FLASH_Program(kind, address, data);
-        HAL_FLASH_Unlock();
-        value.into()
+        let bank_size = self.size() / flash_banks(self.size());
+        let last = FLASH_SECTOR_SIZES.len() - 1;
+        let head = FLASH_SECTOR_SIZES[..last].iter().sum::<u32>();
+
+        (bank_size, last as u32 + bank_size.saturating_sub(head) / FLASH_SECTOR_SIZES[last])
+    }
+
+    fn check_range(&self, address: u32, size: usize) -> RetValue<()>
+    {
+        let offset = address.checked_sub(FLASH_BASE).ok_or(ErrValue::Param)?;
+
+        match offset as u64 + size as u64 <= self.size() as u64
+        {
+            true => Ok(()),
+            false => Err(ErrValue::Param),
+        }
+    }
+
+    /// Unlock the flash during `f`, and lock it again anyway.
+    fn unlocked<T>(&self, f: impl FnOnce() -> RetValue<T>) -> RetValue<T>
+    {
+        unsafe { HAL_FLASH_Unlock().ok()? };
+        let value = f();
+        unsafe { HAL_FLASH_Lock() };
+        value
+    }
+
+    #[cfg(not(feature = "stm32h5"))]
+    unsafe fn program(&self, address: u32, data: &[u8]) -> RetValue<()>
+    {
+        let mut address = address;
+        let mut data = data;
+
+        while !data.is_empty()
+        {
+            let size = match data
+            {
+                [a, b, c, d, ..] if address.is_multiple_of(4) =>
+                {
+                    let word = u32::from_le_bytes([*a, *b, *c, *d]) as u64;
+                    HAL_FLASH_Program(FLASH_TYPEPROGRAM_WORD, address, word).ok()?;
+                    4
+                }
+                [byte, ..] =>
+                {
+                    HAL_FLASH_Program(FLASH_TYPEPROGRAM_BYTE, address, *byte as u64).ok()?;
+                    1
+                }
+                [] => 0,
+            };
+
+            address += size;
+            data = &data[size as usize..];
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "stm32h5")]
+    unsafe fn program(&self, address: u32, data: &[u8]) -> RetValue<()>
+    {
+        let mut address = address;
+        let mut data = data;
+
+        while !data.is_empty()
+        {
+            let start = address % FLASH_ECC_WORD;
+            let size = data.len().min((FLASH_ECC_WORD - start) as usize);
+
+            let mut word = [0xFFu8; FLASH_ECC_WORD as usize];
+            word[start as usize..start as usize + size].copy_from_slice(&data[..size]);
+
+            // The flash word which keeps the erased value is left to be programmed later.
+            if word.iter().any(|x| *x != 0xFF)
+            {
+                HAL_FLASH_Program(
+                    FLASH_TYPEPROGRAM_QUADWORD,
+                    address - start,
+                    word.as_ptr() as u32,
+                )
+                .ok()?;
+            }
+
+            address += size as u32;
+            data = &data[size..];
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "stm32h5"))]
+    fn erase_init(&self, sector: &FlashSector) -> FLASH_EraseInitTypeDef
+    {
+        FLASH_EraseInitTypeDef {
+            TypeErase: FLASH_TYPEERASE_SECTORS,
+            Banks: 0,
+            Sector: sector.index,
+            NbSectors: 1,
+            VoltageRange: FLASH_VOLTAGE_RANGE3,
+        }
+    }
+
+    #[cfg(feature = "stm32h5")]
+    fn erase_init(&self, sector: &FlashSector) -> FLASH_EraseInitTypeDef
+    {
+        let (_, sectors) = self.bank_layout();
+
+        FLASH_EraseInitTypeDef {
+            TypeErase: FLASH_TYPEERASE_SECTORS,
+            Banks: if sector.bank == 0 { FLASH_BANK_1 } else { FLASH_BANK_2 },
+            Sector: sector.index - sector.bank * sectors,
+            NbSectors: 1,
+        }
     }
 }
 
 impl FlashCtrl for OnChipFlash
 {
-    fn erase_sector(&self, sector: u32) -> RetValue<()>
+    fn base(&self) -> u32
+    {
+        FLASH_BASE
+    }
+
+    fn size(&self) -> u32
+    {
+        FLASHSIZE_BASE.map_or(0, |x| unsafe { read_volatile(x as *const u16) } as u32 * 1024)
+    }
+
+    fn write_size(&self) -> u32
+    {
+        match cfg!(feature = "stm32h5")
+        {
+            true => FLASH_ECC_WORD,
+            false => 1,
+        }
+    }
+
+    fn sector_count(&self) -> u32
+    {
+        self.bank_layout().1 * flash_banks(self.size())
+    }
+
+    fn sector(&self, index: u32) -> RetValue<FlashSector>
+    {
+        let (bank_size, sectors) = self.bank_layout();
+        if index >= self.sector_count()
+        {
+            return Err(ErrValue::Param);
+        }
+
+        let bank = index / sectors;
+        let num = (index % sectors) as usize;
+        let last = FLASH_SECTOR_SIZES.len() - 1;
+
+        let (offset, size) = match num < last
+        {
+            true => (FLASH_SECTOR_SIZES[..num].iter().sum(), FLASH_SECTOR_SIZES[num]),
+            false =>
+            {
+                let head = FLASH_SECTOR_SIZES[..last].iter().sum::<u32>();
+                (head + (num - last) as u32 * FLASH_SECTOR_SIZES[last], FLASH_SECTOR_SIZES[last])
+            }
+        };
+
+        Ok(FlashSector { index, bank, address: FLASH_BASE + bank * bank_size + offset, size })
+    }
+
+    fn read(&self, address: u32, data: &mut [u8]) -> RetValue<()>
+    {
+        self.check_range(address, data.len())?;
+        unsafe { copy_nonoverlapping(address as *const u8, data.as_mut_ptr(), data.len()) };
+        Ok(())
+    }
+
+    fn write(&self, address: u32, data: &[u8]) -> RetValue<()>
+    {
+        self.check_range(address, data.len())?;
+        self.unlocked(|| unsafe { self.program(address, data) })
+    }
+
+    fn erase_sector(&self, index: u32) -> RetValue<()>
     {
         let mut error: u32 = 0;
-        let mut erase_init: FLASH_EraseInitTypeDef = Default::default();
+        let mut erase_init = self.erase_init(&self.sector(index)?);
 
-        erase_init.TypeErase = FLASH_TYPEERASE_SECTORS;
-        erase_init.Banks = 0;
-        erase_init.Sector = sector;
-        erase_init.NbSectors = 1;
-        erase_init.VoltageRange = FLASH_VOLTAGE_RANGE3;
+        self.unlocked(|| unsafe { HAL_FLASHEx_Erase(&mut erase_init, &mut error).ok() })
+    }
 
-        unsafe {
-            HAL_FLASH_Lock().ok()?;
-            let value = HAL_FLASHEx_Erase(&mut erase_init, &mut error);
-            HAL_FLASH_Unlock();
-            value.into()
+    /// Only STM32H5 has the ECC, the errors of the system flash and the OTP area are reported
+    /// as the ones of the user flash.
+    fn take_ecc_error(&self) -> Option<FlashEccError>
+    {
+        let (correction, detection) = FLASH_ECC_REGISTERS?;
+        let (bank_size, _) = self.bank_layout();
+
+        [(detection, FLASH_ECC_DETECTED, false), (correction, FLASH_ECC_CORRECTED, true)]
+            .into_iter()
+            .find_map(|(register, flag, corrected)| unsafe {
+                let value = read_volatile(register as *const u32);
+                if value & flag == 0
+                {
+                    return None;
+                }
+
+                // The flag is cleaned by writing `1`, and the other bits are kept.
+                write_volatile(register as *mut u32, value);
+
+                let bank = (value & FLASH_ECC_BANK != 0) as u32;
+                let word = (value & FLASH_ECC_ADDRESS) * FLASH_ECC_WORD;
+                Some(FlashEccError { address: FLASH_BASE + bank * bank_size + word, corrected })
+            })
+    }
+
+    /// STM32H5 has the product state instead, which is `Level0` when it's open, `Level2` when
+    /// it's locked, and `Level1` in the other states.
+    fn read_protection(&self) -> RetValue<FlashReadProtection>
+    {
+        let value = (self.option_bytes()? >> FLASH_OPTION_RDP_SHIFT) as u8;
+
+        let (open, locked) = match cfg!(feature = "stm32h5")
+        {
+            true => (OB_PROD_STATE_OPEN, OB_PROD_STATE_LOCKED),
+            false => (OB_RDP_LEVEL_0, OB_RDP_LEVEL_2),
+        };
+
+        Ok(match value
+        {
+            x if x == open => FlashReadProtection::Level0,
+            x if x == locked => FlashReadProtection::Level2,
+            _ => FlashReadProtection::Level1,
+        })
+    }
+
+    /// Only STM32F4 is supported, the product state of STM32H5 is changed by the provisioning.
+    fn set_read_protection(&self, level: FlashReadProtection) -> RetValue<()>
+    {
+        if cfg!(feature = "stm32h5")
+        {
+            return Err(ErrValue::NotSupport);
         }
+
+        let value = match level
+        {
+            FlashReadProtection::Level0 => OB_RDP_LEVEL_0,
+            FlashReadProtection::Level1 => OB_RDP_LEVEL_1,
+            FlashReadProtection::Level2 => OB_RDP_LEVEL_2,
+        };
+
+        self.set_option_bytes(FLASH_OPTION_RDP_MASK, (value as u32) << FLASH_OPTION_RDP_SHIFT)
     }
 
-    fn write(&self, address: u32, data: u8) -> RetValue<()>
+    /// It's `FLASH_OPTCR` of STM32F4 or `FLASH_OPTSR_CUR` of STM32H5.
+    fn option_bytes(&self) -> RetValue<u32>
     {
-        unsafe { self.write_common(FLASH_TYPEPROGRAM_BYTE, address, data as u64) }
+        let current = FLASH_OPTION_CURRENT.ok_or(ErrValue::NotSupport)?;
+        Ok(unsafe { read_volatile(current as *const u32) } & !FLASH_OPTION_CONTROL)
     }
 
-    fn write32(&self, address: u32, data: u32) -> RetValue<()>
+    /// The new option bytes are programmed at once, some of them take effect after the next reset.
+    fn set_option_bytes(&self, mask: u32, value: u32) -> RetValue<()>
     {
-        unsafe { self.write_common(FLASH_TYPEPROGRAM_WORD, address, data as u64) }
+        let program = FLASH_OPTION_PROGRAM.ok_or(ErrValue::NotSupport)? as *mut u32;
+        let mask = mask & !FLASH_OPTION_CONTROL;
+
+        unsafe {
+            HAL_FLASH_OB_Unlock().ok()?;
+            write_volatile(program, (read_volatile(program) & !mask) | (value & mask));
+            let value = HAL_FLASH_OB_Launch();
+            HAL_FLASH_OB_Lock();
+            value.into()
+        }
     }
 }
@@ -1,18 +1,31 @@
+use core::hint::spin_loop;
 use core::ptr::NonNull;
 
 use sces::value::{ErrValue, RetValue};
-use sces::mcu::i2c::{I2cMasterCtrl, I2cMasterCtrlEvent};
+use sces::mcu::i2c::{I2cError, I2cMasterCtrl, I2cMasterCtrlEvent, I2cTransfer};
 use sces::mcu::i2c::{I2cMemCtrl, I2cMemCtrlEvent, I2cMemWide};
 use sces::mcu::i2c::{I2cSlaveCtrl, I2cSlaveCtrlEvent};
+use sces::mcu::io::IoState;
 use sces::mcu::EventLaunch;
+use sces::os::tick::Duration;
 
+use crate::device::io::GPIO_Pin;
 use crate::device::Handle;
 use crate::native::i2c::*;
+use crate::native::io::*;
+use crate::native::rcc::HAL_RCC_GetHCLKFreq;
+use crate::native::{HAL_GetTick, HAL_StatusTypeDef};
 use crate::sample_queue::SampleQueue;
 use crate::I2C_COUNT;
 
 pub use crate::native::i2c::I2C_HandleTypeDef;
 
+/// The clock pulses to free the bus, a slave which holds SDA releases it after 9 bits at most.
+const I2C_RECOVERY_PULSES: usize = 9;
+
+/// The clock of the recovery in Hz.
+const I2C_RECOVERY_FREQ: u32 = 100_000;
+
 /////////////////////////////////////////////////////////////////////////////
 // I2C Class
 /////////////////////////////////////////////////////////////////////////////
@@ -40,6 +53,7 @@ impl I2c
         Ok(I2c::Master(I2cMaster {
             handle: NonNull::new(handle).ok_or(ErrValue::Param)?,
             event_handle: None,
+            recovery: None,
         }))
     }
 
@@ -92,7 +106,7 @@ impl EventLaunch<dyn I2cMemCtrlEvent> for I2cMem
 impl I2cMemCtrl for I2cMem
 {
     fn mem_write(
-        &self, saddr: u16, maddr: u16, mwide: I2cMemWide, data: &[u8], timeout: u32,
+        &self, saddr: u16, maddr: u16, mwide: I2cMemWide, data: &[u8], timeout: Duration,
     ) -> RetValue<()>
     {
         unsafe {
@@ -103,14 +117,14 @@ impl I2cMemCtrl for I2cMem
                 mwide.into(),
                 data.as_ptr(),
                 data.len() as u16,
-                timeout,
+                timeout.ticks(),
             )
             .into()
         }
     }
 
     fn mem_read(
-        &self, saddr: u16, maddr: u16, mwide: I2cMemWide, data: &mut [u8], timeout: u32,
+        &self, saddr: u16, maddr: u16, mwide: I2cMemWide, data: &mut [u8], timeout: Duration,
     ) -> RetValue<()>
     {
         unsafe {
@@ -121,7 +135,7 @@ impl I2cMemCtrl for I2cMem
                 mwide.into(),
                 data.as_ptr(),
                 data.len() as u16,
-                timeout,
+                timeout.ticks(),
             )
             .into()
         }
@@ -166,11 +180,82 @@ impl I2cMemCtrl for I2cMem
 // I2C struct as a master
 /////////////////////////////////////////////////////////////////////////////
 
+/// A pin of the bus, which is driven by the GPIO during the recovery.
+#[derive(Clone, Copy)]
+struct I2cPin
+{
+    port: NonNull<GPIO_TypeDef>,
+    pin: u16,
+}
+
+impl I2cPin
+{
+    fn new(port: *mut GPIO_TypeDef, pin: GPIO_Pin) -> RetValue<Self>
+    {
+        Ok(Self { port: NonNull::new(port).ok_or(ErrValue::Param)?, pin: pin.into() })
+    }
+
+    fn state(&self) -> IoState
+    {
+        unsafe { HAL_GPIO_ReadPin(self.port.as_ptr(), self.pin).into() }
+    }
+
+    fn set_state(&self, state: IoState, spins: u32)
+    {
+        unsafe { HAL_GPIO_WritePin(self.port.as_ptr(), self.pin, state.into()) };
+
+        for _ in 0..spins
+        {
+            spin_loop();
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct I2cMaster
 {
     handle: NonNull<I2C_HandleTypeDef>,
     event_handle: Option<&'static dyn I2cMasterCtrlEvent>,
+    recovery: Option<(I2cPin, I2cPin)>,
+}
+
+impl I2cMaster
+{
+    /// Set the pins of SCL and SDA, which are needed by [`I2cMasterCtrl::recover`].
+    pub fn set_recovery_pins(
+        &mut self, scl_port: *mut GPIO_TypeDef, scl: GPIO_Pin, sda_port: *mut GPIO_TypeDef,
+        sda: GPIO_Pin,
+    ) -> RetValue<()>
+    {
+        self.recovery = Some((I2cPin::new(scl_port, scl)?, I2cPin::new(sda_port, sda)?));
+        Ok(())
+    }
+
+    /// Wait until the sequential transfer started at `start` is completed.
+    fn wait(&self, saddr: u16, start: u32, timeout: Duration) -> RetValue<()>
+    {
+        let handle = self.handle.as_ptr();
+
+        loop
+        {
+            match unsafe { HAL_I2C_GetState(handle) }
+            {
+                HAL_I2C_StateTypeDef::HAL_I2C_STATE_READY => break,
+                _ if unsafe { HAL_GetTick() }.wrapping_sub(start) >= timeout.ticks() =>
+                {
+                    unsafe { HAL_I2C_Master_Abort_IT(handle, saddr) };
+                    return Err(ErrValue::Timeout);
+                }
+                _ => spin_loop(),
+            }
+        }
+
+        match unsafe { HAL_I2C_GetError(handle) }
+        {
+            HAL_I2C_ERROR_NONE => Ok(()),
+            _ => Err(ErrValue::Param),
+        }
+    }
 }
 
 impl EventLaunch<dyn I2cMasterCtrlEvent> for I2cMaster
@@ -188,7 +273,7 @@ impl EventLaunch<dyn I2cMasterCtrlEvent> for I2cMaster
 
 impl I2cMasterCtrl for I2cMaster
 {
-    fn transmit(&self, saddr: u16, data: &[u8], timeout: u32) -> RetValue<()>
+    fn transmit(&self, saddr: u16, data: &[u8], timeout: Duration) -> RetValue<()>
     {
         unsafe {
             HAL_I2C_Master_Transmit(
@@ -196,13 +281,13 @@ impl I2cMasterCtrl for I2cMaster
                 saddr,
                 data.as_ptr(),
                 data.len() as u16,
-                timeout,
+                timeout.ticks(),
             )
             .into()
         }
     }
 
-    fn receive(&self, saddr: u16, data: &mut [u8], timeout: u32) -> RetValue<()>
+    fn receive(&self, saddr: u16, data: &mut [u8], timeout: Duration) -> RetValue<()>
     {
         unsafe {
             HAL_I2C_Master_Receive(
@@ -210,7 +295,7 @@ impl I2cMasterCtrl for I2cMaster
                 saddr,
                 data.as_ptr(),
                 data.len() as u16,
-                timeout,
+                timeout.ticks(),
             )
             .into()
         }
@@ -241,6 +326,132 @@ impl I2cMasterCtrl for I2cMaster
             .into()
         }
     }
+
+    /// It's made of the sequential transfers like [`I2cMasterCtrl::transfer`].
+    fn write_read(
+        &self, saddr: u16, write: &[u8], read: &mut [u8], timeout: Duration,
+    ) -> RetValue<()>
+    {
+        if write.is_empty()
+        {
+            return self.receive(saddr, read, timeout);
+        }
+        if read.is_empty()
+        {
+            return self.transmit(saddr, write, timeout);
+        }
+
+        self.transfer(saddr, &mut [I2cTransfer::Write(write), I2cTransfer::Read(read)], timeout)
+    }
+
+    /// It's made of the sequential transfers by the interrupts, so the interrupts of the I2C
+    /// should be enabled, and the completion events are reported to the event agent as well.
+    /// The parts must not be empty.
+    fn transfer(&self, saddr: u16, parts: &mut [I2cTransfer<'_>], timeout: Duration)
+        -> RetValue<()>
+    {
+        let last = match parts
+        {
+            [] => return Err(ErrValue::Param),
+            [I2cTransfer::Write(data)] => return self.transmit(saddr, data, timeout),
+            [I2cTransfer::Read(data)] => return self.receive(saddr, data, timeout),
+            _ => parts.len() - 1,
+        };
+
+        let handle = self.handle.as_ptr();
+        let start = unsafe { HAL_GetTick() };
+
+        for (index, part) in parts.iter_mut().enumerate()
+        {
+            let options = match index
+            {
+                0 => I2C_FIRST_FRAME,
+                x if x == last => I2C_LAST_FRAME,
+                _ => I2C_LAST_FRAME_NO_STOP,
+            };
+
+            unsafe {
+                match part
+                {
+                    I2cTransfer::Write(data) => HAL_I2C_Master_Seq_Transmit_IT(
+                        handle,
+                        saddr,
+                        data.as_ptr(),
+                        data.len() as u16,
+                        options,
+                    ),
+                    I2cTransfer::Read(data) => HAL_I2C_Master_Seq_Receive_IT(
+                        handle,
+                        saddr,
+                        data.as_ptr(),
+                        data.len() as u16,
+                        options,
+                    ),
+                }
+                .ok()?;
+            }
+            self.wait(saddr, start, timeout)?;
+        }
+
+        Ok(())
+    }
+
+    fn error(&self) -> I2cError
+    {
+        match unsafe { HAL_I2C_GetError(self.handle.as_ptr()) }
+        {
+            HAL_I2C_ERROR_NONE => I2cError::None,
+            x if x & HAL_I2C_ERROR_BERR != 0 => I2cError::Bus,
+            x if x & HAL_I2C_ERROR_ARLO != 0 => I2cError::ArbitrationLoss,
+            x if x & HAL_I2C_ERROR_AF != 0 => I2cError::NoAcknowledge,
+            x if x & HAL_I2C_ERROR_OVR != 0 => I2cError::Overrun,
+            _ => I2cError::Other,
+        }
+    }
+
+    fn probe(&self, saddr: u16, timeout: Duration) -> RetValue<bool>
+    {
+        match unsafe { HAL_I2C_IsDeviceReady(self.handle.as_ptr(), saddr, 1, timeout.ticks()) }
+        {
+            HAL_StatusTypeDef::HAL_OK => Ok(true),
+            HAL_StatusTypeDef::HAL_ERROR => Ok(false),
+            status => Err(status.into()),
+        }
+    }
+
+    /// The I2C is deinitialized, so its pins are driven by the GPIO, and it's initialized again
+    /// with the same settings after the pulses, which also clears the busy state of it.
+    fn recover(&self) -> RetValue<()>
+    {
+        let (scl, sda) = self.recovery.ok_or(ErrValue::NotSupport)?;
+        let handle = self.handle.as_ptr();
+        let spins = unsafe { HAL_RCC_GetHCLKFreq() } / I2C_RECOVERY_FREQ / 2;
+
+        unsafe { HAL_I2C_DeInit(handle).ok()? };
+
+        for pin in [scl, sda]
+        {
+            pin.set_state(IoState::Set, 0);
+            set_open_drain_output(pin.port.as_ptr(), pin.pin);
+        }
+
+        for _ in 0..I2C_RECOVERY_PULSES
+        {
+            scl.set_state(IoState::Reset, spins);
+            scl.set_state(IoState::Set, spins);
+        }
+
+        // A rising SDA while SCL is high is a stop.
+        scl.set_state(IoState::Reset, spins);
+        sda.set_state(IoState::Reset, spins);
+        scl.set_state(IoState::Set, spins);
+        sda.set_state(IoState::Set, spins);
+
+        let free = scl.state() == IoState::Set && sda.state() == IoState::Set;
+
+        unsafe { HAL_I2C_Init(handle).ok()? };
+        free.then_some(()).ok_or(ErrValue::Busy)
+    }
 }
 
 /////////////////////////////////////////////////////////////////////////////
@@ -274,19 +485,29 @@ impl I2cSlaveCtrl for I2cSlave
         unsafe { HAL_I2C_EnableListen_IT(self.handle.as_ptr()).into() }
     }
 
-    fn transmit(&self, data: &[u8], timeout: u32) -> RetValue<()>
+    fn transmit(&self, data: &[u8], timeout: Duration) -> RetValue<()>
     {
         unsafe {
-            HAL_I2C_Slave_Transmit(self.handle.as_ptr(), data.as_ptr(), data.len() as u16, timeout)
-                .into()
+            HAL_I2C_Slave_Transmit(
+                self.handle.as_ptr(),
+                data.as_ptr(),
+                data.len() as u16,
+                timeout.ticks(),
+            )
+            .into()
         }
     }
 
-    fn receive(&self, data: &mut [u8], timeout: u32) -> RetValue<()>
+    fn receive(&self, data: &mut [u8], timeout: Duration) -> RetValue<()>
     {
         unsafe {
-            HAL_I2C_Slave_Receive(self.handle.as_ptr(), data.as_ptr(), data.len() as u16, timeout)
-                .into()
+            HAL_I2C_Slave_Receive(
+                self.handle.as_ptr(),
+                data.as_ptr(),
+                data.len() as u16,
+                timeout.ticks(),
+            )
+            .into()
         }
     }
 
@@ -1,8 +1,9 @@
 use core::ptr::NonNull;
 
 use sces::value::{ErrValue, RetValue};
-use sces::mcu::spi::{SpiCtrl, SpiCtrlEvent};
+use sces::mcu::spi::{SpiBitOrder, SpiConfig, SpiCtrl, SpiCtrlEvent, SpiMode};
 use sces::mcu::EventLaunch;
+use sces::os::tick::Duration;
 
 use crate::device::Handle;
 use crate::native::spi::*;
@@ -53,21 +54,30 @@ impl EventLaunch<dyn SpiCtrlEvent> for Spi
 
 impl SpiCtrl for Spi
 {
-    fn transmit(&self, data: &[u8], timeout: u32) -> RetValue<()>
+    fn transmit(&self, data: &[u8], timeout: Duration) -> RetValue<()>
     {
         unsafe {
-            HAL_SPI_Transmit(self.handle.as_ptr(), data.as_ptr(), data.len() as u16, timeout).into()
+            HAL_SPI_Transmit(
+                self.handle.as_ptr(),
+                data.as_ptr(),
+                data.len() as u16,
+                timeout.ticks(),
+            )
+            .into()
         }
     }
 
-    fn receive(&self, data: &mut [u8], timeout: u32) -> RetValue<()>
+    fn receive(&self, data: &mut [u8], timeout: Duration) -> RetValue<()>
     {
         unsafe {
-            HAL_SPI_Receive(self.handle.as_ptr(), data.as_ptr(), data.len() as u16, timeout).into()
+            HAL_SPI_Receive(self.handle.as_ptr(), data.as_ptr(), data.len() as u16, timeout.ticks())
+                .into()
         }
     }
 
-    fn transmit_receive(&self, tx_data: &[u8], rx_data: &mut [u8], timeout: u32) -> RetValue<()>
+    fn transmit_receive(
+        &self, tx_data: &[u8], rx_data: &mut [u8], timeout: Duration,
+    ) -> RetValue<()>
     {
         unsafe {
             HAL_SPI_TransmitReceive(
@@ -75,7 +85,7 @@ impl SpiCtrl for Spi
                 tx_data.as_ptr(),
                 rx_data.as_ptr(),
                 tx_data.len() as u16,
-                timeout,
+                timeout.ticks(),
             )
             .into()
         }
@@ -112,6 +122,37 @@ impl SpiCtrl for Spi
     {
         unsafe { HAL_SPI_Abort_IT(self.handle.as_ptr()).into() }
     }
+
+    fn configure(&mut self, config: &SpiConfig) -> RetValue<()>
+    {
+        let handle = self.handle.as_ptr();
+        let clock = spi_clock(unsafe { (*handle).Instance });
+        let prescaler = (0..=SPI_BAUDRATEPRESCALER_MAX)
+            .find(|x| clock >> (x + 1) <= config.frequency)
+            .ok_or(ErrValue::NotSupport)?;
+
+        let (polarity, phase) = match config.mode
+        {
+            SpiMode::Mode0 => (SPI_POLARITY_LOW, SPI_PHASE_1EDGE),
+            SpiMode::Mode1 => (SPI_POLARITY_LOW, SPI_PHASE_2EDGE),
+            SpiMode::Mode2 => (SPI_POLARITY_HIGH, SPI_PHASE_1EDGE),
+            SpiMode::Mode3 => (SPI_POLARITY_HIGH, SPI_PHASE_2EDGE),
+        };
+
+        unsafe {
+            let init = &mut (*handle).Init;
+            init.CLKPolarity = polarity;
+            init.CLKPhase = phase;
+            init.BaudRatePrescaler = prescaler << SPI_BAUDRATEPRESCALER_SHIFT;
+            init.FirstBit = match config.bit_order
+            {
+                SpiBitOrder::MsbFirst => SPI_FIRSTBIT_MSB,
+                SpiBitOrder::LsbFirst => SPI_FIRSTBIT_LSB,
+            };
+
+            HAL_SPI_Init(handle).into()
+        }
+    }
 }
 
 /////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,108 @@
+use core::hint::spin_loop;
+use core::ptr::read_volatile;
+use core::slice;
+
+use sces::value::{ErrValue, RetValue};
+use sces::mcu::sys::{ResetCause, SystemClock, SystemCtrl};
+
+use crate::native::rcc::*;
+use crate::native::sys::*;
+
+#[cfg(not(feature = "stm32h5"))]
+const RESET_CAUSE_BROWN_OUT: ResetCause = ResetCause::BrownOut;
+#[cfg(feature = "stm32h5")]
+const RESET_CAUSE_BROWN_OUT: ResetCause = ResetCause::PowerOn;
+
+/// The reset flags in the order of [`ResetCause`].
+const RESET_CAUSES: [(u32, ResetCause); 7] = [
+    (RCC_FLAG_LPWRRST, ResetCause::LowPower),
+    (RCC_FLAG_WWDGRST, ResetCause::WindowWatchDog),
+    (RCC_FLAG_IWDGRST, ResetCause::IndependentWatchDog),
+    (RCC_FLAG_SFTRST, ResetCause::Software),
+    (RCC_FLAG_PORRST, ResetCause::PowerOn),
+    (RCC_FLAG_BORRST, RESET_CAUSE_BROWN_OUT),
+    (RCC_FLAG_PINRST, ResetCause::Pin),
+];
+
+/// The whole STM32 chip, the chip family is selected by the features like `stm32f4`.
+///
+/// The reset cause, the unique ID, the flash size and the bootloader are not available without
+/// the chip family.
+pub struct System;
+
+impl SystemCtrl for System
+{
+    fn reset(&self) -> !
+    {
+        unsafe { HAL_NVIC_SystemReset() };
+
+        loop
+        {
+            spin_loop();
+        }
+    }
+
+    fn reset_cause(&self) -> ResetCause
+    {
+        reset_flags()
+            .and_then(|flags| RESET_CAUSES.iter().find(|(flag, _)| flags & flag != 0))
+            .map_or(ResetCause::Unknown, |(_, cause)| *cause)
+    }
+
+    fn clean_reset_cause(&self)
+    {
+        remove_reset_flags();
+    }
+
+    fn unique_id(&self) -> &'static [u8]
+    {
+        UID_BASE.map_or(&[], |x| unsafe { slice::from_raw_parts(x as *const u8, UID_SIZE) })
+    }
+
+    fn flash_size(&self) -> u32
+    {
+        FLASHSIZE_BASE.map_or(0, |x| unsafe { read_volatile(x as *const u16) } as u32 * 1024)
+    }
+
+    fn clock_freq(&self, clock: SystemClock) -> u32
+    {
+        unsafe {
+            match clock
+            {
+                SystemClock::Core => HAL_RCC_GetSysClockFreq(),
+                SystemClock::Bus => HAL_RCC_GetHCLKFreq(),
+                SystemClock::Peripheral1 => HAL_RCC_GetPCLK1Freq(),
+                SystemClock::Peripheral2 => HAL_RCC_GetPCLK2Freq(),
+                SystemClock::Unknown => 0,
+            }
+        }
+    }
+
+    /// The clocks and the peripherals are reset to the default state before the jump, so it
+    /// should not be called in an interrupt handler.
+    fn enter_bootloader(&self) -> RetValue<()>
+    {
+        let address = BOOTLOADER_BASE.ok_or(ErrValue::NotSupport)?;
+
+        unsafe {
+            HAL_RCC_DeInit().ok()?;
+            HAL_DeInit().ok()?;
+            quiet_core();
+            remap_system_memory();
+            jump_to_image(address)
+        }
+    }
+
+    /// The clocks and the peripherals are reset to the default state before the jump like
+    /// [`SystemCtrl::enter_bootloader`], and the vector table is moved to `address`.
+    fn start_image(&self, address: u32) -> RetValue<()>
+    {
+        unsafe {
+            HAL_RCC_DeInit().ok()?;
+            HAL_DeInit().ok()?;
+            quiet_core();
+            relocate_vectors(address as usize);
+            jump_to_image(address as usize)
+        }
+    }
+}
@@ -0,0 +1,817 @@
+use core::ptr::{addr_of, addr_of_mut, read_volatile, write_volatile, NonNull};
+
+use sces::mcu::tim::*;
+use sces::mcu::EventLaunch;
+use sces::value::{ErrValue, RetValue};
+
+use crate::device::Handle;
+use crate::native::tim::*;
+use crate::sample_queue::SampleQueue;
+use crate::{TIM_CHANNEL_COUNT, TIM_COUNT};
+
+pub use crate::native::tim::TIM_HandleTypeDef;
+
+/// Get the channel of the HAL functions.
+fn hal_channel(channel: TimChannel) -> u32
+{
+    u32::from(channel) * 4
+}
+
+/// Get the channel which raises the interrupt now.
+unsafe fn active_channel(htim: *mut TIM_HandleTypeDef) -> TimChannel
+{
+    TimChannel::from((*htim).Channel.trailing_zeros())
+}
+
+unsafe fn read_reg(reg: *const u32) -> u32
+{
+    read_volatile(reg)
+}
+
+unsafe fn write_reg(reg: *mut u32, value: u32)
+{
+    write_volatile(reg, value)
+}
+
+fn count_freq(handle: *mut TIM_HandleTypeDef) -> u32
+{
+    unsafe {
+        let tim = (*handle).Instance;
+        tim_clock(tim) / (read_reg(addr_of!((*tim).PSC)) + 1)
+    }
+}
+
+fn frequency(handle: *mut TIM_HandleTypeDef) -> u32
+{
+    let period = unsafe { read_reg(addr_of!((*(*handle).Instance).ARR)) } as u64 + 1;
+    (count_freq(handle) as u64 / period) as u32
+}
+
+/// Set the prescaler and the period for `freq`, the pulses of all channels are scaled with the
+/// period if `keep_duty` is `true`.
+fn set_frequency(handle: *mut TIM_HandleTypeDef, freq: u32, keep_duty: bool) -> RetValue<()>
+{
+    let tim = unsafe { (*handle).Instance };
+    let (prescaler, period) = tim_split_frequency(tim_clock(tim), freq, tim_max_period(tim))?;
+
+    unsafe {
+        let top = read_reg(addr_of!((*tim).ARR)) as u64 + 1;
+
+        if keep_duty
+        {
+            for ccr in (0..4).map(|x| addr_of_mut!((*tim).CCR[x]))
+            {
+                write_reg(ccr, (read_reg(ccr) as u64 * period as u64 / top) as u32);
+            }
+        }
+
+        write_reg(addr_of_mut!((*tim).PSC), prescaler - 1);
+        write_reg(addr_of_mut!((*tim).ARR), period - 1);
+        (*handle).Init.Prescaler = prescaler - 1;
+        (*handle).Init.Period = period - 1;
+    }
+
+    Ok(())
+}
+
+fn data_length(data: &[u32]) -> RetValue<u16>
+{
+    u16::try_from(data.len()).map_err(|_| ErrValue::Param)
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// TIM Base Class
+/////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy)]
+pub struct TimBase
+{
+    handle: NonNull<TIM_HandleTypeDef>,
+    event_handle: Option<&'static dyn TimBaseCtrlEvent>,
+}
+
+impl TimBase
+{
+    fn new(handle: *mut TIM_HandleTypeDef) -> RetValue<Self>
+    {
+        Ok(TimBase { handle: NonNull::new(handle).ok_or(ErrValue::Param)?, event_handle: None })
+    }
+}
+
+impl Handle<TIM_HandleTypeDef> for TimBase
+{
+    fn handle_value(&self) -> *mut TIM_HandleTypeDef
+    {
+        self.handle.as_ptr()
+    }
+}
+
+impl EventLaunch<dyn TimBaseCtrlEvent> for TimBase
+{
+    fn set_event_agent(&mut self, event_handle: &'static dyn TimBaseCtrlEvent)
+    {
+        self.event_handle = Some(event_handle);
+    }
+
+    fn clean_event_agent(&mut self)
+    {
+        self.event_handle = None;
+    }
+}
+
+impl TimBaseCtrl for TimBase
+{
+    fn activate(&self) -> RetValue<()>
+    {
+        unsafe { HAL_TIM_Base_Start(self.handle.as_ptr()).into() }
+    }
+
+    fn deactivate(&self)
+    {
+        unsafe { HAL_TIM_Base_Stop(self.handle.as_ptr()) };
+    }
+
+    fn async_activate(&self) -> RetValue<()>
+    {
+        unsafe { HAL_TIM_Base_Start_IT(self.handle.as_ptr()).into() }
+    }
+
+    fn async_deactivate(&self)
+    {
+        unsafe { HAL_TIM_Base_Stop_IT(self.handle.as_ptr()) };
+    }
+
+    fn async_activate_data(&self, data: &mut [u32]) -> RetValue<()>
+    {
+        let length = data_length(data)?;
+        unsafe { HAL_TIM_Base_Start_DMA(self.handle.as_ptr(), data.as_ptr(), length).into() }
+    }
+
+    fn async_deactivate_data(&self)
+    {
+        unsafe { HAL_TIM_Base_Stop_DMA(self.handle.as_ptr()) };
+    }
+
+    fn count_value(&self) -> u32
+    {
+        unsafe { read_reg(addr_of!((*self.handle.as_ref().Instance).CNT)) }
+    }
+
+    fn set_count_value(&self, value: u32)
+    {
+        unsafe { write_reg(addr_of_mut!((*self.handle.as_ref().Instance).CNT), value) };
+    }
+
+    fn count_freq(&self) -> u32
+    {
+        count_freq(self.handle.as_ptr())
+    }
+
+    fn set_frequency(&self, freq: u32) -> RetValue<()>
+    {
+        set_frequency(self.handle.as_ptr(), freq, false)
+    }
+
+    fn frequency(&self) -> u32
+    {
+        frequency(self.handle.as_ptr())
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// TIM PWM Class
+/////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy)]
+pub struct TimPwm
+{
+    handle: NonNull<TIM_HandleTypeDef>,
+    channel: TimChannel,
+    event_handle: Option<&'static dyn TimPwmCtrlEvent>,
+}
+
+impl TimPwm
+{
+    fn new(handle: *mut TIM_HandleTypeDef, channel: TimChannel) -> RetValue<Self>
+    {
+        (channel != TimChannel::Unknown).then_some(()).ok_or(ErrValue::Param)?;
+        Ok(TimPwm {
+            handle: NonNull::new(handle).ok_or(ErrValue::Param)?,
+            channel,
+            event_handle: None,
+        })
+    }
+
+    fn ccr(&self) -> *mut u32
+    {
+        unsafe {
+            addr_of_mut!((*self.handle.as_ref().Instance).CCR[u32::from(self.channel) as usize])
+        }
+    }
+
+    fn period(&self) -> u64
+    {
+        unsafe { read_reg(addr_of!((*self.handle.as_ref().Instance).ARR)) as u64 + 1 }
+    }
+}
+
+impl Handle<TIM_HandleTypeDef> for TimPwm
+{
+    fn handle_value(&self) -> *mut TIM_HandleTypeDef
+    {
+        self.handle.as_ptr()
+    }
+
+    fn channel_value(&self) -> u32
+    {
+        self.channel.into()
+    }
+}
+
+impl EventLaunch<dyn TimPwmCtrlEvent> for TimPwm
+{
+    fn set_event_agent(&mut self, event_handle: &'static dyn TimPwmCtrlEvent)
+    {
+        self.event_handle = Some(event_handle);
+    }
+
+    fn clean_event_agent(&mut self)
+    {
+        self.event_handle = None;
+    }
+}
+
+impl TimPwmCtrl for TimPwm
+{
+    fn channel(&self) -> TimChannel
+    {
+        self.channel
+    }
+
+    fn activate(&self) -> RetValue<()>
+    {
+        unsafe { HAL_TIM_PWM_Start(self.handle.as_ptr(), hal_channel(self.channel)).into() }
+    }
+
+    fn deactivate(&self)
+    {
+        unsafe { HAL_TIM_PWM_Stop(self.handle.as_ptr(), hal_channel(self.channel)) };
+    }
+
+    fn async_activate(&self) -> RetValue<()>
+    {
+        unsafe { HAL_TIM_PWM_Start_IT(self.handle.as_ptr(), hal_channel(self.channel)).into() }
+    }
+
+    fn async_deactivate(&self)
+    {
+        unsafe { HAL_TIM_PWM_Stop_IT(self.handle.as_ptr(), hal_channel(self.channel)) };
+    }
+
+    fn async_activate_data(&self, data: &mut [u32]) -> RetValue<()>
+    {
+        let length = data_length(data)?;
+
+        unsafe {
+            HAL_TIM_PWM_Start_DMA(
+                self.handle.as_ptr(),
+                hal_channel(self.channel),
+                data.as_ptr(),
+                length,
+            )
+            .into()
+        }
+    }
+
+    fn async_deactivate_data(&self)
+    {
+        unsafe { HAL_TIM_PWM_Stop_DMA(self.handle.as_ptr(), hal_channel(self.channel)) };
+    }
+
+    /// Only the advanced timers, like `TIM1` and `TIM8`, have the complementary outputs.
+    fn activate_complementary(&self) -> RetValue<()>
+    {
+        unsafe { HAL_TIMEx_PWMN_Start(self.handle.as_ptr(), hal_channel(self.channel)).into() }
+    }
+
+    fn deactivate_complementary(&self)
+    {
+        unsafe { HAL_TIMEx_PWMN_Stop(self.handle.as_ptr(), hal_channel(self.channel)) };
+    }
+
+    fn count_value(&self) -> u32
+    {
+        unsafe { read_reg(addr_of!((*self.handle.as_ref().Instance).CNT)) }
+    }
+
+    fn set_frequency(&self, freq: u32) -> RetValue<()>
+    {
+        set_frequency(self.handle.as_ptr(), freq, true)
+    }
+
+    fn frequency(&self) -> u32
+    {
+        frequency(self.handle.as_ptr())
+    }
+
+    fn set_duty(&self, duty: u32) -> RetValue<()>
+    {
+        (duty <= TIM_DUTY_FULL).then_some(()).ok_or(ErrValue::Param)?;
+        self.set_pulse((self.period() * duty as u64 / TIM_DUTY_FULL as u64) as u32)
+    }
+
+    fn duty(&self) -> u32
+    {
+        let pulse = unsafe { read_reg(self.ccr()) } as u64;
+        (pulse * TIM_DUTY_FULL as u64 / self.period()).min(TIM_DUTY_FULL as u64) as u32
+    }
+
+    /// The output is always active when `pulse` is the period of the counter.
+    fn set_pulse(&self, pulse: u32) -> RetValue<()>
+    {
+        (pulse as u64 <= self.period()).then_some(()).ok_or(ErrValue::Param)?;
+        unsafe { write_reg(self.ccr(), pulse) };
+        Ok(())
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// TIM Input Capture Class
+/////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy)]
+pub struct TimCapture
+{
+    handle: NonNull<TIM_HandleTypeDef>,
+    channel: TimChannel,
+    event_handle: Option<&'static dyn TimCaptureCtrlEvent>,
+
+    /// The measurement started by [`TimCaptureCtrl::async_measure`], it's fed by the capture
+    /// interrupt of the channel, and only changed by the task with the interrupt masked.
+    meter: Option<TimCaptureMeter>,
+}
+
+impl TimCapture
+{
+    fn new(handle: *mut TIM_HandleTypeDef, channel: TimChannel) -> RetValue<Self>
+    {
+        (channel != TimChannel::Unknown).then_some(()).ok_or(ErrValue::Param)?;
+        Ok(TimCapture {
+            handle: NonNull::new(handle).ok_or(ErrValue::Param)?,
+            channel,
+            event_handle: None,
+            meter: None,
+        })
+    }
+
+    /// Set the edge to capture by the polarity bits of the channel, the other settings of the
+    /// channel are kept as the platform code sets.
+    fn set_edge(&self, edge: TimCaptureEdge) -> RetValue<()>
+    {
+        let bits = match edge
+        {
+            TimCaptureEdge::Rising => 0,
+            TimCaptureEdge::Falling => TIM_CCER_CC1P,
+            TimCaptureEdge::Both => TIM_CCER_CC1P | TIM_CCER_CC1NP,
+            TimCaptureEdge::Unknown => return Err(ErrValue::Param),
+        };
+
+        set_polarity(self.handle.as_ptr(), self.channel, bits);
+        Ok(())
+    }
+
+    /// Feed the capture `value` to the measurement, and capture the other edge next time. It
+    /// returns the period and the width when a new period has been measured.
+    fn feed_measure(&mut self, value: u32) -> Option<(u32, u32)>
+    {
+        let meter = self.meter.as_mut()?;
+        let handle = self.handle.as_ptr();
+
+        let (rising, top) = unsafe {
+            let tim = (*handle).Instance;
+            let ccer = read_reg(addr_of!((*tim).CCER)) >> (4 * u32::from(self.channel));
+            (ccer & TIM_CCER_CC1P == 0, read_reg(addr_of!((*tim).ARR)))
+        };
+
+        set_polarity(handle, self.channel, if rising { TIM_CCER_CC1P } else { 0 });
+        meter.feed(value, rising, top).then(|| (meter.period(), meter.width()))
+    }
+}
+
+/// Run `f` with the capture interrupt of the channel masked, so the interrupt never sees the
+/// measurement in the middle of a change.
+fn without_capture_irq<R>(
+    handle: *mut TIM_HandleTypeDef, channel: TimChannel, f: impl FnOnce() -> R,
+) -> R
+{
+    let bit = TIM_DIER_CC1IE << u32::from(channel);
+
+    unsafe {
+        let dier = addr_of_mut!((*(*handle).Instance).DIER);
+        let enabled = read_reg(dier) & bit;
+
+        write_reg(dier, read_reg(dier) & !bit);
+        let value = f();
+        write_reg(dier, read_reg(dier) | enabled);
+
+        value
+    }
+}
+
+fn set_polarity(handle: *mut TIM_HandleTypeDef, channel: TimChannel, bits: u32)
+{
+    let shift = 4 * u32::from(channel);
+
+    unsafe {
+        let ccer = addr_of_mut!((*(*handle).Instance).CCER);
+        let mask = (TIM_CCER_CC1P | TIM_CCER_CC1NP) << shift;
+        write_reg(ccer, (read_reg(ccer) & !mask) | (bits << shift));
+    }
+}
+
+impl Handle<TIM_HandleTypeDef> for TimCapture
+{
+    fn handle_value(&self) -> *mut TIM_HandleTypeDef
+    {
+        self.handle.as_ptr()
+    }
+
+    fn channel_value(&self) -> u32
+    {
+        self.channel.into()
+    }
+}
+
+impl EventLaunch<dyn TimCaptureCtrlEvent> for TimCapture
+{
+    fn set_event_agent(&mut self, event_handle: &'static dyn TimCaptureCtrlEvent)
+    {
+        self.event_handle = Some(event_handle);
+    }
+
+    fn clean_event_agent(&mut self)
+    {
+        self.event_handle = None;
+    }
+}
+
+impl TimCaptureCtrl for TimCapture
+{
+    fn channel(&self) -> TimChannel
+    {
+        self.channel
+    }
+
+    fn activate(&self, edge: TimCaptureEdge) -> RetValue<()>
+    {
+        self.set_edge(edge)?;
+        TimCaptureQueue::stop_measure(self.handle.as_ptr(), self.channel);
+        unsafe { HAL_TIM_IC_Start(self.handle.as_ptr(), hal_channel(self.channel)).into() }
+    }
+
+    fn deactivate(&self)
+    {
+        unsafe { HAL_TIM_IC_Stop(self.handle.as_ptr(), hal_channel(self.channel)) };
+    }
+
+    fn async_activate(&self, edge: TimCaptureEdge) -> RetValue<()>
+    {
+        self.set_edge(edge)?;
+        TimCaptureQueue::stop_measure(self.handle.as_ptr(), self.channel);
+        unsafe { HAL_TIM_IC_Start_IT(self.handle.as_ptr(), hal_channel(self.channel)).into() }
+    }
+
+    fn async_measure(&self) -> RetValue<()>
+    {
+        self.set_edge(TimCaptureEdge::Rising)?;
+        TimCaptureQueue::start_measure(self.handle.as_ptr(), self.channel)?;
+        unsafe { HAL_TIM_IC_Start_IT(self.handle.as_ptr(), hal_channel(self.channel)).into() }
+    }
+
+    fn async_deactivate(&self)
+    {
+        unsafe { HAL_TIM_IC_Stop_IT(self.handle.as_ptr(), hal_channel(self.channel)) };
+        TimCaptureQueue::stop_measure(self.handle.as_ptr(), self.channel);
+    }
+
+    fn captured_value(&self) -> u32
+    {
+        unsafe { HAL_TIM_ReadCapturedValue(self.handle.as_ptr(), hal_channel(self.channel)) }
+    }
+
+    fn count_freq(&self) -> u32
+    {
+        count_freq(self.handle.as_ptr())
+    }
+
+    fn frequency(&self) -> u32
+    {
+        TimCaptureQueue::meter(self.handle.as_ptr(), self.channel)
+            .map(|meter| meter.period())
+            .filter(|period| *period > 0)
+            .map_or(0, |period| count_freq(self.handle.as_ptr()) / period)
+    }
+
+    fn pulse_width(&self) -> u32
+    {
+        TimCaptureQueue::meter(self.handle.as_ptr(), self.channel).map_or(0, |meter| meter.width())
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// TIM Encoder Class
+/////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy)]
+pub struct TimEncoder
+{
+    handle: NonNull<TIM_HandleTypeDef>,
+}
+
+impl TimEncoder
+{
+    fn new(handle: *mut TIM_HandleTypeDef) -> RetValue<Self>
+    {
+        Ok(TimEncoder { handle: NonNull::new(handle).ok_or(ErrValue::Param)? })
+    }
+}
+
+impl Handle<TIM_HandleTypeDef> for TimEncoder
+{
+    fn handle_value(&self) -> *mut TIM_HandleTypeDef
+    {
+        self.handle.as_ptr()
+    }
+}
+
+impl TimEncoderCtrl for TimEncoder
+{
+    fn activate(&self) -> RetValue<()>
+    {
+        unsafe { HAL_TIM_Encoder_Start(self.handle.as_ptr(), TIM_CHANNEL_ALL).into() }
+    }
+
+    fn deactivate(&self)
+    {
+        unsafe { HAL_TIM_Encoder_Stop(self.handle.as_ptr(), TIM_CHANNEL_ALL) };
+    }
+
+    fn count_value(&self) -> u32
+    {
+        unsafe { read_reg(addr_of!((*self.handle.as_ref().Instance).CNT)) }
+    }
+
+    fn set_count_value(&self, value: u32)
+    {
+        unsafe { write_reg(addr_of_mut!((*self.handle.as_ref().Instance).CNT), value) };
+    }
+
+    fn direction(&self) -> TimDirection
+    {
+        match unsafe { read_reg(addr_of!((*self.handle.as_ref().Instance).CR1)) } & TIM_CR1_DIR
+        {
+            0 => TimDirection::Up,
+            _ => TimDirection::Down,
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// TIM Queue
+/////////////////////////////////////////////////////////////////////////////
+
+static mut TIM_BASE_QUEUE: SampleQueue<TimBase, TIM_HandleTypeDef, TIM_COUNT> = SampleQueue::new();
+static mut TIM_PWM_QUEUE: SampleQueue<TimPwm, TIM_HandleTypeDef, TIM_CHANNEL_COUNT> =
+    SampleQueue::new();
+static mut TIM_CAPTURE_QUEUE: SampleQueue<TimCapture, TIM_HandleTypeDef, TIM_CHANNEL_COUNT> =
+    SampleQueue::new();
+static mut TIM_ENCODER_QUEUE: SampleQueue<TimEncoder, TIM_HandleTypeDef, TIM_COUNT> =
+    SampleQueue::new();
+
+pub struct TimBaseQueue;
+
+impl TimBaseQueue
+{
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn alloc(sample_handle: *mut TIM_HandleTypeDef) -> RetValue<&'static mut TimBase>
+    {
+        unsafe { TIM_BASE_QUEUE.allocate(&TimBase::new(sample_handle)?) }
+    }
+
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn clean(sample_handle: *mut TIM_HandleTypeDef)
+    {
+        NonNull::new(sample_handle).inspect(|handle| unsafe { TIM_BASE_QUEUE.clean(*handle) });
+    }
+
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn search(sample_handle: *mut TIM_HandleTypeDef) -> RetValue<&'static TimBase>
+    {
+        unsafe { TIM_BASE_QUEUE.search(NonNull::new(sample_handle).ok_or(ErrValue::Param)?) }
+    }
+}
+
+pub struct TimPwmQueue;
+
+impl TimPwmQueue
+{
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn alloc(
+        sample_handle: *mut TIM_HandleTypeDef, channel: TimChannel,
+    ) -> RetValue<&'static mut TimPwm>
+    {
+        unsafe {
+            TIM_PWM_QUEUE.allocate_channel(&TimPwm::new(sample_handle, channel)?, channel.into())
+        }
+    }
+
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn clean(sample_handle: *mut TIM_HandleTypeDef, channel: TimChannel)
+    {
+        NonNull::new(sample_handle)
+            .inspect(|handle| unsafe { TIM_PWM_QUEUE.clean_channel(*handle, channel.into()) });
+    }
+
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn search(
+        sample_handle: *mut TIM_HandleTypeDef, channel: TimChannel,
+    ) -> RetValue<&'static TimPwm>
+    {
+        unsafe {
+            TIM_PWM_QUEUE
+                .search_channel(NonNull::new(sample_handle).ok_or(ErrValue::Param)?, channel.into())
+        }
+    }
+}
+
+pub struct TimCaptureQueue;
+
+impl TimCaptureQueue
+{
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn alloc(
+        sample_handle: *mut TIM_HandleTypeDef, channel: TimChannel,
+    ) -> RetValue<&'static mut TimCapture>
+    {
+        unsafe {
+            TIM_CAPTURE_QUEUE
+                .allocate_channel(&TimCapture::new(sample_handle, channel)?, channel.into())
+        }
+    }
+
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn clean(sample_handle: *mut TIM_HandleTypeDef, channel: TimChannel)
+    {
+        Self::stop_measure(sample_handle, channel);
+        NonNull::new(sample_handle)
+            .inspect(|handle| unsafe { TIM_CAPTURE_QUEUE.clean_channel(*handle, channel.into()) });
+    }
+
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn search(
+        sample_handle: *mut TIM_HandleTypeDef, channel: TimChannel,
+    ) -> RetValue<&'static TimCapture>
+    {
+        unsafe {
+            TIM_CAPTURE_QUEUE
+                .search_channel(NonNull::new(sample_handle).ok_or(ErrValue::Param)?, channel.into())
+        }
+    }
+
+    #[allow(static_mut_refs)]
+    fn search_mut(
+        sample_handle: *mut TIM_HandleTypeDef, channel: TimChannel,
+    ) -> RetValue<&'static mut TimCapture>
+    {
+        unsafe {
+            TIM_CAPTURE_QUEUE.search_mut_channel(
+                NonNull::new(sample_handle).ok_or(ErrValue::Param)?,
+                channel.into(),
+            )
+        }
+    }
+
+    fn start_measure(sample_handle: *mut TIM_HandleTypeDef, channel: TimChannel) -> RetValue<()>
+    {
+        let sample = Self::search_mut(sample_handle, channel)?;
+        without_capture_irq(sample_handle, channel, || sample.meter = Some(TimCaptureMeter::new()));
+        Ok(())
+    }
+
+    fn stop_measure(sample_handle: *mut TIM_HandleTypeDef, channel: TimChannel)
+    {
+        if let Ok(sample) = Self::search_mut(sample_handle, channel)
+        {
+            without_capture_irq(sample_handle, channel, || sample.meter = None);
+        }
+    }
+
+    fn meter(sample_handle: *mut TIM_HandleTypeDef, channel: TimChannel)
+        -> Option<TimCaptureMeter>
+    {
+        let sample = Self::search_mut(sample_handle, channel).ok()?;
+        without_capture_irq(sample_handle, channel, || sample.meter)
+    }
+}
+
+pub struct TimEncoderQueue;
+
+impl TimEncoderQueue
+{
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn alloc(sample_handle: *mut TIM_HandleTypeDef) -> RetValue<&'static mut TimEncoder>
+    {
+        unsafe { TIM_ENCODER_QUEUE.allocate(&TimEncoder::new(sample_handle)?) }
+    }
+
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn clean(sample_handle: *mut TIM_HandleTypeDef)
+    {
+        NonNull::new(sample_handle).inspect(|handle| unsafe { TIM_ENCODER_QUEUE.clean(*handle) });
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// HAL interrupt callback function implementations
+/////////////////////////////////////////////////////////////////////////////
+
+/// The period elapsed callback of the timers.
+///
+/// `HAL_TIM_PeriodElapsedCallback` is generated in `main.c` by STM32CubeMX when a timer is the
+/// time base of the HAL, so it should call this function in its user code section:
+///
+/// ```c
+/// /* USER CODE BEGIN Callback 1 */
+/// sces_tim_period_elapsed(htim);
+/// /* USER CODE END Callback 1 */
+/// ```
+#[no_mangle]
+#[allow(static_mut_refs)]
+pub unsafe extern "C" fn sces_tim_period_elapsed(tim: *mut TIM_HandleTypeDef)
+{
+    if let Ok(sample) = TimBaseQueue::search(tim)
+    {
+        sample.event_handle.inspect(|event_handle| event_handle.on_tim_base_elapse());
+    }
+}
+
+#[no_mangle]
+#[allow(static_mut_refs)]
+pub unsafe extern "C" fn HAL_TIM_PWM_PulseFinishedCallback(tim: *mut TIM_HandleTypeDef)
+{
+    if let Ok(sample) = TimPwmQueue::search(tim, active_channel(tim))
+    {
+        sample.event_handle.inspect(|event_handle| event_handle.on_tim_pwm_finish());
+    }
+}
+
+#[no_mangle]
+#[allow(static_mut_refs)]
+pub unsafe extern "C" fn HAL_TIM_IC_CaptureCallback(tim: *mut TIM_HandleTypeDef)
+{
+    let channel = active_channel(tim);
+
+    if let Ok(sample) = TimCaptureQueue::search_mut(tim, channel)
+    {
+        let value = HAL_TIM_ReadCapturedValue(tim, hal_channel(channel));
+        let measured = sample.feed_measure(value);
+
+        sample.event_handle.inspect(|event_handle| {
+            event_handle.on_tim_capture(value);
+            measured.inspect(|(period, width)| event_handle.on_tim_measure(*period, *width));
+        });
+    }
+}
+
+#[no_mangle]
+#[allow(static_mut_refs)]
+pub unsafe extern "C" fn HAL_TIM_ErrorCallback(tim: *mut TIM_HandleTypeDef)
+{
+    let channel = active_channel(tim);
+
+    if let Ok(sample) = TimBaseQueue::search(tim)
+    {
+        sample.event_handle.inspect(|event_handle| event_handle.on_tim_error());
+    }
+
+    if let Ok(sample) = TimPwmQueue::search(tim, channel)
+    {
+        sample.event_handle.inspect(|event_handle| event_handle.on_tim_error());
+    }
+
+    if let Ok(sample) = TimCaptureQueue::search(tim, channel)
+    {
+        sample.event_handle.inspect(|event_handle| event_handle.on_tim_error());
+    }
+}
@@ -3,10 +3,15 @@
 use core::ptr::NonNull;
 
 use sces::value::{ErrValue, RetValue};
-use sces::mcu::uart::{UartCtrl, UartCtrlEvent};
+use sces::mcu::io::{IoCtrl, IoState};
+use sces::mcu::uart::{UartConfig, UartCtrl, UartCtrlEvent, UartError};
+use sces::mcu::uart::{UartDataBits, UartDriverEnable, UartFlowControl, UartParity, UartStopBits};
 use sces::mcu::EventLaunch;
+use sces::os::tick::Duration;
 
 use crate::device::Handle;
+#[cfg(not(feature = "stm32h5"))]
+use crate::native::dma::{HAL_DMA_Init, DMA_CIRCULAR, DMA_NORMAL};
 use crate::native::uart::*;
 use crate::sample_queue::SampleQueue;
 use crate::UART_COUNT;
@@ -31,13 +36,51 @@ pub struct Uart
 {
     handle: NonNull<UART_HandleTypeDef>,
     event_handle: Option<&'static dyn UartCtrlEvent>,
+    driver_enable: Option<(&'static dyn IoCtrl, IoState)>,
 }
 
 impl Uart
 {
     fn new(handle: *mut UART_HandleTypeDef) -> RetValue<Self>
     {
-        Ok(Uart { handle: NonNull::new(handle).ok_or(ErrValue::Param)?, event_handle: None })
+        Ok(Uart {
+            handle: NonNull::new(handle).ok_or(ErrValue::Param)?,
+            event_handle: None,
+            driver_enable: None,
+        })
+    }
+
+    /// Drive the RS-485 driver enable pin, if it's driven by the software.
+    fn drive(&self, active: bool)
+    {
+        if let Some((pin, state)) = self.driver_enable
+        {
+            pin.set_state(match (active, state)
+            {
+                (true, _) => state,
+                (false, IoState::Set) => IoState::Reset,
+                (false, IoState::Reset) => IoState::Set,
+            });
+        }
+    }
+
+    /// Switch the RX DMA of STM32F4 between the normal and the circular mode, it does nothing if
+    /// the UART has no RX DMA, and the DMA reception will fail later.
+    #[cfg(not(feature = "stm32h5"))]
+    fn set_rx_dma_mode(&self, mode: u32) -> RetValue<()>
+    {
+        unsafe {
+            let hdma = (*self.handle.as_ptr()).hdmarx;
+            match hdma.is_null() || (*hdma).Init.Mode == mode
+            {
+                true => Ok(()),
+                false =>
+                {
+                    (*hdma).Init.Mode = mode;
+                    HAL_DMA_Init(hdma).into()
+                }
+            }
+        }
     }
 }
 
@@ -64,15 +107,24 @@ impl EventLaunch<dyn UartCtrlEvent> for Uart
 
 impl UartCtrl for Uart
 {
-    fn transmit(&self, data: &[u8], timeout: u32) -> RetValue<()>
+    fn transmit(&self, data: &[u8], timeout: Duration) -> RetValue<()>
     {
-        unsafe {
-            HAL_UART_Transmit(self.handle.as_ptr(), data.as_ptr(), data.len() as u16, timeout)
-                .into()
-        }
+        self.drive(true);
+        let value = unsafe {
+            HAL_UART_Transmit(
+                self.handle.as_ptr(),
+                data.as_ptr(),
+                data.len() as u16,
+                timeout.ticks(),
+            )
+            .into()
+        };
+        self.drive(false);
+
+        value
     }
 
-    fn receive(&self, data: &mut [u8], timeout: u32) -> RetValue<u32>
+    fn receive(&self, data: &mut [u8], timeout: Duration) -> RetValue<u32>
     {
         let mut size: u16 = 0;
         unsafe {
@@ -81,29 +133,41 @@ impl UartCtrl for Uart
                 data.as_ptr(),
                 data.len() as u16,
                 &mut size,
-                timeout,
+                timeout.ticks(),
             )
             .ok()?
         };
         Ok(size as u32)
     }
 
-    fn receive_size(&self, data: &mut [u8], timeout: u32) -> RetValue<()>
+    fn receive_size(&self, data: &mut [u8], timeout: Duration) -> RetValue<()>
     {
         unsafe {
-            HAL_UART_Receive(self.handle.as_ptr(), data.as_ptr(), data.len() as u16, timeout).into()
+            HAL_UART_Receive(
+                self.handle.as_ptr(),
+                data.as_ptr(),
+                data.len() as u16,
+                timeout.ticks(),
+            )
+            .into()
         }
     }
 
     fn async_transmit(&self, data: &[u8]) -> RetValue<()>
     {
-        unsafe {
+        self.drive(true);
+        let value: RetValue<()> = unsafe {
             HAL_UART_Transmit_DMA(self.handle.as_ptr(), data.as_ptr(), data.len() as u16).into()
-        }
+        };
+
+        value.inspect_err(|_| self.drive(false))
     }
 
     fn async_receive(&self, data: &mut [u8]) -> RetValue<()>
     {
+        #[cfg(not(feature = "stm32h5"))]
+        self.set_rx_dma_mode(DMA_NORMAL)?;
+
         unsafe {
             HAL_UARTEx_ReceiveToIdle_DMA(self.handle.as_ptr(), data.as_ptr(), data.len() as u16)
                 .into()
@@ -112,14 +176,119 @@ impl UartCtrl for Uart
 
     fn async_receive_size(&self, data: &mut [u8]) -> RetValue<()>
     {
+        #[cfg(not(feature = "stm32h5"))]
+        self.set_rx_dma_mode(DMA_NORMAL)?;
+
         unsafe {
             HAL_UART_Receive_DMA(self.handle.as_ptr(), data.as_ptr(), data.len() as u16).into()
         }
     }
 
+    /// The RX DMA of STM32F4 is switched to the circular mode here, the one of STM32H5 should be
+    /// configured as a circular linked list in the initialization code.
+    fn async_receive_circular(&self, data: &mut [u8]) -> RetValue<()>
+    {
+        let size = u16::try_from(data.len()).or(Err(ErrValue::Param))?;
+
+        #[cfg(not(feature = "stm32h5"))]
+        self.set_rx_dma_mode(DMA_CIRCULAR)?;
+
+        unsafe { HAL_UARTEx_ReceiveToIdle_DMA(self.handle.as_ptr(), data.as_ptr(), size).into() }
+    }
+
     fn abort(&self) -> RetValue<()>
     {
-        unsafe { HAL_UART_Abort(self.handle.as_ptr()).into() }
+        let value = unsafe { HAL_UART_Abort(self.handle.as_ptr()).into() };
+        self.drive(false);
+
+        value
+    }
+
+    /// The RS-485 driver enable of the peripheral is only supported by STM32H5, and the break
+    /// detection uses the LIN mode, which supports 8 bits frames with 1 stop bit only.
+    fn configure(&mut self, config: &UartConfig) -> RetValue<()>
+    {
+        let word = match (config.data_bits, config.parity)
+        {
+            (UartDataBits::Seven, UartParity::None) =>
+            {
+                UART_WORDLENGTH_7B.ok_or(ErrValue::NotSupport)?
+            }
+            (UartDataBits::Seven, _) | (UartDataBits::Eight, UartParity::None) =>
+            {
+                UART_WORDLENGTH_8B
+            }
+            (UartDataBits::Eight, _) | (UartDataBits::Nine, UartParity::None) => UART_WORDLENGTH_9B,
+            (UartDataBits::Nine, _) => return Err(ErrValue::NotSupport),
+        };
+
+        let stop = match config.stop_bits
+        {
+            UartStopBits::Half => UART_STOPBITS_0_5.ok_or(ErrValue::NotSupport)?,
+            UartStopBits::One => UART_STOPBITS_1,
+            UartStopBits::OneAndHalf => UART_STOPBITS_1_5.ok_or(ErrValue::NotSupport)?,
+            UartStopBits::Two => UART_STOPBITS_2,
+        };
+
+        if config.break_detect && (word != UART_WORDLENGTH_8B || stop != UART_STOPBITS_1)
+        {
+            return Err(ErrValue::NotSupport);
+        }
+
+        let handle = self.handle.as_ptr();
+        unsafe {
+            let init = &mut (*handle).Init;
+            init.BaudRate = config.baud_rate;
+            init.WordLength = word;
+            init.StopBits = stop;
+            init.Parity = match config.parity
+            {
+                UartParity::None => UART_PARITY_NONE,
+                UartParity::Even => UART_PARITY_EVEN,
+                UartParity::Odd => UART_PARITY_ODD,
+            };
+            init.HwFlowCtl = match config.flow_control
+            {
+                UartFlowControl::None => UART_HWCONTROL_NONE,
+                UartFlowControl::Rts => UART_HWCONTROL_RTS,
+                UartFlowControl::Cts => UART_HWCONTROL_CTS,
+                UartFlowControl::RtsCts => UART_HWCONTROL_RTS_CTS,
+            };
+        }
+
+        let status = unsafe {
+            match (config.driver_enable, config.break_detect)
+            {
+                #[cfg(feature = "stm32h5")]
+                (Some(UartDriverEnable::Native(timing)), false) =>
+                {
+                    let polarity = match timing.active
+                    {
+                        IoState::Set => UART_DE_POLARITY_HIGH,
+                        IoState::Reset => UART_DE_POLARITY_LOW,
+                    };
+                    let (assertion, deassertion) = (timing.assertion, timing.deassertion);
+                    HAL_RS485Ex_Init(handle, polarity, assertion as u32, deassertion as u32)
+                }
+                (Some(UartDriverEnable::Native(_)), _) => return Err(ErrValue::NotSupport),
+                (_, true) => HAL_LIN_Init(handle, UART_LINBREAKDETECTLENGTH_11B),
+                (_, false) => HAL_UART_Init(handle),
+            }
+        };
+
+        self.driver_enable = match config.driver_enable
+        {
+            Some(UartDriverEnable::Pin(pin, active)) => Some((pin, active)),
+            _ => None,
+        };
+        self.drive(false);
+
+        status.into()
+    }
+
+    fn send_break(&self) -> RetValue<()>
+    {
+        unsafe { HAL_LIN_SendBreak(self.handle.as_ptr()).into() }
     }
 }
 
@@ -165,6 +334,7 @@ pub unsafe extern "C" fn HAL_UART_TxCpltCallback(uart: *mut UART_HandleTypeDef)
 {
     if let Ok(sample) = UartQueue::search(uart)
     {
+        sample.drive(false);
         sample.event_handle.inspect(|event_handle| event_handle.on_uart_tx_complete());
     }
 }
@@ -191,7 +361,17 @@ pub unsafe extern "C" fn HAL_UART_ErrorCallback(uart: *mut UART_HandleTypeDef)
 {
     if let Ok(sample) = UartQueue::search(uart)
     {
-        sample.event_handle.inspect(|event_handle| event_handle.on_uart_error());
+        let code = HAL_UART_GetError(uart);
+        let error = UartError {
+            parity: code & HAL_UART_ERROR_PE != 0,
+            noise: code & HAL_UART_ERROR_NE != 0,
+            framing: code & HAL_UART_ERROR_FE != 0,
+            overrun: code & HAL_UART_ERROR_ORE != 0,
+            line_break: uart_take_break((*uart).Instance),
+            dma: code & HAL_UART_ERROR_DMA != 0,
+        };
+
+        sample.event_handle.inspect(|event_handle| event_handle.on_uart_error(error));
     }
 }
 
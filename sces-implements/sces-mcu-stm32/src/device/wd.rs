@@ -1,31 +1,64 @@
 use core::ptr::NonNull;
 
 use sces::value::{ErrValue, RetValue};
-use sces::mcu::wd::WatchDogCtrl;
+use sces::mcu::wd::{WatchDogCtrl, WatchDogCtrlEvent};
+use sces::mcu::EventLaunch;
+use sces::os::tick::Duration;
 
-use crate::{Handle, IWDG_COUNT, native::iwdg::*, sample_queue::SampleQueue};
+use crate::device::Handle;
+use crate::native::dbgmcu::*;
+use crate::native::iwdg::*;
+use crate::native::rcc::*;
+use crate::native::wwdg::*;
+use crate::sample_queue::SampleQueue;
+use crate::{IWDG_COUNT, WWDG_COUNT};
 
 pub use crate::native::iwdg::IWDG_HandleTypeDef;
+pub use crate::native::wwdg::WWDG_HandleTypeDef;
 
+/// Get the time of `counts` cycles of a clock with `freq`.
+fn cycles_to_duration(counts: u64, freq: u32) -> Duration
+{
+    Duration::from_millis((counts * 1000).div_ceil(freq.max(1) as u64).min(u32::MAX as u64) as u32)
+}
+
+/// Get the cycles of a clock with `freq` in `time`.
+fn duration_to_cycles(time: Duration, freq: u32) -> u64
+{
+    time.as_millis() as u64 * freq as u64 / 1000
+}
+
+/// Check whether the last reset is marked by `flag` in the reset flags of the RCC.
+fn is_reset_by(flag: u32) -> bool
+{
+    reset_flags().is_some_and(|flags| flags & flag != 0)
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// IWDG Class
+/////////////////////////////////////////////////////////////////////////////
+
+/// The independent watch dog, which is driven by the LSI.
+///
+/// The window and the early warning are only available on the chips with them, like STM32H5.
 #[derive(Clone, Copy)]
 pub struct WatchDog
 {
-    handle: *mut IWDG_HandleTypeDef,
+    handle: NonNull<IWDG_HandleTypeDef>,
+    event_handle: Option<&'static dyn WatchDogCtrlEvent>,
 }
 
 impl WatchDog
 {
-    pub fn new(handle: *mut IWDG_HandleTypeDef) -> Self
+    pub fn new(handle: *mut IWDG_HandleTypeDef) -> RetValue<Self>
     {
-        WatchDog { handle }
+        Ok(WatchDog { handle: NonNull::new(handle).ok_or(ErrValue::Param)?, event_handle: None })
     }
-}
 
-impl WatchDogCtrl for WatchDog
-{
-    fn refresh(&self)
+    /// Get the LSI cycles of one count.
+    fn count_cycles(&self) -> u64
     {
-        unsafe { HAL_IWDG_Refresh(self.handle) };
+        4 << unsafe { self.handle.as_ref().Init.Prescaler }.min(IWDG_PRESCALER_MAX)
     }
 }
 
@@ -33,10 +66,129 @@ impl Handle<IWDG_HandleTypeDef> for WatchDog
 {
     fn handle_value(&self) -> *mut IWDG_HandleTypeDef
     {
-        self.handle
+        self.handle.as_ptr()
+    }
+}
+
+impl EventLaunch<dyn WatchDogCtrlEvent> for WatchDog
+{
+    fn set_event_agent(&mut self, event_handle: &'static dyn WatchDogCtrlEvent)
+    {
+        self.event_handle = Some(event_handle);
+    }
+
+    fn clean_event_agent(&mut self)
+    {
+        self.event_handle = None;
+    }
+}
+
+impl WatchDogCtrl for WatchDog
+{
+    fn start(&self, timeout: Duration) -> RetValue<()>
+    {
+        let cycles = duration_to_cycles(timeout, IWDG_LSI_FREQ);
+        let counts = (IWDG_RELOAD_MAX + 1) as u64;
+        let prescaler = (0..=IWDG_PRESCALER_MAX)
+            .find(|x| (4 << x) * counts >= cycles)
+            .ok_or(ErrValue::Param)?;
+
+        #[cfg(feature = "stm32h5")]
+        let window = self.window();
+
+        let init = unsafe { &mut (*self.handle.as_ptr()).Init };
+        init.Prescaler = prescaler;
+        init.Reload = (cycles.div_ceil(4 << prescaler).max(1) - 1) as u32;
+
+        #[cfg(feature = "stm32h5")]
+        {
+            let window = duration_to_cycles(window, IWDG_LSI_FREQ).div_ceil(self.count_cycles());
+
+            init.Window = match window
+            {
+                0 => IWDG_WINDOW_DISABLE,
+                _ => init.Reload.saturating_sub(window as u32),
+            };
+
+            // Warn when a quarter of the timeout is left.
+            init.EWI = match self.event_handle
+            {
+                Some(_) => (init.Reload / 4).max(1),
+                None => IWDG_EWI_DISABLE,
+            };
+        }
+
+        unsafe { HAL_IWDG_Init(self.handle.as_ptr()).into() }
+    }
+
+    fn timeout(&self) -> Duration
+    {
+        let reload = unsafe { self.handle.as_ref().Init.Reload } as u64;
+        cycles_to_duration(self.count_cycles() * (reload + 1), IWDG_LSI_FREQ)
+    }
+
+    /// The window takes effect from the next [`WatchDogCtrl::start`].
+    #[cfg(feature = "stm32h5")]
+    fn set_window(&self, window: Duration) -> RetValue<()>
+    {
+        (window < self.timeout()).then_some(()).ok_or(ErrValue::Param)?;
+
+        let init = unsafe { &mut (*self.handle.as_ptr()).Init };
+        let window = duration_to_cycles(window, IWDG_LSI_FREQ).div_ceil(self.count_cycles());
+
+        init.Window = match window
+        {
+            0 => IWDG_WINDOW_DISABLE,
+            _ => init.Reload.saturating_sub(window as u32),
+        };
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "stm32h5"))]
+    fn set_window(&self, window: Duration) -> RetValue<()>
+    {
+        (window == Duration::ZERO).then_some(()).ok_or(ErrValue::NotSupport)
+    }
+
+    #[cfg(feature = "stm32h5")]
+    fn window(&self) -> Duration
+    {
+        let init = unsafe { &self.handle.as_ref().Init };
+
+        match init.Window
+        {
+            x if x >= init.Reload => Duration::ZERO,
+            x => cycles_to_duration(self.count_cycles() * (init.Reload - x) as u64, IWDG_LSI_FREQ),
+        }
+    }
+
+    #[cfg(not(feature = "stm32h5"))]
+    fn window(&self) -> Duration
+    {
+        Duration::ZERO
+    }
+
+    fn freeze_in_debug(&self, freeze: bool) -> RetValue<()>
+    {
+        freeze_apb1(DBGMCU_IWDG_STOP, freeze).then_some(()).ok_or(ErrValue::NotSupport)
+    }
+
+    fn is_reset_cause(&self) -> bool
+    {
+        is_reset_by(RCC_FLAG_IWDGRST)
+    }
+
+    fn refresh(&self)
+    {
+        unsafe { HAL_IWDG_Refresh(self.handle.as_ptr()) };
     }
 }
 
+/////////////////////////////////////////////////////////////////////////////
+// IWDG Queue
+/////////////////////////////////////////////////////////////////////////////
+
 static mut WD_QUEUE: SampleQueue<WatchDog, IWDG_HandleTypeDef, IWDG_COUNT> = SampleQueue::new();
 
 pub struct WatchDogQueue;
@@ -47,7 +199,7 @@ impl WatchDogQueue
     #[allow(static_mut_refs)]
     pub fn alloc(sample_handle: *mut IWDG_HandleTypeDef) -> RetValue<&'static mut WatchDog>
     {
-        unsafe { WD_QUEUE.allocate(&WatchDog::new(sample_handle)) }
+        unsafe { WD_QUEUE.allocate(&WatchDog::new(sample_handle)?) }
     }
 
     #[inline]
@@ -64,3 +216,202 @@ impl WatchDogQueue
         unsafe { WD_QUEUE.search(NonNull::new(sample_handle).ok_or(ErrValue::Param)?) }
     }
 }
+
+/////////////////////////////////////////////////////////////////////////////
+// WWDG Class
+/////////////////////////////////////////////////////////////////////////////
+
+/// The window watch dog, which is driven by the PCLK1 and warns one count before the reset.
+///
+/// The timeout is short, like 50 ms at most with a 45 MHz PCLK1 on STM32F4.
+#[derive(Clone, Copy)]
+pub struct WindowWatchDog
+{
+    handle: NonNull<WWDG_HandleTypeDef>,
+    event_handle: Option<&'static dyn WatchDogCtrlEvent>,
+}
+
+impl WindowWatchDog
+{
+    pub fn new(handle: *mut WWDG_HandleTypeDef) -> RetValue<Self>
+    {
+        Ok(WindowWatchDog {
+            handle: NonNull::new(handle).ok_or(ErrValue::Param)?,
+            event_handle: None,
+        })
+    }
+
+    /// Get the PCLK1 cycles of one count.
+    fn count_cycles(&self) -> u64
+    {
+        let prescaler = unsafe { self.handle.as_ref().Init.Prescaler } >> WWDG_PRESCALER_POS;
+        (WWDG_CLOCK_DIVIDER as u64) << prescaler.min(WWDG_PRESCALER_MAX)
+    }
+}
+
+impl Handle<WWDG_HandleTypeDef> for WindowWatchDog
+{
+    fn handle_value(&self) -> *mut WWDG_HandleTypeDef
+    {
+        self.handle.as_ptr()
+    }
+}
+
+impl EventLaunch<dyn WatchDogCtrlEvent> for WindowWatchDog
+{
+    fn set_event_agent(&mut self, event_handle: &'static dyn WatchDogCtrlEvent)
+    {
+        self.event_handle = Some(event_handle);
+    }
+
+    fn clean_event_agent(&mut self)
+    {
+        self.event_handle = None;
+    }
+}
+
+impl WatchDogCtrl for WindowWatchDog
+{
+    fn start(&self, timeout: Duration) -> RetValue<()>
+    {
+        let freq = unsafe { HAL_RCC_GetPCLK1Freq() };
+        let cycles = duration_to_cycles(timeout, freq);
+        let counts = (WWDG_COUNTER_MAX - WWDG_COUNTER_MIN + 1) as u64;
+        let prescaler = (0..=WWDG_PRESCALER_MAX)
+            .find(|x| ((WWDG_CLOCK_DIVIDER as u64) << x) * counts >= cycles)
+            .ok_or(ErrValue::Param)?;
+        let window = self.window();
+
+        let init = unsafe { &mut (*self.handle.as_ptr()).Init };
+        init.Prescaler = prescaler << WWDG_PRESCALER_POS;
+        init.Counter = WWDG_COUNTER_MIN - 1
+            + cycles.div_ceil((WWDG_CLOCK_DIVIDER as u64) << prescaler).clamp(1, counts) as u32;
+
+        let window = duration_to_cycles(window, freq).div_ceil(self.count_cycles());
+
+        init.Window = match window
+        {
+            0 => WWDG_WINDOW_DISABLE,
+            _ => init.Counter.saturating_sub(window as u32).max(WWDG_COUNTER_MIN),
+        };
+
+        init.EWIMode = match self.event_handle
+        {
+            Some(_) => WWDG_EWI_ENABLE,
+            None => WWDG_EWI_DISABLE,
+        };
+
+        unsafe { HAL_WWDG_Init(self.handle.as_ptr()).into() }
+    }
+
+    fn timeout(&self) -> Duration
+    {
+        let counter = unsafe { self.handle.as_ref().Init.Counter };
+        let counts = counter.saturating_sub(WWDG_COUNTER_MIN - 1) as u64;
+
+        cycles_to_duration(self.count_cycles() * counts, unsafe { HAL_RCC_GetPCLK1Freq() })
+    }
+
+    /// The window takes effect from the next [`WatchDogCtrl::start`].
+    fn set_window(&self, window: Duration) -> RetValue<()>
+    {
+        (window < self.timeout()).then_some(()).ok_or(ErrValue::Param)?;
+
+        let freq = unsafe { HAL_RCC_GetPCLK1Freq() };
+        let window = duration_to_cycles(window, freq).div_ceil(self.count_cycles());
+        let init = unsafe { &mut (*self.handle.as_ptr()).Init };
+
+        init.Window = match window
+        {
+            0 => WWDG_WINDOW_DISABLE,
+            _ => init.Counter.saturating_sub(window as u32).max(WWDG_COUNTER_MIN),
+        };
+
+        Ok(())
+    }
+
+    fn window(&self) -> Duration
+    {
+        let init = unsafe { &self.handle.as_ref().Init };
+        let freq = unsafe { HAL_RCC_GetPCLK1Freq() };
+
+        match init.Window
+        {
+            x if x >= init.Counter => Duration::ZERO,
+            x => cycles_to_duration(self.count_cycles() * (init.Counter - x) as u64, freq),
+        }
+    }
+
+    fn freeze_in_debug(&self, freeze: bool) -> RetValue<()>
+    {
+        freeze_apb1(DBGMCU_WWDG_STOP, freeze).then_some(()).ok_or(ErrValue::NotSupport)
+    }
+
+    fn is_reset_cause(&self) -> bool
+    {
+        is_reset_by(RCC_FLAG_WWDGRST)
+    }
+
+    fn refresh(&self)
+    {
+        unsafe { HAL_WWDG_Refresh(self.handle.as_ptr()) };
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// WWDG Queue
+/////////////////////////////////////////////////////////////////////////////
+
+static mut WWD_QUEUE: SampleQueue<WindowWatchDog, WWDG_HandleTypeDef, WWDG_COUNT> =
+    SampleQueue::new();
+
+pub struct WindowWatchDogQueue;
+
+impl WindowWatchDogQueue
+{
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn alloc(sample_handle: *mut WWDG_HandleTypeDef) -> RetValue<&'static mut WindowWatchDog>
+    {
+        unsafe { WWD_QUEUE.allocate(&WindowWatchDog::new(sample_handle)?) }
+    }
+
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn clean(sample_handle: *mut WWDG_HandleTypeDef)
+    {
+        NonNull::new(sample_handle).inspect(|handle| unsafe { WWD_QUEUE.clean(*handle) });
+    }
+
+    #[inline]
+    #[allow(static_mut_refs)]
+    pub fn search(sample_handle: *mut WWDG_HandleTypeDef) -> RetValue<&'static WindowWatchDog>
+    {
+        unsafe { WWD_QUEUE.search(NonNull::new(sample_handle).ok_or(ErrValue::Param)?) }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// HAL interrupt callback function implementations
+/////////////////////////////////////////////////////////////////////////////
+
+#[no_mangle]
+#[allow(static_mut_refs)]
+pub unsafe extern "C" fn HAL_WWDG_EarlyWakeupCallback(wwdg: *mut WWDG_HandleTypeDef)
+{
+    if let Ok(sample) = WindowWatchDogQueue::search(wwdg)
+    {
+        sample.event_handle.inspect(|event_handle| event_handle.on_watch_dog_early_warning());
+    }
+}
+
+#[cfg(feature = "stm32h5")]
+#[no_mangle]
+#[allow(static_mut_refs)]
+pub unsafe extern "C" fn HAL_IWDG_EarlyWakeupCallback(iwdg: *mut IWDG_HandleTypeDef)
+{
+    if let Ok(sample) = WatchDogQueue::search(iwdg)
+    {
+        sample.event_handle.inspect(|event_handle| event_handle.on_watch_dog_early_warning());
+    }
+}
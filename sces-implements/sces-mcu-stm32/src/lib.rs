@@ -4,7 +4,12 @@ mod device;
 mod native;
 mod sample_queue;
 
+use sces::mcu::power::WakeupSource;
 use sces::mcu::MCU;
+use sces::os::tick::Duration;
+use sces::value::{ErrValue, RetValue};
+
+use native::pwr::*;
 
 pub use device::*;
 
@@ -13,8 +18,11 @@ const CAN_COUNT: usize = 2;
 const I2C_COUNT: usize = 8;
 const IO_COUNT: usize = 32;
 const SPI_COUNT: usize = 8;
+const TIM_COUNT: usize = 16;
+const TIM_CHANNEL_COUNT: usize = 32;
 const UART_COUNT: usize = 12;
 const IWDG_COUNT: usize = 1;
+const WWDG_COUNT: usize = 1;
 
 pub struct STM32;
 
@@ -28,9 +36,12 @@ impl MCU for STM32
     type I2cSlave = device::i2c::I2cSlave;
     type Io = device::io::Io;
     type Spi = device::spi::Spi;
+    type System = device::sys::System;
+    type TimBase = device::tim::TimBase;
+    type TimCapture = device::tim::TimCapture;
+    type TimEncoder = device::tim::TimEncoder;
+    type TimPwm = device::tim::TimPwm;
     type Uart = device::uart::Uart;
-    // type TimBase = device::adc::Adc;
-    // type TImPwm = device::adc::Adc;
     type WatchDog = device::wd::WatchDog;
 
     fn tick_value() -> u32
@@ -38,8 +49,52 @@ impl MCU for STM32
         unsafe { native::HAL_GetTick() }
     }
 
-    fn sleep(time: u32)
+    fn sleep(time: Duration)
+    {
+        unsafe { native::HAL_Delay(time.ticks()) };
+    }
+
+    fn enter_sleep()
+    {
+        unsafe { HAL_PWR_EnterSLEEPMode(PWR_MAINREGULATOR_ON, PWR_SLEEPENTRY_WFI) };
+    }
+
+    /// `SystemClock_Config` waits for the oscillators and the PLL with the timeouts of
+    /// `HAL_GetTick`, so the tick interrupt must be enabled.
+    fn enter_stop() -> RetValue<()>
+    {
+        unsafe {
+            HAL_PWR_EnterSTOPMode(PWR_LOWPOWERREGULATOR_ON, PWR_STOPENTRY_WFI);
+            SystemClock_Config();
+        }
+
+        Ok(())
+    }
+
+    fn enter_standby() -> RetValue<()>
+    {
+        clean_wakeup_flags();
+        unsafe { HAL_PWR_EnterSTANDBYMode() };
+        Err(ErrValue::LowLevelFailure)
+    }
+
+    /// Only the wakeup pins are supported, the RTC is not managed by this crate.
+    fn set_wakeup(source: WakeupSource, enable: bool) -> RetValue<()>
     {
-        unsafe { native::HAL_Delay(time) };
+        let pin = match source
+        {
+            WakeupSource::Pin(num) => wakeup_pin(num).ok_or(ErrValue::Param)?,
+            WakeupSource::Rtc => return Err(ErrValue::NotSupport),
+        };
+
+        unsafe {
+            match enable
+            {
+                true => HAL_PWR_EnableWakeUpPin(pin),
+                false => HAL_PWR_DisableWakeUpPin(pin),
+            }
+        }
+
+        Ok(())
     }
 }
@@ -4,13 +4,19 @@
 
 pub mod adc;
 pub mod can;
+pub mod dbgmcu;
 pub mod dma;
 pub mod flash;
 pub mod i2c;
 pub mod io;
 pub mod iwdg;
+pub mod pwr;
+pub mod rcc;
 pub mod spi;
+pub mod sys;
+pub mod tim;
 pub mod uart;
+pub mod wwdg;
 
 use sces::value::{ErrValue, RetValue};
 
@@ -0,0 +1,31 @@
+#![allow(dead_code)]
+
+//! The registers of the DBGMCU which are only wrapped by the macros of the HAL.
+
+use core::ptr::{read_volatile, write_volatile};
+
+/// `DBGMCU_APB1FZ` of STM32F4.
+#[cfg(feature = "stm32f4")]
+const DBGMCU_APB1_FREEZE: Option<usize> = Some(0xE004_2000 + 0x08);
+
+/// `DBGMCU_APB1LFZR` of STM32H5.
+#[cfg(feature = "stm32h5")]
+const DBGMCU_APB1_FREEZE: Option<usize> = Some(0x4402_4000 + 0x08);
+
+#[cfg(not(any(feature = "stm32f4", feature = "stm32h5")))]
+const DBGMCU_APB1_FREEZE: Option<usize> = None;
+
+pub const DBGMCU_WWDG_STOP: u32 = 1 << 11;
+pub const DBGMCU_IWDG_STOP: u32 = 1 << 12;
+
+/// Freeze or unfreeze the peripherals of `bits` on the APB1 when the CPU is halted, it returns
+/// `false` if the chip is unknown.
+pub fn freeze_apb1(bits: u32, freeze: bool) -> bool
+{
+    DBGMCU_APB1_FREEZE
+        .inspect(|address| unsafe {
+            let value = read_volatile(*address as *const u32);
+            write_volatile(*address as *mut u32, if freeze { value | bits } else { value & !bits });
+        })
+        .is_some()
+}
@@ -4,7 +4,11 @@
 
 use core::ffi::c_void;
 
-use super::HAL_LockTypeDef;
+use super::{HAL_LockTypeDef, HAL_StatusTypeDef};
+
+/// `DMA_InitTypeDef.Mode` of STM32F4, the stream goes back to the start of the buffer at the end.
+pub const DMA_NORMAL: u32 = 0x0000;
+pub const DMA_CIRCULAR: u32 = 0x0100;
 
 #[repr(C)]
 pub struct DMA_HandleTypeDef
@@ -55,3 +59,9 @@ pub enum HAL_DMA_StateTypeDef
     HAL_DMA_STATE_ERROR = 0x04,
     HAL_DMA_STATE_ABORT = 0x05,
 }
+
+#[rustfmt::skip]
+#[allow(improper_ctypes)]
+unsafe extern "C" {
+    pub fn HAL_DMA_Init(hdma: *mut DMA_HandleTypeDef) -> HAL_StatusTypeDef;
+}
@@ -4,13 +4,79 @@
 
 use super::HAL_StatusTypeDef;
 
+/// The base address of the on chip flash.
+pub const FLASH_BASE: u32 = 0x0800_0000;
+
+/// The sizes of the sectors from the start of a bank of STM32F4, the rest of them are 128 KB.
+#[cfg(not(feature = "stm32h5"))]
+pub const FLASH_SECTOR_SIZES: &[u32] = &[0x4000, 0x4000, 0x4000, 0x4000, 0x10000, 0x20000];
+#[cfg(feature = "stm32h5")]
+pub const FLASH_SECTOR_SIZES: &[u32] = &[0x2000];
+
+/// The option register which is in effect, `FLASH_OPTCR` of STM32F4 or `FLASH_OPTSR_CUR` of
+/// STM32H5, and the one to program, which is the same one on STM32F4.
+#[cfg(feature = "stm32f4")]
+pub const FLASH_OPTION_CURRENT: Option<usize> = Some(0x4002_3C14);
+#[cfg(feature = "stm32h5")]
+pub const FLASH_OPTION_CURRENT: Option<usize> = Some(0x4002_2050);
+#[cfg(not(any(feature = "stm32f4", feature = "stm32h5")))]
+pub const FLASH_OPTION_CURRENT: Option<usize> = None;
+
+#[cfg(feature = "stm32f4")]
+pub const FLASH_OPTION_PROGRAM: Option<usize> = Some(0x4002_3C14);
+#[cfg(feature = "stm32h5")]
+pub const FLASH_OPTION_PROGRAM: Option<usize> = Some(0x4002_2054);
+#[cfg(not(any(feature = "stm32f4", feature = "stm32h5")))]
+pub const FLASH_OPTION_PROGRAM: Option<usize> = None;
+
+/// `OPTLOCK` and `OPTSTRT` of `FLASH_OPTCR`, they are not option bytes.
+#[cfg(not(feature = "stm32h5"))]
+pub const FLASH_OPTION_CONTROL: u32 = 0x0000_0003;
+#[cfg(feature = "stm32h5")]
+pub const FLASH_OPTION_CONTROL: u32 = 0x0000_0000;
+
+/// `RDP` of STM32F4 or `PRODUCT_STATE` of STM32H5 in the option register.
+pub const FLASH_OPTION_RDP_SHIFT: u32 = 8;
+pub const FLASH_OPTION_RDP_MASK: u32 = 0xFF << FLASH_OPTION_RDP_SHIFT;
+
+pub const OB_RDP_LEVEL_0: u8 = 0xAA;
+pub const OB_RDP_LEVEL_1: u8 = 0x55;
+pub const OB_RDP_LEVEL_2: u8 = 0xCC;
+
+/// `PRODUCT_STATE` of STM32H5, the states between them are the ones of the provisioning.
+pub const OB_PROD_STATE_OPEN: u8 = 0xED;
+pub const OB_PROD_STATE_LOCKED: u8 = 0x5C;
+
+/// `FLASH_ECCCORR` and `FLASH_ECCDETR` of STM32H5, the address is the number of the 128 bits
+/// flash word in the bank.
+#[cfg(feature = "stm32h5")]
+pub const FLASH_ECC_REGISTERS: Option<(usize, usize)> = Some((0x4002_2100, 0x4002_2104));
+#[cfg(not(feature = "stm32h5"))]
+pub const FLASH_ECC_REGISTERS: Option<(usize, usize)> = None;
+
+pub const FLASH_ECC_ADDRESS: u32 = 0x0000_FFFF;
+pub const FLASH_ECC_BANK: u32 = 1 << 22;
+pub const FLASH_ECC_CORRECTED: u32 = 1 << 30;
+pub const FLASH_ECC_DETECTED: u32 = 1 << 31;
+pub const FLASH_ECC_WORD: u32 = 16;
+
 pub const FLASH_TYPEPROGRAM_BYTE: u32 = 0x00000000;
 pub const FLASH_TYPEPROGRAM_HALFWORD: u32 = 0x00000001;
 pub const FLASH_TYPEPROGRAM_WORD: u32 = 0x00000002;
 pub const FLASH_TYPEPROGRAM_DOUBLEWORD: u32 = 0x00000003;
 
+/// The 128 bits flash word of STM32H5, which is programmed with its ECC.
+pub const FLASH_TYPEPROGRAM_QUADWORD: u32 = 0x00000002;
+
+#[cfg(not(feature = "stm32h5"))]
 pub const FLASH_TYPEERASE_SECTORS: u32 = 0x00000000;
+#[cfg(not(feature = "stm32h5"))]
 pub const FLASH_TYPEERASE_MASSERASE: u32 = 0x00000001;
+#[cfg(feature = "stm32h5")]
+pub const FLASH_TYPEERASE_SECTORS: u32 = 0x00000004;
+
+pub const FLASH_BANK_1: u32 = 0x00000001;
+pub const FLASH_BANK_2: u32 = 0x00000002;
 
 pub const FLASH_VOLTAGE_RANGE1: u32 = 0x0000_0000;
 pub const FLASH_VOLTAGE_RANGE2: u32 = 0x0000_0001;
@@ -20,6 +86,7 @@ pub const FLASH_VOLTAGE_RANGE4: u32 = 0x0000_0003;
 pub const OB_WRPSTATE_DISABLE: u32 = 0x00000000;
 pub const OB_WRPSTATE_ENABLE: u32 = 0x00000001;
 
+#[cfg(not(feature = "stm32h5"))]
 #[repr(C)]
 #[derive(Default)]
 pub struct FLASH_EraseInitTypeDef
@@ -31,6 +98,18 @@ pub struct FLASH_EraseInitTypeDef
     pub VoltageRange: u32,
 }
 
+#[cfg(feature = "stm32h5")]
+#[repr(C)]
+#[derive(Default)]
+pub struct FLASH_EraseInitTypeDef
+{
+    pub TypeErase: u32,
+    pub Banks: u32,
+    pub Sector: u32,
+    pub NbSectors: u32,
+}
+
+#[cfg(not(feature = "stm32h5"))]
 #[repr(C)]
 pub struct FLASH_OBProgramInitTypeDef
 {
@@ -43,11 +122,27 @@ pub struct FLASH_OBProgramInitTypeDef
     USERConfig: u8,
 }
 
+/// Get the number of the banks of a flash with `size` bytes, the chips with 2 MB of flash of
+/// STM32F4 have 2 banks, and STM32H5 always has 2 banks.
+pub fn flash_banks(size: u32) -> u32
+{
+    match size
+    {
+        _ if cfg!(feature = "stm32h5") => 2,
+        0x20_0000 => 2,
+        _ => 1,
+    }
+}
+
 #[rustfmt::skip]
 #[allow(improper_ctypes)]
 extern "C" {
+    #[cfg(not(feature = "stm32h5"))]
     pub fn HAL_FLASH_Program(TypeProgram: u32, Address: u32, Data: u64) -> HAL_StatusTypeDef;
+    #[cfg(not(feature = "stm32h5"))]
     pub fn HAL_FLASH_Program_IT(TypeProgram: u32, Address: u32, Data: u64) -> HAL_StatusTypeDef;
+    #[cfg(feature = "stm32h5")]
+    pub fn HAL_FLASH_Program(TypeProgram: u32, FlashAddress: u32, DataAddress: u32) -> HAL_StatusTypeDef;
     pub fn HAL_FLASH_Unlock() -> HAL_StatusTypeDef;
     pub fn HAL_FLASH_Lock() -> HAL_StatusTypeDef;
     pub fn HAL_FLASH_OB_Unlock() -> HAL_StatusTypeDef;
@@ -55,6 +150,8 @@ extern "C" {
     pub fn HAL_FLASH_OB_Launch() -> HAL_StatusTypeDef;
     pub fn HAL_FLASHEx_Erase(pEraseInit: *mut FLASH_EraseInitTypeDef, SectorError: *mut u32) -> HAL_StatusTypeDef;
     pub fn HAL_FLASHEx_Erase_IT(pEraseInit: *mut FLASH_EraseInitTypeDef) -> HAL_StatusTypeDef;
+    #[cfg(not(feature = "stm32h5"))]
     pub fn HAL_FLASHEx_OBProgram(pOBInit: *mut FLASH_OBProgramInitTypeDef) -> HAL_StatusTypeDef;
+    #[cfg(not(feature = "stm32h5"))]
     pub fn HAL_FLASHEx_OBGetConfig(pOBInit: *mut FLASH_OBProgramInitTypeDef);
 }
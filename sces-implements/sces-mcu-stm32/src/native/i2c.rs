@@ -5,6 +5,29 @@
 use super::dma::DMA_HandleTypeDef;
 use super::{HAL_LockTypeDef, HAL_StatusTypeDef};
 
+/// The options of the sequential transfers, the first frame ends without a stop, and the last
+/// one starts with a repeated start and ends with a stop. A frame with `I2C_LAST_FRAME_NO_STOP`
+/// ends without a stop as well, so the frame after it in the other direction starts with a
+/// repeated start.
+#[cfg(not(feature = "stm32h5"))]
+pub const I2C_FIRST_FRAME: u32 = 0x0000_0001;
+#[cfg(not(feature = "stm32h5"))]
+pub const I2C_LAST_FRAME_NO_STOP: u32 = 0x0000_0010;
+#[cfg(not(feature = "stm32h5"))]
+pub const I2C_LAST_FRAME: u32 = 0x0000_0020;
+#[cfg(feature = "stm32h5")]
+pub const I2C_FIRST_FRAME: u32 = 0x0000_0000;
+#[cfg(feature = "stm32h5")]
+pub const I2C_LAST_FRAME_NO_STOP: u32 = 0x0000_0000;
+#[cfg(feature = "stm32h5")]
+pub const I2C_LAST_FRAME: u32 = 0x0200_0000;
+
+pub const HAL_I2C_ERROR_NONE: u32 = 0x0000_0000;
+pub const HAL_I2C_ERROR_BERR: u32 = 0x0000_0001;
+pub const HAL_I2C_ERROR_ARLO: u32 = 0x0000_0002;
+pub const HAL_I2C_ERROR_AF: u32 = 0x0000_0004;
+pub const HAL_I2C_ERROR_OVR: u32 = 0x0000_0008;
+
 #[repr(C)]
 pub struct I2C_HandleTypeDef
 {
@@ -71,6 +94,10 @@ pub enum HAL_I2C_ModeTypeDef
 #[rustfmt::skip]
 #[allow(improper_ctypes)]
 extern "C" {
+    pub fn HAL_I2C_Init(hi2c: *mut I2C_HandleTypeDef) -> HAL_StatusTypeDef;
+    pub fn HAL_I2C_DeInit(hi2c: *mut I2C_HandleTypeDef) -> HAL_StatusTypeDef;
+    pub fn HAL_I2C_GetState(hi2c: *mut I2C_HandleTypeDef) -> HAL_I2C_StateTypeDef;
+    pub fn HAL_I2C_GetError(hi2c: *mut I2C_HandleTypeDef) -> u32;
     pub fn HAL_I2C_Master_Transmit(hi2c: *mut I2C_HandleTypeDef, DevAddr: u16, pData: *const u8, Size: u16, Timeout: u32) -> HAL_StatusTypeDef;
     pub fn HAL_I2C_Master_Receive(hi2c: *mut I2C_HandleTypeDef, DevAddr: u16, pData: *const u8, Size: u16, Timeout: u32) -> HAL_StatusTypeDef;
     pub fn HAL_I2C_Slave_Transmit(hi2c: *mut I2C_HandleTypeDef, pData: *const u8, Size: u16, Timeout: u32) -> HAL_StatusTypeDef;
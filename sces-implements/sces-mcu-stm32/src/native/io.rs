@@ -2,13 +2,40 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
+use core::ptr::{read_volatile, write_volatile};
+
 use sces::mcu::io::IoState;
 
 use super::HAL_StatusTypeDef;
 
+/// The offsets of `GPIOx_MODER` and `GPIOx_OTYPER`, which are the same on all the series.
+const GPIO_MODER: usize = 0x00;
+const GPIO_OTYPER: usize = 0x04;
+const GPIO_MODE_OUTPUT: u32 = 0b01;
+const GPIO_MODE_MASK: u32 = 0b11;
+
 #[repr(C)]
 pub struct GPIO_TypeDef {}
 
+/// Switch the pins `pins` of `port` to the open drain outputs by the registers, their pulls and
+/// speeds are kept.
+pub fn set_open_drain_output(port: *mut GPIO_TypeDef, pins: u16)
+{
+    let moder = (port as usize + GPIO_MODER) as *mut u32;
+    let otyper = (port as usize + GPIO_OTYPER) as *mut u32;
+
+    unsafe {
+        let mut mode = read_volatile(moder);
+        for pin in (0..16).filter(|x| pins & (1 << x) != 0)
+        {
+            mode = mode & !(GPIO_MODE_MASK << (pin * 2)) | GPIO_MODE_OUTPUT << (pin * 2);
+        }
+
+        write_volatile(otyper, read_volatile(otyper) | pins as u32);
+        write_volatile(moder, mode);
+    }
+}
+
 #[repr(C)]
 pub enum GPIO_PinState
 {
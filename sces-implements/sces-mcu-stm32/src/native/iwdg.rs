@@ -4,6 +4,24 @@
 
 use super::HAL_StatusTypeDef;
 
+/// The frequency of the LSI which drives the IWDG.
+pub const IWDG_LSI_FREQ: u32 = 32_000;
+
+pub const IWDG_RELOAD_MAX: u32 = 0x0FFF;
+
+/// The prescaler value `n` divides the LSI by `4 << n`.
+#[cfg(not(feature = "stm32h5"))]
+pub const IWDG_PRESCALER_MAX: u32 = 6;
+#[cfg(feature = "stm32h5")]
+pub const IWDG_PRESCALER_MAX: u32 = 8;
+
+/// The window value which disables the window.
+#[cfg(feature = "stm32h5")]
+pub const IWDG_WINDOW_DISABLE: u32 = 0x0FFF;
+
+#[cfg(feature = "stm32h5")]
+pub const IWDG_EWI_DISABLE: u32 = 0x0000;
+
 #[repr(C)]
 pub struct IWDG_HandleTypeDef
 {
@@ -21,12 +39,17 @@ pub struct IWDG_TypeDef {}
 #[allow(non_snake_case)]
 pub struct IWDG_InitTypeDef
 {
-    Prescaler: u32,
-    Reload: u32,
+    pub Prescaler: u32,
+    pub Reload: u32,
+    #[cfg(feature = "stm32h5")]
+    pub Window: u32,
+    #[cfg(feature = "stm32h5")]
+    pub EWI: u32,
 }
 
 #[rustfmt::skip]
 #[allow(improper_ctypes)]
 extern "C" {
+    pub fn HAL_IWDG_Init(iwdg: *mut IWDG_HandleTypeDef) -> HAL_StatusTypeDef;
     pub fn HAL_IWDG_Refresh(iwdg: *mut IWDG_HandleTypeDef) -> HAL_StatusTypeDef;
 }
@@ -0,0 +1,72 @@
+#![allow(dead_code)]
+
+//! The low power modes of the PWR, and the registers which are only wrapped by the macros of the
+//! HAL.
+
+use core::ptr::{read_volatile, write_volatile};
+
+pub const PWR_MAINREGULATOR_ON: u32 = 0x00;
+
+/// STM32H5 ignores the regulator of the stop mode, it's set by `PWR_PMCR`.
+#[cfg(not(feature = "stm32h5"))]
+pub const PWR_LOWPOWERREGULATOR_ON: u32 = 0x01;
+#[cfg(feature = "stm32h5")]
+pub const PWR_LOWPOWERREGULATOR_ON: u32 = PWR_MAINREGULATOR_ON;
+
+pub const PWR_SLEEPENTRY_WFI: u8 = 0x01;
+pub const PWR_STOPENTRY_WFI: u8 = 0x01;
+
+/// `PWR_CR` of STM32F4, the wakeup flag is cleaned by writing `CWUF`.
+#[cfg(feature = "stm32f4")]
+const PWR_WAKEUP_CLEAR: Option<(usize, u32)> = Some((0x4000_7000, 1 << 2));
+
+/// `PWR_WUSCR` of STM32H5, the wakeup flags are cleaned by writing `CWUF1` to `CWUF8`.
+#[cfg(feature = "stm32h5")]
+const PWR_WAKEUP_CLEAR: Option<(usize, u32)> = Some((0x4402_0800 + 0x40, 0xFF));
+
+#[cfg(not(any(feature = "stm32f4", feature = "stm32h5")))]
+const PWR_WAKEUP_CLEAR: Option<(usize, u32)> = None;
+
+/// Get the bit of the wakeup pin `num` for `HAL_PWR_EnableWakeUpPin`, it's `None` if the pin
+/// doesn't exist.
+pub fn wakeup_pin(num: u8) -> Option<u32>
+{
+    #[cfg(feature = "stm32f4")]
+    {
+        (num == 1).then_some(1 << 8)
+    }
+
+    #[cfg(feature = "stm32h5")]
+    {
+        (1..=8).contains(&num).then(|| 1 << (num - 1))
+    }
+
+    #[cfg(not(any(feature = "stm32f4", feature = "stm32h5")))]
+    {
+        let _ = num;
+        None
+    }
+}
+
+/// Clean the wakeup flags, otherwise the MCU wakes up at once from the standby mode.
+pub fn clean_wakeup_flags()
+{
+    if let Some((address, bits)) = PWR_WAKEUP_CLEAR
+    {
+        unsafe { write_volatile(address as *mut u32, read_volatile(address as *const u32) | bits) };
+    }
+}
+
+#[rustfmt::skip]
+#[allow(improper_ctypes)]
+extern "C" {
+    pub fn HAL_PWR_EnterSLEEPMode(Regulator: u32, SLEEPEntry: u8);
+    pub fn HAL_PWR_EnterSTOPMode(Regulator: u32, STOPEntry: u8);
+    pub fn HAL_PWR_EnterSTANDBYMode();
+    pub fn HAL_PWR_EnableWakeUpPin(WakeUpPinx: u32);
+    pub fn HAL_PWR_DisableWakeUpPin(WakeUpPinx: u32);
+
+    /// The clock configuration generated by STM32CubeMX in `main.c`, the stop mode leaves the
+    /// clocks to the internal oscillator, so it's called again after waking up.
+    pub fn SystemClock_Config();
+}
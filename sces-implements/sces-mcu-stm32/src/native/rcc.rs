@@ -0,0 +1,69 @@
+#![allow(dead_code)]
+
+//! The registers of the RCC which are not wrapped by the HAL functions, and the functions of it.
+
+use core::ptr::{read_volatile, write_volatile};
+
+/// `RCC_CSR` of STM32F4.
+#[cfg(feature = "stm32f4")]
+const RCC_RESET_FLAGS: Option<usize> = Some(0x4002_3800 + 0x74);
+
+/// `RCC_RSR` of STM32H5.
+#[cfg(feature = "stm32h5")]
+const RCC_RESET_FLAGS: Option<usize> = Some(0x4402_0C00 + 0xF0);
+
+#[cfg(not(any(feature = "stm32f4", feature = "stm32h5")))]
+const RCC_RESET_FLAGS: Option<usize> = None;
+
+#[cfg(not(feature = "stm32h5"))]
+pub const RCC_FLAG_RMVF: u32 = 1 << 24;
+#[cfg(feature = "stm32h5")]
+pub const RCC_FLAG_RMVF: u32 = 1 << 23;
+
+#[cfg(not(feature = "stm32h5"))]
+pub const RCC_FLAG_BORRST: u32 = 1 << 25;
+#[cfg(feature = "stm32h5")]
+pub const RCC_FLAG_BORRST: u32 = 1 << 27;
+
+/// STM32H5 has no power on flag, a power on is flagged as a brown-out.
+#[cfg(not(feature = "stm32h5"))]
+pub const RCC_FLAG_PORRST: u32 = 1 << 27;
+#[cfg(feature = "stm32h5")]
+pub const RCC_FLAG_PORRST: u32 = 0;
+
+pub const RCC_FLAG_PINRST: u32 = 1 << 26;
+pub const RCC_FLAG_SFTRST: u32 = 1 << 28;
+pub const RCC_FLAG_IWDGRST: u32 = 1 << 29;
+pub const RCC_FLAG_WWDGRST: u32 = 1 << 30;
+pub const RCC_FLAG_LPWRRST: u32 = 1 << 31;
+
+/// Read the reset flags, they are kept until they are removed by [`remove_reset_flags`].
+pub fn reset_flags() -> Option<u32>
+{
+    RCC_RESET_FLAGS.map(|address| unsafe { read_volatile(address as *const u32) })
+}
+
+pub fn remove_reset_flags()
+{
+    if let Some(address) = RCC_RESET_FLAGS
+    {
+        unsafe {
+            write_volatile(
+                address as *mut u32,
+                read_volatile(address as *const u32) | RCC_FLAG_RMVF,
+            )
+        };
+    }
+}
+
+#[rustfmt::skip]
+#[allow(improper_ctypes)]
+extern "C" {
+    pub fn HAL_RCC_DeInit() -> super::HAL_StatusTypeDef;
+    pub fn HAL_RCC_GetSysClockFreq() -> u32;
+    pub fn HAL_RCC_GetHCLKFreq() -> u32;
+    pub fn HAL_RCC_GetPCLK1Freq() -> u32;
+    pub fn HAL_RCC_GetPCLK2Freq() -> u32;
+    #[cfg(feature = "stm32h5")]
+    pub fn HAL_RCC_GetPCLK3Freq() -> u32;
+}
@@ -5,8 +5,54 @@
 use core::ffi::c_void;
 
 use super::dma::DMA_HandleTypeDef;
+use super::rcc::{HAL_RCC_GetPCLK1Freq, HAL_RCC_GetPCLK2Freq};
 use super::{HAL_LockTypeDef, HAL_StatusTypeDef};
 
+/// `SPI_InitTypeDef.CLKPolarity`, `CLKPhase` and `FirstBit`, they are in `SPI_CR1` of STM32F4
+/// and in `SPI_CFG2` of STM32H5.
+#[cfg(not(feature = "stm32h5"))]
+pub const SPI_POLARITY_HIGH: u32 = 1 << 1;
+#[cfg(not(feature = "stm32h5"))]
+pub const SPI_PHASE_2EDGE: u32 = 1 << 0;
+#[cfg(not(feature = "stm32h5"))]
+pub const SPI_FIRSTBIT_LSB: u32 = 1 << 7;
+#[cfg(feature = "stm32h5")]
+pub const SPI_POLARITY_HIGH: u32 = 1 << 25;
+#[cfg(feature = "stm32h5")]
+pub const SPI_PHASE_2EDGE: u32 = 1 << 24;
+#[cfg(feature = "stm32h5")]
+pub const SPI_FIRSTBIT_LSB: u32 = 1 << 23;
+
+pub const SPI_POLARITY_LOW: u32 = 0x0000;
+pub const SPI_PHASE_1EDGE: u32 = 0x0000;
+pub const SPI_FIRSTBIT_MSB: u32 = 0x0000;
+
+/// The shift of `SPI_InitTypeDef.BaudRatePrescaler`, the value `n` divides the clock by
+/// `2 ^ (n + 1)`.
+#[cfg(not(feature = "stm32h5"))]
+pub const SPI_BAUDRATEPRESCALER_SHIFT: u32 = 3;
+#[cfg(feature = "stm32h5")]
+pub const SPI_BAUDRATEPRESCALER_SHIFT: u32 = 28;
+pub const SPI_BAUDRATEPRESCALER_MAX: u32 = 7;
+
+/// Get the clock of the SPI `instance` before the prescaler.
+///
+/// The SPIs are clocked by their buses, the ones on the APB1 are below `0x4001_0000`, and the ones
+/// on the APB3 of STM32H5 are from `0x4400_0000`. The kernel clock of the SPIs of STM32H5 should
+/// be set to their buses in the initialization code.
+pub fn spi_clock(instance: *mut SPI_TypeDef) -> u32
+{
+    unsafe {
+        match (instance as usize >> 16) & 0x0FFF
+        {
+            0x0001 => HAL_RCC_GetPCLK2Freq(),
+            #[cfg(feature = "stm32h5")]
+            0x0400 => super::rcc::HAL_RCC_GetPCLK3Freq(),
+            _ => HAL_RCC_GetPCLK1Freq(),
+        }
+    }
+}
+
 #[repr(C)]
 pub struct SPI_HandleTypeDef
 {
@@ -62,6 +108,7 @@ pub enum HAL_SPI_StateTypeDef
 #[rustfmt::skip]
 #[allow(improper_ctypes)]
 extern "C" {
+    pub fn HAL_SPI_Init(hspi: *mut SPI_HandleTypeDef) -> HAL_StatusTypeDef;
     pub fn HAL_SPI_Transmit(hspi: *mut SPI_HandleTypeDef, pData: *const u8, Size: u16, Timeout: u32) -> HAL_StatusTypeDef;
     pub fn HAL_SPI_Receive(hspi: *mut SPI_HandleTypeDef, pData: *const u8, Size: u16, Timeout: u32) -> HAL_StatusTypeDef;
     pub fn HAL_SPI_TransmitReceive(hspi: *mut SPI_HandleTypeDef, pTxData: *const u8, pRxData: *const u8, Size: u16, Timeout: u32) -> HAL_StatusTypeDef;
@@ -0,0 +1,110 @@
+#![allow(dead_code)]
+
+//! The information blocks of the chip and the functions of the Cortex-M core.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use super::HAL_StatusTypeDef;
+
+/// The 96 bits unique device ID.
+#[cfg(feature = "stm32f4")]
+pub const UID_BASE: Option<usize> = Some(0x1FFF_7A10);
+#[cfg(feature = "stm32h5")]
+pub const UID_BASE: Option<usize> = Some(0x08FF_F800);
+#[cfg(not(any(feature = "stm32f4", feature = "stm32h5")))]
+pub const UID_BASE: Option<usize> = None;
+
+pub const UID_SIZE: usize = 12;
+
+/// The size of the flash in KB, it's a 16 bits value.
+#[cfg(feature = "stm32f4")]
+pub const FLASHSIZE_BASE: Option<usize> = Some(0x1FFF_7A22);
+#[cfg(feature = "stm32h5")]
+pub const FLASHSIZE_BASE: Option<usize> = Some(0x08FF_F80C);
+#[cfg(not(any(feature = "stm32f4", feature = "stm32h5")))]
+pub const FLASHSIZE_BASE: Option<usize> = None;
+
+/// The system memory which holds the bootloader, and the way to map it to the address `0`.
+#[cfg(feature = "stm32f4")]
+pub const BOOTLOADER_BASE: Option<usize> = Some(0x1FFF_0000);
+#[cfg(not(feature = "stm32f4"))]
+pub const BOOTLOADER_BASE: Option<usize> = None;
+
+/// `RCC_APB2ENR.SYSCFGEN` and `SYSCFG_MEMRMP.MEM_MODE` of STM32F4.
+const RCC_APB2ENR: usize = 0x4002_3800 + 0x44;
+const RCC_APB2ENR_SYSCFGEN: u32 = 1 << 14;
+const SYSCFG_MEMRMP: usize = 0x4001_3800;
+const SYSCFG_MEMRMP_SYSTEM_FLASH: u32 = 0x01;
+
+const SYST_CSR: usize = 0xE000_E010;
+const NVIC_ICER: usize = 0xE000_E180;
+const NVIC_ICPR: usize = 0xE000_E280;
+const NVIC_REGISTERS: usize = 8;
+const SCB_VTOR: usize = 0xE000_ED08;
+
+#[rustfmt::skip]
+#[allow(improper_ctypes)]
+extern "C" {
+    pub fn HAL_DeInit() -> HAL_StatusTypeDef;
+    pub fn HAL_NVIC_SystemReset();
+}
+
+/// Map the system memory to the address `0`, the bootloader of STM32F4 expects it.
+pub fn remap_system_memory()
+{
+    #[cfg(feature = "stm32f4")]
+    unsafe {
+        write_volatile(
+            RCC_APB2ENR as *mut u32,
+            read_volatile(RCC_APB2ENR as *const u32) | RCC_APB2ENR_SYSCFGEN,
+        );
+        write_volatile(SYSCFG_MEMRMP as *mut u32, SYSCFG_MEMRMP_SYSTEM_FLASH);
+    }
+}
+
+/// Stop the SysTick and clean all interrupts of the NVIC, so nothing of the application runs
+/// after the jump.
+pub fn quiet_core()
+{
+    unsafe {
+        write_volatile(SYST_CSR as *mut u32, 0);
+
+        for i in 0..NVIC_REGISTERS
+        {
+            write_volatile((NVIC_ICER as *mut u32).add(i), u32::MAX);
+            write_volatile((NVIC_ICPR as *mut u32).add(i), u32::MAX);
+        }
+    }
+}
+
+/// Move the vector table to `address`, which is aligned to the size of the table.
+pub fn relocate_vectors(address: usize)
+{
+    unsafe { write_volatile(SCB_VTOR as *mut u32, address as u32) };
+}
+
+/// Jump to the image whose vector table is at `address`, with its stack pointer and its reset
+/// handler, the main stack is used since then.
+pub unsafe fn jump_to_image(address: usize) -> !
+{
+    let stack = read_volatile(address as *const u32);
+    let entry = read_volatile((address + 4) as *const u32);
+
+    #[cfg(target_arch = "arm")]
+    core::arch::asm!(
+        "msr control, {zero}",
+        "isb",
+        "msr msp, {stack}",
+        "bx {entry}",
+        zero = in(reg) 0u32,
+        stack = in(reg) stack,
+        entry = in(reg) entry,
+        options(noreturn),
+    );
+
+    #[cfg(not(target_arch = "arm"))]
+    {
+        let _ = (stack, entry);
+        unreachable!()
+    }
+}
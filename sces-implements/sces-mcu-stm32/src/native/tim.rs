@@ -0,0 +1,199 @@
+//! The RUST translation of data struct and function declerations of TIM in STM32 HAL libraries.
+
+#![allow(dead_code)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use super::dma::DMA_HandleTypeDef;
+use super::rcc::{HAL_RCC_GetHCLKFreq, HAL_RCC_GetPCLK1Freq, HAL_RCC_GetPCLK2Freq};
+use super::{HAL_LockTypeDef, HAL_StatusTypeDef};
+
+pub const TIM_CHANNEL_1: u32 = 0x00;
+pub const TIM_CHANNEL_2: u32 = 0x04;
+pub const TIM_CHANNEL_3: u32 = 0x08;
+pub const TIM_CHANNEL_4: u32 = 0x0C;
+pub const TIM_CHANNEL_ALL: u32 = 0x3C;
+
+/// The biggest periods of the timers with a 16 bits counter and the ones with a 32 bits counter,
+/// the prescaler of all timers is 16 bits.
+const TIM_PERIOD_16B: u32 = 0xFFFF;
+const TIM_PERIOD_32B: u32 = 0xFFFF_FFFF;
+
+/// STM32H5 maps the peripherals at `0x4000_0000` and the secure alias at `0x5000_0000`, the
+/// timers are found by the address without the alias bit.
+const TIM_ALIAS_MASK: usize = !0x1000_0000;
+
+/// `TIMx_CR1.DIR`, the counter counts down.
+pub const TIM_CR1_DIR: u32 = 1 << 4;
+
+/// `TIMx_CCER.CCxP` and `TIMx_CCER.CCxNP` of the channel 1, the ones of the channel `x` are
+/// shifted by `4 * (x - 1)`.
+pub const TIM_CCER_CC1P: u32 = 1 << 1;
+pub const TIM_CCER_CC1NP: u32 = 1 << 3;
+
+/// `TIMx_DIER.CC1IE`, the capture interrupt of the channel 1, the one of the channel `x` is
+/// shifted by `x - 1`.
+pub const TIM_DIER_CC1IE: u32 = 1 << 1;
+
+#[cfg(not(feature = "stm32h5"))]
+const TIM_CHANNEL_STATES: usize = 4;
+#[cfg(feature = "stm32h5")]
+const TIM_CHANNEL_STATES: usize = 6;
+
+#[repr(C)]
+pub struct TIM_HandleTypeDef
+{
+    pub Instance: *mut TIM_TypeDef,
+    pub Init: TIM_Base_InitTypeDef,
+    pub Channel: u32,
+    pub hdma: [*mut DMA_HandleTypeDef; 7],
+    pub Lock: HAL_LockTypeDef,
+    pub State: u32,
+    pub ChannelState: [u32; TIM_CHANNEL_STATES],
+    pub ChannelNState: [u32; 4],
+    pub DMABurstState: u32,
+}
+
+/// The registers which are the same in all timers of STM32F4 and STM32H5.
+#[repr(C)]
+pub struct TIM_TypeDef
+{
+    pub CR1: u32,
+    pub CR2: u32,
+    pub SMCR: u32,
+    pub DIER: u32,
+    pub SR: u32,
+    pub EGR: u32,
+    pub CCMR1: u32,
+    pub CCMR2: u32,
+    pub CCER: u32,
+    pub CNT: u32,
+    pub PSC: u32,
+    pub ARR: u32,
+    pub RCR: u32,
+    pub CCR: [u32; 4],
+    pub BDTR: u32,
+}
+
+#[repr(C)]
+pub struct TIM_Base_InitTypeDef
+{
+    pub Prescaler: u32,
+    pub CounterMode: u32,
+    pub Period: u32,
+    pub ClockDivision: u32,
+    pub RepetitionCounter: u32,
+    pub AutoReloadPreload: u32,
+}
+
+/// The bus which gives the clock of a timer.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TimBus
+{
+    Apb1,
+    Apb2,
+}
+
+/// The base address of a timer, with its bus and its biggest period.
+struct TimInstance(usize, TimBus, u32);
+
+#[cfg(not(feature = "stm32h5"))]
+#[rustfmt::skip]
+const TIM_INSTANCES: [TimInstance; 14] = [
+    TimInstance(0x4001_0000, TimBus::Apb2, TIM_PERIOD_16B), // TIM1
+    TimInstance(0x4000_0000, TimBus::Apb1, TIM_PERIOD_32B), // TIM2
+    TimInstance(0x4000_0400, TimBus::Apb1, TIM_PERIOD_16B), // TIM3
+    TimInstance(0x4000_0800, TimBus::Apb1, TIM_PERIOD_16B), // TIM4
+    TimInstance(0x4000_0C00, TimBus::Apb1, TIM_PERIOD_32B), // TIM5
+    TimInstance(0x4000_1000, TimBus::Apb1, TIM_PERIOD_16B), // TIM6
+    TimInstance(0x4000_1400, TimBus::Apb1, TIM_PERIOD_16B), // TIM7
+    TimInstance(0x4001_0400, TimBus::Apb2, TIM_PERIOD_16B), // TIM8
+    TimInstance(0x4001_4000, TimBus::Apb2, TIM_PERIOD_16B), // TIM9
+    TimInstance(0x4001_4400, TimBus::Apb2, TIM_PERIOD_16B), // TIM10
+    TimInstance(0x4001_4800, TimBus::Apb2, TIM_PERIOD_16B), // TIM11
+    TimInstance(0x4000_1800, TimBus::Apb1, TIM_PERIOD_16B), // TIM12
+    TimInstance(0x4000_1C00, TimBus::Apb1, TIM_PERIOD_16B), // TIM13
+    TimInstance(0x4000_2000, TimBus::Apb1, TIM_PERIOD_16B), // TIM14
+];
+
+#[cfg(feature = "stm32h5")]
+#[rustfmt::skip]
+const TIM_INSTANCES: [TimInstance; 14] = [
+    TimInstance(0x4001_2C00, TimBus::Apb2, TIM_PERIOD_16B), // TIM1
+    TimInstance(0x4000_0000, TimBus::Apb1, TIM_PERIOD_32B), // TIM2
+    TimInstance(0x4000_0400, TimBus::Apb1, TIM_PERIOD_32B), // TIM3
+    TimInstance(0x4000_0800, TimBus::Apb1, TIM_PERIOD_32B), // TIM4
+    TimInstance(0x4000_0C00, TimBus::Apb1, TIM_PERIOD_32B), // TIM5
+    TimInstance(0x4000_1000, TimBus::Apb1, TIM_PERIOD_16B), // TIM6
+    TimInstance(0x4000_1400, TimBus::Apb1, TIM_PERIOD_16B), // TIM7
+    TimInstance(0x4001_3400, TimBus::Apb2, TIM_PERIOD_16B), // TIM8
+    TimInstance(0x4000_1800, TimBus::Apb1, TIM_PERIOD_16B), // TIM12
+    TimInstance(0x4000_1C00, TimBus::Apb1, TIM_PERIOD_16B), // TIM13
+    TimInstance(0x4000_2000, TimBus::Apb1, TIM_PERIOD_16B), // TIM14
+    TimInstance(0x4001_4000, TimBus::Apb2, TIM_PERIOD_16B), // TIM15
+    TimInstance(0x4001_4400, TimBus::Apb2, TIM_PERIOD_16B), // TIM16
+    TimInstance(0x4001_4800, TimBus::Apb2, TIM_PERIOD_16B), // TIM17
+];
+
+/// Find the timer `instance` in the table of the series.
+fn tim_instance(instance: *mut TIM_TypeDef) -> Option<&'static TimInstance>
+{
+    TIM_INSTANCES.iter().find(|x| x.0 == instance as usize & TIM_ALIAS_MASK)
+}
+
+/// Get the biggest period of the timer `instance`, a timer out of the table of the series is
+/// taken as a 16 bits one.
+pub fn tim_max_period(instance: *mut TIM_TypeDef) -> u32
+{
+    tim_instance(instance).map_or(TIM_PERIOD_16B, |x| x.2)
+}
+
+/// Get the clock of the counter before the prescaler of the timer `instance`.
+///
+/// The bus of the timer is found in the table of the series, a timer out of it is taken as one on
+/// the APB1. The clock of a timer is twice of its bus if the bus is divided from the AHB.
+pub fn tim_clock(instance: *mut TIM_TypeDef) -> u32
+{
+    let bus = unsafe {
+        match tim_instance(instance).map(|x| x.1)
+        {
+            Some(TimBus::Apb2) => HAL_RCC_GetPCLK2Freq(),
+            _ => HAL_RCC_GetPCLK1Freq(),
+        }
+    };
+
+    match bus == unsafe { HAL_RCC_GetHCLKFreq() }
+    {
+        true => bus,
+        false => bus * 2,
+    }
+}
+
+#[rustfmt::skip]
+#[allow(improper_ctypes)]
+unsafe extern "C" {
+    pub fn HAL_TIM_Base_Start(htim: *mut TIM_HandleTypeDef) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_Base_Stop(htim: *mut TIM_HandleTypeDef) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_Base_Start_IT(htim: *mut TIM_HandleTypeDef) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_Base_Stop_IT(htim: *mut TIM_HandleTypeDef) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_Base_Start_DMA(htim: *mut TIM_HandleTypeDef, pData: *const u32, Length: u16) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_Base_Stop_DMA(htim: *mut TIM_HandleTypeDef) -> HAL_StatusTypeDef;
+
+    pub fn HAL_TIM_PWM_Start(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_PWM_Stop(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_PWM_Start_IT(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_PWM_Stop_IT(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_PWM_Start_DMA(htim: *mut TIM_HandleTypeDef, Channel: u32, pData: *const u32, Length: u16) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_PWM_Stop_DMA(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_TIMEx_PWMN_Start(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_TIMEx_PWMN_Stop(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+
+    pub fn HAL_TIM_IC_Start(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_IC_Stop(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_IC_Start_IT(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_IC_Stop_IT(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_ReadCapturedValue(htim: *const TIM_HandleTypeDef, Channel: u32) -> u32;
+
+    pub fn HAL_TIM_Encoder_Start(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_TIM_Encoder_Stop(htim: *mut TIM_HandleTypeDef, Channel: u32) -> HAL_StatusTypeDef;
+}
@@ -4,9 +4,60 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
+use core::ptr::{read_volatile, write_volatile};
+
 use super::dma::DMA_HandleTypeDef;
 use super::{HAL_LockTypeDef, HAL_StatusTypeDef};
 
+/// `UART_InitTypeDef.WordLength`, the length includes the parity bit, STM32F4 has no 7 bits
+/// frame.
+#[cfg(feature = "stm32h5")]
+pub const UART_WORDLENGTH_7B: Option<u32> = Some(1 << 28);
+#[cfg(not(feature = "stm32h5"))]
+pub const UART_WORDLENGTH_7B: Option<u32> = None;
+pub const UART_WORDLENGTH_8B: u32 = 0x0000;
+pub const UART_WORDLENGTH_9B: u32 = 1 << 12;
+
+pub const UART_PARITY_NONE: u32 = 0x0000;
+pub const UART_PARITY_EVEN: u32 = 1 << 10;
+pub const UART_PARITY_ODD: u32 = (1 << 10) | (1 << 9);
+
+/// `UART_InitTypeDef.StopBits`, the HAL of STM32F4 only supports 1 and 2 stop bits in the UART
+/// mode.
+#[cfg(feature = "stm32h5")]
+pub const UART_STOPBITS_0_5: Option<u32> = Some(1 << 12);
+#[cfg(not(feature = "stm32h5"))]
+pub const UART_STOPBITS_0_5: Option<u32> = None;
+pub const UART_STOPBITS_1: u32 = 0x0000;
+#[cfg(feature = "stm32h5")]
+pub const UART_STOPBITS_1_5: Option<u32> = Some(3 << 12);
+#[cfg(not(feature = "stm32h5"))]
+pub const UART_STOPBITS_1_5: Option<u32> = None;
+pub const UART_STOPBITS_2: u32 = 1 << 13;
+
+pub const UART_HWCONTROL_NONE: u32 = 0x0000;
+pub const UART_HWCONTROL_RTS: u32 = 1 << 8;
+pub const UART_HWCONTROL_CTS: u32 = 1 << 9;
+pub const UART_HWCONTROL_RTS_CTS: u32 = (1 << 8) | (1 << 9);
+
+pub const UART_DE_POLARITY_HIGH: u32 = 0x0000;
+pub const UART_DE_POLARITY_LOW: u32 = 1 << 15;
+
+pub const UART_LINBREAKDETECTLENGTH_11B: u32 = 1 << 5;
+
+pub const HAL_UART_ERROR_PE: u32 = 0x01;
+pub const HAL_UART_ERROR_NE: u32 = 0x02;
+pub const HAL_UART_ERROR_FE: u32 = 0x04;
+pub const HAL_UART_ERROR_ORE: u32 = 0x08;
+pub const HAL_UART_ERROR_DMA: u32 = 0x10;
+
+/// The offset of the register which has the LIN break flag `LBD`, the offset of the register to
+/// clean it, and the bit of them. `USART_SR.LBD` of STM32F4 is cleaned by writing `0`.
+#[cfg(not(feature = "stm32h5"))]
+const UART_LIN_BREAK: (usize, usize, u32) = (0x00, 0x00, 1 << 8);
+#[cfg(feature = "stm32h5")]
+const UART_LIN_BREAK: (usize, usize, u32) = (0x1C, 0x20, 1 << 8);
+
 #[repr(C)]
 pub struct UART_HandleTypeDef
 {
@@ -60,9 +111,34 @@ pub enum HAL_UART_StateTypeDef
     HAL_UART_STATE_ERROR = 0xE0,
 }
 
+/// Take the LIN break flag of the UART `instance`, which is set only in the LIN mode.
+pub fn uart_take_break(instance: *mut USART_TypeDef) -> bool
+{
+    let (flag, clean, bit) = UART_LIN_BREAK;
+    let flag = (instance as usize + flag) as *mut u32;
+    let clean = (instance as usize + clean) as *mut u32;
+
+    unsafe {
+        match read_volatile(flag) & bit != 0
+        {
+            true if cfg!(feature = "stm32h5") => write_volatile(clean, bit),
+            true => write_volatile(clean, !bit),
+            false => return false,
+        }
+    }
+
+    true
+}
+
 #[rustfmt::skip]
 #[allow(improper_ctypes)]
 unsafe extern "C" {
+    pub fn HAL_UART_Init(uart: *mut UART_HandleTypeDef) -> HAL_StatusTypeDef;
+    pub fn HAL_LIN_Init(uart: *mut UART_HandleTypeDef, BreakDetectLength: u32) -> HAL_StatusTypeDef;
+    #[cfg(feature = "stm32h5")]
+    pub fn HAL_RS485Ex_Init(uart: *mut UART_HandleTypeDef, Polarity: u32, AssertionTime: u32, DeassertionTime: u32) -> HAL_StatusTypeDef;
+    pub fn HAL_LIN_SendBreak(uart: *mut UART_HandleTypeDef) -> HAL_StatusTypeDef;
+    pub fn HAL_UART_GetError(uart: *const UART_HandleTypeDef) -> u32;
     pub fn HAL_UART_Transmit(uart: *mut UART_HandleTypeDef, pData: *const u8, Size: u16, Timeout: u32) -> HAL_StatusTypeDef;
     pub fn HAL_UART_Receive(uart: *mut UART_HandleTypeDef, pData: *const u8, Size: u16, Timeout: u32) -> HAL_StatusTypeDef;
     pub fn HAL_UART_Transmit_IT(uart: *mut UART_HandleTypeDef, pData: *const u8, Size: u16) -> HAL_StatusTypeDef;
@@ -0,0 +1,55 @@
+#![allow(dead_code)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+use super::HAL_StatusTypeDef;
+
+/// The WWDG counts down once every `4096 << prescaler` cycles of the PCLK1.
+pub const WWDG_CLOCK_DIVIDER: u32 = 4096;
+
+/// The counter resets the system when it goes from `0x40` to `0x3F`.
+pub const WWDG_COUNTER_MIN: u32 = 0x40;
+pub const WWDG_COUNTER_MAX: u32 = 0x7F;
+
+/// The window value which disables the window.
+pub const WWDG_WINDOW_DISABLE: u32 = 0x7F;
+
+pub const WWDG_EWI_DISABLE: u32 = 0x0000_0000;
+pub const WWDG_EWI_ENABLE: u32 = 0x0000_0200;
+
+/// The prescaler value `n << WWDG_PRESCALER_POS` divides the clock by `1 << n`.
+#[cfg(not(feature = "stm32h5"))]
+pub const WWDG_PRESCALER_POS: u32 = 7;
+#[cfg(feature = "stm32h5")]
+pub const WWDG_PRESCALER_POS: u32 = 11;
+
+#[cfg(not(feature = "stm32h5"))]
+pub const WWDG_PRESCALER_MAX: u32 = 3;
+#[cfg(feature = "stm32h5")]
+pub const WWDG_PRESCALER_MAX: u32 = 7;
+
+#[repr(C)]
+pub struct WWDG_HandleTypeDef
+{
+    pub Instance: *mut WWDG_TypeDef,
+    pub Init: WWDG_InitTypeDef,
+}
+
+#[repr(C)]
+pub struct WWDG_TypeDef {}
+
+#[repr(C)]
+pub struct WWDG_InitTypeDef
+{
+    pub Prescaler: u32,
+    pub Window: u32,
+    pub Counter: u32,
+    pub EWIMode: u32,
+}
+
+#[rustfmt::skip]
+#[allow(improper_ctypes)]
+extern "C" {
+    pub fn HAL_WWDG_Init(wwdg: *mut WWDG_HandleTypeDef) -> HAL_StatusTypeDef;
+    pub fn HAL_WWDG_Refresh(wwdg: *mut WWDG_HandleTypeDef) -> HAL_StatusTypeDef;
+}
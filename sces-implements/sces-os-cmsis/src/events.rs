@@ -2,6 +2,7 @@ use core::{ops::Not, ptr::null};
 
 use sces::value::{ErrValue, RetValue};
 use sces::os::events::IEvents;
+use sces::os::tick::Duration;
 
 use crate::native::*;
 
@@ -44,9 +45,10 @@ impl IEvents for Events
         Ok(())
     }
 
-    fn wait(&self, events: u32, timeout: u32) -> RetValue<u32>
+    fn wait(&self, events: u32, timeout: Duration) -> RetValue<u32>
     {
-        let event_state = unsafe { osEventFlagsWait(self.handle, events, osFlagsWaitAny, timeout) };
+        let event_state =
+            unsafe { osEventFlagsWait(self.handle, events, osFlagsWaitAny, timeout.ticks()) };
 
         if event_state & osFlagsError != 0
         {
@@ -0,0 +1,26 @@
+//! The idle hook of FreeRTOS, which runs the one installed by [`sces::os::idle::install`].
+//!
+//! It's called by `configPRE_SLEEP_PROCESSING` when `configUSE_TICKLESS_IDLE` is enabled, the
+//! SysTick has been set to wake the MCU up at the next timer deadline by then. The SysTick counts
+//! 24 bits at the core clock, so the idle time is at most about 99 ticks of 1 ms at 168 MHz even
+//! when no timer is waiting:
+//!
+//! ```c
+//! extern uint32_t sces_cmsis_idle(uint32_t expected);
+//! #define configPRE_SLEEP_PROCESSING(x) ((x) = sces_cmsis_idle(x))
+//! ```
+
+use sces::os::idle;
+use sces::os::tick::Duration;
+
+/// Run the idle hook for `expected` ticks, it returns `0` if the MCU has slept, so FreeRTOS skips
+/// its own `WFI`, otherwise it returns `expected`.
+#[no_mangle]
+pub extern "C" fn sces_cmsis_idle(expected: u32) -> u32
+{
+    match idle::idle(Duration::from_ticks(expected))
+    {
+        true => 0,
+        false => expected,
+    }
+}
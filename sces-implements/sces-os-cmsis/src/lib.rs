@@ -6,6 +6,7 @@
 mod native;
 
 pub mod events;
+pub mod idle;
 pub mod mem;
 pub mod message_queue;
 pub mod mutex;
@@ -15,6 +16,7 @@ pub mod task;
 pub mod timer;
 
 use crate::native::*;
+use sces::os::tick::{Duration, Instant};
 use sces::{os::RTOS, value::RetValue};
 
 pub const COMMON_TASK_TICK: u32 = 500;
@@ -43,15 +45,15 @@ impl RTOS for CMSISOS
     }
 
     #[inline]
-    fn delay(time: u32)
+    fn delay(time: Duration)
     {
-        unsafe { osDelay(time) };
+        unsafe { osDelay(time.ticks()) };
     }
 
     #[inline]
-    fn delay_interval(time: u32)
+    fn delay_interval(time: Instant)
     {
-        unsafe { osDelayUntil(time) };
+        unsafe { osDelayUntil(time.ticks()) };
     }
 
     #[inline]
@@ -72,7 +74,21 @@ impl RTOS for CMSISOS
 
     fn current_task() -> Self::Task
     {
-        todo!()
+        task::Task::from(unsafe { osThreadGetId() })
+    }
+
+    #[inline]
+    fn is_in_isr() -> bool
+    {
+        #[cfg(target_arch = "arm")]
+        {
+            let ipsr: u32;
+            unsafe { core::arch::asm!("mrs {}, IPSR", out(reg) ipsr) };
+            ipsr & 0x1FF != 0
+        }
+
+        #[cfg(not(target_arch = "arm"))]
+        false
     }
 
     fn switch_next_task()
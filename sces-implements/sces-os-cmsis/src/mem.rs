@@ -53,7 +53,7 @@ impl IMemPool for MemPool
 
     fn alloc(&self) -> *mut u8
     {
-        unsafe { osMemoryPoolAlloc(self.handle, CMSISOS::WAIT_200) as *mut u8 }
+        unsafe { osMemoryPoolAlloc(self.handle, CMSISOS::WAIT_200.ticks()) as *mut u8 }
     }
 
     fn free(&self, mem: *mut u8)
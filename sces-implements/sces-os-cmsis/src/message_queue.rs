@@ -3,6 +3,7 @@ use core::ptr::null;
 
 use sces::value::{ErrValue, RetValue};
 use sces::os::message_queue::{IMessageQueue, MessageContent};
+use sces::os::tick::Duration;
 
 use crate::native::*;
 
@@ -28,14 +29,16 @@ impl IMessageQueue for MessageQueue
         Ok(MessageQueue { handle })
     }
 
-    fn send(&self, content: &dyn MessageContent, timeout: u32) -> RetValue<()>
+    fn send(&self, content: &dyn MessageContent, timeout: Duration) -> RetValue<()>
     {
-        unsafe { osMessageQueuePut(self.handle, content.as_ptr(), 0, timeout).into() }
+        unsafe { osMessageQueuePut(self.handle, content.as_ptr(), 0, timeout.ticks()).into() }
     }
 
-    fn receive(&self, cache: &mut dyn MessageContent, timeout: u32) -> RetValue<()>
+    fn receive(&self, cache: &mut dyn MessageContent, timeout: Duration) -> RetValue<()>
     {
         let mut prio: u8 = 0;
-        unsafe { osMessageQueueGet(self.handle, cache.as_mut_ptr(), &mut prio, timeout).into() }
+        unsafe {
+            osMessageQueueGet(self.handle, cache.as_mut_ptr(), &mut prio, timeout.ticks()).into()
+        }
     }
 }
@@ -4,6 +4,7 @@ use core::ptr::null;
 use sces::value::ErrValue;
 use sces::value::RetValue;
 use sces::os::mutex::IMutex;
+use sces::os::tick::Duration;
 
 use crate::native::*;
 
@@ -39,8 +40,8 @@ impl IMutex for Mutex
         unsafe { osMutexRelease(self.handle) };
     }
 
-    fn attempt_lock(&self, time: u32) -> RetValue<()>
+    fn attempt_lock(&self, time: Duration) -> RetValue<()>
     {
-        unsafe { osMutexAcquire(self.handle, time).into() }
+        unsafe { osMutexAcquire(self.handle, time.ticks()).into() }
     }
 }
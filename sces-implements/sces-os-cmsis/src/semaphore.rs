@@ -3,6 +3,7 @@ use core::ptr::null;
 
 use sces::value::{ErrValue, RetValue};
 use sces::os::semaphore::ISemaphore;
+use sces::os::tick::Duration;
 
 use crate::native::*;
 
@@ -33,9 +34,9 @@ impl ISemaphore for Semaphore
         unsafe { osSemaphoreAcquire(self.handle, osWaitForever) };
     }
 
-    fn attempt_take(&self, timeout: u32) -> RetValue<()>
+    fn attempt_take(&self, timeout: Duration) -> RetValue<()>
     {
-        unsafe { osSemaphoreAcquire(self.handle, timeout).into() }
+        unsafe { osSemaphoreAcquire(self.handle, timeout.ticks()).into() }
     }
 
     fn release(&self)
@@ -2,12 +2,13 @@ use core::{ops::Not, ptr::null};
 
 use sces::value::{ErrValue, RetValue};
 use sces::os::sxmutex::ISxMutex;
+use sces::os::tick::Duration;
 use sces::os::RTOS;
 
 use crate::native::*;
 use crate::CMSISOS;
 
-const WAIT_TIME: u32 = 10;
+const WAIT_TIME: Duration = Duration::from_millis(10);
 
 pub struct SxMutex
 {
@@ -14,6 +14,13 @@ pub struct Task
 
 impl Task
 {
+    /// Create a task instance from an existing thread, the thread will not be terminated when the
+    /// instance is dropped.
+    pub fn from(handle: osThreadId_t) -> Self
+    {
+        Task { handle, main_agent: TaskMainAgent::new() }
+    }
+
     #[allow(static_mut_refs)]
     pub unsafe extern "C" fn main(argument: *mut c_void)
     {
@@ -28,7 +35,9 @@ impl Drop for Task
 {
     fn drop(&mut self)
     {
-        (!self.handle.is_null()).then_some(self.handle).map(|x| unsafe { osThreadTerminate(x) });
+        (!self.handle.is_null() && self.main_agent.task_main.is_some())
+            .then_some(self.handle)
+            .map(|x| unsafe { osThreadTerminate(x) });
     }
 }
 
@@ -68,7 +77,8 @@ impl ITask for Task
 
     fn name(&self) -> &str
     {
-        unsafe { CStr::from_ptr(osThreadGetName(self.handle)).to_str().unwrap_or_default() }
+        unsafe { osThreadGetName(self.handle).as_ref() }
+            .map_or("", |x| unsafe { CStr::from_ptr(x) }.to_str().unwrap_or_default())
     }
 
     fn suspend(&self) -> RetValue<()>
@@ -81,6 +91,13 @@ impl ITask for Task
         unsafe { osThreadResume(self.handle).into() }
     }
 
+    fn terminate(&mut self) -> RetValue<()>
+    {
+        (!self.handle.is_null()).then_some(()).ok_or(ErrValue::InstanceInvalid)?;
+        let status: RetValue<()> = unsafe { osThreadTerminate(self.handle).into() };
+        status.inspect(|()| self.handle = null())
+    }
+
     fn stack_size(&self) -> u32
     {
         todo!()
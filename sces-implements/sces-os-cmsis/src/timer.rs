@@ -3,6 +3,7 @@ use core::ptr::null;
 
 use sces::value::ErrValue;
 use sces::value::RetValue;
+use sces::os::tick::Duration;
 use sces::os::timer::{ITimer, ITimerEvent, TimerEventAgent, TimerMode};
 
 use crate::native::*;
@@ -31,7 +32,7 @@ impl ITimer for Timer
         Ok(Timer { handle: null(), mode, agent: TimerEventAgent::new() })
     }
 
-    fn active(&mut self, times: u32, event: &dyn ITimerEvent) -> RetValue<()>
+    fn active(&mut self, times: Duration, event: &dyn ITimerEvent) -> RetValue<()>
     {
         if self.handle.is_null()
         {
@@ -46,7 +47,7 @@ impl ITimer for Timer
             return Err(ErrValue::InstanceCreateFailure);
         }
 
-        unsafe { osTimerStart(self.handle, times).into() }
+        unsafe { osTimerStart(self.handle, times.ticks()).into() }
     }
 
     fn terminate(&mut self)
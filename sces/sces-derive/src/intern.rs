@@ -0,0 +1,33 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+
+/// The section to keep the interned strings, it should be an `INFO` section in the linker
+/// script, so the strings are kept in the ELF file but never loaded into the flash.
+const INTERN_SECTION: &str = ".sces_log";
+
+pub fn intern_str(input: TokenStream) -> TokenStream
+{
+    let input = parse_macro_input!(input as LitStr);
+    let mut content = input.value().into_bytes();
+
+    if content.contains(&0)
+    {
+        panic!("The interned string can't contain a NUL character.");
+    }
+
+    content.push(0);
+
+    let size = content.len();
+    let bytes = syn::LitByteStr::new(&content, input.span());
+
+    let expanded = quote! {
+        {
+            #[cfg_attr(target_os = "none", link_section = #INTERN_SECTION)]
+            static SCES_INTERNED: [u8; #size] = *#bytes;
+            SCES_INTERNED.as_ptr() as usize
+        }
+    };
+
+    TokenStream::from(expanded)
+}
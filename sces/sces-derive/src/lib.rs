@@ -1,4 +1,5 @@
 // mod common;
+mod intern;
 mod types;
 // mod mcu;
 
@@ -6,9 +7,27 @@ use proc_macro::TokenStream;
 use quote::quote;
 
 // use crate::common::*;
+use crate::intern::*;
 use crate::types::enums::*;
 // use crate::mcu::*;
 
+/// Intern a string literal into the `.sces_log` section and get its address as the identity.
+///
+/// The section should be placed as an `INFO` section by the linker script, so the string costs
+/// no flash space, and the host tools could find it in the ELF file by the address:
+///
+/// ```text
+/// .sces_log 0 (INFO) :
+/// {
+///     KEEP(*(.sces_log .sces_log.*));
+/// }
+/// ```
+#[proc_macro]
+pub fn log_intern(input: TokenStream) -> TokenStream
+{
+    intern_str(input)
+}
+
 #[proc_macro_derive(EnumCount)]
 pub fn enum_count(input: TokenStream) -> TokenStream
 {
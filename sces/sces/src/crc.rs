@@ -0,0 +1,45 @@
+//! The CRC-32 of IEEE 802.3, which is the one of zlib and Ethernet.
+//!
+//! It's calculated bit by bit without a table, which is slow but small, so it's for the headers
+//! and the records rather than the large blocks of data in a hot path.
+//!
+//! ```ignore
+//! let crc = crc32_update(0xFFFF_FFFF, b"1234");
+//! assert_eq!(!crc32_update(crc, b"56789"), crc32(b"123456789"));
+//! ```
+
+/// Update `crc` with `bytes`, without the inversions at the beginning and the end.
+pub fn crc32_update(crc: u32, bytes: &[u8]) -> u32
+{
+    bytes.iter().fold(crc, |crc, x| {
+        (0..8).fold(crc ^ *x as u32, |crc, _| (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg()))
+    })
+}
+
+/// Get the CRC-32 of `bytes`, it's `0xCBF4_3926` for `b"123456789"`.
+pub fn crc32(bytes: &[u8]) -> u32
+{
+    !crc32_update(0xFFFF_FFFF, bytes)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn check_value()
+    {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(&[0; 4]), 0x2144_DF1C);
+    }
+
+    #[test]
+    fn update_in_pieces()
+    {
+        let crc = crc32_update(0xFFFF_FFFF, b"1234");
+        assert_eq!(!crc32_update(crc, b"56789"), crc32(b"123456789"));
+        assert_eq!(crc32_update(crc, b""), crc);
+    }
+}
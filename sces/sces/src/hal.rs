@@ -0,0 +1,832 @@
+//! Provide the adapters which implement the traits of `embedded-hal` 1.0 and `embedded-io` on top
+//! of the sces peripherals, so the drivers of the community could run over them.
+//!
+//! The adapters are enabled by the feature `embedded-hal`. Each of them owns a device wrapper and
+//! the timeout of the blocking transfers, and `ErrValue` is the error of all of them but
+//! [`HalI2c`], whose [`HalI2cError`] keeps the failure on the bus as well. A slave on a shared
+//! bus, see [`SpiSlave`], is a SPI device of `embedded-hal` with its chip select:
+//!
+//! ```ignore
+//! let spi = HalSpi::new(SpiDevice::new(SpiQueue::alloc(&mut hspi1)?), Duration::from_millis(10));
+//! let cs = HalPin::new(IoDevice::new(IoQueue::alloc(GPIOA, GPIO_Pin::P04)?));
+//! let mut display = Display::new(spi, cs, HalDelay::<MWOS>::new());
+//! ```
+
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use core::ops::Range;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin, StatefulOutputPin};
+use embedded_hal::i2c::{ErrorKind, I2c, NoAcknowledgeSource, Operation};
+use embedded_hal::spi::{SpiBus, SpiDevice as HalSpiDevice};
+use embedded_io::{Read, Write};
+
+use crate::mcu::i2c::{I2cError, I2cMasterDevice, I2cTransfer};
+use crate::mcu::io::{IoDevice, IoState};
+use crate::mcu::spi::{SpiDevice, SpiOperation, SpiSlave};
+use crate::mcu::uart::{BufferedUart, UartDevice};
+use crate::os::tick::Duration;
+use crate::os::RTOS;
+use crate::value::{ErrValue, RetValue};
+
+/// The size of the buffer on the stack, which joins the continuous operations of the same
+/// direction of an I2C transaction, and holds the data to transmit of an in place SPI transfer.
+const HAL_BUFFER_SIZE: usize = 64;
+
+/// The most groups of the continuous operations of the same direction in an I2C transaction.
+const HAL_I2C_PARTS: usize = 8;
+
+impl embedded_hal::spi::Error for ErrValue
+{
+    fn kind(&self) -> embedded_hal::spi::ErrorKind
+    {
+        embedded_hal::spi::ErrorKind::Other
+    }
+}
+
+impl embedded_io::Error for ErrValue
+{
+    fn kind(&self) -> embedded_io::ErrorKind
+    {
+        match self
+        {
+            ErrValue::Param => embedded_io::ErrorKind::InvalidInput,
+            ErrValue::Timeout => embedded_io::ErrorKind::TimedOut,
+            ErrValue::Permission => embedded_io::ErrorKind::PermissionDenied,
+            ErrValue::MemAllocFailure => embedded_io::ErrorKind::OutOfMemory,
+            ErrValue::InstanceNotFound => embedded_io::ErrorKind::NotFound,
+            ErrValue::InstanceDuplicate => embedded_io::ErrorKind::AlreadyExists,
+            ErrValue::NotSupport => embedded_io::ErrorKind::Unsupported,
+            _ => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+/// The SPI bus of `embedded-hal`, the chip select is not included.
+pub struct HalSpi
+{
+    device: SpiDevice,
+    timeout: Duration,
+}
+
+impl HalSpi
+{
+    pub fn new(device: SpiDevice, timeout: Duration) -> Self
+    {
+        Self { device, timeout }
+    }
+}
+
+impl embedded_hal::spi::ErrorType for HalSpi
+{
+    type Error = ErrValue;
+}
+
+impl SpiBus for HalSpi
+{
+    fn read(&mut self, words: &mut [u8]) -> Result<(), ErrValue>
+    {
+        self.device.as_ref().receive(words, self.timeout)
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), ErrValue>
+    {
+        self.device.as_ref().transmit(words, self.timeout)
+    }
+
+    /// The longer one of `read` and `write` is transferred alone after the common part.
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), ErrValue>
+    {
+        let common = read.len().min(write.len());
+        let (read, read_more) = read.split_at_mut(common);
+        let (write, write_more) = write.split_at(common);
+
+        self.device.as_ref().transmit_receive(write, read, self.timeout)?;
+
+        match (read_more.is_empty(), write_more.is_empty())
+        {
+            (false, _) => self.read(read_more),
+            (_, false) => self.write(write_more),
+            (true, true) => Ok(()),
+        }
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), ErrValue>
+    {
+        let mut buffer = [0; HAL_BUFFER_SIZE];
+
+        for chunk in words.chunks_mut(HAL_BUFFER_SIZE)
+        {
+            let write = &mut buffer[..chunk.len()];
+            write.copy_from_slice(chunk);
+            self.device.as_ref().transmit_receive(write, chunk, self.timeout)?;
+        }
+
+        Ok(())
+    }
+
+    /// The blocking transfers are completed when they return.
+    fn flush(&mut self) -> Result<(), ErrValue>
+    {
+        Ok(())
+    }
+}
+
+impl<OS: RTOS> embedded_hal::spi::ErrorType for SpiSlave<'_, OS>
+{
+    type Error = ErrValue;
+}
+
+/// The delays shorter than a tick are rounded up to a tick.
+impl<OS: RTOS> HalSpiDevice for SpiSlave<'_, OS>
+{
+    fn transaction(
+        &mut self, operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> Result<(), ErrValue>
+    {
+        SpiSlave::transaction(
+            self,
+            operations.iter_mut().map(|x| match x
+            {
+                embedded_hal::spi::Operation::Read(data) => SpiOperation::Read(data),
+                embedded_hal::spi::Operation::Write(data) => SpiOperation::Write(data),
+                embedded_hal::spi::Operation::Transfer(read, write) =>
+                {
+                    SpiOperation::Transfer(read, write)
+                }
+                embedded_hal::spi::Operation::TransferInPlace(data) =>
+                {
+                    SpiOperation::TransferInPlace(data)
+                }
+                embedded_hal::spi::Operation::DelayNs(ns) =>
+                {
+                    SpiOperation::Delay(Duration::from_millis(ns.div_ceil(1_000_000)))
+                }
+            }),
+        )
+    }
+}
+
+/// The I2C master of `embedded-hal` with 7 bits addresses.
+///
+/// A transaction is a single transfer of [`I2cMasterCtrl::transfer`]. The continuous operations
+/// of the same direction are joined in a buffer of `HAL_BUFFER_SIZE` bytes, and a transaction
+/// with a longer group, or more than `HAL_I2C_PARTS` groups, returns `ErrValue::NotSupport`
+/// before anything is sent. The empty operations are left out, and a transaction of only the
+/// empty ones probes the address.
+///
+/// [`I2cMasterCtrl::transfer`]: crate::mcu::i2c::I2cMasterCtrl::transfer
+pub struct HalI2c
+{
+    device: I2cMasterDevice,
+    timeout: Duration,
+}
+
+impl HalI2c
+{
+    pub fn new(device: I2cMasterDevice, timeout: Duration) -> Self
+    {
+        Self { device, timeout }
+    }
+}
+
+/// The error of [`HalI2c`], the failure on the bus is taken from
+/// [`I2cMasterCtrl::error`](crate::mcu::i2c::I2cMasterCtrl::error) when the transfer returns
+/// `ErrValue::Param`.
+#[derive(Debug)]
+pub struct HalI2cError
+{
+    pub value: ErrValue,
+    pub kind: ErrorKind,
+}
+
+impl HalI2cError
+{
+    fn new(value: ErrValue, error: I2cError) -> Self
+    {
+        let kind = match (&value, error)
+        {
+            (ErrValue::Param, I2cError::Bus) => ErrorKind::Bus,
+            (ErrValue::Param, I2cError::ArbitrationLoss) => ErrorKind::ArbitrationLoss,
+            (ErrValue::Param, I2cError::NoAcknowledge) =>
+            {
+                ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown)
+            }
+            (ErrValue::Param, I2cError::Overrun) => ErrorKind::Overrun,
+            _ => ErrorKind::Other,
+        };
+
+        Self { value, kind }
+    }
+}
+
+impl embedded_hal::i2c::Error for HalI2cError
+{
+    fn kind(&self) -> ErrorKind
+    {
+        self.kind
+    }
+}
+
+impl From<HalI2cError> for ErrValue
+{
+    fn from(value: HalI2cError) -> Self
+    {
+        value.value
+    }
+}
+
+impl embedded_hal::i2c::ErrorType for HalI2c
+{
+    type Error = HalI2cError;
+}
+
+impl I2c for HalI2c
+{
+    /// The address of the I2C master of sces is shifted left by 1 bit, the same as the HAL.
+    fn transaction(
+        &mut self, address: u8, operations: &mut [Operation<'_>],
+    ) -> Result<(), HalI2cError>
+    {
+        let master = self.device.as_ref();
+        let saddr = (address as u16) << 1;
+        let fail = |value| HalI2cError::new(value, master.error());
+
+        let mut runs: [Range<usize>; HAL_I2C_PARTS] = core::array::from_fn(|_| 0..0);
+        let mut count = 0;
+
+        for run in hal_i2c_runs(operations)
+        {
+            *runs.get_mut(count).ok_or_else(|| fail(ErrValue::NotSupport))? = run;
+            count += 1;
+        }
+
+        let runs = &runs[..count];
+        if runs.is_empty()
+        {
+            return match master.probe(saddr, self.timeout)
+            {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(fail(ErrValue::Param)),
+                Err(x) => Err(fail(x)),
+            };
+        }
+
+        let mut buffer = [0; HAL_BUFFER_SIZE];
+        let mut parts: [I2cTransfer<'_>; HAL_I2C_PARTS] =
+            core::array::from_fn(|_| I2cTransfer::Write(&[]));
+        let mut free = &mut buffer[..];
+        let mut rest = &mut operations[..];
+        let mut offset = 0;
+
+        for (part, run) in parts.iter_mut().zip(runs)
+        {
+            let (group, tail) =
+                core::mem::take(&mut rest)[run.start - offset..].split_at_mut(run.end - run.start);
+            rest = tail;
+            offset = run.end;
+            *part = hal_i2c_part(group, &mut free).map_err(fail)?;
+        }
+
+        master.transfer(saddr, &mut parts[..runs.len()], self.timeout).map_err(fail)?;
+
+        let mut read = &buffer[..];
+        for run in runs
+        {
+            let group = &mut operations[run.clone()];
+            if hal_i2c_count(group) < 2
+            {
+                continue;
+            }
+
+            for operation in group.iter_mut()
+            {
+                if let Operation::Read(data) = operation
+                {
+                    data.copy_from_slice(&read[..data.len()]);
+                }
+                read = &read[hal_i2c_len(operation)..];
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Split the operations into the groups of the same direction, the empty ones are left out.
+fn hal_i2c_runs<'a>(operations: &'a [Operation<'_>]) -> impl Iterator<Item = Range<usize>> + 'a
+{
+    let mut index = 0;
+
+    core::iter::from_fn(move || {
+        let start = index + operations[index..].iter().position(|x| hal_i2c_len(x) > 0)?;
+        let read = matches!(operations[start], Operation::Read(_));
+
+        index = start
+            + operations[start..]
+                .iter()
+                .position(|x| hal_i2c_len(x) > 0 && matches!(x, Operation::Read(_)) != read)
+                .unwrap_or(operations.len() - start);
+
+        Some(start..index)
+    })
+}
+
+fn hal_i2c_len(operation: &Operation<'_>) -> usize
+{
+    match operation
+    {
+        Operation::Write(data) => data.len(),
+        Operation::Read(data) => data.len(),
+    }
+}
+
+/// Count the operations which are not empty.
+fn hal_i2c_count(group: &[Operation<'_>]) -> usize
+{
+    group.iter().filter(|x| hal_i2c_len(x) > 0).count()
+}
+
+/// Make a part of the transfer from a group, a single operation is used in place, and the
+/// others are joined in the front of `free`, where the writes are copied.
+fn hal_i2c_part<'a>(
+    group: &'a mut [Operation<'_>], free: &mut &'a mut [u8],
+) -> RetValue<I2cTransfer<'a>>
+{
+    if hal_i2c_count(group) == 1
+    {
+        let operation = group.iter_mut().find(|x| hal_i2c_len(x) > 0).ok_or(ErrValue::Param)?;
+
+        return Ok(match operation
+        {
+            Operation::Write(data) => I2cTransfer::Write(data),
+            Operation::Read(data) => I2cTransfer::Read(data),
+        });
+    }
+
+    let size = group.iter().map(hal_i2c_len).sum();
+    if size > free.len()
+    {
+        return Err(ErrValue::NotSupport);
+    }
+
+    let (data, tail) = core::mem::take(free).split_at_mut(size);
+    *free = tail;
+
+    let mut at = 0;
+    for operation in group.iter()
+    {
+        if let Operation::Write(x) = operation
+        {
+            data[at..at + x.len()].copy_from_slice(x);
+        }
+        at += hal_i2c_len(operation);
+    }
+
+    match group.iter().any(|x| hal_i2c_len(x) > 0 && matches!(x, Operation::Read(_)))
+    {
+        true => Ok(I2cTransfer::Read(data)),
+        false => Ok(I2cTransfer::Write(data)),
+    }
+}
+
+/// The digital pin of `embedded-hal`, it's an input and an output at the same time, the output
+/// functions only work when the pin is in the output mode.
+pub struct HalPin
+{
+    device: IoDevice,
+}
+
+impl HalPin
+{
+    pub fn new(device: IoDevice) -> Self
+    {
+        Self { device }
+    }
+}
+
+impl embedded_hal::digital::ErrorType for HalPin
+{
+    type Error = Infallible;
+}
+
+impl InputPin for HalPin
+{
+    fn is_high(&mut self) -> Result<bool, Infallible>
+    {
+        Ok(self.device.as_ref().state() == IoState::Set)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible>
+    {
+        Ok(self.device.as_ref().state() == IoState::Reset)
+    }
+}
+
+impl OutputPin for HalPin
+{
+    fn set_low(&mut self) -> Result<(), Infallible>
+    {
+        self.device.as_ref().set_state(IoState::Reset);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible>
+    {
+        self.device.as_ref().set_state(IoState::Set);
+        Ok(())
+    }
+}
+
+impl StatefulOutputPin for HalPin
+{
+    fn is_set_high(&mut self) -> Result<bool, Infallible>
+    {
+        self.is_high()
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Infallible>
+    {
+        self.is_low()
+    }
+
+    fn toggle(&mut self) -> Result<(), Infallible>
+    {
+        self.device.as_ref().toggle();
+        Ok(())
+    }
+}
+
+/// The delay of `embedded-hal` by [`RTOS::delay`], it yields the CPU to the other tasks, and the
+/// delays shorter than a tick are rounded up to a tick.
+pub struct HalDelay<OS: RTOS>
+{
+    _os: PhantomData<fn() -> OS>,
+}
+
+impl<OS: RTOS> HalDelay<OS>
+{
+    pub const fn new() -> Self
+    {
+        Self { _os: PhantomData }
+    }
+}
+
+impl<OS: RTOS> Default for HalDelay<OS>
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl<OS: RTOS> DelayNs for HalDelay<OS>
+{
+    fn delay_ns(&mut self, ns: u32)
+    {
+        OS::delay(Duration::from_millis(ns.div_ceil(1_000_000)));
+    }
+
+    fn delay_us(&mut self, us: u32)
+    {
+        OS::delay(Duration::from_millis(us.div_ceil(1_000)));
+    }
+
+    fn delay_ms(&mut self, ms: u32)
+    {
+        OS::delay(Duration::from_millis(ms));
+    }
+}
+
+/// The byte stream of `embedded-io` over an UART, which receives until the UART is idle.
+///
+/// The bytes which arrive between two reads are lost, prefer [`BufferedUart`] if the remote may
+/// send at any time.
+pub struct HalUart
+{
+    device: UartDevice,
+    timeout: Duration,
+}
+
+impl HalUart
+{
+    pub fn new(device: UartDevice, timeout: Duration) -> Self
+    {
+        Self { device, timeout }
+    }
+}
+
+impl embedded_io::ErrorType for HalUart
+{
+    type Error = ErrValue;
+}
+
+impl Read for HalUart
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrValue>
+    {
+        match buf.is_empty()
+        {
+            true => Ok(0),
+            false => self.device.as_ref().receive(buf, self.timeout).map(|x| x as usize),
+        }
+    }
+}
+
+impl Write for HalUart
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ErrValue>
+    {
+        self.device.as_ref().transmit(buf, self.timeout).map(|()| buf.len())
+    }
+
+    /// The blocking transmission is completed when it returns.
+    fn flush(&mut self) -> Result<(), ErrValue>
+    {
+        Ok(())
+    }
+}
+
+impl<OS: RTOS + 'static, const RX: usize, const TX: usize> embedded_io::ErrorType
+    for BufferedUart<OS, RX, TX>
+{
+    type Error = ErrValue;
+}
+
+/// A read waits for the received bytes without a timeout.
+impl<OS: RTOS + 'static, const RX: usize, const TX: usize> Read for BufferedUart<OS, RX, TX>
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrValue>
+    {
+        BufferedUart::read(self, buf, Duration::MAX)
+    }
+}
+
+/// A write waits for the space in the transmit queue when the queue is full.
+impl<OS: RTOS + 'static, const RX: usize, const TX: usize> Write for BufferedUart<OS, RX, TX>
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ErrValue>
+    {
+        if buf.is_empty()
+        {
+            return Ok(0);
+        }
+
+        loop
+        {
+            let space = TX - self.pending();
+            let size = match space > 0
+            {
+                true => BufferedUart::write(self, &buf[..space.min(buf.len())]),
+                false => 0,
+            };
+
+            if size > 0
+            {
+                return Ok(size);
+            }
+
+            OS::delay(Duration::from_millis(1));
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), ErrValue>
+    {
+        while self.pending() > 0
+        {
+            OS::delay(Duration::from_millis(1));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use std::boxed::Box;
+    use std::sync::Mutex;
+    use std::vec;
+    use std::vec::Vec;
+
+    use embedded_hal::i2c::Error;
+
+    use super::*;
+    use crate::mcu::i2c::{I2cMasterCtrl, I2cMasterCtrlEvent};
+    use crate::mcu::EventLaunch;
+    use crate::value::RetValue;
+
+    /// The parts of each transfer, a read is `None` and a write is its data.
+    type Log = Mutex<Vec<Vec<Option<Vec<u8>>>>>;
+
+    struct FakeMaster
+    {
+        log: &'static Log,
+        fail: Option<I2cError>,
+        ack: bool,
+    }
+
+    impl EventLaunch<dyn I2cMasterCtrlEvent> for FakeMaster
+    {
+        fn set_event_agent(&mut self, _event_handle: &'static dyn I2cMasterCtrlEvent) {}
+
+        fn clean_event_agent(&mut self) {}
+    }
+
+    impl I2cMasterCtrl for FakeMaster
+    {
+        fn transmit(&self, _saddr: u16, _data: &[u8], _timeout: Duration) -> RetValue<()>
+        {
+            unreachable!()
+        }
+
+        fn receive(&self, _saddr: u16, _data: &mut [u8], _timeout: Duration) -> RetValue<()>
+        {
+            unreachable!()
+        }
+
+        fn async_transmit(&self, _saddr: u16, _data: &[u8]) -> RetValue<()>
+        {
+            unreachable!()
+        }
+
+        fn async_receive(&self, _saddr: u16, _data: &mut [u8]) -> RetValue<()>
+        {
+            unreachable!()
+        }
+
+        fn write_read(
+            &self, _saddr: u16, _write: &[u8], _read: &mut [u8], _timeout: Duration,
+        ) -> RetValue<()>
+        {
+            unreachable!()
+        }
+
+        fn transfer(
+            &self, saddr: u16, parts: &mut [I2cTransfer<'_>], _timeout: Duration,
+        ) -> RetValue<()>
+        {
+            assert_eq!(saddr, 0x50 << 1);
+            if self.fail.is_some()
+            {
+                return Err(ErrValue::Param);
+            }
+
+            let mut next = 0xA0;
+            let mut log = Vec::new();
+
+            for part in parts.iter_mut()
+            {
+                match part
+                {
+                    I2cTransfer::Write(data) => log.push(Some(data.to_vec())),
+                    I2cTransfer::Read(data) =>
+                    {
+                        for x in data.iter_mut()
+                        {
+                            *x = next;
+                            next = next.wrapping_add(1);
+                        }
+                        log.push(None);
+                    }
+                }
+            }
+
+            self.log.lock().unwrap().push(log);
+            Ok(())
+        }
+
+        fn error(&self) -> I2cError
+        {
+            self.fail.unwrap_or(I2cError::None)
+        }
+
+        fn probe(&self, _saddr: u16, _timeout: Duration) -> RetValue<bool>
+        {
+            self.log.lock().unwrap().push(Vec::new());
+            Ok(self.ack)
+        }
+    }
+
+    fn setup(fail: Option<I2cError>, ack: bool) -> (HalI2c, &'static Log)
+    {
+        let log: &'static Log = Box::leak(Box::new(Mutex::new(Vec::new())));
+        let master = Box::leak(Box::new(FakeMaster { log, fail, ack }));
+
+        (HalI2c::new(I2cMasterDevice::new(master), Duration::from_millis(10)), log)
+    }
+
+    #[test]
+    fn joins_same_direction()
+    {
+        let (mut i2c, log) = setup(None, true);
+        let (mut a, mut b) = ([0; 2], [0; 1]);
+
+        i2c.transaction(
+            0x50,
+            &mut [
+                Operation::Write(&[1]),
+                Operation::Write(&[2, 3]),
+                Operation::Read(&mut a),
+                Operation::Read(&mut b),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(*log.lock().unwrap(), [vec![Some(vec![1, 2, 3]), None]]);
+        assert_eq!((a, b), ([0xA0, 0xA1], [0xA2]));
+    }
+
+    #[test]
+    fn restarts_between_directions()
+    {
+        let (mut i2c, log) = setup(None, true);
+        let (mut a, mut b) = ([0; 1], [0; 2]);
+
+        i2c.transaction(
+            0x50,
+            &mut [Operation::Write(&[1]), Operation::Read(&mut a), Operation::Write(&[2])],
+        )
+        .unwrap();
+        i2c.transaction(
+            0x50,
+            &mut [Operation::Write(&[3]), Operation::Read(&mut a), Operation::Read(&mut b)],
+        )
+        .unwrap();
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            [vec![Some(vec![1]), None, Some(vec![2])], vec![Some(vec![3]), None]]
+        );
+        assert_eq!((a, b), ([0xA0], [0xA1, 0xA2]));
+    }
+
+    #[test]
+    fn empty_operations_left_out()
+    {
+        let (mut i2c, log) = setup(None, true);
+        let mut a = [0; 1];
+
+        i2c.transaction(
+            0x50,
+            &mut [Operation::Write(&[1]), Operation::Read(&mut []), Operation::Write(&[2])],
+        )
+        .unwrap();
+        i2c.transaction(0x50, &mut [Operation::Read(&mut []), Operation::Read(&mut a)]).unwrap();
+        i2c.transaction(0x50, &mut [Operation::Write(&[])]).unwrap();
+
+        assert_eq!(*log.lock().unwrap(), [vec![Some(vec![1, 2])], vec![None], vec![]]);
+        assert_eq!(a, [0xA0]);
+
+        let (mut i2c, _) = setup(Some(I2cError::NoAcknowledge), false);
+        let error = i2c.transaction(0x50, &mut []).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown));
+    }
+
+    #[test]
+    fn too_long_sends_nothing()
+    {
+        let (mut i2c, log) = setup(None, true);
+        let long = [0x55; HAL_BUFFER_SIZE];
+        let mut read = [0; 200];
+
+        let error = i2c.transaction(0x50, &mut [Operation::Write(&long), Operation::Write(&[1])]);
+        assert!(matches!(error, Err(HalI2cError { value: ErrValue::NotSupport, .. })));
+
+        let mut reads = [[0; 1]; HAL_I2C_PARTS / 2 + 1];
+        let mut many: Vec<_> =
+            reads.iter_mut().flat_map(|x| [Operation::Write(&[1]), Operation::Read(x)]).collect();
+        let error = i2c.transaction(0x50, &mut many);
+        assert!(matches!(error, Err(HalI2cError { value: ErrValue::NotSupport, .. })));
+
+        assert!(log.lock().unwrap().is_empty());
+
+        i2c.transaction(0x50, &mut [Operation::Write(&long), Operation::Read(&mut read)]).unwrap();
+        assert_eq!(*log.lock().unwrap(), [vec![Some(long.to_vec()), None]]);
+    }
+
+    #[test]
+    fn error_kinds()
+    {
+        for (error, kind) in [
+            (I2cError::Bus, ErrorKind::Bus),
+            (I2cError::ArbitrationLoss, ErrorKind::ArbitrationLoss),
+            (I2cError::NoAcknowledge, ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown)),
+            (I2cError::Overrun, ErrorKind::Overrun),
+            (I2cError::Other, ErrorKind::Other),
+        ]
+        {
+            let (mut i2c, _) = setup(Some(error), true);
+            let error = i2c.write(0x50, &[1]).unwrap_err();
+
+            assert!(matches!(error.value, ErrValue::Param));
+            assert_eq!(error.kind(), kind);
+        }
+
+        let error = HalI2cError::new(ErrValue::Timeout, I2cError::NoAcknowledge);
+        assert_eq!(error.kind(), ErrorKind::Other);
+    }
+}
@@ -1,9 +1,15 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
 pub mod vec;
 pub mod cell;
+pub mod crc;
+#[cfg(feature = "embedded-hal")]
+pub mod hal;
 pub mod mcu;
 pub mod os;
+pub mod retain;
+#[cfg(test)]
+mod sim;
 pub mod value;
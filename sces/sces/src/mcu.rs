@@ -3,11 +3,16 @@ pub mod can;
 pub mod flash;
 pub mod i2c;
 pub mod io;
+pub mod power;
 pub mod spi;
+pub mod sys;
 pub mod tim;
 pub mod uart;
 pub mod wd;
 
+use crate::os::tick::Duration;
+use crate::value::RetValue;
+
 /// Trait for one MCU chip.
 ///
 /// All types in this trait has combined with peripheral trait.
@@ -23,13 +28,33 @@ pub trait MCU
     type I2cSlave: i2c::I2cSlaveCtrl;
     type Io: io::IoCtrl;
     type Spi: spi::SpiCtrl;
+    type System: sys::SystemCtrl;
     type Uart: uart::UartCtrl;
-    // type TimBase: tim::TimBaseCtrl;
-    // type TImPwm: tim::TimPwmCtrl;
+    type TimBase: tim::TimBaseCtrl;
+    type TimCapture: tim::TimCaptureCtrl;
+    type TimEncoder: tim::TimEncoderCtrl;
+    type TimPwm: tim::TimPwmCtrl;
     type WatchDog: wd::WatchDogCtrl;
 
     fn tick_value() -> u32;
-    fn sleep(time: u32);
+    fn sleep(time: Duration);
+
+    /// Enter the sleep mode until an interrupt occurs.
+    fn enter_sleep();
+
+    /// Enter the stop mode until a wakeup source wakes the MCU up, the clocks are restored before
+    /// it returns.
+    ///
+    /// It must be called with the interrupts enabled, because the clocks may be restored with the
+    /// timeouts of the tick, so it's not called by the idle hook, see [`power::PowerIdle`].
+    fn enter_stop() -> RetValue<()>;
+
+    /// Enter the standby mode, the MCU resets when a wakeup source wakes it up, so it never
+    /// returns if it succeeds.
+    fn enter_standby() -> RetValue<()>;
+
+    /// Enable or disable a source to wake the MCU up from the stop and the standby modes.
+    fn set_wakeup(source: power::WakeupSource, enable: bool) -> RetValue<()>;
 }
 
 /// `EventLaunch` is a trait that the peripheral trait who implements this trait means that it can
@@ -1,4 +1,5 @@
 use super::EventLaunch;
+use crate::os::tick::Duration;
 use crate::value::RetValue;
 
 pub trait CanCtrl
@@ -7,8 +8,8 @@ where
 {
     fn activate(&self) -> RetValue<()>;
     fn deactivate(&self) -> RetValue<()>;
-    fn transmit(&self, can_message: &CanMessage, timeout: u32) -> RetValue<()>;
-    fn receive(&self, can_message: &mut CanMessage, timeout: u32) -> RetValue<()>;
+    fn transmit(&self, can_message: &CanMessage, timeout: Duration) -> RetValue<()>;
+    fn receive(&self, can_message: &mut CanMessage, timeout: Duration) -> RetValue<()>;
     fn async_transmit(&self, can_message: &CanMessage) -> RetValue<()>;
     fn async_receive(&mut self, can_message: &mut CanMessage);
 }
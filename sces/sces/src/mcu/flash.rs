@@ -1,10 +1,181 @@
-use crate::value::RetValue;
+//! The flash which is erased and programmed by the software, like the on chip flash or a SPI NOR
+//! flash.
+//!
+//! A flash is split into sectors, which are the smallest units to erase, and the addresses are
+//! the ones of the flash itself, like `0x0800_0000` for the on chip flash of STM32. A write only
+//! clears the bits, so the area should be erased before:
+//!
+//! ```ignore
+//! let sector = flash.sector_at(0x0808_0000)?;
+//!
+//! flash.erase_range(sector.address, sector.size)?;
+//! flash.write_verify(sector.address, &settings)?;
+//! ```
+
+use crate::value::{ErrValue, RetValue};
 
 pub type FlashDevice = &'static mut dyn FlashCtrl;
 
+/// The size of the buffer on the stack, which holds the data read back to verify.
+const FLASH_VERIFY_SIZE: usize = 32;
+
+/// A sector of the flash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlashSector
+{
+    /// The number of the sector in the whole flash, it counts through the banks.
+    pub index: u32,
+
+    /// The bank which has the sector, it's always `0` on the flash with a single bank.
+    pub bank: u32,
+
+    pub address: u32,
+    pub size: u32,
+}
+
+impl FlashSector
+{
+    pub const fn end(&self) -> u32
+    {
+        self.address + self.size
+    }
+
+    pub const fn contains(&self, address: u32) -> bool
+    {
+        address >= self.address && address - self.address < self.size
+    }
+}
+
+/// An error found by the ECC when the flash is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlashEccError
+{
+    /// The address of the program unit which has the error.
+    pub address: u32,
+
+    /// A single bit error is corrected, the data which is read is right anyway.
+    pub corrected: bool,
+}
+
+/// The protection of the flash against the reading by a debugger or a bootloader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashReadProtection
+{
+    /// No protection.
+    Level0,
+
+    /// The flash can't be read by the debugger, going back to `Level0` erases the whole flash.
+    Level1,
+
+    /// The debugger is disabled for good, it can never be undone.
+    Level2,
+}
+
 pub trait FlashCtrl
 {
-    fn erase_sector(&self, sector: u32) -> RetValue<()>;
-    fn write(&self, address: u32, data: u8) -> RetValue<()>;
-    fn write32(&self, address: u32, data: u32) -> RetValue<()>;
+    /// The address of the first byte.
+    fn base(&self) -> u32;
+
+    /// The size of the whole flash in bytes.
+    fn size(&self) -> u32;
+
+    /// The unit of the program in bytes.
+    ///
+    /// A write of a part of a unit pads the rest of the unit with `0xFF`, and some flash like
+    /// the one with ECC programs a unit only once after it's erased, so the following writes to
+    /// the same unit fail.
+    fn write_size(&self) -> u32;
+
+    fn sector_count(&self) -> u32;
+
+    /// Get the sector by its number in the whole flash.
+    fn sector(&self, index: u32) -> RetValue<FlashSector>;
+
+    /// Get the sector which has `address`.
+    fn sector_at(&self, address: u32) -> RetValue<FlashSector>
+    {
+        (0..self.sector_count())
+            .map(|x| self.sector(x))
+            .find(|x| x.as_ref().is_ok_and(|x| x.contains(address)))
+            .unwrap_or(Err(ErrValue::Param))
+    }
+
+    fn read(&self, address: u32, data: &mut [u8]) -> RetValue<()>;
+
+    /// Program `data`, the flash is programmed in the largest units which fit.
+    fn write(&self, address: u32, data: &[u8]) -> RetValue<()>;
+
+    fn erase_sector(&self, index: u32) -> RetValue<()>;
+
+    /// Erase the sectors in the range, both ends of it must be at the boundaries of the sectors.
+    fn erase_range(&self, address: u32, size: u32) -> RetValue<()>
+    {
+        let end = address.checked_add(size).ok_or(ErrValue::Param)?;
+        let mut address = address;
+
+        while address < end
+        {
+            let sector = self.sector_at(address)?;
+            if sector.address != address || sector.end() > end
+            {
+                return Err(ErrValue::Param);
+            }
+
+            self.erase_sector(sector.index)?;
+            address = sector.end();
+        }
+
+        Ok(())
+    }
+
+    /// Compare the flash with `data`, it fails with [`ErrValue::LowLevelFailure`] when they are
+    /// different.
+    fn verify(&self, address: u32, data: &[u8]) -> RetValue<()>
+    {
+        let mut buffer = [0; FLASH_VERIFY_SIZE];
+
+        data.chunks(FLASH_VERIFY_SIZE).zip((address..).step_by(FLASH_VERIFY_SIZE)).try_for_each(
+            |(chunk, address)| {
+                let read = &mut buffer[..chunk.len()];
+                self.read(address, read)?;
+                (read == chunk).then_some(()).ok_or(ErrValue::LowLevelFailure)
+            },
+        )
+    }
+
+    /// Program `data` and read it back to check it.
+    fn write_verify(&self, address: u32, data: &[u8]) -> RetValue<()>
+    {
+        self.write(address, data)?;
+        self.verify(address, data)
+    }
+
+    /// Take the last error found by the ECC, it's cleaned after it's taken.
+    fn take_ecc_error(&self) -> Option<FlashEccError>
+    {
+        None
+    }
+
+    fn read_protection(&self) -> RetValue<FlashReadProtection>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    /// Change the read protection, it takes effect after the next reset on most chips.
+    fn set_read_protection(&self, _level: FlashReadProtection) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    /// Get the raw value of the user option bytes, whose layout depends on the chip.
+    fn option_bytes(&self) -> RetValue<u32>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    /// Program the bits of the user option bytes in `mask` with `value`.
+    fn set_option_bytes(&self, _mask: u32, _value: u32) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
 }
@@ -1,18 +1,23 @@
-use crate::value::RetValue;
+use crate::os::tick::Duration;
+use crate::value::{ErrValue, RetValue};
 use sces_derive::{EnumAsU16, EnumAsU8};
 
 use super::EventLaunch;
 
+mod bus;
+
+pub use bus::{I2cBus, I2cBusSlave};
+
 pub trait I2cMemCtrl
 where
     Self: EventLaunch<dyn I2cMemCtrlEvent>,
 {
     fn mem_write(
-        &self, saddr: u16, maddr: u16, mwide: I2cMemWide, data: &[u8], timeout: u32,
+        &self, saddr: u16, maddr: u16, mwide: I2cMemWide, data: &[u8], timeout: Duration,
     ) -> RetValue<()>;
 
     fn mem_read(
-        &self, saddr: u16, maddr: u16, mwide: I2cMemWide, data: &mut [u8], timeout: u32,
+        &self, saddr: u16, maddr: u16, mwide: I2cMemWide, data: &mut [u8], timeout: Duration,
     ) -> RetValue<()>;
 
     fn async_mem_write(
@@ -47,14 +52,99 @@ pub enum I2cDirection
     Transmit = 1,
 }
 
+/// A part of [`I2cMasterCtrl::transfer`].
+pub enum I2cTransfer<'a>
+{
+    Write(&'a [u8]),
+    Read(&'a mut [u8]),
+}
+
+/// The failure on the bus of the last transfer of an I2C master.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum I2cError
+{
+    /// The last transfer didn't fail on the bus.
+    None,
+    /// A misplaced start or stop.
+    Bus,
+    ArbitrationLoss,
+    /// The slave didn't acknowledge its address or a byte.
+    NoAcknowledge,
+    /// A received byte was lost, or a byte to transmit wasn't ready in time.
+    Overrun,
+    Other,
+}
+
 pub trait I2cMasterCtrl
 where
     Self: EventLaunch<dyn I2cMasterCtrlEvent>,
 {
-    fn transmit(&self, saddr: u16, data: &[u8], timeout: u32) -> RetValue<()>;
-    fn receive(&self, saddr: u16, data: &mut [u8], timeout: u32) -> RetValue<()>;
+    fn transmit(&self, saddr: u16, data: &[u8], timeout: Duration) -> RetValue<()>;
+    fn receive(&self, saddr: u16, data: &mut [u8], timeout: Duration) -> RetValue<()>;
     fn async_transmit(&self, saddr: u16, data: &[u8]) -> RetValue<()>;
     fn async_receive(&self, saddr: u16, data: &mut [u8]) -> RetValue<()>;
+
+    /// Transmit `write` and then receive into `read` with a repeated start between them, it's
+    /// the usual way to read the registers of a slave.
+    fn write_read(
+        &self, saddr: u16, write: &[u8], read: &mut [u8], timeout: Duration,
+    ) -> RetValue<()>;
+
+    /// Do the parts in order in a single transfer, which begins with a start, joins the parts
+    /// by repeated starts and ends with a stop, so the continuous parts of the same direction
+    /// should be joined by the caller first.
+    ///
+    /// The default one only does a single part and a write followed by a read, and returns
+    /// [`ErrValue::NotSupport`] for the others.
+    fn transfer(&self, saddr: u16, parts: &mut [I2cTransfer<'_>], timeout: Duration)
+        -> RetValue<()>
+    {
+        match parts
+        {
+            [I2cTransfer::Write(data)] => self.transmit(saddr, data, timeout),
+            [I2cTransfer::Read(data)] => self.receive(saddr, data, timeout),
+            [I2cTransfer::Write(write), I2cTransfer::Read(read)] =>
+            {
+                self.write_read(saddr, write, read, timeout)
+            }
+            _ => Err(ErrValue::NotSupport),
+        }
+    }
+
+    /// Get the failure on the bus of the last transfer, which tells a NACK from the other
+    /// failures when a transfer returns [`ErrValue::Param`].
+    fn error(&self) -> I2cError
+    {
+        I2cError::Other
+    }
+
+    /// Check whether a slave acknowledges `saddr`, it's `Ok(false)` when no one does.
+    fn probe(&self, saddr: u16, timeout: Duration) -> RetValue<bool>;
+
+    /// Probe the 7 bits addresses which are not reserved, from `0x08` to `0x77`, and call
+    /// `found` with the acknowledged ones, which are shifted left by 1 bit like `saddr`.
+    fn scan(&self, timeout: Duration, found: &mut dyn FnMut(u16)) -> RetValue<()>
+    {
+        for address in 0x08..0x78
+        {
+            if self.probe(address << 1, timeout)?
+            {
+                found(address << 1);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Free the bus which is held by a slave, like the one which was reset during a transfer
+    /// and keeps SDA low, by clocking SCL 9 times and then sending a stop.
+    ///
+    /// It returns [`ErrValue::NotSupport`] if the pins of the bus are unknown, and
+    /// [`ErrValue::Busy`] if the bus is still held after it.
+    fn recover(&self) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
 }
 
 pub trait I2cMasterCtrlEvent
@@ -69,8 +159,8 @@ where
     Self: EventLaunch<dyn I2cSlaveCtrlEvent>,
 {
     fn listen(&self) -> RetValue<()>;
-    fn transmit(&self, data: &[u8], timeout: u32) -> RetValue<()>;
-    fn receive(&self, data: &mut [u8], timeout: u32) -> RetValue<()>;
+    fn transmit(&self, data: &[u8], timeout: Duration) -> RetValue<()>;
+    fn receive(&self, data: &mut [u8], timeout: Duration) -> RetValue<()>;
     fn async_transmit(&self, data: &[u8]) -> RetValue<()>;
     fn async_receive(&self, data: &mut [u8]) -> RetValue<()>;
 }
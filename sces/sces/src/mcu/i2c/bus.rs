@@ -0,0 +1,383 @@
+//! Share an I2C bus between several slaves, each of them has its own address.
+//!
+//! [`I2cBus`] serializes the accesses of the slaves by a mutex, so a transfer of a task is
+//! never broken by the one of another task. When the HAL reports the bus is busy, the bus is
+//! recovered by [`I2cMasterCtrl::recover`] and the transfer is tried once again:
+//!
+//! ```ignore
+//! static mut I2C1: Option<I2cBus<MWOS>> = None;
+//!
+//! let bus = I2C1.insert(I2cBus::new(I2cQueue::allocate_master(&mut hi2c1)?, Duration::from_millis(10))?);
+//! let sensor = bus.slave(0x76 << 1);
+//!
+//! let mut id = [0; 1];
+//! sensor.write_read(&[0xD0], &mut id)?;
+//! ```
+
+use super::{I2cMasterCtrl, I2cMasterDevice};
+use crate::os::mutex::IMutex;
+use crate::os::tick::Duration;
+use crate::os::RTOS;
+use crate::value::{ErrValue, RetValue};
+
+/// An I2C bus which is shared by several slaves.
+pub struct I2cBus<OS: RTOS>
+{
+    i2c: I2cMasterDevice,
+    lock: OS::Mutex,
+    timeout: Duration,
+}
+
+impl<OS: RTOS> I2cBus<OS>
+{
+    /// Create the bus, `timeout` is the limit of each transfer.
+    pub fn new(i2c: &'static mut dyn I2cMasterCtrl, timeout: Duration) -> RetValue<Self>
+    {
+        Ok(Self { i2c: I2cMasterDevice::new(i2c), lock: OS::Mutex::new()?, timeout })
+    }
+
+    /// Create a slave on the bus, `saddr` is shifted left by 1 bit like the one of
+    /// [`I2cMasterCtrl`].
+    pub fn slave(&self, saddr: u16) -> I2cBusSlave<'_, OS>
+    {
+        I2cBusSlave { bus: self, saddr }
+    }
+
+    /// Find the slaves on the bus, see [`I2cMasterCtrl::scan`].
+    pub fn scan(&self, found: &mut dyn FnMut(u16)) -> RetValue<()>
+    {
+        self.run(|i2c, timeout| i2c.scan(timeout, found))
+    }
+
+    /// Free the bus which is held by a slave, see [`I2cMasterCtrl::recover`].
+    pub fn recover(&self) -> RetValue<()>
+    {
+        self.run(|i2c, _| i2c.recover())
+    }
+
+    /// Take the bus during `f`, and recover the bus and call `f` again if the bus is busy.
+    fn run<T>(&self, mut f: impl FnMut(&dyn I2cMasterCtrl, Duration) -> RetValue<T>)
+        -> RetValue<T>
+    {
+        self.lock.lock();
+        let _guard = I2cBusGuard { bus: self };
+        let i2c = self.i2c.as_ref();
+
+        match f(i2c, self.timeout)
+        {
+            Err(ErrValue::Busy) => match i2c.recover()
+            {
+                Ok(()) => f(i2c, self.timeout),
+                Err(_) => Err(ErrValue::Busy),
+            },
+            value => value,
+        }
+    }
+}
+
+unsafe impl<OS: RTOS> Send for I2cBus<OS> {}
+
+unsafe impl<OS: RTOS> Sync for I2cBus<OS> {}
+
+/// The bus taken by a slave, it's given back when the guard is dropped.
+struct I2cBusGuard<'a, OS: RTOS>
+{
+    bus: &'a I2cBus<OS>,
+}
+
+impl<OS: RTOS> Drop for I2cBusGuard<'_, OS>
+{
+    fn drop(&mut self)
+    {
+        self.bus.lock.unlock();
+    }
+}
+
+/// A slave on a shared I2C bus with its address.
+pub struct I2cBusSlave<'a, OS: RTOS>
+{
+    bus: &'a I2cBus<OS>,
+    saddr: u16,
+}
+
+impl<OS: RTOS> I2cBusSlave<'_, OS>
+{
+    pub fn saddr(&self) -> u16
+    {
+        self.saddr
+    }
+
+    /// Check whether the slave acknowledges its address.
+    pub fn probe(&self) -> RetValue<bool>
+    {
+        self.bus.run(|i2c, timeout| i2c.probe(self.saddr, timeout))
+    }
+
+    pub fn read(&self, data: &mut [u8]) -> RetValue<()>
+    {
+        self.bus.run(|i2c, timeout| i2c.receive(self.saddr, data, timeout))
+    }
+
+    pub fn write(&self, data: &[u8]) -> RetValue<()>
+    {
+        self.bus.run(|i2c, timeout| i2c.transmit(self.saddr, data, timeout))
+    }
+
+    /// Transmit `write` and then receive into `read` with a repeated start between them.
+    pub fn write_read(&self, write: &[u8], read: &mut [u8]) -> RetValue<()>
+    {
+        self.bus.run(|i2c, timeout| i2c.write_read(self.saddr, write, read, timeout))
+    }
+}
+
+unsafe impl<OS: RTOS> Send for I2cBusSlave<'_, OS> {}
+
+unsafe impl<OS: RTOS> Sync for I2cBusSlave<'_, OS> {}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use core::cell::Cell;
+    use std::boxed::Box;
+    use std::sync::Mutex;
+    use std::vec;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::mcu::i2c::{I2cMasterCtrlEvent, I2cTransfer};
+    use crate::mcu::EventLaunch;
+    use crate::sim::HostOs;
+
+    #[derive(Debug, PartialEq)]
+    enum Event
+    {
+        Write(u16, Vec<u8>),
+        Read(u16, usize),
+        WriteRead(u16, Vec<u8>, usize),
+        Probe(u16),
+        Recover,
+    }
+
+    type Log = Mutex<Vec<Event>>;
+
+    /// The failures to inject: the transfers which find the bus busy, whether the recover
+    /// frees it, and the address whose probe fails.
+    struct Faults
+    {
+        busy: Cell<u32>,
+        recover: Cell<bool>,
+        probe: Cell<Option<u16>>,
+    }
+
+    /// A master which only does the required methods and logs every call, the slaves of the 7
+    /// bits addresses in `acks` acknowledge.
+    struct FakeMaster
+    {
+        log: &'static Log,
+        faults: &'static Faults,
+        acks: &'static [u16],
+    }
+
+    impl FakeMaster
+    {
+        fn record(&self, event: Event) -> RetValue<()>
+        {
+            self.log.lock().unwrap().push(event);
+
+            match self.faults.busy.get()
+            {
+                0 => Ok(()),
+                x =>
+                {
+                    self.faults.busy.set(x - 1);
+                    Err(ErrValue::Busy)
+                }
+            }
+        }
+    }
+
+    impl EventLaunch<dyn I2cMasterCtrlEvent> for FakeMaster
+    {
+        fn set_event_agent(&mut self, _event_handle: &'static dyn I2cMasterCtrlEvent) {}
+
+        fn clean_event_agent(&mut self) {}
+    }
+
+    impl I2cMasterCtrl for FakeMaster
+    {
+        fn transmit(&self, saddr: u16, data: &[u8], _timeout: Duration) -> RetValue<()>
+        {
+            self.record(Event::Write(saddr, data.to_vec()))
+        }
+
+        fn receive(&self, saddr: u16, data: &mut [u8], _timeout: Duration) -> RetValue<()>
+        {
+            data.fill(0xA5);
+            self.record(Event::Read(saddr, data.len()))
+        }
+
+        fn async_transmit(&self, _saddr: u16, _data: &[u8]) -> RetValue<()>
+        {
+            unreachable!()
+        }
+
+        fn async_receive(&self, _saddr: u16, _data: &mut [u8]) -> RetValue<()>
+        {
+            unreachable!()
+        }
+
+        fn write_read(
+            &self, saddr: u16, write: &[u8], read: &mut [u8], _timeout: Duration,
+        ) -> RetValue<()>
+        {
+            read.fill(0x5A);
+            self.record(Event::WriteRead(saddr, write.to_vec(), read.len()))
+        }
+
+        fn probe(&self, saddr: u16, _timeout: Duration) -> RetValue<bool>
+        {
+            self.record(Event::Probe(saddr))?;
+
+            match self.faults.probe.get() == Some(saddr >> 1)
+            {
+                true => Err(ErrValue::Timeout),
+                false => Ok(self.acks.contains(&(saddr >> 1))),
+            }
+        }
+
+        fn recover(&self) -> RetValue<()>
+        {
+            self.log.lock().unwrap().push(Event::Recover);
+
+            match self.faults.recover.get()
+            {
+                true => Ok(()),
+                false => Err(ErrValue::Busy),
+            }
+        }
+    }
+
+    fn setup(acks: &'static [u16]) -> (&'static I2cBus<HostOs>, &'static Faults, &'static Log)
+    {
+        let log: &'static Log = Box::leak(Box::new(Mutex::new(Vec::new())));
+        let faults = Box::leak(Box::new(Faults {
+            busy: Cell::new(0),
+            recover: Cell::new(true),
+            probe: Cell::new(None),
+        }));
+        let master = Box::leak(Box::new(FakeMaster { log, faults, acks }));
+        let bus = Box::leak(Box::new(I2cBus::new(master, Duration::from_millis(10)).unwrap()));
+
+        (bus, faults, log)
+    }
+
+    fn take(log: &Log) -> Vec<Event>
+    {
+        core::mem::take(&mut *log.lock().unwrap())
+    }
+
+    #[test]
+    fn run_recovers_once()
+    {
+        let (bus, faults, log) = setup(&[]);
+        let slave = bus.slave(0x50 << 1);
+        let mut read = [0; 2];
+
+        faults.busy.set(1);
+        slave.write_read(&[0x10], &mut read).unwrap();
+        assert_eq!(
+            take(log),
+            [
+                Event::WriteRead(0xA0, vec![0x10], 2),
+                Event::Recover,
+                Event::WriteRead(0xA0, vec![0x10], 2),
+            ]
+        );
+        assert_eq!(read, [0x5A; 2]);
+        assert!(!bus.lock.is_locked());
+
+        // The transfer is tried only once again.
+        faults.busy.set(2);
+        assert!(matches!(slave.write(&[1]), Err(ErrValue::Busy)));
+        assert_eq!(
+            take(log),
+            [Event::Write(0xA0, vec![1]), Event::Recover, Event::Write(0xA0, vec![1])]
+        );
+        assert!(!bus.lock.is_locked());
+
+        // The bus is still busy when the recover fails.
+        faults.busy.set(1);
+        faults.recover.set(false);
+        assert!(matches!(slave.read(&mut read), Err(ErrValue::Busy)));
+        assert_eq!(take(log), [Event::Read(0xA0, 2), Event::Recover]);
+        assert!(!bus.lock.is_locked());
+
+        faults.busy.set(0);
+        slave.read(&mut read).unwrap();
+        assert_eq!(take(log), [Event::Read(0xA0, 2)]);
+        assert!(!bus.lock.is_locked());
+    }
+
+    #[test]
+    fn scan_addresses()
+    {
+        let (bus, faults, log) = setup(&[0x03, 0x08, 0x50, 0x77, 0x78]);
+        let mut found = Vec::new();
+
+        bus.scan(&mut |x| found.push(x)).unwrap();
+
+        let probes: Vec<_> = (0x08..0x78).map(|x| Event::Probe(x << 1)).collect();
+        assert_eq!(take(log), probes);
+        assert_eq!(found, [0x08 << 1, 0x50 << 1, 0x77 << 1]);
+        assert!(!bus.lock.is_locked());
+
+        // A failed probe stops the scan.
+        faults.probe.set(Some(0x20));
+        found.clear();
+
+        assert!(matches!(bus.scan(&mut |x| found.push(x)), Err(ErrValue::Timeout)));
+        assert_eq!(take(log).last(), Some(&Event::Probe(0x20 << 1)));
+        assert_eq!(found, [0x08 << 1]);
+        assert!(!bus.lock.is_locked());
+
+        faults.probe.set(None);
+        assert!(bus.slave(0x50 << 1).probe().unwrap());
+        assert!(!bus.slave(0x51 << 1).probe().unwrap());
+    }
+
+    #[test]
+    fn default_transfer()
+    {
+        let (_, faults, log) = setup(&[]);
+        let master = FakeMaster { log, faults, acks: &[] };
+        let timeout = Duration::from_millis(10);
+        let (mut a, mut b) = ([0; 2], [0; 1]);
+
+        master.transfer(0xA0, &mut [I2cTransfer::Write(&[1])], timeout).unwrap();
+        master.transfer(0xA0, &mut [I2cTransfer::Read(&mut a)], timeout).unwrap();
+        master
+            .transfer(0xA0, &mut [I2cTransfer::Write(&[2]), I2cTransfer::Read(&mut b)], timeout)
+            .unwrap();
+        assert_eq!(
+            take(log),
+            [Event::Write(0xA0, vec![1]), Event::Read(0xA0, 2), Event::WriteRead(0xA0, vec![2], 1)]
+        );
+
+        let (mut c, mut d) = ([0; 1], [0; 1]);
+        let mut shapes: [&mut [I2cTransfer<'_>]; 4] = [
+            &mut [],
+            &mut [I2cTransfer::Read(&mut c), I2cTransfer::Write(&[1])],
+            &mut [I2cTransfer::Write(&[1]), I2cTransfer::Write(&[2])],
+            &mut [I2cTransfer::Write(&[1]), I2cTransfer::Read(&mut d), I2cTransfer::Write(&[2])],
+        ];
+
+        for parts in shapes.iter_mut()
+        {
+            assert!(matches!(master.transfer(0xA0, parts, timeout), Err(ErrValue::NotSupport)));
+        }
+
+        assert!(take(log).is_empty());
+    }
+}
@@ -0,0 +1,383 @@
+//! Provide the low power modes of the MCU, the locks which keep the MCU awake, and the policy
+//! which decides the mode when the system is idle.
+//!
+//! A driver holds a lock by [`stay_awake`] during a transfer, so the MCU doesn't enter a mode
+//! which stops the clock of the peripheral. The RTOS calls the idle hook, see [`crate::os::idle`],
+//! when no task is ready, and [`PowerIdle`] enters the deepest mode allowed by both the locks and
+//! the time until the next timer deadline:
+//!
+//! ```ignore
+//! static POWER_IDLE: PowerIdle<STM32> =
+//!     PowerIdle::new(PowerPolicy::new().with_sleep(Duration::from_millis(2)));
+//!
+//! idle::install(&POWER_IDLE);
+//!
+//! let _awake = power::stay_awake(PowerMode::Sleep);
+//! uart.send(&data, Duration::from_millis(10))?;
+//! ```
+
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use super::MCU;
+use crate::os::idle::IdleHook;
+use crate::os::tick::Duration;
+
+/// The count of the modes which could be a limit of the locks, the standby mode is not a limit.
+const LOCK_LIMITS: usize = 3;
+
+static POWER_LOCKS: PowerLocks = PowerLocks::new();
+
+/// The power modes of the MCU, from the shallowest to the deepest.
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum PowerMode
+{
+    /// The CPU keeps running.
+    Run = 0,
+
+    /// The CPU stops until an interrupt occurs, the peripherals keep running.
+    Sleep = 1,
+
+    /// Most clocks stop until a wakeup source wakes the MCU up, the RAM and the registers are
+    /// kept.
+    Stop = 2,
+
+    /// Almost everything is powered off, and the MCU resets when a wakeup source wakes it up.
+    Standby = 3,
+}
+
+/// The sources which wake the MCU up from the stop and the standby modes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum WakeupSource
+{
+    /// The wakeup pin with the number in the datasheet, it starts from `1`.
+    Pin(u8),
+
+    /// The wakeup timer of the RTC.
+    Rtc,
+}
+
+/// The locks which keep the MCU from entering the modes deeper than their limits.
+///
+/// The locks are counted with the atomic operations, so they could be taken and given back in the
+/// interrupt handlers.
+pub struct PowerLocks
+{
+    counts: [AtomicU32; LOCK_LIMITS],
+}
+
+impl PowerLocks
+{
+    pub const fn new() -> Self
+    {
+        Self { counts: [const { AtomicU32::new(0) }; LOCK_LIMITS] }
+    }
+
+    /// Take a lock which allows the modes no deeper than `limit`, and give it back when the
+    /// returned guard is dropped.
+    pub fn lock(&self, limit: PowerMode) -> AwakeLock<'_>
+    {
+        self.acquire(limit);
+        AwakeLock { locks: self, limit }
+    }
+
+    /// Take a lock which allows the modes no deeper than `limit`, it should be given back by
+    /// [`PowerLocks::release`] with the same `limit`.
+    ///
+    /// It's for the lock which lives across the functions, like from the start of a transfer to
+    /// its complete callback, prefer [`PowerLocks::lock`] in the other cases.
+    pub fn acquire(&self, limit: PowerMode)
+    {
+        if let Some(count) = self.counts.get(limit as usize)
+        {
+            count.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    /// Give back a lock taken by [`PowerLocks::acquire`].
+    pub fn release(&self, limit: PowerMode)
+    {
+        if let Some(count) = self.counts.get(limit as usize)
+        {
+            let _ = count.fetch_update(Ordering::AcqRel, Ordering::Acquire, |x| x.checked_sub(1));
+        }
+    }
+
+    /// Get the deepest mode allowed by all locks.
+    pub fn allowed(&self) -> PowerMode
+    {
+        [PowerMode::Run, PowerMode::Sleep, PowerMode::Stop]
+            .into_iter()
+            .zip(&self.counts)
+            .find(|(_, count)| count.load(Ordering::Acquire) > 0)
+            .map_or(PowerMode::Standby, |(mode, _)| mode)
+    }
+}
+
+impl Default for PowerLocks
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+/// The guard of a lock taken by [`PowerLocks::lock`] or [`stay_awake`], the lock is given back
+/// when it's dropped.
+pub struct AwakeLock<'a>
+{
+    locks: &'a PowerLocks,
+    limit: PowerMode,
+}
+
+impl AwakeLock<'_>
+{
+    pub fn limit(&self) -> PowerMode
+    {
+        self.limit
+    }
+}
+
+impl Drop for AwakeLock<'_>
+{
+    fn drop(&mut self)
+    {
+        self.locks.release(self.limit);
+    }
+}
+
+/// Get the locks of the system, which are used by [`PowerIdle`].
+pub fn power_locks() -> &'static PowerLocks
+{
+    &POWER_LOCKS
+}
+
+/// Keep the MCU from entering the modes deeper than `limit` until the returned guard is dropped.
+pub fn stay_awake(limit: PowerMode) -> AwakeLock<'static>
+{
+    POWER_LOCKS.lock(limit)
+}
+
+/// The policy to decide the mode by the time the system is going to be idle.
+///
+/// A deeper mode costs more time to enter and to leave, so it's chosen only when the system is
+/// idle long enough. The tick of the RTOS may stop in the stop mode, so the stop and the standby
+/// modes are never chosen by default.
+#[derive(Debug, Clone, Copy)]
+pub struct PowerPolicy
+{
+    sleep_after: Duration,
+    stop_after: Duration,
+    standby_after: Duration,
+}
+
+impl PowerPolicy
+{
+    /// Create the policy which enters the sleep mode only.
+    pub const fn new() -> Self
+    {
+        Self {
+            sleep_after: Duration::ZERO,
+            stop_after: Duration::MAX,
+            standby_after: Duration::MAX,
+        }
+    }
+
+    /// Enter the sleep mode only when the system is idle for `after` at least.
+    pub const fn with_sleep(mut self, after: Duration) -> Self
+    {
+        self.sleep_after = after;
+        self
+    }
+
+    /// Enter the stop mode when the system is idle for `after` at least, `Duration::MAX` never
+    /// enters it.
+    pub const fn with_stop(mut self, after: Duration) -> Self
+    {
+        self.stop_after = after;
+        self
+    }
+
+    /// Enter the standby mode when the system is idle for `after` at least, `Duration::MAX` never
+    /// enters it.
+    pub const fn with_standby(mut self, after: Duration) -> Self
+    {
+        self.standby_after = after;
+        self
+    }
+
+    /// Decide the mode when the system is going to be idle for `idle`, and the locks allow the
+    /// modes no deeper than `allowed`.
+    ///
+    /// `idle` is `Duration::MAX` when no timer is waiting.
+    pub fn decide(&self, allowed: PowerMode, idle: Duration) -> PowerMode
+    {
+        [
+            (self.standby_after, PowerMode::Standby),
+            (self.stop_after, PowerMode::Stop),
+            (self.sleep_after, PowerMode::Sleep),
+        ]
+        .into_iter()
+        .find(|(after, _)| !after.is_forever() && idle >= *after)
+        .map_or(PowerMode::Run, |(_, mode)| mode)
+        .min(allowed)
+    }
+}
+
+impl Default for PowerPolicy
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+/// The idle hook which enters the mode decided by the policy with the locks of the system.
+///
+/// The stop mode is not supported by the idle hook yet, the MCU sleeps instead. The tick of the
+/// RTOS stops in it, so it needs a low power timer to wake the MCU up at the next timer deadline
+/// and to step the tick by the time slept, and the clocks are restored with the interrupts
+/// masked here, which the HAL can't do without its tick interrupt.
+///
+/// The standby mode resets the MCU, so it's entered only when the kernel tells the time until the
+/// next deadline, and never with `Duration::MAX`, which MWOS always passes as it doesn't know the
+/// deadline. FreeRTOS passes at most the ticks the SysTick counts at once, like 99 ms on a F4 at
+/// 168 MHz, so a longer time of the policy is never reached there. Call [`MCU::enter_standby`] by
+/// the application to stay in the standby mode for long.
+pub struct PowerIdle<M: MCU>
+{
+    policy: PowerPolicy,
+    _mcu: PhantomData<fn() -> M>,
+}
+
+impl<M: MCU> PowerIdle<M>
+{
+    pub const fn new(policy: PowerPolicy) -> Self
+    {
+        Self { policy, _mcu: PhantomData }
+    }
+
+    pub fn policy(&self) -> &PowerPolicy
+    {
+        &self.policy
+    }
+}
+
+impl<M: MCU> IdleHook for PowerIdle<M>
+{
+    /// The MCU enters the sleep mode instead when it fails to enter the standby mode, like the
+    /// wakeup source is not configured.
+    fn on_idle(&self, idle: Duration) -> bool
+    {
+        match idle_mode(&self.policy, POWER_LOCKS.allowed(), idle)
+        {
+            PowerMode::Run => return false,
+            PowerMode::Standby => M::enter_standby().unwrap_or_else(|_| M::enter_sleep()),
+            _ => M::enter_sleep(),
+        }
+
+        true
+    }
+}
+
+/// Get the mode which [`PowerIdle`] enters when the system is going to be idle for `idle`.
+fn idle_mode(policy: &PowerPolicy, allowed: PowerMode, idle: Duration) -> PowerMode
+{
+    match policy.decide(allowed, idle)
+    {
+        PowerMode::Run => PowerMode::Run,
+        PowerMode::Standby if !idle.is_forever() => PowerMode::Standby,
+        _ => PowerMode::Sleep,
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const fn ms(millis: u32) -> Duration
+    {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn decide_by_idle_time()
+    {
+        let policy = PowerPolicy::new().with_sleep(ms(2)).with_stop(ms(20)).with_standby(ms(5000));
+
+        assert_eq!(policy.decide(PowerMode::Standby, ms(1)), PowerMode::Run);
+        assert_eq!(policy.decide(PowerMode::Standby, ms(2)), PowerMode::Sleep);
+        assert_eq!(policy.decide(PowerMode::Standby, ms(19)), PowerMode::Sleep);
+        assert_eq!(policy.decide(PowerMode::Standby, ms(20)), PowerMode::Stop);
+        assert_eq!(policy.decide(PowerMode::Standby, ms(5000)), PowerMode::Standby);
+        assert_eq!(policy.decide(PowerMode::Standby, Duration::MAX), PowerMode::Standby);
+    }
+
+    #[test]
+    fn decide_by_allowed_mode()
+    {
+        let policy = PowerPolicy::new().with_stop(ms(20));
+
+        assert_eq!(policy.decide(PowerMode::Stop, ms(30)), PowerMode::Stop);
+        assert_eq!(policy.decide(PowerMode::Sleep, ms(30)), PowerMode::Sleep);
+        assert_eq!(policy.decide(PowerMode::Run, ms(30)), PowerMode::Run);
+    }
+
+    #[test]
+    fn decide_by_default()
+    {
+        let policy = PowerPolicy::new();
+
+        assert_eq!(policy.decide(PowerMode::Standby, Duration::ZERO), PowerMode::Sleep);
+        assert_eq!(policy.decide(PowerMode::Standby, Duration::MAX), PowerMode::Sleep);
+    }
+
+    #[test]
+    fn idle_hook_modes()
+    {
+        let policy = PowerPolicy::new().with_sleep(ms(2)).with_stop(ms(20)).with_standby(ms(5000));
+
+        assert_eq!(idle_mode(&policy, PowerMode::Standby, ms(1)), PowerMode::Run);
+        assert_eq!(idle_mode(&policy, PowerMode::Standby, ms(2)), PowerMode::Sleep);
+
+        // The stop mode is not supported yet.
+        assert_eq!(idle_mode(&policy, PowerMode::Standby, ms(20)), PowerMode::Sleep);
+        assert_eq!(idle_mode(&policy, PowerMode::Stop, ms(5000)), PowerMode::Sleep);
+
+        // The standby mode is not entered when the next deadline is not known.
+        assert_eq!(idle_mode(&policy, PowerMode::Standby, ms(5000)), PowerMode::Standby);
+        assert_eq!(idle_mode(&policy, PowerMode::Standby, Duration::MAX), PowerMode::Sleep);
+        assert_eq!(idle_mode(&policy, PowerMode::Run, Duration::MAX), PowerMode::Run);
+    }
+
+    #[test]
+    fn locks_limit_the_mode()
+    {
+        let locks = PowerLocks::new();
+        assert_eq!(locks.allowed(), PowerMode::Standby);
+
+        let stop = locks.lock(PowerMode::Stop);
+        assert_eq!(locks.allowed(), PowerMode::Stop);
+
+        locks.acquire(PowerMode::Run);
+        let sleep = locks.lock(PowerMode::Sleep);
+        assert_eq!(locks.allowed(), PowerMode::Run);
+
+        locks.release(PowerMode::Run);
+        assert_eq!(locks.allowed(), PowerMode::Sleep);
+
+        drop(sleep);
+        assert_eq!(locks.allowed(), PowerMode::Stop);
+
+        // A release without a lock is ignored.
+        drop(stop);
+        locks.release(PowerMode::Stop);
+        assert_eq!(locks.allowed(), PowerMode::Standby);
+
+        // The standby mode is not a limit, it's always allowed.
+        drop(locks.lock(PowerMode::Standby));
+        assert_eq!(locks.allowed(), PowerMode::Standby);
+    }
+}
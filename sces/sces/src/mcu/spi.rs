@@ -1,15 +1,22 @@
 use super::EventLaunch;
+use crate::os::tick::Duration;
 use crate::value::RetValue;
 
+mod bus;
+
+pub use bus::{SpiBus, SpiOperation, SpiSlave};
+
 pub trait SpiCtrl
 where
     Self: EventLaunch<dyn SpiCtrlEvent>,
 {
-    fn transmit(&self, data: &[u8], timeout: u32) -> RetValue<()>;
+    fn transmit(&self, data: &[u8], timeout: Duration) -> RetValue<()>;
 
-    fn receive(&self, data: &mut [u8], timeout: u32) -> RetValue<()>;
+    fn receive(&self, data: &mut [u8], timeout: Duration) -> RetValue<()>;
 
-    fn transmit_receive(&self, tx_data: &[u8], rx_data: &mut [u8], timeout: u32) -> RetValue<()>;
+    fn transmit_receive(
+        &self, tx_data: &[u8], rx_data: &mut [u8], timeout: Duration,
+    ) -> RetValue<()>;
 
     fn async_transmit(&self, data: &[u8]) -> RetValue<()>;
 
@@ -18,6 +25,10 @@ where
     fn async_transmit_receive(&self, tx_data: &[u8], rx_data: &mut [u8]) -> RetValue<()>;
 
     fn abort(&self) -> RetValue<()>;
+
+    /// Configure the mode, the bit order and the speed, the clock is the highest one which is
+    /// not above `config.frequency`.
+    fn configure(&mut self, config: &SpiConfig) -> RetValue<()>;
 }
 
 pub trait SpiCtrlEvent
@@ -29,6 +40,45 @@ pub trait SpiCtrlEvent
     fn on_spi_error(&self) {}
 }
 
+/// The clock polarity and phase, `Mode0` samples on the rising edge of a low idle clock.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SpiMode
+{
+    Mode0,
+    Mode1,
+    Mode2,
+    Mode3,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SpiBitOrder
+{
+    MsbFirst,
+    LsbFirst,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SpiConfig
+{
+    pub mode: SpiMode,
+    pub bit_order: SpiBitOrder,
+    pub frequency: u32,
+}
+
+impl SpiConfig
+{
+    pub const fn new(mode: SpiMode, frequency: u32) -> Self
+    {
+        Self { mode, bit_order: SpiBitOrder::MsbFirst, frequency }
+    }
+
+    pub const fn with_bit_order(mut self, bit_order: SpiBitOrder) -> Self
+    {
+        self.bit_order = bit_order;
+        self
+    }
+}
+
 pub struct SpiDevice
 {
     instance: *mut dyn SpiCtrl,
@@ -0,0 +1,618 @@
+//! Share a SPI bus between several slaves, each of them has its own chip select and settings.
+//!
+//! [`SpiBus`] serializes the accesses of the slaves by a mutex, and [`SpiSlave`] holds the bus
+//! with its chip select active during a whole transaction, the bus is configured again only when
+//! the last transaction was for a slave with other settings:
+//!
+//! ```ignore
+//! static mut SPI1: Option<SpiBus<MWOS>> = None;
+//!
+//! let bus = SPI1.insert(SpiBus::new(SpiQueue::alloc(&mut hspi1)?, Duration::from_millis(10))?);
+//! let flash = bus.slave(IoQueue::alloc(GPIOA, GPIO_Pin::P04)?, SpiConfig::new(SpiMode::Mode0, 20_000_000));
+//!
+//! let mut id = [0; 3];
+//! flash.transaction([SpiOperation::Write(&[0x9F]), SpiOperation::Read(&mut id)])?;
+//! ```
+
+use core::cell::UnsafeCell;
+
+use super::{SpiConfig, SpiCtrl, SpiDevice};
+use crate::mcu::io::{IoCtrl, IoState};
+use crate::os::mutex::IMutex;
+use crate::os::tick::Duration;
+use crate::os::RTOS;
+use crate::value::RetValue;
+
+/// The size of the buffer on the stack, which holds the data to transmit of an in place transfer.
+const SPI_IN_PLACE_SIZE: usize = 64;
+
+/// The operations in a transaction of a slave.
+pub enum SpiOperation<'a>
+{
+    Read(&'a mut [u8]),
+    Write(&'a [u8]),
+
+    /// Transmit the second slice and receive into the first one at the same time, the longer
+    /// part of them is transferred alone after the common part.
+    Transfer(&'a mut [u8], &'a [u8]),
+
+    /// Transmit the slice and receive into it at the same time.
+    TransferInPlace(&'a mut [u8]),
+
+    /// Wait with the chip select active.
+    Delay(Duration),
+}
+
+/// A SPI bus which is shared by several slaves.
+pub struct SpiBus<OS: RTOS>
+{
+    spi: UnsafeCell<SpiDevice>,
+    config: UnsafeCell<Option<SpiConfig>>,
+    lock: OS::Mutex,
+    timeout: Duration,
+}
+
+impl<OS: RTOS> SpiBus<OS>
+{
+    /// Create the bus, `timeout` is the limit of each transfer.
+    pub fn new(spi: &'static mut dyn SpiCtrl, timeout: Duration) -> RetValue<Self>
+    {
+        Ok(Self {
+            spi: UnsafeCell::new(SpiDevice::new(spi)),
+            config: UnsafeCell::new(None),
+            lock: OS::Mutex::new()?,
+            timeout,
+        })
+    }
+
+    /// Create a slave on the bus, whose chip select is active at the low level.
+    pub fn slave(&self, cs: &'static dyn IoCtrl, config: SpiConfig) -> SpiSlave<'_, OS>
+    {
+        cs.set_state(IoState::Set);
+
+        SpiSlave {
+            bus: self,
+            cs,
+            active: IoState::Reset,
+            config,
+            setup: Duration::ZERO,
+            hold: Duration::ZERO,
+        }
+    }
+
+    /// Take the bus, and configure it with `config` if the last one is different.
+    fn acquire(&self, config: &SpiConfig) -> RetValue<SpiBusGuard<'_, OS>>
+    {
+        self.lock.lock();
+        let guard = SpiBusGuard { bus: self };
+
+        let last = unsafe { &mut *self.config.get() };
+        if last.as_ref() != Some(config)
+        {
+            *last = None;
+            unsafe { (*self.spi.get()).as_mut().configure(config)? };
+            *last = Some(*config);
+        }
+
+        Ok(guard)
+    }
+}
+
+unsafe impl<OS: RTOS> Send for SpiBus<OS> {}
+
+unsafe impl<OS: RTOS> Sync for SpiBus<OS> {}
+
+/// The bus taken by a slave, it's given back when the guard is dropped.
+struct SpiBusGuard<'a, OS: RTOS>
+{
+    bus: &'a SpiBus<OS>,
+}
+
+impl<OS: RTOS> SpiBusGuard<'_, OS>
+{
+    fn execute(&self, operation: SpiOperation<'_>) -> RetValue<()>
+    {
+        let spi = unsafe { (*self.bus.spi.get()).as_ref() };
+        let timeout = self.bus.timeout;
+
+        match operation
+        {
+            SpiOperation::Read(data) => spi.receive(data, timeout),
+            SpiOperation::Write(data) => spi.transmit(data, timeout),
+            SpiOperation::Transfer(read, write) =>
+            {
+                let common = read.len().min(write.len());
+                let (read, read_more) = read.split_at_mut(common);
+                let (write, write_more) = write.split_at(common);
+
+                spi.transmit_receive(write, read, timeout)?;
+
+                match (read_more.is_empty(), write_more.is_empty())
+                {
+                    (false, _) => spi.receive(read_more, timeout),
+                    (_, false) => spi.transmit(write_more, timeout),
+                    (true, true) => Ok(()),
+                }
+            }
+            SpiOperation::TransferInPlace(data) =>
+            {
+                let mut buffer = [0; SPI_IN_PLACE_SIZE];
+
+                data.chunks_mut(SPI_IN_PLACE_SIZE).try_for_each(|chunk| {
+                    let write = &mut buffer[..chunk.len()];
+                    write.copy_from_slice(chunk);
+                    spi.transmit_receive(write, chunk, timeout)
+                })
+            }
+            SpiOperation::Delay(time) =>
+            {
+                OS::delay(time);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<OS: RTOS> Drop for SpiBusGuard<'_, OS>
+{
+    fn drop(&mut self)
+    {
+        self.bus.lock.unlock();
+    }
+}
+
+/// A slave on a shared SPI bus, with its chip select and settings.
+pub struct SpiSlave<'a, OS: RTOS>
+{
+    bus: &'a SpiBus<OS>,
+    cs: &'static dyn IoCtrl,
+    active: IoState,
+    config: SpiConfig,
+    setup: Duration,
+    hold: Duration,
+}
+
+impl<OS: RTOS> SpiSlave<'_, OS>
+{
+    /// Set the level of the chip select when it's active.
+    pub fn with_active(mut self, active: IoState) -> Self
+    {
+        self.active = active;
+        self.cs.set_state(Self::inactive(active));
+        self
+    }
+
+    /// Wait `setup` after the chip select is active and `hold` before it's inactive.
+    ///
+    /// The delays are in the ticks of the RTOS, and toggling the pin takes longer than the setup
+    /// and the hold time of most chips, so they are only for the chips which need a long time,
+    /// like the ones waking up from the deep power down.
+    pub fn with_delays(mut self, setup: Duration, hold: Duration) -> Self
+    {
+        self.setup = setup;
+        self.hold = hold;
+        self
+    }
+
+    pub fn config(&self) -> &SpiConfig
+    {
+        &self.config
+    }
+
+    /// Run `operations` with the chip select active and the bus held during all of them.
+    ///
+    /// The operations after a failed one are skipped, and the chip select is given back anyway.
+    pub fn transaction<'b, I>(&self, operations: I) -> RetValue<()>
+    where
+        I: IntoIterator<Item = SpiOperation<'b>>,
+    {
+        let guard = self.bus.acquire(&self.config)?;
+
+        self.cs.set_state(self.active);
+        if self.setup != Duration::ZERO
+        {
+            OS::delay(self.setup);
+        }
+
+        let value = operations.into_iter().try_for_each(|x| guard.execute(x));
+
+        if self.hold != Duration::ZERO
+        {
+            OS::delay(self.hold);
+        }
+        self.cs.set_state(Self::inactive(self.active));
+
+        value
+    }
+
+    pub fn read(&self, data: &mut [u8]) -> RetValue<()>
+    {
+        self.transaction([SpiOperation::Read(data)])
+    }
+
+    pub fn write(&self, data: &[u8]) -> RetValue<()>
+    {
+        self.transaction([SpiOperation::Write(data)])
+    }
+
+    pub fn transfer(&self, read: &mut [u8], write: &[u8]) -> RetValue<()>
+    {
+        self.transaction([SpiOperation::Transfer(read, write)])
+    }
+
+    /// Transmit `write` and then receive into `read` in a transaction, it's the usual way to
+    /// send a command and read its response.
+    pub fn write_read(&self, write: &[u8], read: &mut [u8]) -> RetValue<()>
+    {
+        self.transaction([SpiOperation::Write(write), SpiOperation::Read(read)])
+    }
+
+    fn inactive(active: IoState) -> IoState
+    {
+        match active
+        {
+            IoState::Set => IoState::Reset,
+            IoState::Reset => IoState::Set,
+        }
+    }
+}
+
+unsafe impl<OS: RTOS> Send for SpiSlave<'_, OS> {}
+
+unsafe impl<OS: RTOS> Sync for SpiSlave<'_, OS> {}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use core::cell::Cell;
+    use std::boxed::Box;
+    use std::sync::Mutex;
+    use std::vec;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::mcu::io::IoCtrlEvent;
+    use crate::mcu::spi::{SpiCtrlEvent, SpiMode};
+    use crate::mcu::EventLaunch;
+    use crate::sim::HostOs;
+    use crate::value::ErrValue;
+
+    #[derive(Debug, PartialEq)]
+    enum Event
+    {
+        /// The chip select is set high or low.
+        Cs(bool),
+        Configure(u32),
+        Write(Vec<u8>),
+        Read(usize),
+        Transfer(Vec<u8>, usize),
+    }
+
+    type Log = Mutex<Vec<Event>>;
+
+    /// The failures to inject: the next configure, and the transfer with the index `at`.
+    struct Faults
+    {
+        configure: Cell<bool>,
+        at: Cell<Option<usize>>,
+    }
+
+    /// A controller which logs every call and reads the bytes from `0xA0` on.
+    struct FakeSpi
+    {
+        log: &'static Log,
+        faults: &'static Faults,
+        next: Cell<u8>,
+    }
+
+    impl FakeSpi
+    {
+        fn record(&self, event: Event, read: &mut [u8]) -> RetValue<()>
+        {
+            self.log.lock().unwrap().push(event);
+
+            for x in read.iter_mut()
+            {
+                *x = self.next.get();
+                self.next.set(x.wrapping_add(1));
+            }
+
+            match self.faults.at.get()
+            {
+                Some(0) => Err(ErrValue::Timeout),
+                Some(x) =>
+                {
+                    self.faults.at.set(Some(x - 1));
+                    Ok(())
+                }
+                None => Ok(()),
+            }
+        }
+    }
+
+    impl EventLaunch<dyn SpiCtrlEvent> for FakeSpi
+    {
+        fn set_event_agent(&mut self, _event_handle: &'static dyn SpiCtrlEvent) {}
+
+        fn clean_event_agent(&mut self) {}
+    }
+
+    impl SpiCtrl for FakeSpi
+    {
+        fn transmit(&self, data: &[u8], _timeout: Duration) -> RetValue<()>
+        {
+            self.record(Event::Write(data.to_vec()), &mut [])
+        }
+
+        fn receive(&self, data: &mut [u8], _timeout: Duration) -> RetValue<()>
+        {
+            self.record(Event::Read(data.len()), data)
+        }
+
+        fn transmit_receive(
+            &self, tx_data: &[u8], rx_data: &mut [u8], _timeout: Duration,
+        ) -> RetValue<()>
+        {
+            assert_eq!(tx_data.len(), rx_data.len());
+            self.record(Event::Transfer(tx_data.to_vec(), rx_data.len()), rx_data)
+        }
+
+        fn async_transmit(&self, _data: &[u8]) -> RetValue<()>
+        {
+            unreachable!()
+        }
+
+        fn async_receive(&self, _data: &mut [u8]) -> RetValue<()>
+        {
+            unreachable!()
+        }
+
+        fn async_transmit_receive(&self, _tx_data: &[u8], _rx_data: &mut [u8]) -> RetValue<()>
+        {
+            unreachable!()
+        }
+
+        fn abort(&self) -> RetValue<()>
+        {
+            unreachable!()
+        }
+
+        fn configure(&mut self, config: &SpiConfig) -> RetValue<()>
+        {
+            self.log.lock().unwrap().push(Event::Configure(config.frequency));
+
+            match self.faults.configure.replace(false)
+            {
+                true => Err(ErrValue::Param),
+                false => Ok(()),
+            }
+        }
+    }
+
+    struct FakeCs
+    {
+        log: &'static Log,
+        state: Cell<IoState>,
+    }
+
+    impl EventLaunch<dyn IoCtrlEvent> for FakeCs
+    {
+        fn set_event_agent(&mut self, _event_handle: &'static dyn IoCtrlEvent) {}
+
+        fn clean_event_agent(&mut self) {}
+    }
+
+    impl IoCtrl for FakeCs
+    {
+        fn state(&self) -> IoState
+        {
+            self.state.get()
+        }
+
+        fn set_state(&self, state: IoState)
+        {
+            self.state.set(state);
+            self.log.lock().unwrap().push(Event::Cs(state == IoState::Set));
+        }
+
+        fn toggle(&self)
+        {
+            unreachable!()
+        }
+    }
+
+    fn setup() -> (&'static SpiBus<HostOs>, &'static Faults, &'static Log)
+    {
+        let log: &'static Log = Box::leak(Box::new(Mutex::new(Vec::new())));
+        let faults =
+            Box::leak(Box::new(Faults { configure: Cell::new(false), at: Cell::new(None) }));
+        let spi = Box::leak(Box::new(FakeSpi { log, faults, next: Cell::new(0xA0) }));
+        let bus = Box::leak(Box::new(SpiBus::new(spi, Duration::from_millis(10)).unwrap()));
+
+        (bus, faults, log)
+    }
+
+    fn cs(log: &'static Log) -> &'static FakeCs
+    {
+        Box::leak(Box::new(FakeCs { log, state: Cell::new(IoState::Reset) }))
+    }
+
+    fn take(log: &Log) -> Vec<Event>
+    {
+        core::mem::take(&mut *log.lock().unwrap())
+    }
+
+    #[test]
+    fn cs_around_operations()
+    {
+        let (bus, _, log) = setup();
+        let flash = bus.slave(cs(log), SpiConfig::new(SpiMode::Mode0, 1_000_000));
+        let mut id = [0; 3];
+
+        flash.write_read(&[0x9F], &mut id).unwrap();
+
+        assert_eq!(
+            take(log),
+            [
+                Event::Cs(true),
+                Event::Configure(1_000_000),
+                Event::Cs(false),
+                Event::Write(vec![0x9F]),
+                Event::Read(3),
+                Event::Cs(true),
+            ]
+        );
+        assert_eq!(id, [0xA0, 0xA1, 0xA2]);
+        assert!(!bus.lock.is_locked());
+
+        // The delays are inside the chip select, and a high active one is set low at first.
+        let flash = bus
+            .slave(cs(log), SpiConfig::new(SpiMode::Mode0, 1_000_000))
+            .with_active(IoState::Set)
+            .with_delays(Duration::from_millis(2), Duration::from_millis(3));
+        let start = HostOs::ticks();
+
+        flash
+            .transaction([SpiOperation::Write(&[1]), SpiOperation::Delay(Duration::from_millis(5))])
+            .unwrap();
+
+        assert_eq!(HostOs::ticks().wrapping_sub(start), 10);
+        assert_eq!(
+            take(log),
+            [
+                Event::Cs(true),
+                Event::Cs(false),
+                Event::Cs(true),
+                Event::Write(vec![1]),
+                Event::Cs(false),
+            ]
+        );
+        assert!(!bus.lock.is_locked());
+    }
+
+    #[test]
+    fn cs_released_on_failure()
+    {
+        let (bus, faults, log) = setup();
+        let flash = bus.slave(cs(log), SpiConfig::new(SpiMode::Mode0, 1_000_000));
+        let mut read = [0; 2];
+        take(log);
+
+        faults.at.set(Some(1));
+        let value = flash.transaction([
+            SpiOperation::Write(&[1]),
+            SpiOperation::Read(&mut read),
+            SpiOperation::Write(&[2]),
+        ]);
+
+        assert!(matches!(value, Err(ErrValue::Timeout)));
+        assert_eq!(
+            take(log),
+            [
+                Event::Configure(1_000_000),
+                Event::Cs(false),
+                Event::Write(vec![1]),
+                Event::Read(2),
+                Event::Cs(true),
+            ]
+        );
+        assert!(!bus.lock.is_locked());
+
+        // The bus is free for the next transaction.
+        faults.at.set(None);
+        flash.write(&[3]).unwrap();
+        assert_eq!(take(log), [Event::Cs(false), Event::Write(vec![3]), Event::Cs(true)]);
+        assert!(!bus.lock.is_locked());
+    }
+
+    #[test]
+    fn configure_on_change()
+    {
+        let (bus, faults, log) = setup();
+        let a = bus.slave(cs(log), SpiConfig::new(SpiMode::Mode0, 1_000_000));
+        let b = bus.slave(cs(log), SpiConfig::new(SpiMode::Mode3, 8_000_000));
+        take(log);
+
+        for slave in [&a, &a, &b, &b, &a]
+        {
+            slave.write(&[]).unwrap();
+        }
+
+        let configures: Vec<_> =
+            take(log).into_iter().filter(|x| matches!(x, Event::Configure(_))).collect();
+        assert_eq!(
+            configures,
+            [Event::Configure(1_000_000), Event::Configure(8_000_000), Event::Configure(1_000_000)]
+        );
+
+        // A failed configure leaves the chip select alone, and is done again next time.
+        faults.configure.set(true);
+        assert!(matches!(b.write(&[1]), Err(ErrValue::Param)));
+        assert_eq!(take(log), [Event::Configure(8_000_000)]);
+        assert!(!bus.lock.is_locked());
+
+        b.write(&[1]).unwrap();
+        b.write(&[2]).unwrap();
+        assert_eq!(
+            take(log),
+            [
+                Event::Configure(8_000_000),
+                Event::Cs(false),
+                Event::Write(vec![1]),
+                Event::Cs(true),
+                Event::Cs(false),
+                Event::Write(vec![2]),
+                Event::Cs(true),
+            ]
+        );
+        assert!(!bus.lock.is_locked());
+    }
+
+    #[test]
+    fn transfer_split()
+    {
+        let (bus, _, log) = setup();
+        let slave = bus.slave(cs(log), SpiConfig::new(SpiMode::Mode0, 1_000_000));
+        let (mut long, mut short, mut same) = ([0; 4], [0; 1], [0; 2]);
+
+        slave
+            .transaction([
+                SpiOperation::Transfer(&mut long, &[1, 2]),
+                SpiOperation::Transfer(&mut short, &[3, 4, 5]),
+                SpiOperation::Transfer(&mut same, &[6, 7]),
+            ])
+            .unwrap();
+
+        let events: Vec<_> = take(log).into_iter().filter(|x| !matches!(x, Event::Cs(_))).collect();
+        assert_eq!(
+            events,
+            [
+                Event::Configure(1_000_000),
+                Event::Transfer(vec![1, 2], 2),
+                Event::Read(2),
+                Event::Transfer(vec![3], 1),
+                Event::Write(vec![4, 5]),
+                Event::Transfer(vec![6, 7], 2),
+            ]
+        );
+        assert_eq!((long, short, same), ([0xA0, 0xA1, 0xA2, 0xA3], [0xA4], [0xA5, 0xA6]));
+
+        // An in place transfer goes through the buffer on the stack by chunks.
+        let mut data: [u8; SPI_IN_PLACE_SIZE + 6] = core::array::from_fn(|x| x as u8);
+        let sent = data.to_vec();
+        slave.transaction([SpiOperation::TransferInPlace(&mut data)]).unwrap();
+
+        let events: Vec<_> = take(log).into_iter().filter(|x| !matches!(x, Event::Cs(_))).collect();
+        assert_eq!(
+            events,
+            [
+                Event::Transfer(sent[..SPI_IN_PLACE_SIZE].to_vec(), SPI_IN_PLACE_SIZE),
+                Event::Transfer(sent[SPI_IN_PLACE_SIZE..].to_vec(), 6),
+            ]
+        );
+        assert!(data.iter().enumerate().all(|(i, x)| *x == 0xA7u8.wrapping_add(i as u8)));
+        assert!(!bus.lock.is_locked());
+    }
+}
@@ -0,0 +1,104 @@
+//! Provide a common trait to control the whole MCU, like the reset and the identification.
+
+use sces_derive::EnumAsU32;
+
+use crate::value::{ErrValue, RetValue};
+
+pub type SystemDevice = &'static dyn SystemCtrl;
+
+/// The cause of the last reset, which is decoded from the reset flags of the hardware.
+///
+/// Some causes set several flags at once, like a power on sets the pin reset flag too, so the
+/// cause is decided in the order of the variants.
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumAsU32)]
+pub enum ResetCause
+{
+    /// The MCU woke up from a low power mode which could only be left by a reset.
+    LowPower = 0,
+
+    /// The window watch dog expired or was refreshed out of its window.
+    WindowWatchDog = 1,
+
+    /// The independent watch dog expired.
+    IndependentWatchDog = 2,
+
+    /// The software requested a reset, like [`SystemCtrl::reset`] or the debugger.
+    Software = 3,
+
+    /// The power was turned on.
+    PowerOn = 4,
+
+    /// The supply voltage dropped below the threshold, some chips report it as a power on.
+    BrownOut = 5,
+
+    /// The reset pin was pulled down, like by the reset button or an external watch dog.
+    Pin = 6,
+
+    /// No flag is set, or the flags are not known.
+    Unknown = 255,
+}
+
+/// The clocks of the MCU, see [`SystemCtrl::clock_freq`].
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumAsU32)]
+pub enum SystemClock
+{
+    /// The clock of the system, which drives the CPU core.
+    Core = 0,
+
+    /// The clock of the AHB bus and the memories.
+    Bus = 1,
+
+    /// The clock of the first APB bus.
+    Peripheral1 = 2,
+
+    /// The clock of the second APB bus.
+    Peripheral2 = 3,
+
+    Unknown = 255,
+}
+
+/// `SystemCtrl` is the common trait to control the whole MCU, there is only one instance of it
+/// in an MCU.
+pub trait SystemCtrl
+{
+    /// Reset the whole MCU by the software.
+    fn reset(&self) -> !;
+
+    /// Get the cause of the last reset.
+    ///
+    /// The reset flags are kept by the hardware until they are cleaned, so they should be cleaned
+    /// by [`SystemCtrl::clean_reset_cause`] after they are got at the boot time, otherwise the
+    /// next reset is mixed with this one.
+    fn reset_cause(&self) -> ResetCause;
+
+    /// Clean the reset flags of the hardware.
+    fn clean_reset_cause(&self);
+
+    /// Get the unique identification of the MCU.
+    fn unique_id(&self) -> &'static [u8];
+
+    /// Get the size of the on chip flash in bytes.
+    fn flash_size(&self) -> u32;
+
+    /// Get the frequency of the `clock` in Hz.
+    fn clock_freq(&self, clock: SystemClock) -> u32;
+
+    /// Leave the application and enter the bootloader in the ROM, like to update the firmware by
+    /// the UART.
+    ///
+    /// It never returns if it succeeds, and it returns [`crate::value::ErrValue::NotSupport`]
+    /// if the bootloader is unknown.
+    fn enter_bootloader(&self) -> RetValue<()>;
+
+    /// Leave the application and start the image whose vector table is at `address`, like a
+    /// bootloader starts the application in a slot.
+    ///
+    /// It never returns if it succeeds, and it returns [`crate::value::ErrValue::NotSupport`]
+    /// if the core is unknown.
+    fn start_image(&self, _address: u32) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+}
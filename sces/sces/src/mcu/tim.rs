@@ -1,40 +1,316 @@
+//! Provide the common traits to operate the hardware timers, as a time base, a PWM output, an
+//! input capture or an encoder interface.
+
+use sces_derive::EnumAsU32;
+
 use super::EventLaunch;
-use crate::value::RetValue;
+use crate::value::{ErrValue, RetValue};
+
+/// The duty when the output is always active, the duty is in 0.01%.
+pub const TIM_DUTY_FULL: u32 = 10_000;
+
+/// The channels of a timer, each channel is a PWM output or an input capture.
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumAsU32)]
+pub enum TimChannel
+{
+    Channel1 = 0,
+    Channel2 = 1,
+    Channel3 = 2,
+    Channel4 = 3,
+    Unknown = 255,
+}
+
+/// The edges of the input signal which are captured.
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumAsU32)]
+pub enum TimCaptureEdge
+{
+    Rising = 0,
+    Falling = 1,
+    Both = 2,
+    Unknown = 255,
+}
+
+/// The direction of the counter of an encoder interface.
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumAsU32)]
+pub enum TimDirection
+{
+    Up = 0,
+    Down = 1,
+    Unknown = 255,
+}
 
+/// `TimBaseCtrl` is the common trait for the timer which counts the time, and raises an event
+/// every period.
 pub trait TimBaseCtrl
 where
     Self: EventLaunch<dyn TimBaseCtrlEvent>,
 {
+    /// Start to count.
     fn activate(&self) -> RetValue<()>;
+
+    /// Stop to count.
     fn deactivate(&self);
+
+    /// Start to count, and raise [`TimBaseCtrlEvent::on_tim_base_elapse`] every period.
     fn async_activate(&self) -> RetValue<()>;
+
+    /// Stop to count and to raise the events.
     fn async_deactivate(&self);
+
+    /// Start to count, and load the period of every next period from `data` by the DMA.
     fn async_activate_data(&self, data: &mut [u32]) -> RetValue<()>;
+
+    /// Stop to count and to load the periods.
     fn async_deactivate_data(&self);
+
+    /// Get the value of the counter.
     fn count_value(&self) -> u32;
+
+    /// Set the value of the counter.
+    fn set_count_value(&self, value: u32);
+
+    /// Get the frequency of the counter in Hz.
+    fn count_freq(&self) -> u32;
+
+    /// Set the frequency of the periods in Hz, by choosing the prescaler and the period of the
+    /// counter, it takes effect from the next period.
+    fn set_frequency(&self, freq: u32) -> RetValue<()>;
+
+    /// Get the frequency of the periods in Hz.
+    fn frequency(&self) -> u32;
 }
 
+/// `TimBaseCtrlEvent` handles the events from the time base.
+///
+/// The callback functions are called in the interrupt handler, don't wait in them.
 pub trait TimBaseCtrlEvent
 {
+    /// A period of the timer has elapsed.
     fn on_tim_base_elapse(&self) {}
+
+    /// The timer has some errors, like the DMA fails.
+    fn on_tim_error(&self) {}
 }
 
+/// `TimPwmCtrl` is the common trait for one channel of a timer which outputs the PWM.
+///
+/// All channels of a timer share the counter, so the frequency is the one of the timer, and the
+/// duties of all channels are kept when it's changed.
 pub trait TimPwmCtrl
 where
     Self: EventLaunch<dyn TimPwmCtrlEvent>,
 {
+    /// Get the channel of the output.
+    fn channel(&self) -> TimChannel;
+
+    /// Start to output.
     fn activate(&self) -> RetValue<()>;
+
+    /// Stop to output.
     fn deactivate(&self);
+
+    /// Start to output, and raise [`TimPwmCtrlEvent::on_tim_pwm_finish`] every pulse.
     fn async_activate(&self) -> RetValue<()>;
+
+    /// Stop to output and to raise the events.
     fn async_deactivate(&self);
+
+    /// Start to output, and load the pulse of every next period from `data` by the DMA.
     fn async_activate_data(&self, data: &mut [u32]) -> RetValue<()>;
+
+    /// Stop to output and to load the pulses.
     fn async_deactivate_data(&self);
+
+    /// Start the complementary output of the channel, the output is inverted from the channel
+    /// with the dead time set by the platform code.
+    ///
+    /// It fails if the timer has no complementary output.
+    fn activate_complementary(&self) -> RetValue<()>;
+
+    /// Stop the complementary output of the channel.
+    fn deactivate_complementary(&self);
+
+    /// Get the value of the counter.
     fn count_value(&self) -> u32;
+
+    /// Set the frequency of the PWM in Hz, it's shared by all channels of the timer.
+    fn set_frequency(&self, freq: u32) -> RetValue<()>;
+
+    /// Get the frequency of the PWM in Hz.
+    fn frequency(&self) -> u32;
+
+    /// Set the duty of the channel from `0` to [`TIM_DUTY_FULL`].
+    fn set_duty(&self, duty: u32) -> RetValue<()>;
+
+    /// Get the duty of the channel from `0` to [`TIM_DUTY_FULL`].
+    fn duty(&self) -> u32;
+
+    /// Set the pulse of the channel in the counts of the counter, it's the raw value of the duty.
+    fn set_pulse(&self, pulse: u32) -> RetValue<()>;
 }
 
+/// `TimPwmCtrlEvent` handles the events from the PWM output.
+///
+/// The callback functions are called in the interrupt handler, don't wait in them.
 pub trait TimPwmCtrlEvent
 {
+    /// A pulse has finished.
     fn on_tim_pwm_finish(&self) {}
+
+    /// The timer has some errors, like the DMA fails.
+    fn on_tim_error(&self) {}
+}
+
+/// `TimCaptureCtrl` is the common trait for one channel of a timer which captures the value of
+/// the counter at the edges of the input.
+pub trait TimCaptureCtrl
+where
+    Self: EventLaunch<dyn TimCaptureCtrlEvent>,
+{
+    /// Get the channel of the input.
+    fn channel(&self) -> TimChannel;
+
+    /// Start to capture at `edge`, the value is got by [`TimCaptureCtrl::captured_value`].
+    fn activate(&self, edge: TimCaptureEdge) -> RetValue<()>;
+
+    /// Stop to capture.
+    fn deactivate(&self);
+
+    /// Start to capture at `edge`, and raise [`TimCaptureCtrlEvent::on_tim_capture`] at every
+    /// capture.
+    fn async_activate(&self, edge: TimCaptureEdge) -> RetValue<()>;
+
+    /// Start to measure the period and the pulse width of the input, by capturing the rising
+    /// and the falling edges one after another, and raise [`TimCaptureCtrlEvent::on_tim_measure`]
+    /// every period.
+    ///
+    /// The period should be shorter than the one of the counter.
+    fn async_measure(&self) -> RetValue<()>;
+
+    /// Stop to capture and to raise the events.
+    fn async_deactivate(&self);
+
+    /// Get the value of the counter of the last capture.
+    fn captured_value(&self) -> u32;
+
+    /// Get the frequency of the counter in Hz.
+    fn count_freq(&self) -> u32;
+
+    /// Get the frequency of the input in Hz by the last measured period, it's `0` if no period
+    /// has been measured.
+    fn frequency(&self) -> u32;
+
+    /// Get the high pulse width of the input in the counts of the counter.
+    fn pulse_width(&self) -> u32;
+}
+
+/// `TimCaptureCtrlEvent` handles the events from the input capture.
+///
+/// The callback functions are called in the interrupt handler, don't wait in them.
+pub trait TimCaptureCtrlEvent
+{
+    /// The counter has been captured with `value`.
+    fn on_tim_capture(&self, _value: u32) {}
+
+    /// A period of the input has been measured, `period` and `width` are in the counts of the
+    /// counter.
+    fn on_tim_measure(&self, _period: u32, _width: u32) {}
+
+    /// The timer has some errors, like the DMA fails.
+    fn on_tim_error(&self) {}
+}
+
+/// `TimEncoderCtrl` is the common trait for the timer which counts the pulses of a quadrature
+/// encoder.
+pub trait TimEncoderCtrl
+{
+    /// Start to count the pulses.
+    fn activate(&self) -> RetValue<()>;
+
+    /// Stop to count the pulses.
+    fn deactivate(&self);
+
+    /// Get the value of the counter, it wraps at the period of the counter.
+    fn count_value(&self) -> u32;
+
+    /// Set the value of the counter.
+    fn set_count_value(&self, value: u32);
+
+    /// Get the direction of the last counting.
+    fn direction(&self) -> TimDirection;
+}
+
+/// Split the division from `clock` to `freq` into the divider of the prescaler and the one of the
+/// period, the period is no more than `max_period` counts.
+///
+/// The registers of the prescaler and the period are usually the dividers minus 1.
+pub fn tim_split_frequency(clock: u32, freq: u32, max_period: u32) -> RetValue<(u32, u32)>
+{
+    let total = clock.checked_div(freq).filter(|x| *x > 0).ok_or(ErrValue::Param)? as u64;
+    let prescaler = total.div_ceil(max_period as u64 + 1);
+
+    (prescaler <= u16::MAX as u64 + 1).then_some(()).ok_or(ErrValue::Param)?;
+    Ok((prescaler as u32, (total / prescaler) as u32))
+}
+
+/// The measurement of the period and the pulse width by the captures at the rising and the
+/// falling edges one after another.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TimCaptureMeter
+{
+    rise: Option<u32>,
+    period: u32,
+    width: u32,
+}
+
+impl TimCaptureMeter
+{
+    pub const fn new() -> Self
+    {
+        Self { rise: None, period: 0, width: 0 }
+    }
+
+    /// Feed the capture `value` at a rising edge or a falling edge, the counter wraps after
+    /// `top`. It returns `true` when a new period has been measured.
+    pub fn feed(&mut self, value: u32, rising: bool, top: u32) -> bool
+    {
+        let since = |from: u32| match value >= from
+        {
+            true => value - from,
+            false => (top - from).wrapping_add(value).wrapping_add(1),
+        };
+
+        match (rising, self.rise)
+        {
+            (true, rise) =>
+            {
+                rise.inspect(|x| self.period = since(*x));
+                self.rise = Some(value);
+                rise.is_some()
+            }
+            (false, Some(rise)) =>
+            {
+                self.width = since(rise);
+                false
+            }
+            (false, None) => false,
+        }
+    }
+
+    /// Get the last period in the counts of the counter, it's `0` if no period has been measured.
+    pub fn period(&self) -> u32
+    {
+        self.period
+    }
+
+    /// Get the last high pulse width in the counts of the counter.
+    pub fn width(&self) -> u32
+    {
+        self.width
+    }
 }
 
 pub struct TimBaseDevice
@@ -94,3 +370,116 @@ impl AsMut<dyn TimPwmCtrl> for TimPwmDevice
         unsafe { &mut *self.instance }
     }
 }
+
+pub struct TimCaptureDevice
+{
+    instance: *mut dyn TimCaptureCtrl,
+}
+
+impl TimCaptureDevice
+{
+    pub const fn new(instance: &'static mut dyn TimCaptureCtrl) -> Self
+    {
+        Self { instance }
+    }
+}
+
+impl AsRef<dyn TimCaptureCtrl> for TimCaptureDevice
+{
+    fn as_ref(&self) -> &'static dyn TimCaptureCtrl
+    {
+        unsafe { &*self.instance }
+    }
+}
+
+impl AsMut<dyn TimCaptureCtrl> for TimCaptureDevice
+{
+    fn as_mut(&mut self) -> &'static mut dyn TimCaptureCtrl
+    {
+        unsafe { &mut *self.instance }
+    }
+}
+
+pub struct TimEncoderDevice
+{
+    instance: *mut dyn TimEncoderCtrl,
+}
+
+impl TimEncoderDevice
+{
+    pub const fn new(instance: &'static mut dyn TimEncoderCtrl) -> Self
+    {
+        Self { instance }
+    }
+}
+
+impl AsRef<dyn TimEncoderCtrl> for TimEncoderDevice
+{
+    fn as_ref(&self) -> &'static dyn TimEncoderCtrl
+    {
+        unsafe { &*self.instance }
+    }
+}
+
+impl AsMut<dyn TimEncoderCtrl> for TimEncoderDevice
+{
+    fn as_mut(&mut self) -> &'static mut dyn TimEncoderCtrl
+    {
+        unsafe { &mut *self.instance }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn split_frequency()
+    {
+        assert_eq!(tim_split_frequency(84_000_000, 1_000, 0xFFFF).unwrap(), (2, 42_000));
+        assert_eq!(tim_split_frequency(84_000_000, 1_000, 0xFFFF_FFFF).unwrap(), (1, 84_000));
+        assert_eq!(tim_split_frequency(84_000_000, 1, 0xFFFF).unwrap(), (1282, 65_522));
+        assert_eq!(tim_split_frequency(84_000_000, 84_000_000, 0xFFFF).unwrap(), (1, 1));
+    }
+
+    #[test]
+    fn split_frequency_out_of_range()
+    {
+        assert!(tim_split_frequency(84_000_000, 0, 0xFFFF).is_err());
+        assert!(tim_split_frequency(84_000_000, 84_000_001, 0xFFFF).is_err());
+
+        // The prescaler is 16 bits on all timers.
+        assert!(tim_split_frequency(84_000_000, 1, 0xFF).is_err());
+    }
+
+    #[test]
+    fn capture_meter()
+    {
+        let mut meter = TimCaptureMeter::new();
+
+        assert!(!meter.feed(50, false, 0xFFFF));
+        assert_eq!(meter.width(), 0);
+
+        assert!(!meter.feed(100, true, 0xFFFF));
+        assert!(!meter.feed(400, false, 0xFFFF));
+        assert_eq!(meter.width(), 300);
+        assert_eq!(meter.period(), 0);
+
+        assert!(meter.feed(1100, true, 0xFFFF));
+        assert_eq!(meter.period(), 1000);
+    }
+
+    #[test]
+    fn capture_meter_across_wrap()
+    {
+        let mut meter = TimCaptureMeter::new();
+
+        meter.feed(65_000, true, 0xFFFF);
+        meter.feed(200, false, 0xFFFF);
+        assert_eq!(meter.width(), 736);
+
+        assert!(meter.feed(464, true, 0xFFFF));
+        assert_eq!(meter.period(), 1000);
+    }
+}
@@ -1,8 +1,14 @@
 //! Provide a common trait to operate the Universal Asynchronous Receiver Transmitter (UART).
 
+use super::io::{IoCtrl, IoState};
 use super::EventLaunch;
+use crate::os::tick::Duration;
 use crate::value::RetValue;
 
+mod buffered;
+
+pub use buffered::BufferedUart;
+
 /// A common trait to control UART peripheral, with functions to let the UART to do
 /// some basic actions.
 ///
@@ -21,8 +27,8 @@ where
     /// The count of transmited data is specified by the length of the slice.
     ///
     /// You could input a timeout value, to avoid the transmiting cost such a long time.
-    /// If you want to wait until the transmission end, you should set `timeout` with `0xFFFFFFFF`.
-    fn transmit(&self, data: &[u8], timeout: u32) -> RetValue<()>;
+    /// If you want to wait until the transmission end, you should set `timeout` with `Duration::MAX`.
+    fn transmit(&self, data: &[u8], timeout: Duration) -> RetValue<()>;
 
     /// Receive some data until the UART to be idle.
     ///
@@ -39,8 +45,8 @@ where
     /// You could input a timeout value, to avoid to hung the system a long time if the remote
     /// doesn't transmit data in a long time.
     /// If you want to wait until and hung until receive something, you should set `timeout` with
-    /// `0xFFFFFFFF`.
-    fn receive(&self, data: &mut [u8], timeout: u32) -> RetValue<u32>;
+    /// `Duration::MAX`.
+    fn receive(&self, data: &mut [u8], timeout: Duration) -> RetValue<u32>;
 
     /// Receive the specified length data via UART.
     ///
@@ -53,8 +59,8 @@ where
     /// You could input a timeout value, to avoid to hung the system a long time if the remote
     /// doesn't transmit enough data in a long time.
     /// If you want to wait until and hung until receive something, you should set `timeout` with
-    /// `0xFFFFFFFF`.
-    fn receive_size(&self, data: &mut [u8], timeout: u32) -> RetValue<()>;
+    /// `Duration::MAX`.
+    fn receive_size(&self, data: &mut [u8], timeout: Duration) -> RetValue<()>;
 
     /// Transmit the specified length data via UART in asynchronous mode.
     ///
@@ -94,10 +100,31 @@ where
     /// DMA in the initialization code.
     fn async_receive_size(&self, data: &mut [u8]) -> RetValue<()>;
 
+    /// Receive continuously into `data` as a ring buffer, until [`UartCtrl::abort`] is called.
+    ///
+    /// The reception goes back to the start of `data` when it reaches the end, so it never stops
+    /// and no byte is lost between two events.
+    /// The event [`UartCtrlEvent::on_uart_rx_complete`] will be called when the first half or
+    /// the whole of `data` is filled, and when the UART comes back to idle, with the position
+    /// behind the last received byte in `data`, it's the length of `data` when the end is reached.
+    /// The reception stops when the UART has some errors, and it should be started again.
+    /// Use this function means that you have enable the circular DMA reception of this UART in
+    /// the initialization code, if the implementation can't switch it by itself.
+    fn async_receive_circular(&self, data: &mut [u8]) -> RetValue<()>;
+
     /// Abort all asynchronous actions.
     ///
     /// Use this function to abort all asynchronous transmit or receive actions.
     fn abort(&self) -> RetValue<()>;
+
+    /// Configure the baud rate, the framing, the flow control and the RS-485 driver of the UART.
+    ///
+    /// The UART is initialized again with `config`, so all transfers should have been stopped.
+    /// It returns `ErrValue::NotSupport` for the settings which the peripheral doesn't have.
+    fn configure(&mut self, config: &UartConfig) -> RetValue<()>;
+
+    /// Send a break, which keeps the TX line low for a whole frame at least.
+    fn send_break(&self) -> RetValue<()>;
 }
 
 /// `UartEventAgent` as the meanings of the word, it is an agent, or the real handler, whatever,
@@ -122,6 +149,8 @@ pub trait UartCtrlEvent
     /// Please attention, if the received data length is over the length of slice `data`, this
     /// function will also be called and the `_size` is the length of slice `data`, the overed
     /// data will be drop.
+    /// In [`UartCtrl::async_receive_circular`], `_size` is the position behind the last received
+    /// byte in `data` instead.
     fn on_uart_rx_complete(&self, _size: u32) {}
 
     /// This function will call when you received specified length of data and it has been move to the buffer
@@ -132,7 +161,172 @@ pub trait UartCtrlEvent
     fn on_uart_abort_complete(&self) {}
 
     /// This function will be called when the UART peripheral has some errors.
-    fn on_uart_error(&self) {}
+    ///
+    /// The reception in progress is stopped by the errors, and `_error` tells what the errors
+    /// are.
+    fn on_uart_error(&self, _error: UartError) {}
+}
+
+/// The count of data bits in a frame, the parity bit is not included.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UartDataBits
+{
+    Seven,
+    Eight,
+    Nine,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UartParity
+{
+    None,
+    Even,
+    Odd,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UartStopBits
+{
+    Half,
+    One,
+    OneAndHalf,
+    Two,
+}
+
+/// The hardware flow control, `Rts` lets the UART tell the remote to stop when it can't receive
+/// more, and `Cts` lets the remote stop the transmission of the UART.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UartFlowControl
+{
+    None,
+    Rts,
+    Cts,
+    RtsCts,
+}
+
+/// The driver enable of a RS-485 transceiver, which is active during the transmission.
+#[derive(Clone, Copy)]
+pub enum UartDriverEnable
+{
+    /// The DE pin of the peripheral, which is driven by the hardware.
+    Native(UartDriverTiming),
+
+    /// A GPIO which is driven by the driver, it's set to the state before the transmission and
+    /// set back when the transmission is completed.
+    Pin(&'static dyn IoCtrl, IoState),
+}
+
+/// The active state of the DE pin of the peripheral, and the time between the DE and the start
+/// or the end of a frame, in the sample time of the UART, which is 1/16 or 1/8 of a bit.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct UartDriverTiming
+{
+    pub active: IoState,
+    pub assertion: u8,
+    pub deassertion: u8,
+}
+
+/// The settings of an UART, the default is `115200 8N1` without the flow control.
+///
+/// ```ignore
+/// uart.configure(&UartConfig::new(9600).with_parity(UartParity::Even))?;
+/// ```
+#[derive(Clone, Copy)]
+pub struct UartConfig
+{
+    pub baud_rate: u32,
+    pub data_bits: UartDataBits,
+    pub parity: UartParity,
+    pub stop_bits: UartStopBits,
+    pub flow_control: UartFlowControl,
+    pub driver_enable: Option<UartDriverEnable>,
+
+    /// Detect the breaks on the RX line, which are reported by [`UartError::line_break`].
+    pub break_detect: bool,
+}
+
+impl UartConfig
+{
+    pub const fn new(baud_rate: u32) -> Self
+    {
+        Self {
+            baud_rate,
+            data_bits: UartDataBits::Eight,
+            parity: UartParity::None,
+            stop_bits: UartStopBits::One,
+            flow_control: UartFlowControl::None,
+            driver_enable: None,
+            break_detect: false,
+        }
+    }
+
+    pub const fn with_data_bits(mut self, data_bits: UartDataBits) -> Self
+    {
+        self.data_bits = data_bits;
+        self
+    }
+
+    pub const fn with_parity(mut self, parity: UartParity) -> Self
+    {
+        self.parity = parity;
+        self
+    }
+
+    pub const fn with_stop_bits(mut self, stop_bits: UartStopBits) -> Self
+    {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    pub const fn with_flow_control(mut self, flow_control: UartFlowControl) -> Self
+    {
+        self.flow_control = flow_control;
+        self
+    }
+
+    pub const fn with_driver_enable(mut self, driver_enable: UartDriverEnable) -> Self
+    {
+        self.driver_enable = Some(driver_enable);
+        self
+    }
+
+    pub const fn with_break_detect(mut self, break_detect: bool) -> Self
+    {
+        self.break_detect = break_detect;
+        self
+    }
+}
+
+impl Default for UartConfig
+{
+    fn default() -> Self
+    {
+        Self::new(115_200)
+    }
+}
+
+/// The errors reported by [`UartCtrlEvent::on_uart_error`], several of them could occur at once.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct UartError
+{
+    /// The parity bit of a frame doesn't match its data.
+    pub parity: bool,
+
+    /// The noise is detected in the samples of a bit.
+    pub noise: bool,
+
+    /// The stop bit of a frame is not found.
+    pub framing: bool,
+
+    /// A frame is received before the last one is taken.
+    pub overrun: bool,
+
+    /// A break is received, it's also a framing error, and it's only detected when
+    /// [`UartConfig::break_detect`] is set.
+    pub line_break: bool,
+
+    /// The DMA fails to move the data.
+    pub dma: bool,
 }
 
 pub struct UartDevice
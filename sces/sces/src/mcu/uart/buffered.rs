@@ -0,0 +1,431 @@
+//! A buffered UART over [`UartCtrl`], which receives continuously into a ring buffer and queues
+//! the data to transmit.
+//!
+//! [`UartCtrl::async_receive`] stops after each reception, so the bytes which arrive before it's
+//! started again are lost. [`BufferedUart`] keeps a circular reception running instead, readers
+//! take the received bytes from the ring with [`BufferedUart::read`], and writers push the bytes
+//! to the transmit queue with [`BufferedUart::write`] without waiting for the transmission:
+//!
+//! ```ignore
+//! static mut SHELL_UART: Option<BufferedUart<MWOS, 256, 512>> = None;
+//!
+//! let uart = SHELL_UART.insert(BufferedUart::new(UartQueue::alloc(&mut huart1)?)?);
+//! uart.start()?;
+//!
+//! let mut line = [0; 64];
+//! let size = uart.read(&mut line, Duration::from_millis(100))?;
+//! uart.write(&line[..size]);
+//! ```
+
+use core::cell::UnsafeCell;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+use super::{UartCtrl, UartCtrlEvent, UartDevice, UartError};
+use crate::os::mutex::IMutex;
+use crate::os::semaphore::ISemaphore;
+use crate::os::tick::Duration;
+use crate::os::RTOS;
+use crate::value::{ErrValue, RetValue};
+
+/// The UART with a receive ring of `RX` bytes and a transmit queue of `TX` bytes.
+///
+/// The positions of both rings are counted from the start and wrap at `usize::MAX`, so the count
+/// of bytes in a ring is the difference of its two positions.
+///
+/// It becomes the event agent of the UART when it's started, so it should be placed in a static
+/// place, and not be moved after that.
+pub struct BufferedUart<OS: RTOS, const RX: usize, const TX: usize>
+{
+    uart: UartDevice,
+
+    rx: UnsafeCell<[u8; RX]>,
+    rx_position: AtomicUsize,
+    rx_received: AtomicUsize,
+    rx_consumed: AtomicUsize,
+    rx_stopped: AtomicBool,
+    rx_signal: OS::Semaphore,
+    rx_lock: OS::Mutex,
+
+    tx: UnsafeCell<[u8; TX]>,
+    tx_queued: AtomicUsize,
+    tx_sent: AtomicUsize,
+    tx_flight: AtomicUsize,
+    tx_busy: AtomicBool,
+    tx_lock: OS::Mutex,
+
+    rx_overflows: AtomicU32,
+    tx_overflows: AtomicU32,
+    errors: AtomicU32,
+}
+
+impl<OS: RTOS + 'static, const RX: usize, const TX: usize> BufferedUart<OS, RX, TX>
+{
+    pub fn new(uart: &'static mut dyn UartCtrl) -> RetValue<Self>
+    {
+        if RX == 0 || TX == 0 || RX > u16::MAX as usize
+        {
+            return Err(ErrValue::Param);
+        }
+
+        Ok(Self {
+            uart: UartDevice::new(uart),
+            rx: UnsafeCell::new([0; RX]),
+            rx_position: AtomicUsize::new(0),
+            rx_received: AtomicUsize::new(0),
+            rx_consumed: AtomicUsize::new(0),
+            rx_stopped: AtomicBool::new(true),
+            rx_signal: OS::Semaphore::new(1)?,
+            rx_lock: OS::Mutex::new()?,
+            tx: UnsafeCell::new([0; TX]),
+            tx_queued: AtomicUsize::new(0),
+            tx_sent: AtomicUsize::new(0),
+            tx_flight: AtomicUsize::new(0),
+            tx_busy: AtomicBool::new(false),
+            tx_lock: OS::Mutex::new()?,
+            rx_overflows: AtomicU32::new(0),
+            tx_overflows: AtomicU32::new(0),
+            errors: AtomicU32::new(0),
+        })
+    }
+
+    /// Become the event agent of the UART and start the circular reception.
+    pub fn start(&mut self) -> RetValue<()>
+    {
+        let agent = NonNull::from(&*self);
+        self.uart.as_mut().set_event_agent(unsafe { agent.as_ref() });
+
+        self.rx_restart()
+    }
+
+    /// Abort the reception and the transmission, and give the events of the UART back.
+    ///
+    /// The received bytes which are not read and the queued bytes which are not sent are dropped.
+    pub fn stop(&mut self) -> RetValue<()>
+    {
+        self.rx_stopped.store(true, Ordering::Release);
+        let value = self.uart.as_ref().abort();
+        self.uart.as_mut().clean_event_agent();
+
+        self.rx_consumed.store(self.rx_received.load(Ordering::Acquire), Ordering::Release);
+        self.tx_sent.store(self.tx_queued.load(Ordering::Acquire), Ordering::Release);
+        self.tx_busy.store(false, Ordering::Release);
+
+        value
+    }
+
+    /// Read the received bytes into `buf`, and return the count of them.
+    ///
+    /// It returns at once with the bytes in the ring, which may be less than the length of `buf`,
+    /// and waits for `timeout` at most only when the ring is empty.
+    /// The reception is started again here after it's stopped by an error, when all bytes
+    /// received before the error have been read.
+    pub fn read(&self, buf: &mut [u8], timeout: Duration) -> RetValue<usize>
+    {
+        if buf.is_empty()
+        {
+            return Ok(0);
+        }
+
+        let start = OS::now();
+        self.rx_lock.attempt_lock(timeout)?;
+
+        let value = loop
+        {
+            let size = self.rx_pop(buf);
+            if size > 0
+            {
+                break Ok(size);
+            }
+
+            if self.rx_stopped.load(Ordering::Acquire)
+            {
+                if let Err(e) = self.rx_restart()
+                {
+                    break Err(e);
+                }
+            }
+
+            let remain = match timeout.is_forever()
+            {
+                true => Duration::MAX,
+                false => timeout.saturating_sub(OS::now().duration_since(start)),
+            };
+
+            if let Err(e) = self.rx_signal.attempt_take(remain)
+            {
+                break Err(e);
+            }
+        };
+
+        self.rx_lock.unlock();
+        value
+    }
+
+    /// Get the count of the received bytes which are not read.
+    pub fn available(&self) -> usize
+    {
+        let pending = self
+            .rx_received
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.rx_consumed.load(Ordering::Acquire));
+        pending.min(RX)
+    }
+
+    /// Queue `data` to transmit, and return the count of the bytes queued.
+    ///
+    /// It never waits for the transmission, the bytes which don't fit into the queue are dropped
+    /// and counted by [`BufferedUart::tx_overflows`]. It should not be called in the interrupt
+    /// handlers.
+    pub fn write(&self, data: &[u8]) -> usize
+    {
+        self.tx_lock.lock();
+
+        let queued = self.tx_queued.load(Ordering::Acquire);
+        let space = TX - queued.wrapping_sub(self.tx_sent.load(Ordering::Acquire));
+        let size = data.len().min(space);
+
+        ring_write(unsafe { &mut *self.tx.get() }, queued % TX, &data[..size]);
+        self.tx_queued.store(queued.wrapping_add(size), Ordering::Release);
+
+        self.tx_lock.unlock();
+
+        if size < data.len()
+        {
+            self.tx_overflows.fetch_add((data.len() - size) as u32, Ordering::Relaxed);
+        }
+
+        if !self.tx_busy.swap(true, Ordering::AcqRel)
+        {
+            self.tx_kick();
+        }
+
+        size
+    }
+
+    /// Get the count of the queued bytes which are not sent.
+    pub fn pending(&self) -> usize
+    {
+        self.tx_queued.load(Ordering::Acquire).wrapping_sub(self.tx_sent.load(Ordering::Acquire))
+    }
+
+    /// Get the count of the received bytes which are overwritten before they are read.
+    pub fn rx_overflows(&self) -> u32
+    {
+        self.rx_overflows.load(Ordering::Relaxed)
+    }
+
+    /// Get the count of the bytes which are dropped because the transmit queue is full, or the
+    /// transmission fails to start.
+    pub fn tx_overflows(&self) -> u32
+    {
+        self.tx_overflows.load(Ordering::Relaxed)
+    }
+
+    /// Get the count of the errors of the UART.
+    pub fn errors(&self) -> u32
+    {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// Start the circular reception at the start of the ring, which is the position of the next
+    /// byte to read.
+    fn rx_restart(&self) -> RetValue<()>
+    {
+        self.rx_position.store(0, Ordering::Release);
+        self.rx_received.store(0, Ordering::Release);
+        self.rx_consumed.store(0, Ordering::Release);
+
+        self.uart.as_ref().async_receive_circular(unsafe { &mut *self.rx.get() })?;
+        self.rx_stopped.store(false, Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Copy the oldest received bytes into `buf`, the oldest ones are dropped if the ring has
+    /// been overrun.
+    fn rx_pop(&self, buf: &mut [u8]) -> usize
+    {
+        let received = self.rx_received.load(Ordering::Acquire);
+        let mut consumed = self.rx_consumed.load(Ordering::Acquire);
+
+        let pending = received.wrapping_sub(consumed);
+        if pending > RX
+        {
+            self.rx_overflows.fetch_add((pending - RX) as u32, Ordering::Relaxed);
+            consumed = received.wrapping_sub(RX);
+        }
+
+        let size = pending.min(RX).min(buf.len());
+        ring_read(unsafe { &*self.rx.get() }, consumed % RX, &mut buf[..size]);
+        self.rx_consumed.store(consumed.wrapping_add(size), Ordering::Release);
+
+        size
+    }
+
+    /// Transmit the next continuous part of the queue, the caller should have set `tx_busy`.
+    fn tx_kick(&self)
+    {
+        loop
+        {
+            let sent = self.tx_sent.load(Ordering::Acquire);
+            let queued = self.tx_queued.load(Ordering::Acquire);
+            let size = queued.wrapping_sub(sent).min(TX - sent % TX);
+
+            if size == 0
+            {
+                self.tx_busy.store(false, Ordering::Release);
+
+                // A writer may have queued some bytes after the check but seen `tx_busy` set.
+                match self.tx_queued.load(Ordering::Acquire) == sent
+                    || self.tx_busy.swap(true, Ordering::AcqRel)
+                {
+                    true => return,
+                    false => continue,
+                }
+            }
+
+            self.tx_flight.store(size, Ordering::Release);
+
+            let ring = unsafe { &*self.tx.get() };
+            if self.uart.as_ref().async_transmit(&ring[sent % TX..sent % TX + size]).is_ok()
+            {
+                return;
+            }
+
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            self.tx_overflows.fetch_add(queued.wrapping_sub(sent) as u32, Ordering::Relaxed);
+            self.tx_sent.store(queued, Ordering::Release);
+        }
+    }
+}
+
+impl<OS: RTOS + 'static, const RX: usize, const TX: usize> UartCtrlEvent
+    for BufferedUart<OS, RX, TX>
+{
+    fn on_uart_tx_complete(&self)
+    {
+        let flight = self.tx_flight.swap(0, Ordering::AcqRel);
+        self.tx_sent.fetch_add(flight, Ordering::AcqRel);
+        self.tx_kick();
+    }
+
+    /// It's called at the half and the end of the ring and when the UART is idle, `position` is
+    /// `RX` at the end of the ring.
+    fn on_uart_rx_complete(&self, position: u32)
+    {
+        let size = ring_advance(self.rx_position.load(Ordering::Acquire), position as usize, RX);
+        if size == 0
+        {
+            return;
+        }
+
+        self.rx_position.store(position as usize % RX, Ordering::Release);
+        self.rx_received.fetch_add(size, Ordering::AcqRel);
+        self.rx_signal.release();
+    }
+
+    /// The reception is stopped by the errors, it's started again by the next read.
+    fn on_uart_error(&self, _error: UartError)
+    {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        self.rx_stopped.store(true, Ordering::Release);
+        self.rx_signal.release();
+    }
+}
+
+unsafe impl<OS: RTOS, const RX: usize, const TX: usize> Send for BufferedUart<OS, RX, TX> {}
+
+unsafe impl<OS: RTOS, const RX: usize, const TX: usize> Sync for BufferedUart<OS, RX, TX> {}
+
+/// Get the count of the bytes received from the position `last` to `position` in a ring of
+/// `capacity` bytes, `position` could be `capacity` at the end of the ring.
+fn ring_advance(last: usize, position: usize, capacity: usize) -> usize
+{
+    (position % capacity + capacity - last) % capacity
+}
+
+/// Copy the bytes of `ring` from `start` into `buf` with wrapping, `buf` should not be longer than
+/// `ring`.
+fn ring_read(ring: &[u8], start: usize, buf: &mut [u8])
+{
+    let first = buf.len().min(ring.len() - start);
+    let (head, tail) = buf.split_at_mut(first);
+
+    head.copy_from_slice(&ring[start..start + first]);
+    tail.copy_from_slice(&ring[..tail.len()]);
+}
+
+/// Copy `data` into `ring` from `start` with wrapping, `data` should not be longer than `ring`.
+fn ring_write(ring: &mut [u8], start: usize, data: &[u8])
+{
+    let first = data.len().min(ring.len() - start);
+
+    ring[start..start + first].copy_from_slice(&data[..first]);
+    ring[..data.len() - first].copy_from_slice(&data[first..]);
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn advance()
+    {
+        assert_eq!(ring_advance(0, 10, 256), 10);
+        assert_eq!(ring_advance(200, 56, 256), 112);
+        assert_eq!(ring_advance(10, 10, 256), 0);
+
+        // The DMA reports the end of the ring as `capacity` at the transfer complete event.
+        assert_eq!(ring_advance(200, 256, 256), 56);
+        assert_eq!(ring_advance(0, 256, 256), 0);
+    }
+
+    #[test]
+    fn read_with_wrap()
+    {
+        let ring = [0, 1, 2, 3, 4, 5, 6, 7];
+        let mut buf = [0; 5];
+
+        ring_read(&ring, 1, &mut buf);
+        assert_eq!(buf, [1, 2, 3, 4, 5]);
+
+        ring_read(&ring, 6, &mut buf);
+        assert_eq!(buf, [6, 7, 0, 1, 2]);
+
+        let mut all = [0; 8];
+        ring_read(&ring, 7, &mut all);
+        assert_eq!(all, [7, 0, 1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn write_with_wrap()
+    {
+        let mut ring = [0; 8];
+
+        ring_write(&mut ring, 2, &[1, 2, 3]);
+        assert_eq!(ring, [0, 0, 1, 2, 3, 0, 0, 0]);
+
+        ring_write(&mut ring, 6, &[4, 5, 6, 7]);
+        assert_eq!(ring, [6, 7, 1, 2, 3, 0, 4, 5]);
+
+        ring_write(&mut ring, 5, &[]);
+        assert_eq!(ring, [6, 7, 1, 2, 3, 0, 4, 5]);
+    }
+
+    #[test]
+    fn write_then_read()
+    {
+        let mut ring = [0; 16];
+        let data: [u8; 16] = core::array::from_fn(|x| x as u8 + 100);
+        let mut buf = [0; 16];
+
+        for start in 0..16
+        {
+            ring_write(&mut ring, start, &data[..11]);
+            ring_read(&ring, start, &mut buf[..11]);
+            assert_eq!(buf[..11], data[..11]);
+        }
+    }
+}
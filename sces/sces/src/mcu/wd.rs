@@ -1,6 +1,142 @@
+//! Provide a common trait to operate the watch dogs, which reset the system when they are not
+//! refreshed in time.
+
+use super::io::{IoDevice, IoState};
+use super::EventLaunch;
+use crate::os::tick::Duration;
+use crate::value::{ErrValue, RetValue};
+
 pub type WatchDogDevice = &'static mut dyn WatchDogCtrl;
 
+/// `WatchDogCtrl` is the common trait for the watch dogs, both the ones in the MCU and the ones
+/// outside of it.
 pub trait WatchDogCtrl
+where
+    Self: EventLaunch<dyn WatchDogCtrlEvent>,
 {
+    /// Start the watch dog, it resets the system if it's not refreshed within `timeout`.
+    ///
+    /// The actual timeout may be a little longer because of the resolution of the counter, see
+    /// [`WatchDogCtrl::timeout`]. Most watch dogs can't be stopped anymore once they are started.
+    fn start(&self, timeout: Duration) -> RetValue<()>;
+
+    /// Get the actual timeout of the watch dog.
+    fn timeout(&self) -> Duration;
+
+    /// Set the window of the watch dog, a refresh within `window` after the last one resets the
+    /// system as the timeout does, and `Duration::ZERO` disables the window.
+    ///
+    /// It returns [`ErrValue::NotSupport`] if the watch dog has no window.
+    fn set_window(&self, window: Duration) -> RetValue<()>;
+
+    /// Get the window of the watch dog, it's `Duration::ZERO` if the window is disabled.
+    fn window(&self) -> Duration;
+
+    /// Freeze the counter of the watch dog when the CPU is halted by the debugger, so a break
+    /// point doesn't reset the system.
+    fn freeze_in_debug(&self, freeze: bool) -> RetValue<()>;
+
+    /// Check whether the last reset is caused by this watch dog.
+    fn is_reset_cause(&self) -> bool;
+
+    /// Refresh the watch dog.
     fn refresh(&self);
 }
+
+/// `WatchDogCtrlEvent` handles the events from the watch dog.
+///
+/// The callback functions are called in the interrupt handler, don't wait in them.
+pub trait WatchDogCtrlEvent
+{
+    /// The watch dog is going to reset the system soon, it's the last chance to save something.
+    ///
+    /// The agent should be set before [`WatchDogCtrl::start`], some watch dogs enable the early
+    /// warning only when they are started.
+    fn on_watch_dog_early_warning(&self) {}
+}
+
+/// A watch dog IC outside of the MCU, like `TPS3823` or `STWD100`, which is refreshed by toggling
+/// its input pin.
+///
+/// The timeout of the IC is fixed by the hardware, so it's given when it's created.
+pub struct ExternalWatchDog
+{
+    input: IoDevice,
+    enable: Option<(IoDevice, IoState)>,
+    timeout: Duration,
+    event_handle: Option<&'static dyn WatchDogCtrlEvent>,
+}
+
+impl ExternalWatchDog
+{
+    /// Create the watch dog refreshed by the pin `input`, it resets the system after `timeout`.
+    pub const fn new(input: IoDevice, timeout: Duration) -> Self
+    {
+        Self { input, enable: None, timeout, event_handle: None }
+    }
+
+    /// Enable the IC by setting the pin `enable` to the level `active` when it's started.
+    pub fn with_enable(mut self, enable: IoDevice, active: IoState) -> Self
+    {
+        self.enable = Some((enable, active));
+        self
+    }
+}
+
+impl EventLaunch<dyn WatchDogCtrlEvent> for ExternalWatchDog
+{
+    fn set_event_agent(&mut self, event_handle: &'static dyn WatchDogCtrlEvent)
+    {
+        self.event_handle = Some(event_handle);
+    }
+
+    fn clean_event_agent(&mut self)
+    {
+        self.event_handle = None;
+    }
+}
+
+impl WatchDogCtrl for ExternalWatchDog
+{
+    /// The `timeout` can't be longer than the one of the IC, otherwise the IC may reset the system
+    /// before the first refresh.
+    fn start(&self, timeout: Duration) -> RetValue<()>
+    {
+        (timeout <= self.timeout).then_some(()).ok_or(ErrValue::Param)?;
+
+        self.refresh();
+        self.enable.as_ref().inspect(|(enable, active)| enable.as_ref().set_state(*active));
+        Ok(())
+    }
+
+    fn timeout(&self) -> Duration
+    {
+        self.timeout
+    }
+
+    fn set_window(&self, _window: Duration) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn window(&self) -> Duration
+    {
+        Duration::ZERO
+    }
+
+    fn freeze_in_debug(&self, _freeze: bool) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    /// The IC resets the system by the reset pin, which can't be told from the other resets.
+    fn is_reset_cause(&self) -> bool
+    {
+        false
+    }
+
+    fn refresh(&self)
+    {
+        self.input.as_ref().toggle();
+    }
+}
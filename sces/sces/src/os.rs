@@ -1,3 +1,4 @@
+use crate::os::tick::{Duration, Instant};
 use crate::value::RetValue;
 
 /// sces OS Library
@@ -11,12 +12,14 @@ use crate::value::RetValue;
 extern crate alloc;
 
 pub mod events;
+pub mod idle;
 pub mod mem;
 pub mod message_queue;
 pub mod mutex;
 pub mod semaphore;
 pub mod sxmutex;
 pub mod task;
+pub mod tick;
 pub mod timer;
 
 #[repr(u32)]
@@ -45,31 +48,31 @@ pub trait RTOS
 {
     /// Common wait time constants
     /// No wait
-    const WAIT_0: u32 = 0;
+    const WAIT_0: Duration = Duration::ZERO;
 
     /// Common wait time constants
     /// 50 milliseconds
-    const WAIT_50: u32 = 50;
+    const WAIT_50: Duration = Duration::from_millis(50);
 
     /// Common wait time constants
     /// 100 milliseconds
-    const WAIT_100: u32 = 100;
+    const WAIT_100: Duration = Duration::from_millis(100);
 
     /// Common wait time constants
     /// 200 milliseconds
-    const WAIT_200: u32 = 200;
+    const WAIT_200: Duration = Duration::from_millis(200);
 
     /// Common wait time constants
     /// 500 milliseconds
-    const WAIT_500: u32 = 500;
+    const WAIT_500: Duration = Duration::from_millis(500);
 
     /// Common wait time constants
     /// Forever wait
-    const WAIT_MAX: u32 = 0xFFFFFFFF;
+    const WAIT_MAX: Duration = Duration::MAX;
 
     /// Common wait time constants
     /// Default wait time
-    const WAIT_DEF: u32 = Self::WAIT_200;
+    const WAIT_DEF: Duration = Self::WAIT_200;
 
     /// Stack size constant for tasks
     /// 1 Kilobyte stack size
@@ -131,6 +134,14 @@ pub trait RTOS
     /// * `u32` - The current OS tick count in milliseconds
     fn ticks() -> u32;
 
+    /// Get the current OS tick count as an instant
+    /// # Returns
+    /// * `Instant` - The current instant, which could be compared with the wrapping arithmetic
+    fn now() -> Instant
+    {
+        Instant::from_ticks(Self::ticks())
+    }
+
     /// Get the current number of tasks
     /// # Returns
     /// * `u32` - The current number of tasks in the OS
@@ -141,6 +152,11 @@ pub trait RTOS
     /// * `Self::Task` - The handle of the currently running task
     fn current_task() -> Self::Task;
 
+    /// Check whether the caller is running in an interrupt handler
+    /// # Returns
+    /// * `bool` - `true` if called from an interrupt service routine
+    fn is_in_isr() -> bool;
+
     /// Switch the execution to the next task
     fn switch_next_task();
 
@@ -148,13 +164,13 @@ pub trait RTOS
     /// This function does not return
     fn exit_current_task();
 
-    /// Create a delay for the specified time in milliseconds
+    /// Create a delay for the specified time
     /// # Arguments
-    /// * `time: u32` - The delay duration in milliseconds
-    fn delay(time: u32);
+    /// * `time: Duration` - The delay duration
+    fn delay(time: Duration);
 
-    /// Create a delay until the specified time in milliseconds
+    /// Create a delay until the specified time
     /// # Arguments
-    /// * `time: u32` - The target time in milliseconds
-    fn delay_interval(time: u32);
+    /// * `time: Instant` - The target time, like the last target plus the period
+    fn delay_interval(time: Instant);
 }
@@ -4,6 +4,7 @@
 /// and receiving events.
 /// The IEvents trait can be implemented for different RTOS backends
 /// to provide a consistent API for event management across various platforms.
+use crate::os::tick::Duration;
 use crate::value::RetValue;
 
 /// Events Interface
@@ -28,8 +29,8 @@ pub trait IEvents
     /// Wait events
     /// # Arguments
     /// * `events: u32` - The events to be waited for
-    /// * `timeout: u32` - The timeout duration in milliseconds
+    /// * `timeout: Duration` - The timeout duration
     /// # Returns
     /// * `RetValue<u32>` - Result containing the received events or an error
-    fn wait(&self, events: u32, timeout: u32) -> RetValue<u32>;
+    fn wait(&self, events: u32, timeout: Duration) -> RetValue<u32>;
 }
@@ -0,0 +1,44 @@
+//! Provide the hook which is called by the RTOS when no task is ready to run, so the system could
+//! save the power while it's idle, see [`crate::mcu::power::PowerIdle`].
+//!
+//! The RTOS backends call [`idle`] from the idle task of the kernel, with the time until the next
+//! timer deadline when the kernel knows it.
+
+use crate::os::tick::Duration;
+
+static mut IDLE_HOOK: Option<&'static dyn IdleHook> = None;
+
+/// Trait for the hook called when the system is idle.
+pub trait IdleHook: Send + Sync
+{
+    /// The system is going to be idle for `idle`, it's `Duration::MAX` when no timer is waiting or
+    /// the kernel doesn't know the next timer deadline.
+    ///
+    /// It's called with the scheduler suspended, so don't wait in it. It returns whether the MCU
+    /// has slept in it, so the RTOS doesn't wait for the interrupt again.
+    fn on_idle(&self, idle: Duration) -> bool;
+}
+
+/// Install the idle hook of the system.
+pub fn install(hook: &'static dyn IdleHook)
+{
+    unsafe { IDLE_HOOK = Some(hook) };
+}
+
+/// Remove the idle hook of the system, the RTOS waits for the interrupt by itself since then.
+pub fn uninstall()
+{
+    unsafe { IDLE_HOOK = None };
+}
+
+/// Get the idle hook installed by [`install`].
+pub fn idle_hook() -> Option<&'static dyn IdleHook>
+{
+    unsafe { IDLE_HOOK }
+}
+
+/// Run the idle hook for the RTOS backends, it returns whether the MCU has slept.
+pub fn idle(idle: Duration) -> bool
+{
+    idle_hook().is_some_and(|x| x.on_idle(idle))
+}
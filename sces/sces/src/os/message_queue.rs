@@ -17,6 +17,7 @@ use core::ffi::c_void;
 /// let message = MyMessageContent { data: [0; 128] };
 /// message_queue.launch(&message, 1000).unwrap();
 /// ```
+use crate::os::tick::Duration;
 use crate::value::RetValue;
 
 /// IMessageQueue Trait
@@ -37,18 +38,18 @@ pub trait IMessageQueue
     /// Send a message into the queue
     /// # Arguments
     /// * `content: &dyn MessageContent` - The message content to be sent
-    /// * `timeout: u32` - The timeout duration in milliseconds
+    /// * `timeout: Duration` - The timeout duration
     /// # Returns
     /// * `RetValue<()>` - Result indicating success or failure
-    fn send(&self, content: &dyn MessageContent, timeout: u32) -> RetValue<()>;
+    fn send(&self, content: &dyn MessageContent, timeout: Duration) -> RetValue<()>;
 
     /// Receive a message from the queue
     /// # Arguments
     /// * `cache: &mut dyn MessageContent` - The buffer to store the received message
-    /// * `timeout: u32` - The timeout duration in milliseconds
+    /// * `timeout: Duration` - The timeout duration
     /// # Returns
     /// * `RetValue<()>` - Result indicating success or failure
-    fn receive(&self, cache: &mut dyn MessageContent, timeout: u32) -> RetValue<()>;
+    fn receive(&self, cache: &mut dyn MessageContent, timeout: Duration) -> RetValue<()>;
 }
 
 /// MessageContent Trait
@@ -24,9 +24,13 @@ use core::cell::{RefCell, RefMut};
 /// with different RTOS backends.
 use core::ops::{Deref, DerefMut};
 
+use crate::os::tick::Duration;
 use crate::os::RTOS;
 use crate::value::RetValue;
 
+/// The timeout of `MutexSample::attempt_lock` and `MutexSample::attempt_lock_then`
+const MUTEX_SAMPLE_WAIT: Duration = Duration::from_secs(1);
+
 /// Mutex Interface
 /// Implement this trait to define mutex handling mechanisms
 /// for your RTOS backend.
@@ -47,10 +51,10 @@ pub trait IMutex
 
     /// Attempt to lock the mutex with a timeout
     /// # Arguments
-    /// * `time: u32` - The timeout duration in milliseconds
+    /// * `time: Duration` - The timeout duration
     /// # Returns
     /// * `RetValue<()>` - Result indicating success or failure
-    fn attempt_lock(&self, time: u32) -> RetValue<()>;
+    fn attempt_lock(&self, time: Duration) -> RetValue<()>;
 
     /// Unlock the mutex
     fn unlock(&self);
@@ -176,7 +180,7 @@ where
     /// ```
     pub fn attempt_lock(&self) -> RetValue<MutexGuid<S>>
     {
-        self.mutex.attempt_lock(1000)?;
+        self.mutex.attempt_lock(MUTEX_SAMPLE_WAIT)?;
         Ok(MutexGuid::new(&self.mutex, self.sample.try_borrow_mut()?))
     }
 
@@ -200,7 +204,7 @@ where
     where
         F: FnOnce(&mut S) -> RetValue<T>,
     {
-        self.mutex.attempt_lock(1000)?;
+        self.mutex.attempt_lock(MUTEX_SAMPLE_WAIT)?;
         let value = f(&mut *self.sample.try_borrow_mut()?);
         self.mutex.unlock();
 
@@ -7,6 +7,7 @@
 /// // Critical section code here
 /// semaphore.back();                             // Release the semaphore
 /// ```
+use crate::os::tick::Duration;
 use crate::value::RetValue;
 
 /// ISemaphore Trait
@@ -27,12 +28,12 @@ pub trait ISemaphore
 
     /// Attempt to take the semaphore with error handling
     /// # Arguments
-    /// * `timeout: u32` - The timeout duration in milliseconds
+    /// * `timeout: Duration` - The timeout duration
     /// # Returns
     /// * `RetValue<()>` - Result indicating success or failure
     /// # Errors
     /// * `ErrValue` - If the semaphore could not be taken within the timeout period
-    fn attempt_take(&self, timeout: u32) -> RetValue<()>;
+    fn attempt_take(&self, timeout: Duration) -> RetValue<()>;
 
     /// Release the semaphore
     fn release(&self);
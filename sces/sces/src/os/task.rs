@@ -3,12 +3,12 @@
 /// This module provides abstractions for creating, managing, and controlling tasks.
 /// It includes traits for task main functions and task management blocks,
 /// as well as a sample implementation to facilitate task handling.
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use core::ffi::c_void;
 use core::ops::{Deref, DerefMut};
 
 use crate::os::RTOS;
-use crate::value::RetValue;
+use crate::value::{ErrValue, RetValue};
 
 /// Task States
 /// Defines various states that a task can be in within the RTOS
@@ -41,6 +41,7 @@ pub enum TaskState
 /// Defines various priority levels for tasks in the RTOS
 /// The priorities range from None to RealTime, allowing for flexible task scheduling.
 #[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TaskPriority
 {
     /// Task has no priority
@@ -145,6 +146,21 @@ pub trait ITask
     /// Resume the task
     /// Returns a RetValue indicating success or failure
     fn resume(&self) -> RetValue<()>;
+
+    /// Terminate the task, it could be activated again later
+    /// Returns a RetValue indicating success or failure
+    fn terminate(&mut self) -> RetValue<()>;
+}
+
+/// Task Restart Interface
+/// Implement this trait for the tasks which could be restarted by others,
+/// like the alive watch service restarts a task which doesn't respond
+pub trait ITaskRestart
+{
+    /// Terminate the task and activate it again with the same parameters
+    /// # Returns
+    /// * `RetValue<()>` - Result indicating success or failure
+    fn restart(&self) -> RetValue<()>;
 }
 
 /// Task Main Interface
@@ -243,6 +259,7 @@ where
     S: Sized + ITaskMain,
 {
     task: RefCell<OS::Task>,
+    setting: Cell<Option<(&'static str, u32, TaskPriority)>>,
     sample: S,
 }
 
@@ -255,20 +272,38 @@ impl<OS: RTOS, S: ITaskMain> TaskSample<OS, S>
     /// * `RetValue<Self>` - Result containing the new TaskSample instance or an error
     pub fn new(sample: S) -> RetValue<Self>
     {
-        Ok(Self { task: RefCell::new(OS::Task::new()?), sample })
+        Ok(Self { task: RefCell::new(OS::Task::new()?), setting: Cell::new(None), sample })
     }
 
-    /// Activate the task with the given parameters
+    /// Activate the task with the given parameters, they are kept for [`TaskSample::restart`]
+    /// only when the task is activated
     /// # Arguments
-    /// * `name: &str` - The name of the task
+    /// * `name: &'static str` - The name of the task, it's static because it's kept for the restart
     /// * `stack: u32` - The stack size for the task
     /// * `priorities: TaskPriority` - The priority level for the task
     /// # Returns
     /// * `RetValue<&Self>` - Result containing a reference to the TaskSample instance or an error
-    pub fn active(&self, name: &str, stack: u32, priorities: TaskPriority) -> RetValue<&Self>
+    pub fn active(
+        &self, name: &'static str, stack: u32, priorities: TaskPriority,
+    ) -> RetValue<&Self>
     {
-        #[allow(unused_must_use)]
-        self.task.try_borrow_mut().map(|mut x| x.active(name, stack, priorities, &self.sample))?;
+        self.task.try_borrow_mut()?.active(name, stack, priorities, &self.sample)?;
+        self.setting.set(Some((name, stack, priorities)));
+        Ok(self)
+    }
+
+    /// Terminate the task and activate it again with the parameters of [`TaskSample::active`]
+    /// The task main implementation is not recreated, its `main` runs again from the beginning
+    /// with the state left by the terminated one
+    /// # Returns
+    /// * `RetValue<&Self>` - Result containing a reference to the TaskSample instance or an error
+    pub fn restart(&self) -> RetValue<&Self>
+    {
+        let (name, stack, priorities) = self.setting.get().ok_or(ErrValue::InstanceInvalid)?;
+        let mut task = self.task.try_borrow_mut()?;
+
+        task.terminate()?;
+        task.active(name, stack, priorities, &self.sample)?;
         Ok(self)
     }
 
@@ -293,6 +328,14 @@ impl<OS: RTOS, S: ITaskMain> TaskSample<OS, S>
     }
 }
 
+impl<OS: RTOS, S: ITaskMain> ITaskRestart for TaskSample<OS, S>
+{
+    fn restart(&self) -> RetValue<()>
+    {
+        TaskSample::restart(self).map(|_| ())
+    }
+}
+
 impl<OS: RTOS, S: ITaskMain> Deref for TaskSample<OS, S>
 {
     type Target = S;
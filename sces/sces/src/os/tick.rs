@@ -0,0 +1,287 @@
+/// Tick Time Module
+/// Provides the types of the time measured by the OS ticks, one tick is one millisecond.
+/// The tick counter is a `u32` which wraps around after about 49.7 days, so the instants
+/// are compared and subtracted with the wrapping arithmetic, and the durations never overflow.
+/// # Examples
+/// ```rust
+/// let start = OS::now();
+/// OS::delay(Duration::from_millis(100));
+/// let past = OS::now() - start;
+/// ```
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// Duration
+/// A span of time in OS ticks
+/// `Duration::MAX` means to wait forever when it's used as a timeout
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration(u32);
+
+impl Duration
+{
+    /// No wait
+    pub const ZERO: Self = Self(0);
+
+    /// Wait forever
+    pub const MAX: Self = Self(u32::MAX);
+
+    /// Create a duration from the ticks
+    pub const fn from_ticks(ticks: u32) -> Self
+    {
+        Self(ticks)
+    }
+
+    /// Create a duration from the milliseconds
+    pub const fn from_millis(millis: u32) -> Self
+    {
+        Self(millis)
+    }
+
+    /// Create a duration from the seconds, it saturates to `Duration::MAX`
+    pub const fn from_secs(secs: u32) -> Self
+    {
+        Self(secs.saturating_mul(1000))
+    }
+
+    /// Get the ticks of the duration
+    pub const fn ticks(&self) -> u32
+    {
+        self.0
+    }
+
+    /// Get the milliseconds of the duration
+    pub const fn as_millis(&self) -> u32
+    {
+        self.0
+    }
+
+    /// Check whether it means to wait forever
+    pub const fn is_forever(&self) -> bool
+    {
+        self.0 == u32::MAX
+    }
+
+    pub const fn saturating_add(self, other: Self) -> Self
+    {
+        Self(self.0.saturating_add(other.0))
+    }
+
+    pub const fn saturating_sub(self, other: Self) -> Self
+    {
+        Self(self.0.saturating_sub(other.0))
+    }
+}
+
+impl From<u32> for Duration
+{
+    fn from(ticks: u32) -> Self
+    {
+        Self(ticks)
+    }
+}
+
+impl From<Duration> for u32
+{
+    fn from(duration: Duration) -> Self
+    {
+        duration.0
+    }
+}
+
+/// The sum saturates to `Duration::MAX`
+impl Add for Duration
+{
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self
+    {
+        self.saturating_add(other)
+    }
+}
+
+impl AddAssign for Duration
+{
+    fn add_assign(&mut self, other: Self)
+    {
+        *self = *self + other;
+    }
+}
+
+/// The difference saturates to `Duration::ZERO`
+impl Sub for Duration
+{
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self
+    {
+        self.saturating_sub(other)
+    }
+}
+
+impl SubAssign for Duration
+{
+    fn sub_assign(&mut self, other: Self)
+    {
+        *self = *self - other;
+    }
+}
+
+/// Instant
+/// A value of the OS tick counter, got by `RTOS::now`
+/// Two instants could be compared correctly when they are less than half of the counter range
+/// (about 24.8 days) apart, even if the counter wraps around between them
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Instant(u32);
+
+impl Instant
+{
+    /// Create an instant from the value of the tick counter
+    pub const fn from_ticks(ticks: u32) -> Self
+    {
+        Self(ticks)
+    }
+
+    /// Get the value of the tick counter
+    pub const fn ticks(&self) -> u32
+    {
+        self.0
+    }
+
+    /// Get the duration from `earlier` to this instant
+    /// The result is wrong if `earlier` is actually later than this instant
+    pub const fn duration_since(&self, earlier: Self) -> Duration
+    {
+        Duration(self.0.wrapping_sub(earlier.0))
+    }
+
+    /// Check whether this instant is the same as or later than `other`
+    pub const fn is_reached(&self, other: Self) -> bool
+    {
+        (self.0.wrapping_sub(other.0) as i32) >= 0
+    }
+}
+
+impl From<u32> for Instant
+{
+    fn from(ticks: u32) -> Self
+    {
+        Self(ticks)
+    }
+}
+
+/// The instant after the duration, it wraps around as the tick counter does
+impl Add<Duration> for Instant
+{
+    type Output = Self;
+
+    fn add(self, duration: Duration) -> Self
+    {
+        Self(self.0.wrapping_add(duration.0))
+    }
+}
+
+impl AddAssign<Duration> for Instant
+{
+    fn add_assign(&mut self, duration: Duration)
+    {
+        *self = *self + duration;
+    }
+}
+
+/// The instant before the duration, it wraps around as the tick counter does
+impl Sub<Duration> for Instant
+{
+    type Output = Self;
+
+    fn sub(self, duration: Duration) -> Self
+    {
+        Self(self.0.wrapping_sub(duration.0))
+    }
+}
+
+/// The duration between two instants, see `Instant::duration_since`
+impl Sub for Instant
+{
+    type Output = Duration;
+
+    fn sub(self, earlier: Self) -> Duration
+    {
+        self.duration_since(earlier)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// The instant `ticks` before the tick counter wraps around to 0.
+    const fn before_wrap(ticks: u32) -> Instant
+    {
+        Instant::from_ticks(0u32.wrapping_sub(ticks))
+    }
+
+    #[test]
+    fn instant_sub_across_wrap()
+    {
+        let earlier = before_wrap(10);
+        let later = Instant::from_ticks(5);
+
+        assert_eq!(later - earlier, Duration::from_ticks(15));
+        assert_eq!(later.duration_since(earlier), Duration::from_ticks(15));
+        assert_eq!(Instant::from_ticks(0).duration_since(before_wrap(1)), Duration::from_ticks(1));
+        assert_eq!(later.duration_since(later), Duration::ZERO);
+    }
+
+    #[test]
+    fn instant_add_across_wrap()
+    {
+        assert_eq!(before_wrap(10) + Duration::from_ticks(15), Instant::from_ticks(5));
+        assert_eq!(Instant::from_ticks(5) - Duration::from_ticks(15), before_wrap(10));
+
+        let mut instant = before_wrap(1);
+        instant += Duration::from_ticks(1);
+        assert_eq!(instant, Instant::from_ticks(0));
+    }
+
+    #[test]
+    fn is_reached_across_wrap()
+    {
+        let deadline = before_wrap(10) + Duration::from_ticks(20);
+
+        assert!(!before_wrap(10).is_reached(deadline));
+        assert!(!before_wrap(1).is_reached(deadline));
+        assert!(!Instant::from_ticks(9).is_reached(deadline));
+        assert!(Instant::from_ticks(10).is_reached(deadline));
+        assert!(Instant::from_ticks(11).is_reached(deadline));
+
+        // The instants less than half of the range apart are still ordered.
+        assert!(Instant::from_ticks(i32::MAX as u32 - 1).is_reached(before_wrap(1)));
+        assert!(!before_wrap(1).is_reached(Instant::from_ticks(i32::MAX as u32 - 1)));
+    }
+
+    #[test]
+    fn duration_saturates()
+    {
+        let long = Duration::from_ticks(u32::MAX - 5);
+
+        assert_eq!(long + Duration::from_ticks(10), Duration::MAX);
+        assert_eq!(Duration::from_ticks(5) - Duration::from_ticks(10), Duration::ZERO);
+
+        let mut duration = long;
+        duration += long;
+        assert_eq!(duration, Duration::MAX);
+        duration -= Duration::MAX;
+        assert_eq!(duration, Duration::ZERO);
+    }
+
+    #[test]
+    fn from_secs_saturates_to_forever()
+    {
+        assert_eq!(Duration::from_secs(4_294_967), Duration::from_ticks(4_294_967_000));
+        assert!(!Duration::from_secs(4_294_967).is_forever());
+
+        assert_eq!(Duration::from_secs(4_294_968), Duration::MAX);
+        assert!(Duration::from_secs(u32::MAX).is_forever());
+        assert!(Duration::MAX.is_forever());
+    }
+}
@@ -12,8 +12,9 @@ use core::ffi::c_void;
 ///     }
 /// }
 /// let mut timer = MyTimer::new(TimerMode::Periodic, MyTimerEvent);
-/// timer.start(1000).unwrap(); // Start timer for 1000 milliseconds
+/// timer.active(Duration::from_secs(1), &MyTimerEvent).unwrap(); // Start timer for 1 second
 /// ```
+use crate::os::tick::Duration;
 use crate::value::RetValue;
 
 /// TimerMode Enum
@@ -96,11 +97,11 @@ pub trait ITimer
 
     /// Activate the timer
     /// # Arguments
-    /// * `times: u32` - The duration for the timer in milliseconds
+    /// * `times: Duration` - The duration for the timer
     /// * `event: &dyn ITimerEvent` - The event handler for timer expiration
     /// # Returns
     /// * `RetValue<()>` - Result indicating success or failure
-    fn active(&mut self, times: u32, event: &dyn ITimerEvent) -> RetValue<()>;
+    fn active(&mut self, times: Duration, event: &dyn ITimerEvent) -> RetValue<()>;
 
     /// Terminate the timer
     fn terminate(&mut self);
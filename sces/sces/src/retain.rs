@@ -0,0 +1,535 @@
+//! Provide a log ring in the retained RAM, to keep the last records and the reset reason across
+//! the resets.
+//!
+//! The ring should be placed in a section which is not initialized by the startup code, like a
+//! `(NOLOAD)` section `.noinit` in the linker script:
+//!
+//! ```ignore
+//! #[link_section = ".noinit"]
+//! static mut RETAINED: MaybeUninit<RetainedRing<2048>> = MaybeUninit::uninit();
+//!
+//! retain::install(RetainedRing::attach(&mut RETAINED));
+//! ```
+//!
+//! The head of the ring is validated by a magic number and a CRC when it's attached at the boot
+//! time, and the ring is cleaned when the validation fails, that's what happens after a power on.
+//! Every line of the records ends with the CRC of it in 8 hex digits, and a line which is broken
+//! in the RAM is skipped when the records are read.
+
+use core::cell::UnsafeCell;
+use core::fmt::{self, Arguments, Write};
+use core::mem::MaybeUninit;
+use core::ptr::addr_of_mut;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use sces_derive::EnumAsU32;
+
+use crate::crc::crc32_update;
+
+const RETAINED_MAGIC: u32 = 0x5343_4552;
+
+/// The CRC in hex digits at the end of a line, before the line end.
+const LINE_CRC: usize = 8;
+
+static mut RETAINED: Option<&'static dyn RetainedLog> = None;
+
+/// The reason of the last reset, which is marked by the software before the reset.
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, EnumAsU32)]
+pub enum ResetReason
+{
+    /// The retained RAM is invalid, it's the first boot after the power on.
+    PowerOn = 0,
+
+    /// The software requests a reset.
+    Software = 1,
+
+    /// A panic occurred.
+    Panic = 2,
+
+    /// The watch dog has not been refreshed because some tasks don't respond.
+    WatchDog = 3,
+
+    /// A fault exception of the CPU occurred.
+    Fault = 4,
+
+    /// Nothing was marked before the reset, like a reset by the pin or a brown-out.
+    Unknown = 255,
+}
+
+/// Trait for the log records and the reset reason which survive the resets.
+pub trait RetainedLog: Send + Sync
+{
+    /// Get the reason marked before the last reset.
+    fn reset_reason(&self) -> ResetReason;
+
+    /// Mark the reason of the coming reset, it will be got by [`RetainedLog::reset_reason`]
+    /// after the reset.
+    fn mark_reset(&self, reason: ResetReason);
+
+    /// Get how many times the system has booted since the retained RAM became valid.
+    fn boot_count(&self) -> u32;
+
+    /// Write one record, the line end is appended.
+    ///
+    /// The oldest records are overwritten when there is no space, and the record is dropped
+    /// when the ring is in use by the interrupted task.
+    fn write(&self, record: Arguments);
+
+    /// Copy the last `count` records to `buf` from the old to the new, every record ends with a
+    /// line end, and return the copied length.
+    ///
+    /// The older records are not copied if the `buf` can't hold all of them.
+    fn read_last(&self, count: usize, buf: &mut [u8]) -> usize;
+
+    /// Remove all records.
+    fn clean(&self);
+}
+
+/// Install the retained log of the system, so the panic handler, the services and the logger
+/// could find it.
+pub fn install(log: &'static dyn RetainedLog)
+{
+    unsafe { RETAINED = Some(log) };
+}
+
+/// Get the retained log installed by [`install`].
+pub fn retained() -> Option<&'static dyn RetainedLog>
+{
+    unsafe { RETAINED }
+}
+
+/// A log ring with `N` bytes which is placed in the retained RAM, every line takes 9 bytes more
+/// for its CRC and line end.
+#[repr(C)]
+pub struct RetainedRing<const N: usize>
+{
+    magic: AtomicU32,
+    head: AtomicU32,
+    length: AtomicU32,
+    reason: AtomicU32,
+    boots: AtomicU32,
+    crc: AtomicU32,
+    previous: AtomicU32,
+    busy: AtomicBool,
+    data: UnsafeCell<[u8; N]>,
+}
+
+unsafe impl<const N: usize> Sync for RetainedRing<N> {}
+
+impl<const N: usize> RetainedRing<N>
+{
+    /// Validate the ring left by the last boot, or clean it if it's invalid, then mark this boot
+    /// in it.
+    ///
+    /// The reset reason is moved out to be got by [`RetainedLog::reset_reason`], and the one
+    /// stored in the ring becomes [`ResetReason::Unknown`] until it's marked again.
+    pub fn attach(place: &'static mut MaybeUninit<Self>) -> &'static Self
+    {
+        let ring = place.as_mut_ptr();
+
+        // The head and the records are integers and bytes, any value left in the RAM is valid for
+        // them, and the garbage values after a power on are rejected by the validation.
+        unsafe {
+            addr_of_mut!((*ring).busy).write(AtomicBool::new(false));
+            addr_of_mut!((*ring).previous).write(AtomicU32::new(ResetReason::PowerOn.into()));
+        }
+
+        let ring = unsafe { &*ring };
+
+        if ring.is_valid()
+        {
+            ring.previous.store(ring.reason.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+        else
+        {
+            ring.magic.store(RETAINED_MAGIC, Ordering::Relaxed);
+            ring.boots.store(0, Ordering::Relaxed);
+            ring.clean();
+        }
+
+        ring.boots.fetch_add(1, Ordering::Relaxed);
+        ring.mark_reset(ResetReason::Unknown);
+        ring.write(format_args!(
+            "---- Boot {}, reset by {:?} ----",
+            ring.boot_count(),
+            ring.reset_reason()
+        ));
+
+        ring
+    }
+
+    fn is_valid(&self) -> bool
+    {
+        self.magic.load(Ordering::Relaxed) == RETAINED_MAGIC
+            && self.crc.load(Ordering::Relaxed) == self.head_crc()
+            && self.head.load(Ordering::Relaxed) < N as u32
+            && self.length.load(Ordering::Relaxed) <= N as u32
+    }
+
+    fn head_crc(&self) -> u32
+    {
+        [&self.magic, &self.head, &self.length, &self.reason, &self.boots]
+            .iter()
+            .fold(0xFFFF_FFFF, |crc, x| crc32_update(crc, &x.load(Ordering::Relaxed).to_le_bytes()))
+            ^ 0xFFFF_FFFF
+    }
+
+    fn update_crc(&self)
+    {
+        self.crc.store(self.head_crc(), Ordering::Relaxed);
+    }
+
+    fn data(&self) -> *mut u8
+    {
+        self.data.get() as *mut u8
+    }
+
+    /// Get the byte at `offset` from the oldest one.
+    fn byte(&self, head: usize, offset: usize) -> u8
+    {
+        unsafe { *self.data().add((head + offset) % N) }
+    }
+
+    /// Check the CRC of the line in `start..end`, which includes the line end.
+    fn is_line_valid(&self, head: usize, start: usize, end: usize) -> bool
+    {
+        let Some(text) = (end - start).checked_sub(LINE_CRC + 1)
+        else
+        {
+            return false;
+        };
+
+        let crc = (start..start + text)
+            .fold(0xFFFF_FFFF, |crc, x| crc32_update(crc, &[self.byte(head, x)]));
+
+        self.byte(head, end - 1) == b'\n'
+            && (0..LINE_CRC).all(|x| self.byte(head, start + text + x) == hex_digit(!crc, x))
+    }
+}
+
+impl<const N: usize> RetainedLog for RetainedRing<N>
+{
+    fn reset_reason(&self) -> ResetReason
+    {
+        self.previous.load(Ordering::Relaxed).into()
+    }
+
+    fn mark_reset(&self, reason: ResetReason)
+    {
+        self.reason.store(reason.into(), Ordering::Relaxed);
+        self.update_crc();
+    }
+
+    fn boot_count(&self) -> u32
+    {
+        self.boots.load(Ordering::Relaxed)
+    }
+
+    fn write(&self, record: Arguments)
+    {
+        if self.busy.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_err()
+        {
+            return;
+        }
+
+        let mut writer = RingWriter {
+            data: self.data(),
+            head: self.head.load(Ordering::Relaxed) as usize,
+            length: self.length.load(Ordering::Relaxed) as usize,
+            capacity: N,
+            crc: 0xFFFF_FFFF,
+        };
+
+        writer.write_fmt(record).ok();
+        writer.write_str("\n").ok();
+
+        self.head.store(writer.head as u32, Ordering::Relaxed);
+        self.length.store(writer.length as u32, Ordering::Relaxed);
+        self.update_crc();
+
+        self.busy.store(false, Ordering::Release);
+    }
+
+    fn read_last(&self, count: usize, buf: &mut [u8]) -> usize
+    {
+        let head = self.head.load(Ordering::Relaxed) as usize;
+        let length = self.length.load(Ordering::Relaxed) as usize;
+        let line = |end: usize| {
+            let start =
+                (0..end - 1).rev().find(|x| self.byte(head, *x) == b'\n').map_or(0, |x| x + 1);
+            (start, self.is_line_valid(head, start, end))
+        };
+
+        // Search the start of the records from the newest one, every line ends with a line end.
+        let (mut start, mut end, mut size, mut taken) = (length, length, 0, 0);
+
+        while taken < count && end > 0
+        {
+            let (previous, valid) = line(end);
+
+            if valid
+            {
+                let text = end - previous - LINE_CRC - 1;
+                if size + text + 1 > buf.len()
+                {
+                    break;
+                }
+
+                size += text + 1;
+                taken += 1;
+                start = previous;
+            }
+
+            end = previous;
+        }
+
+        // Copy the valid lines without their CRC.
+        let (mut end, mut copied) = (length, size);
+
+        while end > start
+        {
+            let (previous, valid) = line(end);
+
+            if valid
+            {
+                let text = end - previous - LINE_CRC - 1;
+                copied -= text + 1;
+
+                (0..text).for_each(|x| buf[copied + x] = self.byte(head, previous + x));
+                buf[copied + text] = b'\n';
+            }
+
+            end = previous;
+        }
+
+        size
+    }
+
+    fn clean(&self)
+    {
+        self.head.store(0, Ordering::Relaxed);
+        self.length.store(0, Ordering::Relaxed);
+        self.update_crc();
+    }
+}
+
+/// Get the hex digit `index` of `crc` from the highest one.
+fn hex_digit(crc: u32, index: usize) -> u8
+{
+    b"0123456789ABCDEF"[(crc >> ((LINE_CRC - 1 - index) * 4)) as usize & 0xF]
+}
+
+/// Write the bytes into the ring, and drop the oldest records when there is no space. The CRC of
+/// every line is written before its line end.
+struct RingWriter
+{
+    data: *mut u8,
+    head: usize,
+    length: usize,
+    capacity: usize,
+
+    /// The CRC of the line being written.
+    crc: u32,
+}
+
+impl RingWriter
+{
+    fn push(&mut self, byte: u8)
+    {
+        if self.length >= self.capacity
+        {
+            self.drop_oldest();
+        }
+
+        unsafe { *self.data.add((self.head + self.length) % self.capacity) = byte };
+        self.length += 1;
+    }
+
+    fn drop_oldest(&mut self)
+    {
+        while self.length > 0
+        {
+            let byte = unsafe { *self.data.add(self.head) };
+
+            self.head = (self.head + 1) % self.capacity;
+            self.length -= 1;
+
+            if byte == b'\n'
+            {
+                break;
+            }
+        }
+    }
+}
+
+impl Write for RingWriter
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result
+    {
+        if self.capacity == 0
+        {
+            return Ok(());
+        }
+
+        for &x in s.as_bytes()
+        {
+            if x == b'\n'
+            {
+                (0..LINE_CRC).for_each(|index| self.push(hex_digit(!self.crc, index)));
+                self.crc = 0xFFFF_FFFF;
+            }
+            else
+            {
+                self.crc = crc32_update(self.crc, &[x]);
+            }
+
+            self.push(x);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    extern crate std;
+
+    use std::boxed::Box;
+    use std::string::String;
+    use std::vec;
+
+    use super::*;
+
+    type Ring = RetainedRing<160>;
+
+    /// A place in the RAM with the garbage of a power on.
+    fn place() -> *mut MaybeUninit<Ring>
+    {
+        let place = Box::into_raw(Box::new(MaybeUninit::<Ring>::uninit()));
+        unsafe { place.cast::<u8>().write_bytes(0xA5, size_of::<Ring>()) };
+        place
+    }
+
+    fn boot(place: *mut MaybeUninit<Ring>) -> &'static Ring
+    {
+        Ring::attach(unsafe { &mut *place })
+    }
+
+    /// A ring with just `lines`.
+    fn records(lines: &[&str]) -> &'static Ring
+    {
+        let ring = boot(place());
+        ring.clean();
+        lines.iter().for_each(|x| ring.write(format_args!("{x}")));
+        ring
+    }
+
+    fn read(ring: &Ring, count: usize, size: usize) -> String
+    {
+        let mut buf = vec![0; size];
+        let size = ring.read_last(count, &mut buf);
+        String::from_utf8(buf[..size].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn boots_and_reasons()
+    {
+        let place = place();
+
+        let ring = boot(place);
+        assert_eq!((ring.boot_count(), ring.reset_reason()), (1, ResetReason::PowerOn));
+        assert_eq!(read(ring, 10, 256), "---- Boot 1, reset by PowerOn ----\n");
+
+        ring.write(format_args!("x = {}", 1));
+        ring.mark_reset(ResetReason::Panic);
+
+        let ring = boot(place);
+        assert_eq!((ring.boot_count(), ring.reset_reason()), (2, ResetReason::Panic));
+        assert_eq!(read(ring, 2, 256), "x = 1\n---- Boot 2, reset by Panic ----\n");
+
+        // Nothing is marked before this reset.
+        let ring = boot(place);
+        assert_eq!((ring.boot_count(), ring.reset_reason()), (3, ResetReason::Unknown));
+    }
+
+    #[test]
+    fn invalid_head_cleans()
+    {
+        let place = place();
+        let ring = boot(place);
+        ring.write(format_args!("lost"));
+
+        // The CRC of the head doesn't match.
+        ring.boots.store(7, Ordering::Relaxed);
+        let ring = boot(place);
+        assert_eq!((ring.boot_count(), ring.reset_reason()), (1, ResetReason::PowerOn));
+        assert_eq!(read(ring, 10, 256), "---- Boot 1, reset by PowerOn ----\n");
+
+        // The head is out of the ring with a right CRC.
+        ring.write(format_args!("lost"));
+        ring.head.store(160, Ordering::Relaxed);
+        ring.update_crc();
+        let ring = boot(place);
+        assert_eq!((ring.boot_count(), ring.reset_reason()), (1, ResetReason::PowerOn));
+        assert_eq!(read(ring, 10, 256), "---- Boot 1, reset by PowerOn ----\n");
+    }
+
+    #[test]
+    fn wraparound()
+    {
+        let ring = records(&[]);
+
+        // Every line takes 9 bytes, its CRC and the line end, so 8 of them fit in the ring.
+        for x in 0..100
+        {
+            ring.write(format_args!("record {x:02}"));
+            assert!(ring.length.load(Ordering::Relaxed) <= 160);
+        }
+
+        let expected: String = (92..100).map(|x| std::format!("record {x}\n")).collect();
+        assert_eq!(read(ring, 100, 256), expected);
+        assert_eq!(read(ring, 2, 256), "record 98\nrecord 99\n");
+
+        // A record with a line end is two lines.
+        ring.write(format_args!("a\nb"));
+        assert_eq!(read(ring, 3, 256), "record 99\na\nb\n");
+    }
+
+    #[test]
+    fn read_last_small_buffer()
+    {
+        let ring = records(&["one", "two", "three"]);
+
+        assert_eq!(read(ring, 3, 14), "one\ntwo\nthree\n");
+        assert_eq!(read(ring, 3, 13), "two\nthree\n");
+        assert_eq!(read(ring, 3, 6), "three\n");
+        assert_eq!(read(ring, 3, 5), "");
+        assert_eq!(read(ring, 0, 100), "");
+        assert_eq!(read(ring, 2, 100), "two\nthree\n");
+    }
+
+    #[test]
+    fn broken_lines_skipped()
+    {
+        let ring = records(&["one", "two", "three"]);
+
+        // The line "one" takes 12 bytes with its CRC and the line end.
+        unsafe { *ring.data().add(13) = b'X' };
+        assert_eq!(read(ring, 3, 100), "one\nthree\n");
+        assert_eq!(read(ring, 2, 100), "one\nthree\n");
+
+        // A line shorter than the CRC.
+        unsafe { *ring.data().add(2) = b'\n' };
+        assert_eq!(read(ring, 3, 100), "three\n");
+
+        // A record longer than the ring loses its start, and it's dropped by the next ones.
+        let long = [b'x'; 200];
+        ring.write(format_args!("{}", core::str::from_utf8(&long).unwrap()));
+        assert_eq!(read(ring, 3, 256), "");
+
+        ring.write(format_args!("after"));
+        assert_eq!(read(ring, 3, 256), "after\n");
+    }
+}
@@ -0,0 +1,298 @@
+//! An RTOS with a single task on the host for the tests, its clock only moves by the delays and
+//! its mutex reports whether it's held, so a test could check the mutex is given back.
+
+extern crate std;
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::os::events::IEvents;
+use crate::os::mem::IMemPool;
+use crate::os::message_queue::{IMessageQueue, MessageContent};
+use crate::os::mutex::IMutex;
+use crate::os::semaphore::ISemaphore;
+use crate::os::task::{ITask, ITaskMain, TaskPriority, TaskState};
+use crate::os::tick::{Duration, Instant};
+use crate::os::timer::{ITimer, ITimerEvent, TimerMode, TimerState};
+use crate::os::{OSState, RTOS};
+use crate::value::{ErrValue, RetValue};
+
+std::thread_local! {
+    static TICKS: Cell<u32> = const { Cell::new(0) };
+}
+
+pub struct HostOs;
+
+/// A mutex which panics when it's locked twice or unlocked without a lock, as nothing runs at
+/// the same time.
+pub struct HostMutex
+{
+    locks: AtomicU32,
+}
+
+impl HostMutex
+{
+    pub fn is_locked(&self) -> bool
+    {
+        self.locks.load(Ordering::Relaxed) > 0
+    }
+}
+
+/// The other objects of [`HostOs`], which are not supported.
+pub struct HostObject;
+
+impl RTOS for HostOs
+{
+    type Events = HostObject;
+    type MemPool = HostObject;
+    type MessageQueue = HostObject;
+    type Mutex = HostMutex;
+    type Semaphore = HostObject;
+    type Task = HostObject;
+    type Timer = HostObject;
+
+    fn initialize() -> RetValue<()>
+    {
+        Ok(())
+    }
+
+    fn state() -> OSState
+    {
+        OSState::Running
+    }
+
+    fn ticks() -> u32
+    {
+        TICKS.with(|x| x.get())
+    }
+
+    fn task_count() -> u32
+    {
+        1
+    }
+
+    fn current_task() -> HostObject
+    {
+        HostObject
+    }
+
+    fn is_in_isr() -> bool
+    {
+        false
+    }
+
+    fn switch_next_task() {}
+
+    fn exit_current_task() {}
+
+    fn delay(time: Duration)
+    {
+        TICKS.with(|x| x.set(x.get().wrapping_add(time.ticks())));
+    }
+
+    fn delay_interval(time: Instant)
+    {
+        TICKS.with(|x| x.set(x.get().max(time.ticks())));
+    }
+}
+
+impl IMutex for HostMutex
+{
+    fn new() -> RetValue<Self>
+    {
+        Ok(Self { locks: AtomicU32::new(0) })
+    }
+
+    fn lock(&self)
+    {
+        assert_eq!(self.locks.fetch_add(1, Ordering::Relaxed), 0, "locked twice");
+    }
+
+    fn attempt_lock(&self, _time: Duration) -> RetValue<()>
+    {
+        match self.is_locked()
+        {
+            true => Err(ErrValue::Timeout),
+            false =>
+            {
+                self.lock();
+                Ok(())
+            }
+        }
+    }
+
+    fn unlock(&self)
+    {
+        assert_eq!(self.locks.fetch_sub(1, Ordering::Relaxed), 1, "unlocked without a lock");
+    }
+}
+
+impl IEvents for HostObject
+{
+    fn new() -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn put(&self, _events: u32) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn wait(&self, _events: u32, _timeout: Duration) -> RetValue<u32>
+    {
+        Err(ErrValue::NotSupport)
+    }
+}
+
+impl IMemPool for HostObject
+{
+    fn new(
+        _name: &str, _buf: &'static mut [u8], _block_size: u32, _max_block_count: u32,
+    ) -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn name(&self) -> &str
+    {
+        ""
+    }
+
+    fn block_size(&self) -> u32
+    {
+        0
+    }
+
+    fn block_count(&self) -> u32
+    {
+        0
+    }
+
+    fn max_block_count(&self) -> u32
+    {
+        0
+    }
+
+    fn alloc(&self) -> *mut u8
+    {
+        core::ptr::null_mut()
+    }
+
+    fn free(&self, _mem: *mut u8) {}
+}
+
+impl IMessageQueue for HostObject
+{
+    fn new(_message_size: u32, _message_count: u32) -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn send(&self, _content: &dyn MessageContent, _timeout: Duration) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn receive(&self, _cache: &mut dyn MessageContent, _timeout: Duration) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+}
+
+impl ISemaphore for HostObject
+{
+    fn new(_max_count: u32) -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn take(&self) {}
+
+    fn attempt_take(&self, _timeout: Duration) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn release(&self) {}
+}
+
+impl ITask for HostObject
+{
+    fn new() -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn active(
+        &mut self, _name: &str, _stack: u32, _priority: TaskPriority, _main: &dyn ITaskMain,
+    ) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn name(&self) -> &str
+    {
+        "host"
+    }
+
+    fn stack_size(&self) -> u32
+    {
+        0
+    }
+
+    fn priority(&self) -> TaskPriority
+    {
+        TaskPriority::Normal
+    }
+
+    fn state(&self) -> TaskState
+    {
+        TaskState::Running
+    }
+
+    fn set_priority(&mut self, _priority: TaskPriority) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn suspend(&self) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn resume(&self) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn terminate(&mut self) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+}
+
+impl ITimer for HostObject
+{
+    fn new(_mode: TimerMode) -> RetValue<Self>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn mode(&self) -> TimerMode
+    {
+        TimerMode::Once
+    }
+
+    fn state(&self) -> TimerState
+    {
+        TimerState::Idle
+    }
+
+    fn active(&mut self, _times: Duration, _event: &dyn ITimerEvent) -> RetValue<()>
+    {
+        Err(ErrValue::NotSupport)
+    }
+
+    fn terminate(&mut self) {}
+}
@@ -0,0 +1,304 @@
+//! The image file of `sces-svc-dfu`, which is the header, the padding up to the offset of the
+//! image and the image itself. The layout must be the same as the one of `DfuHeader`.
+
+use std::fmt::{self, Display, Formatter};
+
+/// `"SDFU"` in little endian.
+const DFU_MAGIC: u32 = 0x5546_4453;
+pub const DFU_HEADER_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version
+{
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u16,
+}
+
+impl Version
+{
+    /// Parse the version like `1.2.3`.
+    pub fn parse(text: &str) -> Option<Self>
+    {
+        let mut parts = text.split('.');
+        let version = Self {
+            major: parts.next()?.parse().ok()?,
+            minor: parts.next()?.parse().ok()?,
+            patch: parts.next()?.parse().ok()?,
+        };
+
+        parts.next().is_none().then_some(version)
+    }
+
+    fn to_u32(self) -> u32
+    {
+        (self.major as u32) << 24 | (self.minor as u32) << 16 | self.patch as u32
+    }
+
+    fn from_u32(value: u32) -> Self
+    {
+        Self { major: (value >> 24) as u8, minor: (value >> 16) as u8, patch: value as u16 }
+    }
+}
+
+impl Display for Version
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+pub struct Header
+{
+    pub header_size: u16,
+    pub version: Version,
+    pub build: u32,
+    pub size: u32,
+    pub crc: u32,
+    pub sha256: [u8; 32],
+    pub load_address: u32,
+}
+
+impl Header
+{
+    /// Make the header of `image`.
+    pub fn new(
+        image: &[u8], header_size: u16, version: Version, build: u32, load_address: u32,
+    ) -> Self
+    {
+        Self {
+            header_size,
+            version,
+            build,
+            size: image.len() as u32,
+            crc: crc32(image),
+            sha256: sha256(image),
+            load_address,
+        }
+    }
+
+    pub fn encode(&self) -> [u8; DFU_HEADER_SIZE]
+    {
+        let mut bytes = [0; DFU_HEADER_SIZE];
+        bytes[0..4].copy_from_slice(&DFU_MAGIC.to_le_bytes());
+        bytes[4..6].copy_from_slice(&self.header_size.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.version.to_u32().to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.build.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.size.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.crc.to_le_bytes());
+        bytes[24..56].copy_from_slice(&self.sha256);
+        bytes[56..60].copy_from_slice(&self.load_address.to_le_bytes());
+
+        let crc = crc32(&bytes[..60]);
+        bytes[60..64].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, String>
+    {
+        let bytes = bytes.get(..DFU_HEADER_SIZE).ok_or("The file is shorter than a header.")?;
+        let word = |x: usize| u32::from_le_bytes(bytes[x..x + 4].try_into().unwrap());
+
+        if word(0) != DFU_MAGIC
+        {
+            return Err(String::from("The file is not an image file."));
+        }
+        if word(60) != crc32(&bytes[..60])
+        {
+            return Err(String::from("The CRC of the header is wrong."));
+        }
+
+        Ok(Self {
+            header_size: u16::from_le_bytes([bytes[4], bytes[5]]),
+            version: Version::from_u32(word(8)),
+            build: word(12),
+            size: word(16),
+            crc: word(20),
+            sha256: bytes[24..56].try_into().unwrap(),
+            load_address: word(56),
+        })
+    }
+
+    /// Make the image file of `image`, the padding is `0xFF` like the erased flash.
+    pub fn pack(&self, image: &[u8]) -> Vec<u8>
+    {
+        let mut file = self.encode().to_vec();
+        file.resize(self.header_size as usize, 0xFF);
+        file.extend_from_slice(image);
+        file
+    }
+}
+
+pub fn crc32(bytes: &[u8]) -> u32
+{
+    !bytes.iter().fold(0xFFFF_FFFF, |crc, x| {
+        (0..8).fold(crc ^ *x as u32, |crc, _| (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg()))
+    })
+}
+
+#[rustfmt::skip]
+const K: [u32; 64] = [
+    0x428A_2F98, 0x7137_4491, 0xB5C0_FBCF, 0xE9B5_DBA5, 0x3956_C25B, 0x59F1_11F1, 0x923F_82A4,
+    0xAB1C_5ED5, 0xD807_AA98, 0x1283_5B01, 0x2431_85BE, 0x550C_7DC3, 0x72BE_5D74, 0x80DE_B1FE,
+    0x9BDC_06A7, 0xC19B_F174, 0xE49B_69C1, 0xEFBE_4786, 0x0FC1_9DC6, 0x240C_A1CC, 0x2DE9_2C6F,
+    0x4A74_84AA, 0x5CB0_A9DC, 0x76F9_88DA, 0x983E_5152, 0xA831_C66D, 0xB003_27C8, 0xBF59_7FC7,
+    0xC6E0_0BF3, 0xD5A7_9147, 0x06CA_6351, 0x1429_2967, 0x27B7_0A85, 0x2E1B_2138, 0x4D2C_6DFC,
+    0x5338_0D13, 0x650A_7354, 0x766A_0ABB, 0x81C2_C92E, 0x9272_2C85, 0xA2BF_E8A1, 0xA81A_664B,
+    0xC24B_8B70, 0xC76C_51A3, 0xD192_E819, 0xD699_0624, 0xF40E_3585, 0x106A_A070, 0x19A4_C116,
+    0x1E37_6C08, 0x2748_774C, 0x34B0_BCB5, 0x391C_0CB3, 0x4ED8_AA4A, 0x5B9C_CA4F, 0x682E_6FF3,
+    0x748F_82EE, 0x78A5_636F, 0x84C8_7814, 0x8CC7_0208, 0x90BE_FFFA, 0xA450_6CEB, 0xBEF9_A3F7,
+    0xC671_78F2,
+];
+
+#[rustfmt::skip]
+const H: [u32; 8] = [
+    0x6A09_E667, 0xBB67_AE85, 0x3C6E_F372, 0xA54F_F53A, 0x510E_527F, 0x9B05_688C, 0x1F83_D9AB,
+    0x5BE0_CD19,
+];
+
+pub fn sha256(bytes: &[u8]) -> [u8; 32]
+{
+    let mut state = H;
+
+    let mut data = bytes.to_vec();
+    data.push(0x80);
+    while data.len() % 64 != 56
+    {
+        data.push(0);
+    }
+    data.extend_from_slice(&(bytes.len() as u64 * 8).to_be_bytes());
+
+    for block in data.chunks(64)
+    {
+        let mut w = [0u32; 64];
+        for (i, chunk) in block.chunks(4).enumerate()
+        {
+            w[i] = u32::from_be_bytes(chunk.try_into().unwrap());
+        }
+        for i in 16..64
+        {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64
+        {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+        }
+
+        for (state, x) in state.iter_mut().zip([a, b, c, d, e, f, g, h])
+        {
+            *state = state.wrapping_add(x);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (chunk, word) in digest.chunks_mut(4).zip(state)
+    {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// The image file shared with the tests of `sces-svc-dfu`, it's packed from `image()` with
+    /// the version 1.2.3, the build 7 and the header size 0x80.
+    const FILE: &[u8] = include_bytes!("../../../sces-addons/sces-svc-dfu/testdata/image.dfu");
+
+    const CRC: u32 = 0x82D3_F886;
+
+    #[rustfmt::skip]
+    const SHA: [u8; 32] = [
+        0xA0, 0x0A, 0x71, 0x91, 0x28, 0x1E, 0xB6, 0xED, 0x3B, 0x11, 0x72, 0x11, 0x5E, 0x88, 0x5B,
+        0x42, 0x4D, 0x26, 0xDD, 0xE5, 0xC3, 0xF4, 0x7C, 0x6F, 0x54, 0x35, 0x7C, 0x1F, 0x74, 0x36,
+        0xD4, 0xAA,
+    ];
+
+    fn image() -> Vec<u8>
+    {
+        (0..600).map(|x| ((x * 31 + 7) % 251) as u8).collect()
+    }
+
+    fn hex(text: &str) -> Vec<u8>
+    {
+        (0..text.len())
+            .step_by(2)
+            .map(|x| u8::from_str_radix(&text[x..x + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn crc32_check_value()
+    {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(&image()), CRC);
+    }
+
+    /// The examples of FIPS 180-4.
+    #[test]
+    fn sha256_known_answers()
+    {
+        let vectors = [
+            (&b""[..], "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            (b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+        ];
+
+        for (data, digest) in vectors
+        {
+            assert_eq!(sha256(data).to_vec(), hex(digest));
+        }
+
+        assert_eq!(
+            sha256(&[b'a'; 1_000_000]).to_vec(),
+            hex("cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0")
+        );
+        assert_eq!(sha256(&image()), SHA);
+    }
+
+    #[test]
+    fn pack_shared_file()
+    {
+        let image = image();
+        let header = Header::new(&image, 0x80, Version::parse("1.2.3").unwrap(), 7, 0);
+
+        assert_eq!(header.pack(&image), FILE);
+
+        let header = Header::decode(FILE).unwrap();
+        assert_eq!(header.header_size, 0x80);
+        assert_eq!(header.version, Version { major: 1, minor: 2, patch: 3 });
+        assert_eq!((header.build, header.size, header.load_address), (7, 600, 0));
+        assert_eq!((header.crc, header.sha256), (CRC, SHA));
+    }
+
+    #[test]
+    fn decode_broken_header()
+    {
+        let mut file = FILE.to_vec();
+
+        assert!(Header::decode(&file[..63]).is_err());
+        file[16] ^= 1;
+        assert!(Header::decode(&file).is_err());
+        file[16] ^= 1;
+        file[0] ^= 1;
+        assert!(Header::decode(&file).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Pack a raw firmware image into the image file of `sces-svc-dfu`, and show or send it.
+//!
+//! Usage:
+//!
+//! * `sces-dfupack pack <image.bin> <output> --version <x.y.z> [--build <n>]
+//!   [--header-size <n>] [--load-address <address>]`: Make the image file, the image must be
+//!   linked at the address after the header in the slot, which is also checked by the device
+//!   when `--load-address` is given.
+//! * `sces-dfupack info <file>`: Show the header of the image file and check it.
+//! * `sces-dfupack script <file> [--chunk <n>]`: Print the commands of the console which send
+//!   the image file, like `dfu begin`, `dfu write <hex>` and `dfu finish`.
+//!
+//! The numbers could be in hex with `0x`. The header size is `0x400` by default, it's a power
+//! of two so the vector table after it is aligned.
+
+mod image;
+
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+use crate::image::{crc32, sha256, Header, Version, DFU_HEADER_SIZE};
+
+const HEADER_SIZE: u16 = 0x400;
+
+/// The bytes in a line of `dfu write` by default.
+const SCRIPT_CHUNK: usize = 64;
+
+/// The most bytes in a line of `dfu write` taken by the device.
+const SCRIPT_CHUNK_MAX: usize = 256;
+
+const USAGE: &str = "Usage:
+  sces-dfupack pack <image.bin> <output> --version <x.y.z> [--build <n>] [--header-size <n>] [--load-address <address>]
+  sces-dfupack info <file>
+  sces-dfupack script <file> [--chunk <n>]";
+
+fn main() -> ExitCode
+{
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str)
+    {
+        Some("pack") => pack(&args[1..]),
+        Some("info") => info(&args[1..]),
+        Some("script") => script(&args[1..]),
+        _ => Err(String::from(USAGE)),
+    };
+
+    match result
+    {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(x) =>
+        {
+            eprintln!("{x}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The arguments split into the positional ones and the values of the options.
+struct Args<'a>
+{
+    positions: Vec<&'a str>,
+    values: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Args<'a>
+{
+    fn option(&self, name: &str) -> Option<&'a str>
+    {
+        self.values.iter().rev().find(|(x, _)| *x == name).map(|(_, x)| *x)
+    }
+}
+
+fn parse_args<'a>(args: &'a [String], options: &[&str]) -> Result<Args<'a>, String>
+{
+    let mut positions = Vec::new();
+    let mut values = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next()
+    {
+        if !arg.starts_with("--")
+        {
+            positions.push(arg.as_str());
+            continue;
+        }
+
+        if !options.contains(&arg.as_str())
+        {
+            return Err(format!("Unknown option {arg}.\n{USAGE}"));
+        }
+
+        let value = args.next().ok_or(format!("No value of {arg}."))?;
+        values.push((arg.as_str(), value.as_str()));
+    }
+
+    Ok(Args { positions, values })
+}
+
+fn number(text: &str) -> Result<u32, String>
+{
+    let value = match text.strip_prefix("0x").or(text.strip_prefix("0X"))
+    {
+        Some(x) => u32::from_str_radix(&x.replace('_', ""), 16),
+        None => text.parse(),
+    };
+
+    value.or(Err(format!("Bad number {text}.")))
+}
+
+fn read(path: &str) -> Result<Vec<u8>, String>
+{
+    fs::read(path).map_err(|x| format!("Can't read {path}: {x}"))
+}
+
+fn pack(args: &[String]) -> Result<(), String>
+{
+    let options = ["--version", "--build", "--header-size", "--load-address"];
+    let args = parse_args(args, &options)?;
+    let [input, output] = args.positions[..]
+    else
+    {
+        return Err(String::from(USAGE));
+    };
+
+    let version = args.option("--version").ok_or("The version is needed.")?;
+    let version = Version::parse(version).ok_or(format!("Bad version {version}."))?;
+    let build = args.option("--build").map_or(Ok(0), number)?;
+    let load_address = args.option("--load-address").map_or(Ok(0), number)?;
+    let header_size = match args.option("--header-size")
+    {
+        Some(x) => u16::try_from(number(x)?).or(Err(format!("Bad header size {x}.")))?,
+        None => HEADER_SIZE,
+    };
+
+    if !header_size.is_power_of_two() || (header_size as usize) < DFU_HEADER_SIZE
+    {
+        return Err(format!("The header size must be a power of two from {DFU_HEADER_SIZE}."));
+    }
+
+    let image = read(input)?;
+    if image.is_empty() || image.len() > u32::MAX as usize
+    {
+        return Err(format!("Bad size of {input}."));
+    }
+
+    let header = Header::new(&image, header_size, version, build, load_address);
+    fs::write(output, header.pack(&image)).map_err(|x| format!("Can't write {output}: {x}"))?;
+
+    show(&header);
+    Ok(())
+}
+
+fn info(args: &[String]) -> Result<(), String>
+{
+    let [path] = args
+    else
+    {
+        return Err(String::from(USAGE));
+    };
+
+    let file = read(path)?;
+    let header = Header::decode(&file)?;
+    show(&header);
+
+    let image = file
+        .get(header.header_size as usize..)
+        .filter(|x| x.len() == header.size as usize)
+        .ok_or("The size of the image is wrong.")?;
+
+    if crc32(image) != header.crc || sha256(image) != header.sha256
+    {
+        return Err(String::from("The image is broken."));
+    }
+
+    println!("The image is valid.");
+    Ok(())
+}
+
+fn script(args: &[String]) -> Result<(), String>
+{
+    let args = parse_args(args, &["--chunk"])?;
+    let [path] = args.positions[..]
+    else
+    {
+        return Err(String::from(USAGE));
+    };
+
+    let chunk = args.option("--chunk").map_or(Ok(SCRIPT_CHUNK as u32), number)? as usize;
+    if chunk == 0
+    {
+        return Err(String::from("The chunk must not be empty."));
+    }
+    if chunk > SCRIPT_CHUNK_MAX
+    {
+        return Err(format!("The chunk must not be over {SCRIPT_CHUNK_MAX} bytes."));
+    }
+
+    let file = read(path)?;
+    Header::decode(&file)?;
+
+    println!("dfu begin");
+    for part in file.chunks(chunk)
+    {
+        let hex: String = part.iter().map(|x| format!("{x:02x}")).collect();
+        println!("dfu write {hex}");
+    }
+    println!("dfu finish");
+    Ok(())
+}
+
+fn show(header: &Header)
+{
+    let sha: String = header.sha256.iter().map(|x| format!("{x:02x}")).collect();
+
+    println!("Version:      {} build {}", header.version, header.build);
+    println!("Image:        {} bytes at offset {:#x}", header.size, header.header_size);
+    println!("CRC-32:       {:08x}", header.crc);
+    println!("SHA-256:      {sha}");
+    match header.load_address
+    {
+        0 => println!("Load address: any slot"),
+        x => println!("Load address: {x:#010x}"),
+    }
+}
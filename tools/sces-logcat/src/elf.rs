@@ -0,0 +1,41 @@
+//! Read the interned format strings from the firmware ELF file.
+
+use std::fs;
+
+use object::{Object, ObjectSection};
+
+/// The section where `sces_derive::log_intern` puts the strings.
+const INTERN_SECTION: &str = ".sces_log";
+
+pub struct Strings
+{
+    address: u64,
+    data: Vec<u8>,
+}
+
+impl Strings
+{
+    pub fn load(path: &str) -> Result<Self, String>
+    {
+        let file = fs::read(path).map_err(|x| format!("Can't read {path}: {x}"))?;
+        let elf = object::File::parse(&*file).map_err(|x| format!("Can't parse {path}: {x}"))?;
+
+        let section = elf
+            .section_by_name(INTERN_SECTION)
+            .ok_or(format!("No {INTERN_SECTION} section in {path}, check the linker script."))?;
+
+        let data = section.data().map_err(|x| format!("Can't read {INTERN_SECTION}: {x}"))?;
+
+        Ok(Self { address: section.address(), data: data.to_vec() })
+    }
+
+    /// Get the string interned at the address `id`.
+    pub fn get(&self, id: u64) -> Option<&str>
+    {
+        let offset = usize::try_from(id.checked_sub(self.address)?).ok()?;
+        let content = self.data.get(offset..)?;
+        let end = content.iter().position(|x| *x == 0)?;
+
+        std::str::from_utf8(&content[..end]).ok()
+    }
+}
@@ -0,0 +1,129 @@
+//! Format the arguments of a binary record with its format string, as `core::fmt` does.
+//!
+//! Only a subset of the format spec is supported: the alternate flag `#`, the zero padding,
+//! the width, the precision and the types `?`, `x`, `X`, `o` and `b`.
+
+use crate::frame::Arg;
+
+pub fn format(template: &str, args: &[Arg]) -> String
+{
+    let mut output = String::new();
+    let mut args = args.iter();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next()
+    {
+        match c
+        {
+            '{' if chars.peek() == Some(&'{') =>
+            {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') =>
+            {
+                chars.next();
+                output.push('}');
+            }
+            '{' =>
+            {
+                let spec: String = chars.by_ref().take_while(|x| *x != '}').collect();
+                let spec = spec.split_once(':').map_or("", |(_, x)| x);
+
+                match args.next()
+                {
+                    Some(arg) => output.push_str(&format_arg(arg, spec)),
+                    None => output.push_str("<missing>"),
+                }
+            }
+            _ => output.push(c),
+        }
+    }
+
+    args.for_each(|x| output.push_str(&format!(" <extra {}>", format_arg(x, ""))));
+    output
+}
+
+fn format_arg(arg: &Arg, spec: &str) -> String
+{
+    let alternate = spec.contains('#');
+    let kind = spec.chars().last().filter(|x| "?xXob".contains(*x));
+    let body = spec.trim_start_matches('#').trim_end_matches(|x| "?xXob".contains(x));
+    let zero = body.starts_with('0');
+    let (width, precision) = body.split_once('.').unwrap_or((body, ""));
+    let width = width.parse::<usize>().unwrap_or(0);
+    let precision = precision.parse::<usize>().ok();
+
+    let text = match (arg, kind)
+    {
+        (Arg::Unsigned(x), Some('x')) => radix(alternate, "0x", format!("{x:x}")),
+        (Arg::Unsigned(x), Some('X')) => radix(alternate, "0x", format!("{x:X}")),
+        (Arg::Unsigned(x), Some('o')) => radix(alternate, "0o", format!("{x:o}")),
+        (Arg::Unsigned(x), Some('b')) => radix(alternate, "0b", format!("{x:b}")),
+        (Arg::Signed(x), Some('x')) => radix(alternate, "0x", format!("{x:x}")),
+        (Arg::Signed(x), Some('X')) => radix(alternate, "0x", format!("{x:X}")),
+        (Arg::Unsigned(x), _) => x.to_string(),
+        (Arg::Signed(x), _) => x.to_string(),
+        (Arg::Float(x), _) => precision.map_or(x.to_string(), |p| format!("{x:.p$}")),
+        (Arg::Bool(x), _) => x.to_string(),
+        (Arg::Char(x), Some('?')) => format!("{x:?}"),
+        (Arg::Char(x), _) => x.to_string(),
+        (Arg::Str(x), Some('?')) => format!("{x:?}"),
+        (Arg::Str(x), _) => x.clone(),
+    };
+
+    if zero && !matches!(arg, Arg::Str(_) | Arg::Char(_))
+    {
+        format!("{text:0>width$}")
+    }
+    else
+    {
+        format!("{text:>width$}")
+    }
+}
+
+fn radix(alternate: bool, prefix: &str, digits: String) -> String
+{
+    if alternate
+    {
+        format!("{prefix}{digits}")
+    }
+    else
+    {
+        digits
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn format_spec()
+    {
+        let args = [
+            Arg::Unsigned(255),
+            Arg::Unsigned(7),
+            Arg::Signed(-12),
+            Arg::Float(1.5),
+            Arg::Str("a\0b".into()),
+            Arg::Char('é'),
+            Arg::Bool(true),
+        ];
+
+        assert_eq!(
+            format("{:#x} {:03} {:5} {:.2} {:?} {} {} {{}}", &args),
+            format!("{:#x} {:03} {:5} {:.2} {:?} {} {} {{}}", 255, 7, -12, 1.5, "a\0b", 'é', true)
+        );
+    }
+
+    #[test]
+    fn format_args_count()
+    {
+        let args = [Arg::Unsigned(1), Arg::Unsigned(2)];
+
+        assert_eq!(format("{} {} {}", &args[..1]), "1 <missing> <missing>");
+        assert_eq!(format("{}", &args), "1 <extra 2>");
+    }
+}
@@ -0,0 +1,244 @@
+//! Decode the COBS framed binary log records sent by the `sces-svc-console`.
+
+const FRAME_TEXT: u8 = 0x80;
+
+const ARG_UNSIGNED: u8 = 0x01;
+const ARG_SIGNED: u8 = 0x02;
+const ARG_F32: u8 = 0x03;
+const ARG_F64: u8 = 0x04;
+const ARG_BOOL: u8 = 0x05;
+const ARG_CHAR: u8 = 0x06;
+const ARG_STR: u8 = 0x07;
+
+#[derive(Debug)]
+pub enum Arg
+{
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    Str(String),
+}
+
+#[derive(Debug)]
+pub enum Body
+{
+    /// A record formatted on the device.
+    Text(String),
+
+    /// A record whose format string is interned at the address `id` of the firmware.
+    Interned
+    {
+        id: u64, args: Vec<Arg>
+    },
+}
+
+#[derive(Debug)]
+pub struct Frame
+{
+    pub seq: u8,
+    pub level: u8,
+    pub ticks: u64,
+    pub body: Body,
+}
+
+/// Decode one COBS block sequence, without the `0x00` frame end.
+pub fn cobs_decode(data: &[u8]) -> Option<Vec<u8>>
+{
+    let mut output = Vec::with_capacity(data.len());
+    let mut position = 0;
+
+    while position < data.len()
+    {
+        let code = data[position] as usize;
+
+        if code == 0
+        {
+            return None;
+        }
+
+        let block = data.get(position + 1..position + code)?;
+        output.extend_from_slice(block);
+        position += code;
+
+        if code < 0xFF && position < data.len()
+        {
+            output.push(0);
+        }
+    }
+
+    Some(output)
+}
+
+impl Frame
+{
+    pub fn parse(data: &[u8]) -> Option<Self>
+    {
+        let mut reader = Reader { data, position: 0 };
+
+        let seq = reader.byte()?;
+        let level = reader.byte()?;
+        let ticks = reader.varint()?;
+
+        let body = if level & FRAME_TEXT != 0
+        {
+            match reader.arg()?
+            {
+                Arg::Str(text) => Body::Text(text),
+                _ => return None,
+            }
+        }
+        else
+        {
+            let id = reader.varint()?;
+            let mut args = Vec::new();
+
+            while !reader.is_end()
+            {
+                args.push(reader.arg()?);
+            }
+
+            Body::Interned { id, args }
+        };
+
+        Some(Self { seq, level: level & !FRAME_TEXT, ticks, body })
+    }
+}
+
+struct Reader<'a>
+{
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a>
+{
+    fn is_end(&self) -> bool
+    {
+        self.position >= self.data.len()
+    }
+
+    fn bytes(&mut self, size: usize) -> Option<&'a [u8]>
+    {
+        let bytes = self.data.get(self.position..self.position + size)?;
+        self.position += size;
+        Some(bytes)
+    }
+
+    fn byte(&mut self) -> Option<u8>
+    {
+        self.bytes(1).map(|x| x[0])
+    }
+
+    fn varint(&mut self) -> Option<u64>
+    {
+        let mut value = 0u64;
+
+        for shift in (0..64).step_by(7)
+        {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+
+            if byte & 0x80 == 0
+            {
+                return Some(value);
+            }
+        }
+
+        None
+    }
+
+    fn arg(&mut self) -> Option<Arg>
+    {
+        match self.byte()?
+        {
+            ARG_UNSIGNED => self.varint().map(Arg::Unsigned),
+            ARG_SIGNED => self.varint().map(|x| Arg::Signed((x >> 1) as i64 ^ -((x & 1) as i64))),
+            ARG_F32 =>
+            {
+                self.bytes(4).map(|x| Arg::Float(f32::from_le_bytes(x.try_into().unwrap()) as f64))
+            }
+            ARG_F64 => self.bytes(8).map(|x| Arg::Float(f64::from_le_bytes(x.try_into().unwrap()))),
+            ARG_BOOL => self.byte().map(|x| Arg::Bool(x != 0)),
+            ARG_CHAR => self.varint().and_then(|x| char::from_u32(x as u32)).map(Arg::Char),
+            ARG_STR =>
+            {
+                let size = self.varint()? as usize;
+                self.bytes(size).map(|x| Arg::Str(String::from_utf8_lossy(x).into_owned()))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// The frames encoded by the tests of `sces-svc-console`: an interned record with `0x00` in
+    /// its head and arguments, and a text record longer than a COBS block.
+    const FRAMES: &[u8] =
+        include_bytes!("../../../sces-addons/sces-svc-console/testdata/frames.bin");
+
+    fn frames() -> Vec<Frame>
+    {
+        FRAMES
+            .split_inclusive(|x| *x == 0)
+            .map(|x| cobs_decode(&x[..x.len() - 1]).as_deref().and_then(Frame::parse).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn interned_frame()
+    {
+        let frames = frames();
+        let Frame { seq, level, ticks, body: Body::Interned { id, args } } = &frames[0]
+        else
+        {
+            panic!("{:?}", frames[0]);
+        };
+
+        assert_eq!((*seq, *level, *ticks, *id), (0, 3, 0x80, 0x0800_1000));
+        assert!(matches!(
+            args.as_slice(),
+            [
+                Arg::Unsigned(0),
+                Arg::Signed(-1),
+                Arg::Float(x),
+                Arg::Bool(true),
+                Arg::Char('é'),
+                Arg::Str(text),
+                Arg::Float(y),
+                Arg::Unsigned(u64::MAX),
+            ] if *x == 1.5 && text == "a\0b" && *y == 2.25
+        ));
+    }
+
+    #[test]
+    fn long_text_frame()
+    {
+        let frames = frames();
+        let text: String = (0..300).map(|x| (b'a' + (x % 26) as u8) as char).collect();
+
+        assert_eq!(frames.len(), 2);
+        assert!(matches!(
+            &frames[1],
+            Frame { seq: 1, level: 2, ticks: 1_234_567, body: Body::Text(x) } if *x == text
+        ));
+    }
+
+    #[test]
+    fn broken_frames()
+    {
+        assert_eq!(cobs_decode(&[0x01, 0x02, 0x41, 0x01]), Some(vec![0x00, 0x41, 0x00]));
+        assert_eq!(cobs_decode(&[0x03, 0x41]), None);
+        assert_eq!(cobs_decode(&[0x02, 0x41, 0x00]), None);
+
+        let end = FRAMES.iter().position(|x| *x == 0).unwrap();
+        let frame = cobs_decode(&FRAMES[..end]).unwrap();
+        assert!(Frame::parse(&frame[..frame.len() - 1]).is_none());
+        assert!(Frame::parse(&[0x01, 0x03, 0x80]).is_none());
+    }
+}
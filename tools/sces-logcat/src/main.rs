@@ -0,0 +1,118 @@
+//! Decode the binary log records of `sces-svc-console` and print them as text.
+//!
+//! Usage: `sces-logcat <firmware.elf> [input]`
+//!
+//! The `input` is a file or a serial device which has been configured, like `/dev/ttyACM0`,
+//! the standard input is used when it's not given.
+//! The firmware ELF file must be the one running on the device, the format strings are read
+//! from its `.sces_log` section.
+
+mod elf;
+mod format;
+mod frame;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::ExitCode;
+
+use crate::elf::Strings;
+use crate::frame::{cobs_decode, Body, Frame};
+
+const LEVELS: [&str; 6] = ["?", "ERROR", "WARN", "INFO", "DEBUG", "TRACE"];
+
+fn main() -> ExitCode
+{
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() < 2
+    {
+        eprintln!("Usage: {} <firmware.elf> [input]", args[0]);
+        return ExitCode::FAILURE;
+    }
+
+    let strings = match Strings::load(&args[1])
+    {
+        Ok(x) => x,
+        Err(x) =>
+        {
+            eprintln!("{x}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let input: Box<dyn Read> = match args.get(2)
+    {
+        Some(path) => match File::open(path)
+        {
+            Ok(x) => Box::new(x),
+            Err(x) =>
+            {
+                eprintln!("Can't open {path}: {x}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Box::new(io::stdin()),
+    };
+
+    match decode(&strings, BufReader::new(input))
+    {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(x) =>
+        {
+            eprintln!("{x}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn decode(strings: &Strings, mut input: impl BufRead) -> io::Result<()>
+{
+    let mut data = Vec::new();
+    let mut last_seq: Option<u8> = None;
+
+    while input.read_until(0, &mut data)? > 0
+    {
+        if data.pop() != Some(0)
+        {
+            break;
+        }
+
+        let Some(frame) = cobs_decode(&data).as_deref().and_then(Frame::parse)
+        else
+        {
+            println!("<broken frame of {} bytes>", data.len());
+            data.clear();
+            continue;
+        };
+
+        let lost = last_seq.map_or(0, |x| frame.seq.wrapping_sub(x).wrapping_sub(1));
+        last_seq = Some(frame.seq);
+
+        if lost > 0
+        {
+            println!("<{lost} frames lost>");
+        }
+
+        print_frame(strings, &frame);
+        data.clear();
+    }
+
+    Ok(())
+}
+
+fn print_frame(strings: &Strings, frame: &Frame)
+{
+    let text = match &frame.body
+    {
+        Body::Text(text) => text.clone(),
+        Body::Interned { id, args } => match strings.get(*id)
+        {
+            Some(template) => format::format(template, args),
+            None => format!("<unknown string {id:#x}> {args:?}"),
+        },
+    };
+
+    let level = LEVELS.get(frame.level as usize).unwrap_or(&LEVELS[0]);
+    println!("[{:>5}.{:03}] [{level:>5}] {text}", frame.ticks / 1000, frame.ticks % 1000);
+}